@@ -7,9 +7,11 @@ use std::time::{Duration, Instant};
 use anyhow::Context;
 use clap::{Parser, ValueEnum};
 use interpreter::csv::{CsvEventSource, CsvVerdictSink};
+use interpreter::ExternalFunctions;
 use interpreter::Monitor;
-use streamir_lib::ir::StreamReference;
-use streamir_lib::{parse, ParserConfig};
+use streamir_lib::ir::{OverflowPolicy, StreamReference};
+use streamir_lib::pipeline::Pipeline;
+use streamir_lib::{parse_with_sources, ParserConfig};
 
 #[derive(Parser, Debug, Clone)]
 struct Args {
@@ -21,8 +23,17 @@ struct Args {
     output_streams: Vec<String>,
     #[arg(short, long)]
     optimize: bool,
+    #[arg(long, conflicts_with = "optimize")]
+    /// The rewriting pipeline to apply: the name of a preset, a list of rules, or `@` followed by the path of a pipeline file
+    pipeline: Option<Pipeline>,
+    #[arg(long)]
+    /// How integer operations treat results that do not fit their type: `wrap` (the default), `saturate` or `trap`
+    overflow: Option<OverflowPolicy>,
     #[arg(long)]
     benchmark: bool,
+    #[arg(long, value_enum)]
+    /// Print a report of the static memory footprint of the monitor to stdout
+    resource_report: Option<ReportFormat>,
     // #[arg(long)]
     // cache: Option<PathBuf>,
 }
@@ -35,6 +46,13 @@ enum Verbosity {
     Streams,
 }
 
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum ReportFormat {
+    Text,
+    #[cfg(feature = "serde")]
+    Json,
+}
+
 // fn load_cache(config: &ParserConfig, cache: &PathBuf) -> anyhow::Result<Option<RtLolaMir>> {
 //     let file = File::open(cache)?;
 //     let hashed_mir: HashedMir =
@@ -47,9 +65,14 @@ fn build(
     trace: PathBuf,
     verbosity: Verbosity,
     output_streams: Vec<String>,
-    optimize: bool,
+    pipeline: Pipeline,
+    overflow: Option<OverflowPolicy>,
+    resource_report: Option<ReportFormat>,
 ) -> anyhow::Result<(Monitor, CsvEventSource<File>, CsvVerdictSink<Stderr>)> {
-    let streamir = parse(config).context("parsing spec")?;
+    let mut streamir = parse_with_sources(config).context("parsing spec")?;
+    if let Some(overflow) = overflow {
+        streamir.set_overflow(overflow);
+    }
     let csv_source = CsvEventSource::new(File::open(trace)?, &streamir);
 
     let csv_fields: Vec<StreamReference> = if !output_streams.is_empty() {
@@ -79,7 +102,20 @@ fn build(
     let csv_writer = CsvVerdictSink::new(stderr(), &streamir, &csv_fields)
         .context("building csv output writer")?;
 
-    let monitor = Monitor::build(streamir, optimize);
+    if let Some(format) = resource_report {
+        let report = pipeline
+            .run(streamir.clone())
+            .context("optimizing StreamIR")?
+            .resource_report();
+        match format {
+            ReportFormat::Text => println!("{report}"),
+            #[cfg(feature = "serde")]
+            ReportFormat::Json => println!("{}", report.to_json()),
+        }
+    }
+
+    let monitor = Monitor::build_with_pipeline(streamir, &pipeline, ExternalFunctions::default())
+        .context("building monitor")?;
 
     Ok((monitor, csv_source, csv_writer))
 }
@@ -128,9 +164,14 @@ fn main() -> anyhow::Result<()> {
         verbosity,
         output_streams,
         optimize,
+        pipeline,
+        overflow,
         benchmark,
+        resource_report,
         // cache,
     } = Args::parse();
+    let pipeline =
+        pipeline.unwrap_or_else(|| Pipeline::preset(if optimize { "all" } else { "jit" }).unwrap());
 
     let config = match ParserConfig::from_path(spec).context("loading specification file") {
         Ok(config) => config,
@@ -140,7 +181,15 @@ fn main() -> anyhow::Result<()> {
         }
     };
 
-    let (monitor, source, sink) = build(&config, trace, verbosity, output_streams, optimize)?;
+    let (monitor, source, sink) = build(
+        &config,
+        trace,
+        verbosity,
+        output_streams,
+        pipeline,
+        overflow,
+        resource_report,
+    )?;
     run(monitor, source, sink, benchmark);
     Ok(())
 }
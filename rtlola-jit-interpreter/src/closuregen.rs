@@ -3,10 +3,12 @@ use std::{cell::RefCell, collections::HashMap, marker::PhantomData, rc::Rc};
 use bit_set::BitSet;
 use streamir_lib::ir::{
     memory::Memory as IrMemory, windows::Window, InputReference, LocalFreq, LocalFreqRef,
-    OutputReference, StreamReference, WindowReference,
+    OutputReference, OverflowPolicy, SourceMap, StreamReference, VariableReference,
+    WindowReference,
 };
 
 use crate::{
+    functions::ExternalFunctions,
     memory::{Instance, Memory},
     schedule::{DeadlineEvent, Schedule, StaticSchedule},
     value::Value,
@@ -23,6 +25,15 @@ pub(crate) struct Closuregen<E: Event> {
     pub(crate) wref2window: HashMap<WindowReference, Window>,
     pub(crate) lr2local_freq: HashMap<LocalFreqRef, LocalFreq>,
     pub(crate) static_schedule: Option<StaticSchedule>,
+    pub(crate) functions: ExternalFunctions,
+    pub(crate) sources: SourceMap,
+    pub(crate) overflow: OverflowPolicy,
+    /// The external functions called by the compiled statements without an implementation
+    pub(crate) missing_functions: RefCell<Vec<String>>,
+    /// The clause whose expressions are currently compiled, cited by the runtime errors of the expressions
+    pub(crate) location: RefCell<Option<Rc<str>>>,
+    /// The variables bound around the currently compiled statement, the position of a variable is its slot in the evaluation context
+    pub(crate) scope: RefCell<Vec<VariableReference>>,
     phantom: PhantomData<E>,
 }
 
@@ -32,22 +43,45 @@ impl<E: Event> Closuregen<E> {
         wref2window: HashMap<WindowReference, Window>,
         lr2local_freq: HashMap<LocalFreqRef, LocalFreq>,
         static_schedule: Option<StaticSchedule>,
+        functions: ExternalFunctions,
     ) -> Self {
         Self {
             sr2memory,
             wref2window,
             lr2local_freq,
             static_schedule,
+            functions,
+            sources: SourceMap::default(),
+            overflow: OverflowPolicy::default(),
+            missing_functions: RefCell::new(Vec::new()),
+            location: RefCell::new(None),
+            scope: RefCell::new(Vec::new()),
             phantom: PhantomData,
         }
     }
 
+    /// Sets the locations in the specification that are cited by the runtime errors of the streams
+    pub fn with_sources(mut self, sources: SourceMap) -> Self {
+        self.sources = sources;
+        self
+    }
+
+    /// Sets the behavior of integer operations whose result does not fit their type
+    pub fn with_overflow(mut self, overflow: OverflowPolicy) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
     pub fn with_event<O: Event>(self) -> Closuregen<O> {
         let Self {
             sr2memory,
             wref2window,
             lr2local_freq,
             static_schedule,
+            functions,
+            sources,
+            overflow,
+            missing_functions,
             ..
         } = self;
         Closuregen {
@@ -55,6 +89,12 @@ impl<E: Event> Closuregen<E> {
             wref2window,
             lr2local_freq,
             static_schedule,
+            functions,
+            sources,
+            overflow,
+            missing_functions,
+            location: RefCell::new(None),
+            scope: RefCell::new(Vec::new()),
             phantom: Default::default(),
         }
     }
@@ -111,6 +151,8 @@ pub(crate) struct EvaluationContext<'e> {
     pub(crate) schedule: &'e mut Schedule,
     parameter: Option<Rc<Instance>>,
     lambda_parameter: RefCell<Option<Rc<Instance>>>,
+    /// The values of the local variables that are currently in scope, indexed by their slot
+    variables: Vec<Value>,
 }
 
 impl<'a> EvaluationContext<'a> {
@@ -139,6 +181,7 @@ impl<'a> EvaluationContext<'a> {
             instances,
             parameter: None,
             lambda_parameter: RefCell::new(None),
+            variables: Vec::new(),
             schedule,
         }
     }
@@ -1,11 +1,14 @@
-use std::ops::{Add, BitAnd, BitOr, BitXor, Div, Mul, Neg, Not, Rem, Shl, Shr, Sub};
+use std::{
+    fmt::Display,
+    ops::{Add, BitAnd, BitOr, BitXor, Div, Mul, Neg, Not, Rem, Shl, Shr, Sub},
+};
 
 use ordered_float::NotNan;
 use streamir_lib::{
     formatter::expressions::ExprFormatter,
     ir::{
         expressions::{Constant, Expr, Function, Operator},
-        OutputReference, StreamReference, Type, WindowReference,
+        OutputReference, OverflowPolicy, StreamReference, Type, VariableReference, WindowReference,
     },
 };
 
@@ -36,6 +39,78 @@ fn translate_constant(c: Constant) -> Value {
     }
 }
 
+/// Raises a runtime error of the monitor, citing the clause in the specification that caused it if it is known
+fn raise(location: Option<&str>, msg: impl Display) -> ! {
+    match location {
+        Some(location) => panic!("{location}: {msg}"),
+        None => panic!("{msg}"),
+    }
+}
+
+/// Converts the result of a float operation, raising a runtime error if it is not a number
+fn float_result(location: Option<&str>, function: &str, result: f64) -> Value {
+    Value::try_from(result).unwrap_or_else(|_| {
+        raise(
+            location,
+            format!("the result of {function} is not a number"),
+        )
+    })
+}
+
+/// Returns whether the integer operation divides by zero, which is a runtime error under every overflow policy
+fn divides_by_zero(op: Operator, lhs: i128, rhs: i128) -> bool {
+    match op {
+        Operator::Div | Operator::Rem => rhs == 0,
+        Operator::Pow => lhs == 0 && rhs < 0,
+        _ => false,
+    }
+}
+
+/// Applies an arithmetic operator to integer values of the given type following the overflow policy
+///
+/// The unary operator is given without a right-hand side.
+fn integer_op(
+    policy: OverflowPolicy,
+    location: Option<&str>,
+    op: Operator,
+    ty: &Type,
+    lhs: Value,
+    rhs: Option<Value>,
+) -> Value {
+    let binary = |lhs: i128, rhs: i128| {
+        if divides_by_zero(op, lhs, rhs) {
+            raise(location, "attempt to divide by zero");
+        }
+        policy.binary(op, lhs, rhs, ty)
+    };
+    let result = match (lhs, rhs) {
+        (Value::Unsigned(operand), None) => policy
+            .negate(operand.into(), ty)
+            .map(|v| Value::Unsigned(v as u64)),
+        (Value::Signed(operand), None) => policy
+            .negate(operand.into(), ty)
+            .map(|v| Value::Signed(v as i64)),
+        (Value::Unsigned(lhs), Some(Value::Unsigned(rhs))) => {
+            binary(lhs.into(), rhs.into()).map(|v| Value::Unsigned(v as u64))
+        }
+        (Value::Signed(lhs), Some(Value::Signed(rhs))) => {
+            binary(lhs.into(), rhs.into()).map(|v| Value::Signed(v as i64))
+        }
+        (a, b) => panic!("Incompatible types: ({:?},{:?})", a, b),
+    };
+    result.unwrap_or_else(|overflow| raise(location, overflow))
+}
+
+impl<E: Event + 'static> Closuregen<E> {
+    /// Compiles the default value of an access, an access without a default results in [Value::None]
+    fn access_default(&self, default: Option<Expr>) -> CompiledExpr {
+        match default {
+            Some(default) => self.expr(default),
+            None => CompiledExpr::new(|_| Value::None),
+        }
+    }
+}
+
 impl<E: Event + 'static> ExprFormatter for Closuregen<E> {
     type Return = CompiledExpr;
 
@@ -45,6 +120,21 @@ impl<E: Event + 'static> ExprFormatter for Closuregen<E> {
     }
 
     fn unary(&self, op: Operator, operand: Expr) -> Self::Return {
+        if OverflowPolicy::governs(op) && matches!(operand.ty, Type::Int(_) | Type::UInt(_)) {
+            let (policy, ty) = (self.overflow, operand.ty.clone());
+            let location = self.location.borrow().clone();
+            let operand = self.expr(operand);
+            return CompiledExpr::new(move |ctx| {
+                integer_op(
+                    policy,
+                    location.as_deref(),
+                    op,
+                    &ty,
+                    operand.execute(ctx),
+                    None,
+                )
+            });
+        }
         let operand = self.expr(operand);
 
         macro_rules! create_unop {
@@ -66,6 +156,30 @@ impl<E: Event + 'static> ExprFormatter for Closuregen<E> {
     }
 
     fn binary(&self, op: Operator, lhs: Expr, rhs: Expr) -> Self::Return {
+        if OverflowPolicy::governs(op) && matches!(lhs.ty, Type::Int(_) | Type::UInt(_)) {
+            let (policy, ty) = (self.overflow, lhs.ty.clone());
+            let location = self.location.borrow().clone();
+            let lhs = self.expr(lhs);
+            let rhs = self.expr(rhs);
+            return CompiledExpr::new(move |ctx| {
+                let lhs = lhs.execute(ctx);
+                let rhs = rhs.execute(ctx);
+                integer_op(policy, location.as_deref(), op, &ty, lhs, Some(rhs))
+            });
+        }
+        if op == Operator::Pow && matches!(lhs.ty, Type::Float32 | Type::Float64) {
+            let location = self.location.borrow().clone();
+            let lhs = self.expr(lhs);
+            let rhs = self.expr(rhs);
+            return CompiledExpr::new(move |ctx| {
+                let result = match (lhs.execute(ctx), rhs.execute(ctx)) {
+                    (Value::Float(base), Value::Float(exp)) => base.powf(*exp),
+                    (Value::Float(base), Value::Signed(exp)) => base.powi(exp as i32),
+                    (v1, v2) => unreachable!("wrong Value types of {:?}, {:?} for pow", v1, v2),
+                };
+                float_result(location.as_deref(), "pow", result)
+            });
+        }
         let lhs = self.expr(lhs);
         let rhs = self.expr(rhs);
 
@@ -172,10 +286,10 @@ impl<E: Event + 'static> ExprFormatter for Closuregen<E> {
         &self,
         sr: StreamReference,
         offset: u32,
-        default: Expr,
+        default: Option<Expr>,
         parameters: Vec<Expr>,
     ) -> Self::Return {
-        let default = self.expr(default);
+        let default = self.access_default(default);
         match sr {
             StreamReference::In(sr) => CompiledExpr::new(move |ctx| {
                 ctx.memory
@@ -208,10 +322,10 @@ impl<E: Event + 'static> ExprFormatter for Closuregen<E> {
     fn hold_access(
         &self,
         sr: StreamReference,
-        default: Expr,
+        default: Option<Expr>,
         parameters: Vec<Expr>,
     ) -> Self::Return {
-        let default = self.expr(default);
+        let default = self.access_default(default);
         match sr {
             StreamReference::In(sr) => CompiledExpr::new(move |ctx| {
                 ctx.memory
@@ -244,10 +358,10 @@ impl<E: Event + 'static> ExprFormatter for Closuregen<E> {
     fn get_access(
         &self,
         sr: StreamReference,
-        default: Expr,
+        default: Option<Expr>,
         parameters: Vec<Expr>,
     ) -> Self::Return {
-        let default = self.expr(default);
+        let default = self.access_default(default);
         match sr {
             StreamReference::In(sr) => CompiledExpr::new(move |ctx| {
                 if ctx.fresh_inputs.contains(sr) {
@@ -377,6 +491,23 @@ impl<E: Event + 'static> ExprFormatter for Closuregen<E> {
         CompiledExpr::new(move |ctx| ctx.lambda_parameter.borrow().as_ref().unwrap()[idx].clone())
     }
 
+    fn variable(&self, var: VariableReference, _ty: Type) -> Self::Return {
+        // the innermost binding of the variable shadows the outer ones
+        let slot = self
+            .scope
+            .borrow()
+            .iter()
+            .rposition(|v| *v == var)
+            .unwrap_or_else(|| panic!("variable {var} is not bound"));
+        CompiledExpr::new(move |ctx| ctx.variables[slot].clone())
+    }
+
+    fn with_default(&self, expr: Expr, default: Expr) -> Self::Return {
+        let expr = self.expr(expr);
+        let default = self.expr(default);
+        CompiledExpr::new(move |ctx| expr.execute(ctx).unwrap_or_else(|| default.execute(ctx)))
+    }
+
     fn cast(&self, to_ty: Type, expr: Expr) -> Self::Return {
         let from_ty = expr.ty.clone();
         let f_expr = self.expr(expr);
@@ -481,11 +612,14 @@ impl<E: Event + 'static> ExprFormatter for Closuregen<E> {
     fn function_call(&self, function: Function, args: Vec<Expr>) -> Self::Return {
         macro_rules! create_floatfn {
             ($fn:ident) => {{
+                let location = self.location.borrow().clone();
                 let arg = self.expr(args[0].clone());
                 CompiledExpr::new(move |ctx| {
                     let arg = arg.execute(ctx);
                     match arg {
-                        Value::Float(f) => Value::try_from(f.$fn()).unwrap(),
+                        Value::Float(f) => {
+                            float_result(location.as_deref(), stringify!($fn), f.$fn())
+                        }
                         _ => unreachable!(),
                     }
                 })
@@ -515,6 +649,30 @@ impl<E: Event + 'static> ExprFormatter for Closuregen<E> {
             }};
         }
 
+        macro_rules! create_binary_floatfn {
+            ($fn:ident) => {{
+                let (lhs, rhs) = match &args[..] {
+                    [lhs, rhs] => (lhs, rhs),
+                    _ => unreachable!("wrong number of arguments for function $fn"),
+                };
+                let location = self.location.borrow().clone();
+                let lhs = self.expr(lhs.clone());
+                let rhs = self.expr(rhs.clone());
+                CompiledExpr::new(move |ctx| {
+                    let lhs = lhs.execute(ctx);
+                    let rhs = rhs.execute(ctx);
+                    match (lhs, rhs) {
+                        (Value::Float(f1), Value::Float(f2)) => {
+                            float_result(location.as_deref(), stringify!($fn), f1.$fn(*f2))
+                        }
+                        (v1, v2) => {
+                            unreachable!("wrong Value types of {:?}, {:?} for function $fn", v1, v2)
+                        }
+                    }
+                })
+            }};
+        }
+
         match function {
             Function::Sqrt => create_floatfn!(sqrt),
             Function::Abs => {
@@ -536,6 +694,69 @@ impl<E: Event + 'static> ExprFormatter for Closuregen<E> {
             Function::Arctan => create_floatfn!(atan),
             Function::Min => create_binary_arith!(min),
             Function::Max => create_binary_arith!(max),
+            Function::Floor => create_floatfn!(floor),
+            Function::Ceil => create_floatfn!(ceil),
+            Function::Round => create_floatfn!(round),
+            Function::Exp => create_floatfn!(exp),
+            Function::Ln => create_floatfn!(ln),
+            Function::Log => create_binary_floatfn!(log),
+            Function::Atan2 => create_binary_floatfn!(atan2),
+            Function::Pow => {
+                let [lhs, rhs]: [Expr; 2] = args.try_into().unwrap();
+                self.binary(Operator::Pow, lhs, rhs)
+            }
+            Function::Signum => {
+                let arg = self.expr(args[0].clone());
+                CompiledExpr::new(move |ctx| {
+                    let arg = arg.execute(ctx);
+                    match arg {
+                        // like the integers, a zero of either sign has the signum zero
+                        Value::Float(f) => {
+                            Value::try_from(if *f == 0.0 { 0.0 } else { f.signum() }).unwrap()
+                        }
+                        Value::Signed(i) => Value::Signed(i.signum()),
+                        Value::Unsigned(u) => Value::Unsigned(u.min(1)),
+                        v => unreachable!("wrong Value type of {:?}, for function signum", v),
+                    }
+                })
+            }
+            Function::Clamp => {
+                let [value, min, max]: [Expr; 3] = args.try_into().unwrap();
+                let max = self.function_call(Function::Min, vec![value, max]);
+                let min = self.expr(min);
+                CompiledExpr::new(move |ctx| {
+                    let max = max.execute(ctx);
+                    let min = min.execute(ctx);
+                    match (max, min) {
+                        (Value::Float(f1), Value::Float(f2)) => Value::Float(f1.max(f2)),
+                        (Value::Signed(s1), Value::Signed(s2)) => Value::Signed(s1.max(s2)),
+                        (Value::Unsigned(u1), Value::Unsigned(u2)) => Value::Unsigned(u1.max(u2)),
+                        (v1, v2) => {
+                            unreachable!(
+                                "wrong Value types of {:?}, {:?} for function clamp",
+                                v1, v2
+                            )
+                        }
+                    }
+                })
+            }
+            Function::External(name, _signature) => {
+                let Some(f) = self.functions.get(&name) else {
+                    // reported as an error when building the monitor, so the expression is never executed
+                    let mut missing = self.missing_functions.borrow_mut();
+                    if !missing.contains(&name) {
+                        missing.push(name.clone());
+                    }
+                    return CompiledExpr::new(move |_| {
+                        unreachable!("no implementation for the external function {name}")
+                    });
+                };
+                let args: Vec<_> = args.into_iter().map(|arg| self.expr(arg)).collect();
+                CompiledExpr::new(move |ctx| {
+                    let args: Vec<_> = args.iter().map(|arg| arg.execute(ctx)).collect();
+                    f(&args)
+                })
+            }
         }
     }
 }
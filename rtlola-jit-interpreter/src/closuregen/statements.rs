@@ -5,8 +5,8 @@ use itertools::Itertools;
 use streamir_lib::{
     formatter::{expressions::ExprFormatter, guards::GuardFormatter, statements::StmtFormatter},
     ir::{
-        expressions::Expr, Guard, InputReference, LocalFreq, LocalFreqRef, OutputReference, Stmt,
-        StreamReference, WindowReference,
+        expressions::Expr, Guard, InputReference, LocalFreq, LocalFreqRef, OutputReference,
+        SourceSpan, Stmt, StreamReference, VariableReference, WindowReference,
     },
 };
 
@@ -37,6 +37,25 @@ impl<E: 'static> FromIterator<CompiledStmt<E>> for CompiledStmt<E> {
     }
 }
 
+impl<E: Event + 'static> Closuregen<E> {
+    /// Compiles the expressions of a statement such that their runtime errors cite the clause of the stream in the specification, if it is known
+    fn located<T>(
+        &self,
+        action: &str,
+        sr: OutputReference,
+        span: SourceSpan,
+        compile: impl FnOnce() -> T,
+    ) -> T {
+        let location = self.sources.locate(span, sr.sr()).map(|location| {
+            format!("{action} {} at {location}", self.sr2memory[&sr.sr()].name).into()
+        });
+        let outer = self.location.replace(location);
+        let compiled = compile();
+        self.location.replace(outer);
+        compiled
+    }
+}
+
 impl<E: Event + 'static> StmtFormatter for Closuregen<E> {
     type Return = CompiledStmt<E>;
 
@@ -59,6 +78,13 @@ impl<E: Event + 'static> StmtFormatter for Closuregen<E> {
                 CompiledStmt::new(move |_ev, ctx| ctx.memory.shift_input(idx))
             }
             StreamReference::Out(idx) => match idx {
+                OutputReference::Unparameterized(i) if self.sr2memory[&sr].delay > 0 => {
+                    CompiledStmt::new(move |_ev, ctx| {
+                        if !ctx.memory.is_warming_up(i) {
+                            ctx.memory.shift_output_value(i)
+                        }
+                    })
+                }
                 OutputReference::Unparameterized(i) => {
                     CompiledStmt::new(move |_ev, ctx| ctx.memory.shift_output_value(i))
                 }
@@ -85,6 +111,7 @@ impl<E: Event + 'static> StmtFormatter for Closuregen<E> {
         with: Option<Vec<Expr>>,
         local_frequencies: Vec<LocalFreqRef>,
         windows: Vec<WindowReference>,
+        span: SourceSpan,
     ) -> Self::Return {
         match sr {
             OutputReference::Unparameterized(idx) => {
@@ -156,8 +183,9 @@ impl<E: Event + 'static> StmtFormatter for Closuregen<E> {
                 })
             }
             OutputReference::Parameterized(idx) => {
-                let compiled_with =
-                    with.map(|with| with.into_iter().map(|p| self.expr(p)).collect::<Vec<_>>());
+                let compiled_with = self.located("spawning", sr, span, || {
+                    with.map(|with| with.into_iter().map(|p| self.expr(p)).collect::<Vec<_>>())
+                });
 
                 let dynamic_queue_update: Vec<_> = local_frequencies
                     .into_iter()
@@ -237,10 +265,21 @@ impl<E: Event + 'static> StmtFormatter for Closuregen<E> {
         }
     }
 
-    fn eval(&self, sr: OutputReference, with: Expr, _idx: usize) -> Self::Return {
-        let compiled_expr = self.expr(with);
+    fn eval(&self, sr: OutputReference, with: Expr, _idx: usize, span: SourceSpan) -> Self::Return {
+        let compiled_expr = self.located("evaluating", sr, span, || self.expr(with));
         let windows = self.accept_values(StreamReference::Out(sr));
         match sr {
+            OutputReference::Unparameterized(i) if self.sr2memory[&sr.sr()].delay > 0 => {
+                CompiledStmt::new(move |_ev, ctx| {
+                    if ctx.memory.warm_up(i) {
+                        return;
+                    }
+                    let new_value = compiled_expr.execute(ctx);
+                    ctx.fresh_outputs.insert(i);
+                    windows.execute(&new_value, ctx);
+                    ctx.memory.eval_output_value(i, new_value);
+                })
+            }
             OutputReference::Unparameterized(i) => CompiledStmt::new(move |_ev, ctx| {
                 let new_value = compiled_expr.execute(ctx);
                 ctx.fresh_outputs.insert(i);
@@ -262,6 +301,7 @@ impl<E: Event + 'static> StmtFormatter for Closuregen<E> {
         sr: OutputReference,
         local_frequencies: Vec<LocalFreqRef>,
         _windows: Vec<WindowReference>,
+        _span: SourceSpan,
     ) -> Self::Return {
         let local_frequencies: BitSet = local_frequencies.into_iter().collect();
         let (closing_unparam_local, closing_param_local) = local_frequencies
@@ -345,4 +385,19 @@ impl<E: Event + 'static> StmtFormatter for Closuregen<E> {
             }
         })
     }
+
+    fn r#let(&self, var: VariableReference, expr: Expr, inner: Stmt) -> Self::Return {
+        let expr = self.expr(expr);
+        let slot = self.scope.borrow().len();
+        self.scope.borrow_mut().push(var);
+        let inner = self.stmt(inner);
+        self.scope.borrow_mut().pop();
+        CompiledStmt::new(move |ev, ctx| {
+            debug_assert_eq!(ctx.variables.len(), slot);
+            let value = expr.execute(ctx);
+            ctx.variables.push(value);
+            inner.execute(ev, ctx);
+            ctx.variables.pop();
+        })
+    }
 }
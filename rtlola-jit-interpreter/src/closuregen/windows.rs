@@ -213,6 +213,7 @@ impl<E: Event + 'static> Closuregen<E> {
             ty,
             origin: _,
             origin_pacing: _,
+            span: _,
         } = window;
         let WindowKind::Instances { selection } = kind else {
             unreachable!()
@@ -359,6 +360,7 @@ impl<E: Event + 'static> Closuregen<E> {
                     origin_pacing,
                     kind: _,
                     ty: _,
+                    span: _,
                 } = &self.wref2window[&wref];
                 let target_idx = target.out_idx().parameterized_idx();
                 let spawn_window_instance = move |ctx: &mut EvaluationContext, ts| {
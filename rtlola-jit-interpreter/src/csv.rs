@@ -98,10 +98,14 @@ impl<W: Write> CsvVerdictSink<W> {
         let field_map = fields.iter().enumerate().map(|(i, sr)| (*sr, i)).collect();
 
         let mut writer = csv::Writer::from_writer(write);
+        // the values of delayed streams belong to earlier activations than the time of the row
         let header = fields
             .iter()
-            .map(|sr| ir.name(*sr))
-            .chain(std::iter::once("time"));
+            .map(|sr| match ir.stream_memory(*sr).delay {
+                0 => ir.name(*sr).to_owned(),
+                delay => format!("{}.offset(by: -{delay})", ir.name(*sr)),
+            })
+            .chain(std::iter::once("time".to_owned()));
 
         writer.write_record(header)?;
 
@@ -0,0 +1,354 @@
+//! Differential testing of rewriting rules.
+//!
+//! Runs the StreamIR before and after each application of a rewriting rule on random input traces
+//! and compares the verdicts of both versions step by step.
+//! If the verdicts differ, the trace is shrunk to a small trace that still shows the difference.
+
+use std::{
+    cell::RefCell,
+    fmt::Display,
+    panic::{self, AssertUnwindSafe},
+    rc::Rc,
+    time::Duration,
+};
+
+use itertools::Itertools;
+use ordered_float::NotNan;
+use streamir_lib::{
+    ir::{InputReference, OutputReference, StreamIr, StreamReference, Type},
+    rewrite_rules::{RewriteError, RewriteRule, Rewriter},
+};
+use thiserror::Error;
+
+use crate::{
+    verdict::{Change, TotalIncremental, Verdict},
+    BuildError, ExternalFunctions, Inputs, Monitor, Time, Value,
+};
+
+#[derive(Debug, Clone)]
+/// A sequence of input events together with their timestamps
+pub struct Trace(pub Vec<(Time, Inputs)>);
+
+impl Display for Trace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (ts, Inputs(inputs)) in &self.0 {
+            let inputs = inputs
+                .iter()
+                .map(|v| v.as_ref().map(|v| v.to_string()).unwrap_or("#".into()))
+                .join(", ");
+            writeln!(f, "{}: {inputs}", ts.as_secs_f64())?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+/// An application of a rewriting rule that changed the verdicts of the monitor
+pub struct Mismatch {
+    /// The name of the rewriting rule
+    pub rule: String,
+    /// The StreamIR before the application of the rule
+    pub before: StreamIr,
+    /// The StreamIR after the application of the rule
+    pub after: StreamIr,
+    /// The shrunk trace on which the verdicts differ
+    pub trace: Trace,
+    /// The index of the first event whose verdict differs, or the length of the trace if only the final periodic verdicts differ
+    pub step: usize,
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "the rewrite rule {} changed the verdict at step {} of the trace",
+            self.rule, self.step
+        )?;
+        write!(f, "{}", self.trace)
+    }
+}
+
+#[derive(Debug, Error)]
+/// An error found by the [DifferentialTester]
+pub enum DifferentialError {
+    #[error("rewriting failed: {0}")]
+    /// The rewriting itself failed
+    Rewrite(#[from] RewriteError),
+    #[error("building the monitor failed: {0}")]
+    /// The monitor of the original StreamIR can not be built
+    Build(#[from] BuildError),
+    #[error("{0}")]
+    /// A rewriting rule changed the behavior of the monitor
+    Mismatch(Box<Mismatch>),
+}
+
+#[derive(Debug, Clone)]
+/// Checks that rewriting rules preserve the behavior of the monitor on random input traces
+///
+/// The monitors are built without further optimizations, such that only the given rules are tested.
+pub struct DifferentialTester {
+    traces: usize,
+    trace_length: usize,
+    seed: u64,
+    functions: ExternalFunctions,
+}
+
+impl Default for DifferentialTester {
+    fn default() -> Self {
+        Self {
+            traces: 20,
+            trace_length: 20,
+            seed: 0,
+            functions: ExternalFunctions::default(),
+        }
+    }
+}
+
+impl DifferentialTester {
+    /// Constructs a tester with 20 random traces of 20 events each
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the number of random traces for each application of a rule
+    pub fn with_traces(mut self, traces: usize) -> Self {
+        self.traces = traces;
+        self
+    }
+
+    /// Sets the number of events of each random trace
+    pub fn with_trace_length(mut self, trace_length: usize) -> Self {
+        self.trace_length = trace_length;
+        self
+    }
+
+    /// Sets the seed of the random traces
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Sets the implementations of the external functions used by the specification
+    pub fn with_functions(mut self, functions: ExternalFunctions) -> Self {
+        self.functions = functions;
+        self
+    }
+
+    /// Applies the rules to the StreamIR until a fixedpoint is reached and checks each application that changed the StreamIR.
+    ///
+    /// Returns the rewritten StreamIR, or the first application of a rule that changed the verdicts.
+    pub fn check(
+        &self,
+        ir: StreamIr,
+        rules: Vec<Box<dyn RewriteRule>>,
+    ) -> Result<StreamIr, DifferentialError> {
+        // rewriting does not add external functions, so the monitors of all snapshots can be built as well
+        Monitor::build_with_functions(ir.clone(), false, self.functions.clone())?;
+        let mut generator = TraceGenerator::new(&ir, self.seed);
+        let traces = (0..self.traces)
+            .map(|_| generator.generate(self.trace_length))
+            .collect::<Vec<_>>();
+        let mismatch = Rc::new(RefCell::new(None));
+        let rewriter = Rewriter::try_new(rules)?.with_snapshots({
+            let mismatch = mismatch.clone();
+            let tester = self.clone();
+            move |rule, before, after| {
+                if mismatch.borrow().is_some() {
+                    return;
+                }
+                *mismatch.borrow_mut() = traces.iter().find_map(|trace| {
+                    tester.compare(before, after, trace)?;
+                    let trace = tester.shrink(before, after, trace.clone());
+                    let step = tester.compare(before, after, &trace).unwrap();
+                    Some(Mismatch {
+                        rule: rule.into(),
+                        before: before.clone(),
+                        after: after.clone(),
+                        trace,
+                        step,
+                    })
+                });
+            }
+        });
+        let ir = rewriter.run(ir)?;
+        let mismatch = mismatch.borrow_mut().take();
+        match mismatch {
+            Some(mismatch) => Err(DifferentialError::Mismatch(Box::new(mismatch))),
+            None => Ok(ir),
+        }
+    }
+
+    /// Returns the first step at which the verdicts of both StreamIRs differ on the trace
+    fn compare(&self, before: &StreamIr, after: &StreamIr, trace: &Trace) -> Option<usize> {
+        let before = self.run(before, trace);
+        let after = self.run(after, trace);
+        before.iter().zip(&after).position(|(b, a)| b != a)
+    }
+
+    /// Runs the StreamIR on the trace and returns the normalized verdicts of each step.
+    ///
+    /// The last step holds the periodic verdicts after the last event.
+    /// A step in which the monitor panics is None and ends the run.
+    /// The messages of the panics are not compared, as they cite the clauses of the specification differently after rewriting.
+    fn run(&self, ir: &StreamIr, trace: &Trace) -> Vec<Option<Vec<(Time, Step)>>> {
+        let mut monitor = Monitor::build_with_functions(ir.clone(), false, self.functions.clone())
+            .expect("the monitor of the original StreamIR was built");
+        let mut steps = Vec::new();
+        for (ts, inputs) in &trace.0 {
+            let step = panic::catch_unwind(AssertUnwindSafe(|| {
+                let Verdict { timed, ts, event } = monitor.accept_event(inputs.clone(), *ts);
+                timed
+                    .iter()
+                    .map(|(ts, verdict)| (*ts, Step::new(verdict)))
+                    .chain(std::iter::once((ts, Step::new(&event))))
+                    .collect()
+            }));
+            let panicked = step.is_err();
+            steps.push(step.ok());
+            if panicked {
+                return steps;
+            }
+        }
+        let end = trace.0.last().map(|(ts, _)| *ts).unwrap_or_default();
+        let last = panic::catch_unwind(AssertUnwindSafe(|| {
+            monitor
+                .finish(end)
+                .iter()
+                .map(|(ts, verdict)| (*ts, Step::new(verdict)))
+                .collect()
+        }));
+        steps.push(last.ok());
+        steps
+    }
+
+    /// Shrinks the trace as long as the verdicts of both StreamIRs still differ
+    fn shrink(&self, before: &StreamIr, after: &StreamIr, mut trace: Trace) -> Trace {
+        let fails = |trace: &Trace| self.compare(before, after, trace).is_some();
+        let mut progress = true;
+        while progress {
+            progress = false;
+            let mut i = 0;
+            while i < trace.0.len() {
+                let mut candidate = trace.clone();
+                candidate.0.remove(i);
+                if fails(&candidate) {
+                    trace = candidate;
+                    progress = true;
+                } else {
+                    i += 1;
+                }
+            }
+            for i in 0..trace.0.len() {
+                for j in 0..trace.0[i].1 .0.len() {
+                    if trace.0[i].1 .0[j].is_none() {
+                        continue;
+                    }
+                    let mut candidate = trace.clone();
+                    candidate.0[i].1 .0[j] = None;
+                    if fails(&candidate) {
+                        trace = candidate;
+                        progress = true;
+                    }
+                }
+            }
+        }
+        trace
+    }
+}
+
+/// The verdict of a single evaluation cycle, independent of the order of changes
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Step {
+    inputs: Vec<(InputReference, Value)>,
+    outputs: Vec<(OutputReference, Vec<Change>)>,
+}
+
+impl Step {
+    fn new(verdict: &TotalIncremental) -> Self {
+        Self {
+            inputs: verdict
+                .sorted_inputs()
+                .into_iter()
+                .map(|(i, v)| (i, v.clone()))
+                .collect(),
+            outputs: verdict
+                .sorted_outputs()
+                .into_iter()
+                .map(|(o, changes)| (o, changes.into_iter().cloned().sorted().collect()))
+                .collect(),
+        }
+    }
+}
+
+/// Generates random traces with values of the types of the input streams
+#[derive(Debug, Clone)]
+pub struct TraceGenerator {
+    types: Vec<Type>,
+    state: u64,
+}
+
+impl TraceGenerator {
+    /// Constructs a generator for the input streams of the StreamIR
+    pub fn new(ir: &StreamIr, seed: u64) -> Self {
+        let types = ir
+            .inputs()
+            .sorted()
+            .map(|i| ir.stream_memory(StreamReference::In(i)).ty.clone())
+            .collect();
+        Self {
+            types,
+            // the state of the generator must not be zero
+            state: seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1,
+        }
+    }
+
+    /// Generates a trace with the given number of events and increasing timestamps
+    ///
+    /// Each event provides a new value for each input stream with a probability of one half.
+    /// Numeric values are chosen from a small range around zero to avoid overflows.
+    pub fn generate(&mut self, length: usize) -> Trace {
+        let mut ts = Duration::ZERO;
+        let events = (0..length)
+            .map(|_| {
+                ts += Duration::from_millis(100 * (1 + self.next() % 10));
+                let inputs = self
+                    .types
+                    .clone()
+                    .iter()
+                    .map(|ty| (self.next() & 1 == 0).then(|| self.value(ty)).flatten())
+                    .collect();
+                (ts, Inputs(inputs))
+            })
+            .collect();
+        Trace(events)
+    }
+
+    /// Returns a random value of the given type, or None if values of the type can not be generated
+    fn value(&mut self, ty: &Type) -> Option<Value> {
+        let value = match ty {
+            Type::Bool => Value::Bool(self.next() & 1 == 0),
+            Type::UInt(_) => Value::Unsigned(self.next() % 11),
+            Type::Int(_) => Value::Signed((self.next() % 21) as i64 - 10),
+            Type::Float32 | Type::Float64 => {
+                Value::Float(NotNan::new((self.next() % 41) as f64 / 2.0 - 10.0).unwrap())
+            }
+            Type::String => Value::Str(["", "a", "b"][(self.next() % 3) as usize].into()),
+            Type::Tuple(inner) => Value::Tuple(
+                inner
+                    .iter()
+                    .map(|ty| self.value(ty))
+                    .collect::<Option<_>>()?,
+            ),
+            Type::Option(_) | Type::Fixed(_) | Type::UFixed(_) | Type::Bytes => return None,
+        };
+        Some(value)
+    }
+
+    /// Returns the next pseudo-random number (xorshift64*)
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+}
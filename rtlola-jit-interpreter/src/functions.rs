@@ -0,0 +1,42 @@
+//! Native implementations of the external functions of a specification
+
+use std::{collections::HashMap, rc::Rc};
+
+use crate::value::Value;
+
+/// The implementation of an external function, receiving the values of the arguments
+pub type ExternalFunction = Rc<dyn Fn(&[Value]) -> Value>;
+
+#[derive(Clone, Default)]
+/// A table mapping the names of external functions to their implementation
+///
+/// External functions must be pure, i.e., their result may only depend on their arguments.
+pub struct ExternalFunctions(HashMap<String, ExternalFunction>);
+
+impl ExternalFunctions {
+    /// Constructs an empty table
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the implementation of the external function with the given name
+    pub fn register(
+        mut self,
+        name: impl Into<String>,
+        f: impl Fn(&[Value]) -> Value + 'static,
+    ) -> Self {
+        self.0.insert(name.into(), Rc::new(f));
+        self
+    }
+
+    /// Returns the implementation of the external function with the given name
+    pub(crate) fn get(&self, name: &str) -> Option<ExternalFunction> {
+        self.0.get(name).cloned()
+    }
+}
+
+impl std::fmt::Debug for ExternalFunctions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.0.keys()).finish()
+    }
+}
@@ -17,19 +17,24 @@ use std::time::Duration;
 
 use bit_set::BitSet;
 use closuregen::{statements::CompiledStmt, Closuregen, EvaluationContext, InstanceCollection};
+pub use functions::{ExternalFunction, ExternalFunctions};
 use memory::Memory;
 use schedule::{DeadlineEvent, Schedule};
 use streamir_lib::{
     formatter::statements::StmtFormatter,
-    ir::StreamIr,
-    optimize, optimize_all,
-    rewrite_rules::{EvaluateGuards, FastGuards},
+    ir::{OutputReference, StreamIr},
+    optimize,
+    pipeline::Pipeline,
+    rewrite_rules::{EvaluateGuards, RewriteError},
 };
-use value::Value;
+use thiserror::Error;
+pub use value::Value;
 use verdict::{TotalIncremental, Verdict, VerdictFactory};
 
 mod closuregen;
 pub mod csv;
+pub mod differential;
+mod functions;
 mod memory;
 mod schedule;
 #[cfg(test)]
@@ -47,6 +52,17 @@ type Time = Duration;
 /// The list is sorted according to the stream reference index.
 pub struct Inputs(pub Vec<Option<Value>>);
 
+#[derive(Debug, Error)]
+/// An error that can happen when building a [Monitor]
+pub enum BuildError {
+    #[error("rewriting failed: {0}")]
+    /// Rewriting the event-based or time-based fragment failed
+    Rewrite(#[from] RewriteError),
+    #[error("no implementation for the external functions {}", .0.join(", "))]
+    /// The StreamIR calls external functions without an implementation
+    MissingFunctions(Vec<String>),
+}
+
 /// Represents the main monitor struct holding the state of the monitor between evaluation cycles
 pub struct Monitor {
     /// Holds current and old values of streams and windows
@@ -88,7 +104,30 @@ impl Monitor {
     ///
     /// Performs partial evaluation to split the IR into event-based and time-based fragment,
     /// and performs JIT compilation of both parts.
-    pub fn build(ir: StreamIr, optimized: bool) -> Self {
+    pub fn build(ir: StreamIr, optimized: bool) -> Result<Self, BuildError> {
+        Self::build_with_functions(ir, optimized, ExternalFunctions::default())
+    }
+
+    /// Construct a new monitor for the given StreamIR using the given implementations of external functions
+    ///
+    /// Fails if the StreamIR calls an external function without an implementation.
+    pub fn build_with_functions(
+        ir: StreamIr,
+        optimized: bool,
+        functions: ExternalFunctions,
+    ) -> Result<Self, BuildError> {
+        let pipeline = Pipeline::preset(if optimized { "all" } else { "jit" }).unwrap();
+        Self::build_with_pipeline(ir, &pipeline, functions)
+    }
+
+    /// Construct a new monitor for the given StreamIR, applying the given pipeline to the event-based and time-based fragment
+    ///
+    /// Fails if the StreamIR calls an external function without an implementation.
+    pub fn build_with_pipeline(
+        ir: StreamIr,
+        pipeline: &Pipeline,
+        functions: ExternalFunctions,
+    ) -> Result<Self, BuildError> {
         let schedule = Schedule::new(&ir);
         let verdict_factory = VerdictFactory::new(&ir);
         let num_inputs = ir.num_inputs();
@@ -98,22 +137,18 @@ impl Monitor {
         let event_based = optimize(
             ir.clone(),
             vec![Box::new(EvaluateGuards::only_event_based())],
-        )
-        .unwrap();
+        )?;
 
         // optimize event-based fragment based on argument
-        let event_based = if optimized {
-            optimize_all(event_based).unwrap()
-        } else {
-            // To be able to compare to the old interpreter, we always want fast guards
-            optimize(event_based, vec![Box::new(FastGuards)]).unwrap()
-        };
+        let event_based = pipeline.run(event_based)?;
 
         let StreamIr {
             stmt,
             sr2memory,
             wref2window,
             lref2lfreq,
+            sources,
+            overflow,
             ..
         } = event_based;
 
@@ -123,17 +158,15 @@ impl Monitor {
             wref2window,
             lref2lfreq,
             schedule.static_schedule.clone(),
-        );
+            functions,
+        )
+        .with_sources(sources)
+        .with_overflow(overflow);
         let event_prog = event_clouregen.stmt(stmt);
 
         //  Partially evaluate the (original) StreamIR for the time-based fragment
-        let time_based = optimize(ir, vec![Box::new(EvaluateGuards::only_time_based())]).unwrap();
-        let time_based = if optimized {
-            optimize_all(time_based).unwrap()
-        } else {
-            // To be able to compare to the old interpreter, we always want fast guards
-            optimize(time_based, vec![Box::new(FastGuards)]).unwrap()
-        };
+        let time_based = optimize(ir, vec![Box::new(EvaluateGuards::only_time_based())])?;
+        let time_based = pipeline.run(time_based)?;
 
         let StreamIr {
             stmt,
@@ -146,6 +179,10 @@ impl Monitor {
         // So we just reuse the old StreamIR information for compilating the time-based fragment
         let timed_closuregen = event_clouregen.with_event::<DeadlineEvent>();
         let timed_prog = timed_closuregen.stmt(stmt);
+        let missing_functions = timed_closuregen.missing_functions.take();
+        if !missing_functions.is_empty() {
+            return Err(BuildError::MissingFunctions(missing_functions));
+        }
         // And construct the memory (for the time-based fragment, but the memory information is identical to the event-based fragment).
         let memory = timed_closuregen.memory();
 
@@ -159,7 +196,7 @@ impl Monitor {
             .map(|_| InstanceCollection::new())
             .collect();
 
-        Self {
+        Ok(Self {
             memory,
             event_prog,
             timed_prog,
@@ -172,7 +209,15 @@ impl Monitor {
             instances,
             schedule,
             verdict_factory,
-        }
+        })
+    }
+
+    /// Returns the number of activations by which the values of the output in the verdicts lag behind
+    ///
+    /// A stream accessing future values is delayed: a verdict containing a new value of a stream with delay `n`
+    /// holds the value the stream had `n` activations earlier in the specification.
+    pub fn delay(&self, sr: OutputReference) -> u32 {
+        self.verdict_factory.delay(sr)
     }
 
     /// Update the monitor with a new event providing new values to input streams and returns the [Verdict].
@@ -104,14 +104,12 @@ impl From<&IrMemory> for ParameterizedStreamBuffer {
             StreamMemory::NoMemory | StreamMemory::Static(_) | StreamMemory::Dynamic { .. } => {
                 unreachable!("Streambuffer invalid for Parameterized Stream")
             }
-            StreamMemory::Instances {
-                buffer,
-                parameter: _,
-            } => {
+            StreamMemory::Instances { buffer, bound, .. } => {
                 let memory_bound = buffer.bound().unwrap();
+                let capacity = bound.unwrap_or_default();
                 Self {
-                    instances: HashMap::new(),
-                    alive_instances: HashSet::new(),
+                    instances: HashMap::with_capacity(capacity),
+                    alive_instances: HashSet::with_capacity(capacity),
                     memory_bound,
                 }
             }
@@ -281,6 +279,8 @@ pub(crate) struct Memory {
     inputs: Vec<UnparameterizedStreamBuffer>,
     parameterized_outputs: Vec<ParameterizedStreamBuffer>,
     unparameterized_outputs: Vec<UnparameterizedStreamBuffer>,
+    /// The number of activations each unparameterized output still skips due to its delay
+    warmup: Vec<u32>,
     pub(crate) sliding_windows: Vec<Box<dyn SlidingWindowTrait>>,
     pub(crate) instanced_sliding_windows: Vec<HashMap<Rc<Instance>, Box<dyn SlidingWindowTrait>>>,
     instance_windows: Vec<Box<dyn InstanceWindowTrait>>,
@@ -303,6 +303,22 @@ impl Memory {
         self.unparameterized_outputs[sr].replace_value(value);
     }
 
+    pub(crate) fn is_warming_up(&self, sr: usize) -> bool {
+        self.warmup[sr] > 0
+    }
+
+    /// Consumes one activation of a delayed stream that is still warming up
+    ///
+    /// Returns false if the stream is already warmed up and has to be evaluated.
+    pub(crate) fn warm_up(&mut self, sr: usize) -> bool {
+        if self.warmup[sr] > 0 {
+            self.warmup[sr] -= 1;
+            true
+        } else {
+            false
+        }
+    }
+
     pub(crate) fn shift_output_instance(&mut self, sr: usize, instance: &Rc<Instance>) {
         self.parameterized_outputs[sr].push_instance_value(instance)
     }
@@ -440,6 +456,10 @@ impl<E: Event + 'static> Closuregen<E> {
                 OutputReference::Unparameterized(i) => itertools::Either::Left((i, mem)),
                 OutputReference::Parameterized(i) => itertools::Either::Right((i, mem)),
             });
+        let unparameterized_outputs = unparameterized_outputs
+            .into_iter()
+            .sorted_by_key(|(i, _mem)| *i)
+            .collect::<Vec<_>>();
         Memory {
             inputs: inputs
                 .into_iter()
@@ -451,9 +471,12 @@ impl<E: Event + 'static> Closuregen<E> {
                 .sorted_by_key(|(i, _mem)| *i)
                 .map(|(_i, mem)| ParameterizedStreamBuffer::from(mem))
                 .collect(),
+            warmup: unparameterized_outputs
+                .iter()
+                .map(|(_i, mem)| mem.delay)
+                .collect(),
             unparameterized_outputs: unparameterized_outputs
                 .into_iter()
-                .sorted_by_key(|(i, _mem)| *i)
                 .map(|(_i, mem)| UnparameterizedStreamBuffer::from(mem))
                 .collect(),
             sliding_windows,
@@ -12,8 +12,8 @@ fn setup(spec: &str) -> (Monitor, Monitor) {
     let config = ParserConfig::for_string(spec.to_string());
     let streamir = parse(&config).context("parsing spec").unwrap();
     (
-        Monitor::build(streamir.clone(), true),
-        Monitor::build(streamir, false),
+        Monitor::build(streamir.clone(), true).unwrap(),
+        Monitor::build(streamir, false).unwrap(),
     )
 }
 
@@ -178,13 +178,15 @@ macro_rules! accept_event {
 mod unparameterized {
     use std::time::Duration;
 
+    use crate::csv::CsvVerdictSink;
     use crate::tests::accept_event;
     use crate::tests::setup;
-    use crate::Inputs;
     use crate::Value::{self, *};
+    use crate::{BuildError, ExternalFunctions, Inputs, Monitor};
     use ordered_float::NotNan;
 
-    use streamir_lib::ir::OutputReference;
+    use streamir_lib::ir::{OutputReference, OverflowPolicy, StreamIr};
+    use streamir_lib::{parse, ParserConfig};
 
     use crate::verdict::{Change, TotalIncremental};
 
@@ -339,6 +341,271 @@ mod unparameterized {
         )
     }
 
+    #[test]
+    fn future_offset_lookup() {
+        let spec = "input a: UInt8\n\
+        output b := a.offset(by: 1).defaults(to: 0) + a\n\
+        output c := b";
+        let (mut optimized, mut unoptimized) = setup(spec);
+        let ts = Duration::from_secs_f64(0.0);
+        let event = vec![Some(Unsigned(1))];
+        let verdict: Vec<Option<Value>> = vec![Option::None, Option::None];
+        accept_event!(optimized, unoptimized, ts, event, verdict);
+        let ts = Duration::from_secs_f64(0.1);
+        let event = vec![Some(Unsigned(2))];
+        let verdict = vec![Some(Unsigned(3)), Some(Unsigned(3))];
+        accept_event!(optimized, unoptimized, ts, event, verdict);
+        let ts = Duration::from_secs_f64(0.2);
+        let event = vec![Some(Unsigned(5))];
+        let verdict = vec![Some(Unsigned(7)), Some(Unsigned(7))];
+        accept_event!(optimized, unoptimized, ts, event, verdict);
+    }
+
+    #[test]
+    fn future_offset_delays() {
+        let spec = "input a: UInt8\n\
+        output b := a.offset(by: 1).defaults(to: 0) + a\n\
+        output c := a";
+        let config = ParserConfig::for_string(spec.to_string());
+        let ir = parse(&config).unwrap();
+        let b = ir.stream_by_name("b").unwrap();
+        let c = ir.stream_by_name("c").unwrap();
+        let monitor = Monitor::build(ir.clone(), false).unwrap();
+        assert_eq!(monitor.delay(b.out_idx()), 1);
+        assert_eq!(monitor.delay(c.out_idx()), 0);
+
+        let mut csv = Vec::new();
+        CsvVerdictSink::new(&mut csv, &ir, &[b, c]).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap(), "b.offset(by: -1),c,time\n");
+    }
+
+    #[test]
+    fn future_offset_by_delay() {
+        use streamir_lib::ir::{
+            expressions::{Expr, ExprKind, Operator},
+            Offset, Stmt, StreamAccessKind,
+        };
+
+        fn eval(stmt: &Stmt, sr: OutputReference) -> Option<&Expr> {
+            match stmt {
+                Stmt::Eval {
+                    sr: target, with, ..
+                } if *target == sr => Some(with),
+                Stmt::Seq(stmts) | Stmt::Parallel(stmts) => {
+                    stmts.iter().find_map(|stmt| eval(stmt, sr))
+                }
+                Stmt::If(if_stmt) => {
+                    eval(if_stmt.cons(), sr).or_else(|| if_stmt.alt().and_then(|alt| eval(alt, sr)))
+                }
+                _ => None,
+            }
+        }
+
+        let spec = "input a: UInt8\n\
+        output b := a.offset(by: 1).defaults(to: 0) + a";
+        let config = ParserConfig::for_string(spec.to_string());
+        let ir = parse(&config).unwrap();
+        let a = ir.stream_by_name("a").unwrap();
+        let b = ir.stream_by_name("b").unwrap();
+        let (target, accesses) = &ir.accesses[&b][0];
+        assert_eq!(*target, a);
+        let mut kinds = accesses.iter().map(|(_, kind)| *kind).collect::<Vec<_>>();
+        kinds.sort_by_key(|kind| *kind == StreamAccessKind::Sync);
+        assert_eq!(
+            kinds,
+            vec![
+                StreamAccessKind::Offset(Offset::Past(1)),
+                StreamAccessKind::Sync
+            ]
+        );
+
+        // b is delayed by exactly the future offset, so the future access reads the current value of a
+        let with = eval(&ir.stmt, b.out_idx()).unwrap();
+        let ExprKind::BinaryOperation(Operator::Add, lhs, rhs) = &with.kind else {
+            panic!("expected an addition, found {with:?}");
+        };
+        assert!(matches!(lhs.kind, ExprKind::SyncStreamAccess { target, .. } if target == a));
+        assert!(matches!(
+            rhs.kind,
+            ExprKind::OffsetStreamAccess { target, offset: 1, .. } if target == a
+        ));
+    }
+
+    #[test]
+    fn optional_stream() {
+        let spec = "input a: UInt8\n\
+        output b := a.offset(by: -1)\n\
+        output c := b.defaults(to: 0)";
+        let (mut optimized, mut unoptimized) = setup(spec);
+        let ts = Duration::from_secs_f64(0.0);
+        let event = vec![Some(Unsigned(1))];
+        let verdict = vec![Some(None), Some(Unsigned(0))];
+        accept_event!(optimized, unoptimized, ts, event, verdict);
+        let ts = Duration::from_secs_f64(0.1);
+        let event = vec![Some(Unsigned(2))];
+        let verdict = vec![Some(Unsigned(1)), Some(Unsigned(1))];
+        accept_event!(optimized, unoptimized, ts, event, verdict);
+    }
+
+    #[test]
+    fn external_functions() {
+        let text = "input in0 a: Float(64) = static(single);
+output out0 b: Float(64) = static(single);
+output out1 c: Int(64) = static(single);
+stmt seq {
+    input in0;
+    if @in0 then
+        seq {
+            eval_0 out0 with extern<Float(64)> scale(floor(in0), 3.0f64);
+            eval_0 out1 with clamp(signum(cast<Int(64)>(in0) - 2i64) * 5i64, -3i64, 3i64)
+        }
+    fi
+}";
+        let ir = StreamIr::from_text(text).unwrap();
+        let functions = ExternalFunctions::new().register("scale", |args| match args {
+            [Float(x), Float(y)] => Float(*x * *y),
+            _ => unreachable!(),
+        });
+        let mut optimized =
+            Monitor::build_with_functions(ir.clone(), true, functions.clone()).unwrap();
+        let mut unoptimized = Monitor::build_with_functions(ir, false, functions).unwrap();
+        let ts = Duration::from_secs_f64(0.0);
+        let event = vec![Some(Float(NotNan::try_from(1.5).unwrap()))];
+        let verdict = vec![
+            Some(Float(NotNan::try_from(3.0).unwrap())),
+            Some(Signed(-3)),
+        ];
+        accept_event!(optimized, unoptimized, ts, event, verdict);
+        let ts = Duration::from_secs_f64(0.1);
+        let event = vec![Some(Float(NotNan::try_from(4.5).unwrap()))];
+        let verdict = vec![
+            Some(Float(NotNan::try_from(12.0).unwrap())),
+            Some(Signed(3)),
+        ];
+        accept_event!(optimized, unoptimized, ts, event, verdict);
+    }
+
+    #[test]
+    fn missing_external_function() {
+        let text = "input in0 a: Float(64) = static(single);
+output out0 b: Float(64) = static(single);
+stmt seq {
+    input in0;
+    if @in0 then
+        eval_0 out0 with extern<Float(64)> scale(in0, 3.0f64)
+    fi
+}";
+        let ir = StreamIr::from_text(text).unwrap();
+        let Err(BuildError::MissingFunctions(missing)) = Monitor::build(ir, false) else {
+            panic!("expected a missing external function");
+        };
+        assert_eq!(missing, vec!["scale".to_owned()]);
+    }
+
+    #[test]
+    fn float_signum() {
+        let text = "input in0 a: Float(64) = static(single);
+output out0 b: Float(64) = static(single);
+stmt seq {
+    input in0;
+    if @in0 then eval_0 out0 with signum(in0) fi
+}";
+        let ir = StreamIr::from_text(text).unwrap();
+        let mut optimized = Monitor::build(ir.clone(), true).unwrap();
+        let mut unoptimized = Monitor::build(ir, false).unwrap();
+        for (i, (value, signum)) in [(0.0, 0.0), (-0.0, 0.0), (2.5, 1.0), (-2.5, -1.0)]
+            .into_iter()
+            .enumerate()
+        {
+            let ts = Duration::from_secs(i as u64);
+            let event = vec![Some(Float(NotNan::new(value).unwrap()))];
+            let verdict = vec![Some(Float(NotNan::new(signum).unwrap()))];
+            accept_event!(optimized, unoptimized, ts, event, verdict);
+        }
+    }
+
+    #[test]
+    fn float_functions_without_result() {
+        for with in ["pow(in0, 0.5f64)", "log(in0, 2.0f64)", "sqrt(in0)"] {
+            let ir = StreamIr::from_text(&format!(
+                "input in0 a: Float(64) = static(single);
+output out0 x: Float(64) = static(single);
+source \"spec.lola\";
+stmt seq {{
+    input in0;
+    eval_0 out0 with {with} at 2:10
+}}"
+            ))
+            .unwrap();
+            let mut monitor = Monitor::build(ir, false).unwrap();
+            let payload = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                let input = Float(NotNan::new(-1.0).unwrap());
+                monitor.accept_event(Inputs(vec![Some(input)]), Duration::ZERO)
+            }))
+            .unwrap_err();
+            let msg = payload.downcast_ref::<String>().unwrap();
+            assert!(
+                msg.starts_with("evaluating x at spec.lola:2:10: the result of")
+                    && msg.ends_with("is not a number"),
+                "{msg}"
+            );
+        }
+    }
+
+    #[test]
+    fn overflow_policies() {
+        let text = "input in0 a: UInt(8) = static(single);
+input in1 i: Int(8) = static(single);
+output out0 b: UInt(8) = static(single);
+output out1 c: Int(8) = static(single);
+stmt seq {
+    par {
+        input in0;
+        input in1
+    };
+    par {
+        eval_0 out0 with in0 + 200u8;
+        eval_0 out1 with -in1
+    }
+}";
+        let ir = StreamIr::from_text(text).unwrap();
+        let event = vec![Some(Unsigned(100)), Some(Signed(-128))];
+        for (policy, verdict) in [
+            (OverflowPolicy::Wrap, [Unsigned(44), Signed(-128)]),
+            (OverflowPolicy::Saturate, [Unsigned(255), Signed(127)]),
+        ] {
+            let ir = StreamIr {
+                overflow: policy,
+                ..ir.clone()
+            };
+            let mut optimized = Monitor::build(ir.clone(), true).unwrap();
+            let mut unoptimized = Monitor::build(ir, false).unwrap();
+            let verdict = verdict.map(Some);
+            accept_event!(
+                optimized,
+                unoptimized,
+                Duration::ZERO,
+                event.clone(),
+                verdict
+            );
+        }
+
+        let ir = StreamIr {
+            overflow: OverflowPolicy::Trap,
+            ..ir
+        };
+        let mut monitor = Monitor::build(ir, false).unwrap();
+        let payload = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let event = vec![Some(Unsigned(100)), Some(Signed(-5))];
+            monitor.accept_event(Inputs(event), Duration::ZERO)
+        }))
+        .unwrap_err();
+        assert_eq!(
+            payload.downcast_ref::<String>().unwrap(),
+            "attempt to add with overflow"
+        );
+    }
+
     #[test]
     fn get_fresh_hold_lookup() {
         let spec = "input a: UInt8\n\
@@ -1524,3 +1791,222 @@ mod parameterized {
         );
     }
 }
+
+mod differential {
+    use std::{collections::HashMap, time::Duration};
+
+    use streamir_lib::{
+        ir::{
+            expressions::{Expr, ExprKind, Operator},
+            memory::Memory,
+            LivetimeEquivalences, StreamIr, StreamReference,
+        },
+        rewrite_rules::{
+            ChangeSet, CombineIf, CombineIterate, CombineNestedIf, CombineSeq, ImpliedGuards,
+            LoopInvariantCodeMotion, MoveIfOutside, RemoveIfs, RewriteError, RewriteRule,
+            SimplifyGuard,
+        },
+    };
+
+    use crate::{
+        differential::{DifferentialError, DifferentialTester},
+        value::Value,
+        Inputs, Monitor,
+    };
+
+    const TEXT: &str = "input in0 a: UInt(64) = static(single);
+input in1 b: UInt(64) = static(single);
+output out0 x: UInt(64) = static(single);
+output pout0 p(p: UInt(64)): UInt(64) = instances(single);
+output pout1 q(p: UInt(64)): UInt(64) = instances(single);
+livetime [inputs, out0] [pout0, pout1];
+stmt seq {
+    par {
+        if @in0 then
+            input in0
+        fi;
+        if @in1 then
+            input in1
+        fi
+    };
+    if @in0 && @in1 then
+        if @in0 then
+            eval_0 out0 with in0 + in1
+        fi
+    fi;
+    if @in0 then
+        par {
+            spawn pout0 with (in0);
+            spawn pout1 with (in0)
+        }
+    fi;
+    iterate [pout0]
+        if @in1 then
+            eval_0 pout0 with param(pout0, 0) + in1
+        fi;
+    iterate [pout1]
+        if @in1 then
+            eval_0 pout1 with param(pout1, 0) * in1
+        fi;
+    if @in1 then
+        seq {
+            assign [pout0] (in1)
+                close pout0;
+            assign [pout1] (in1)
+                close pout1
+        }
+    fi
+}";
+
+    #[derive(Debug)]
+    struct AddToMul;
+
+    impl RewriteRule for AddToMul {
+        fn rewrite_expr(
+            &self,
+            expr: Expr,
+            _memory: &HashMap<StreamReference, Memory>,
+            _liveness_equivalences: &LivetimeEquivalences,
+        ) -> Result<(Expr, ChangeSet), RewriteError> {
+            match expr.kind {
+                ExprKind::BinaryOperation(Operator::Add, lhs, rhs) => Ok((
+                    Expr {
+                        ty: expr.ty,
+                        kind: ExprKind::BinaryOperation(Operator::Mul, lhs, rhs),
+                        span: expr.span,
+                    },
+                    ChangeSet::local_change(),
+                )),
+                kind => Ok((
+                    Expr {
+                        ty: expr.ty,
+                        kind,
+                        span: expr.span,
+                    },
+                    ChangeSet::default(),
+                )),
+            }
+        }
+    }
+
+    #[test]
+    fn rules_preserve_verdicts() {
+        let ir = StreamIr::from_text(TEXT).unwrap();
+        let rules: Vec<Box<dyn RewriteRule>> = vec![
+            Box::new(ImpliedGuards),
+            Box::new(SimplifyGuard),
+            Box::new(RemoveIfs),
+            Box::new(CombineIf),
+            Box::new(CombineSeq),
+            Box::new(MoveIfOutside),
+            Box::new(CombineNestedIf),
+            Box::new(CombineIterate),
+        ];
+        let optimized = DifferentialTester::new()
+            .with_seed(42)
+            .check(ir.clone(), rules)
+            .unwrap();
+        assert_ne!(optimized.to_text(), ir.to_text());
+    }
+
+    #[test]
+    fn loop_invariants_preserve_verdicts() {
+        let ir = StreamIr::from_text(TEXT).unwrap();
+        let optimized = DifferentialTester::new()
+            .with_seed(42)
+            .check(ir, vec![Box::new(LoopInvariantCodeMotion)])
+            .unwrap();
+        let text = optimized.to_text();
+        // the guards are checked once, the bare accesses to `in1` are not bound to variables
+        assert!(!text.contains("iterate [pout0]\n        if @in1"), "{text}");
+        assert!(!text.contains("let var0 = in1"), "{text}");
+    }
+
+    #[test]
+    fn panics_cite_the_specification() {
+        let ir = StreamIr::from_text(
+            "input in0 a: UInt(64) = static(single);
+output out0 x: UInt(64) = static(single);
+source \"spec.lola\";
+stmt seq {
+    input in0;
+    eval_0 out0 with 10u64 / in0 at 2:10
+}",
+        )
+        .unwrap();
+        let mut monitor = Monitor::build(ir, false).unwrap();
+        let payload = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            monitor.accept_event(Inputs(vec![Some(Value::Unsigned(0))]), Duration::ZERO)
+        }))
+        .unwrap_err();
+        let msg = payload.downcast_ref::<String>().unwrap();
+        assert!(
+            msg.starts_with("evaluating x at spec.lola:2:10: attempt to divide by zero"),
+            "{msg}"
+        );
+    }
+
+    #[derive(Debug)]
+    struct AddToDivByZero;
+
+    impl RewriteRule for AddToDivByZero {
+        fn rewrite_expr(
+            &self,
+            expr: Expr,
+            _memory: &HashMap<StreamReference, Memory>,
+            _liveness_equivalences: &LivetimeEquivalences,
+        ) -> Result<(Expr, ChangeSet), RewriteError> {
+            match expr.kind {
+                ExprKind::BinaryOperation(Operator::Add, lhs, rhs) => {
+                    let zero = Expr {
+                        ty: rhs.ty.clone(),
+                        kind: ExprKind::BinaryOperation(Operator::Sub, rhs.clone(), rhs),
+                        span: expr.span,
+                    };
+                    Ok((
+                        Expr {
+                            ty: expr.ty,
+                            kind: ExprKind::BinaryOperation(Operator::Div, lhs, Box::new(zero)),
+                            span: expr.span,
+                        },
+                        ChangeSet::local_change(),
+                    ))
+                }
+                kind => Ok((
+                    Expr {
+                        ty: expr.ty,
+                        kind,
+                        span: expr.span,
+                    },
+                    ChangeSet::default(),
+                )),
+            }
+        }
+    }
+
+    #[test]
+    fn broken_rule() {
+        let ir = StreamIr::from_text(TEXT).unwrap();
+        let res = DifferentialTester::new().check(ir, vec![Box::new(AddToMul)]);
+        let Err(DifferentialError::Mismatch(mismatch)) = res else {
+            panic!("expected a mismatch, found {res:?}");
+        };
+        assert_eq!(mismatch.rule, "AddToMul");
+        // a single event with both inputs is the smallest trace evaluating `in0 + in1` and `param(pout0, 0) + in1`
+        assert_eq!(mismatch.trace.0.len(), 1, "{}", mismatch.trace);
+        assert_eq!(mismatch.step, 0);
+    }
+
+    #[test]
+    fn panicking_rule() {
+        let ir = StreamIr::from_text(TEXT).unwrap();
+        let res = DifferentialTester::new().check(ir, vec![Box::new(AddToDivByZero)]);
+        let Err(DifferentialError::Mismatch(mismatch)) = res else {
+            panic!("expected a mismatch, found {res:?}");
+        };
+        // the monitor after the rewriting panics on the first event evaluating a division
+        assert_eq!(mismatch.rule, "AddToDivByZero");
+        assert_eq!(mismatch.trace.0.len(), 1, "{}", mismatch.trace);
+        assert_eq!(mismatch.step, 0);
+    }
+}
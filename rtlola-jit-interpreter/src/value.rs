@@ -13,13 +13,21 @@ use Value::*;
 use crate::csv::ParseValueFn;
 
 #[derive(Clone, Eq, PartialEq, Hash, Debug)]
+/// A value of a stream during the execution of the monitor
 pub enum Value {
+    /// A boolean value
     Bool(bool),
+    /// An unsigned integer value
     Unsigned(u64),
+    /// A signed integer value
     Signed(i64),
+    /// A floating point value
     Float(NotNan<f64>),
+    /// A string value
     Str(String),
+    /// A tuple of values
     Tuple(Vec<Value>),
+    /// The absence of an optional value
     None,
 }
 
@@ -1,5 +1,6 @@
 //! The module contains the functionality to output the current monitoring state after each evaluation cycle
 
+use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
 
 use itertools::Itertools;
@@ -49,6 +50,8 @@ pub struct VerdictFactory {
     static_outputs: Vec<usize>,
     dynamic_outputs: Vec<usize>,
     parameterized_outputs: Vec<usize>,
+    /// The delays of the delayed outputs
+    delays: HashMap<OutputReference, u32>,
 }
 
 impl VerdictFactory {
@@ -67,8 +70,20 @@ impl VerdictFactory {
                 .parameterized_outputs()
                 .map(|sr| sr.parameterized_idx())
                 .collect(),
+            delays: ir
+                .outputs()
+                .filter_map(|sr| {
+                    let delay = ir.stream_memory(sr.sr()).delay;
+                    (delay > 0).then_some((sr, delay))
+                })
+                .collect(),
         }
     }
+
+    /// Returns the number of activations by which the values of the output in the verdicts lag behind
+    pub fn delay(&self, sr: OutputReference) -> u32 {
+        self.delays.get(&sr).copied().unwrap_or(0)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -1,16 +1,25 @@
-use std::path::PathBuf;
+use std::{collections::HashMap, path::PathBuf};
 
 use anyhow::Context;
 use clap::{Parser, ValueEnum};
 use itertools::Itertools;
 use rtlola2rust::{MainFunction, RustFormatter};
-use streamir_lib::{ir::StreamReference, optimize_all, parse, translate, ParserConfig};
+use streamir_lib::{
+    ir::{OverflowPolicy, StreamReference},
+    parse_with_external_functions,
+    pipeline::Pipeline,
+    rewrite_rules::{DeadStreamElimination, NarrowIntegers, Rewriter},
+    translate, ParserConfig,
+};
 
 #[derive(Parser)]
 struct Args {
     spec: PathBuf,
     #[clap(short, long)]
     optimize: bool,
+    #[clap(long, conflicts_with = "optimize")]
+    /// The rewriting pipeline to apply: the name of a preset, a list of rules, or `@` followed by the path of a pipeline file
+    pipeline: Option<Pipeline>,
     #[clap(short = 'd', long, default_value = ".")]
     output_dir: PathBuf,
     #[clap(long)]
@@ -22,6 +31,21 @@ struct Args {
     #[clap(long)]
     /// Only output the following streams
     output_streams: Vec<String>,
+    #[clap(long)]
+    /// Remove all streams that influence neither the triggers nor the output streams
+    remove_unobserved: bool,
+    #[clap(long)]
+    /// Store integer output streams with fewer bits wherever their values provably fit
+    narrow_integers: bool,
+    #[clap(long)]
+    /// How integer operations treat results that do not fit their type: `wrap` (the default), `saturate` or `trap`
+    overflow: Option<OverflowPolicy>,
+    #[clap(long = "external-function", value_name = "NAME=PATH")]
+    /// The path of the Rust function implementing the external function with the given name
+    external_functions: Vec<String>,
+    #[clap(long, value_enum)]
+    /// Print a report of the static memory footprint of the monitor to stdout
+    resource_report: Option<ReportFormat>,
 }
 
 #[derive(Clone, Copy, ValueEnum)]
@@ -32,24 +56,52 @@ enum Verbosity {
     Silent,
 }
 
+#[derive(Clone, Copy, ValueEnum)]
+enum ReportFormat {
+    Text,
+    #[cfg(feature = "serde")]
+    Json,
+}
+
 fn main() -> anyhow::Result<()> {
     let Args {
         spec,
         optimize,
+        pipeline,
         output_dir,
         overwrite,
         main,
         verbosity,
         output_streams,
+        remove_unobserved,
+        narrow_integers,
+        overflow,
+        external_functions,
+        resource_report,
     } = Args::parse();
 
+    let external_functions = external_functions
+        .iter()
+        .map(|f| {
+            f.split_once('=')
+                .map(|(name, path)| (name.trim().to_owned(), path.trim().to_owned()))
+                .ok_or_else(|| anyhow::anyhow!("expected NAME=PATH, found {f}"))
+        })
+        .collect::<anyhow::Result<HashMap<_, _>>>()?;
+
     let config = ParserConfig::from_path(spec).context("Loading specification file")?;
-    let mut ir = parse(&config).context("parsing specification")?;
-    if optimize {
-        ir = optimize_all(ir).context("optimizing StreamIR")?;
+    let declared = external_functions.keys().cloned().collect::<Vec<_>>();
+    let mut ir =
+        parse_with_external_functions(&config, &declared).context("parsing specification")?;
+    if let Some(overflow) = overflow {
+        ir.set_overflow(overflow);
+    }
+    let pipeline = pipeline.or_else(|| optimize.then(|| Pipeline::preset("all").unwrap()));
+    if let Some(pipeline) = pipeline {
+        ir = pipeline.run(ir).context("optimizing StreamIR")?;
     }
 
-    let verdict_streams: Vec<StreamReference> = if !output_streams.is_empty() {
+    let mut verdict_streams: Vec<StreamReference> = if !output_streams.is_empty() {
         output_streams
             .iter()
             .flat_map(|s| s.split(','))
@@ -72,6 +124,49 @@ fn main() -> anyhow::Result<()> {
         }
     };
 
-    let formatter = RustFormatter::new(&ir, output_dir, overwrite, main, verdict_streams);
+    if remove_unobserved {
+        let names = verdict_streams
+            .iter()
+            .map(|sr| ir.name(*sr).to_owned())
+            .collect::<Vec<_>>();
+        let observed = verdict_streams
+            .iter()
+            .copied()
+            .chain(ir.triggers().map(StreamReference::Out))
+            .collect::<Vec<_>>();
+        let rule = DeadStreamElimination::new(&ir, observed);
+        ir = Rewriter::new(vec![Box::new(rule)])
+            .run(ir)
+            .context("removing unobserved streams")?;
+        // the remaining streams were renumbered
+        verdict_streams = names
+            .iter()
+            .map(|name| {
+                ir.sr2memory
+                    .iter()
+                    .find_map(|(sr, m)| (&m.name == name).then_some(*sr))
+                    .unwrap()
+            })
+            .collect();
+    }
+
+    if narrow_integers {
+        let rule = NarrowIntegers::new(&ir);
+        ir = Rewriter::new(vec![Box::new(rule)])
+            .run(ir)
+            .context("narrowing integer streams")?;
+    }
+
+    if let Some(format) = resource_report {
+        let report = ir.resource_report();
+        match format {
+            ReportFormat::Text => println!("{report}"),
+            #[cfg(feature = "serde")]
+            ReportFormat::Json => println!("{}", report.to_json()),
+        }
+    }
+
+    let formatter = RustFormatter::new(&ir, output_dir, overwrite, main, verdict_streams)
+        .with_external_functions(external_functions);
     translate(ir, formatter).context("generating rust code")
 }
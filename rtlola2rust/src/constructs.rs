@@ -6,10 +6,14 @@ use std::{
 use itertools::Itertools;
 use streamir_lib::{
     formatter::{
+        expressions::ExprFormatter,
         files::{FilesFormatter, Requirement},
         types::TypeFormatter,
     },
-    ir::{windows::WindowOperation, InputReference, OutputReference, StreamReference, Type},
+    ir::{
+        expressions::Expr, windows::WindowOperation, InputReference, OutputReference,
+        StreamReference, Type,
+    },
 };
 
 use crate::{error::MonitorError, RustFormatter};
@@ -40,6 +44,8 @@ pub enum RequirementKey {
     VerdictConstructor,
     VerdictDisplay,
     VerdictHeader,
+    VerdictDelays,
+    IntegerPow(Type, Type),
     ExprFunction(usize),
     ClearActivations,
     Cycle,
@@ -91,7 +97,8 @@ pub enum StreamAccessType {
 #[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
 pub enum StatementType {
     Input(InputReference),
-    Eval(OutputReference, usize),
+    /// The eval clause of a stream, and the variant of its expression
+    Eval(OutputReference, usize, usize),
 }
 
 impl FilesFormatter for RustFormatter {
@@ -513,6 +520,21 @@ impl RustFormatter {
             .collect()
     }
 
+    /// Returns the local variables that are used in `expr` as function arguments
+    pub(crate) fn variable_arguments(&self, expr: &Expr) -> Vec<(String, RustType)> {
+        expr.variables()
+            .into_iter()
+            .map(|(var, ty)| (self.variable(var, ty.clone()), RustType::from(ty)))
+            .collect()
+    }
+
+    /// Returns the values of the local variables that are passed to a function evaluating `expr`
+    pub(crate) fn variable_values(&self, expr: &Expr) -> impl Iterator<Item = String> {
+        self.variable_arguments(expr)
+            .into_iter()
+            .map(|(name, _)| format!("{name}.clone()"))
+    }
+
     pub(crate) fn format_duration(&self, duration: Duration) -> String {
         format!(
             "Duration::new({}, {})",
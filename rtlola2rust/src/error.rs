@@ -3,7 +3,7 @@ use streamir_lib::{
         files::{FilesFormatter, Requirement},
         names::GetStreamName,
     },
-    ir::StreamReference,
+    ir::{expressions::Operator, Overflow, StreamReference},
 };
 
 use crate::{constructs::RequirementKey, RustFormatter};
@@ -24,6 +24,20 @@ impl MonitorError {
         let stream_name = f.stream_name(stream);
         format!("{}::InstanceNotFound {{ stream: \"{stream_name}\", instance: format!(\"{{:?}}\", ({instance})) }}", f.error_name())
     }
+
+    pub(crate) fn overflow(op: Operator, f: &RustFormatter) -> String {
+        Self::add_requirement(f);
+        format!(
+            "{}::Overflow {{ message: \"{}\" }}",
+            f.error_name(),
+            Overflow(op)
+        )
+    }
+
+    pub(crate) fn division_by_zero(f: &RustFormatter) -> String {
+        Self::add_requirement(f);
+        format!("{}::DivisionByZero", f.error_name())
+    }
 }
 
 impl Requirement<RustFormatter> for MonitorError {
@@ -36,7 +50,9 @@ impl Requirement<RustFormatter> for MonitorError {
             "#[derive(Debug, Clone)]
 			pub enum {} {{
 			InstanceNotFound {{ stream: &'static str, instance: String }},
-			OutOfBoundsAccess {{ accessed_offset: usize, buffer_size: usize }}
+			OutOfBoundsAccess {{ accessed_offset: usize, buffer_size: usize }},
+			Overflow {{ message: &'static str }},
+			DivisionByZero
 		}}",
             formatter.error_name()
         )
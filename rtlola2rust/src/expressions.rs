@@ -4,6 +4,7 @@ use hold::HoldAccess;
 use is_fresh::IsFresh;
 use itertools::Itertools;
 use offset::OffsetAccess;
+use pow::IntegerPow;
 use streamir_lib::{
     formatter::{
         expressions::{
@@ -15,10 +16,10 @@ use streamir_lib::{
         types::TypeFormatter,
     },
     ir::{
-        expressions::{Expr, Function, Operator},
+        expressions::{Expr, Function, FunctionSignature, Operator},
         memory::Parameter,
         windows::{InstanceSelection, WindowKind},
-        StreamReference, Type, WindowReference,
+        OverflowPolicy, StreamReference, Type, WindowReference,
     },
 };
 use sync::SyncAccess;
@@ -31,6 +32,7 @@ pub(crate) mod get;
 mod hold;
 pub(crate) mod is_fresh;
 mod offset;
+mod pow;
 pub(crate) mod sync;
 
 impl DefaultExprFormatter for RustFormatter {
@@ -44,7 +46,7 @@ impl DefaultExprFormatter for RustFormatter {
         &self,
         sr: StreamReference,
         offset: u32,
-        default: Expr,
+        default: Option<Expr>,
         parameters: Vec<Expr>,
     ) -> String {
         let mut args = parameters
@@ -52,25 +54,32 @@ impl DefaultExprFormatter for RustFormatter {
             .map(|p| self.expr(p))
             .collect::<Vec<_>>();
         args.insert(0, offset.to_string());
-        let default = self.expr(default);
         let offset = OffsetAccess(sr);
         let offset = self.call_self_function(offset, &args);
-        format!("{offset}?.unwrap_or({default})")
+        self.access_default(sr, offset, default)
     }
 
-    fn hold_access(&self, sr: StreamReference, default: Expr, parameters: Vec<Expr>) -> String {
+    fn hold_access(
+        &self,
+        sr: StreamReference,
+        default: Option<Expr>,
+        parameters: Vec<Expr>,
+    ) -> String {
         let parameters: Vec<_> = parameters.into_iter().map(|p| self.expr(p)).collect();
         let hold = HoldAccess(sr);
-        let default = self.expr(default);
         let hold = self.call_self_function(hold, &parameters);
-        format!("{hold}?.unwrap_or({default})")
+        self.access_default(sr, hold, default)
     }
 
-    fn get_access(&self, sr: StreamReference, default: Expr, parameters: Vec<Expr>) -> String {
+    fn get_access(
+        &self,
+        sr: StreamReference,
+        default: Option<Expr>,
+        parameters: Vec<Expr>,
+    ) -> String {
         let parameters: Vec<_> = parameters.into_iter().map(|p| self.expr(p)).collect();
         let call = self.call_self_function(GetAccess(sr), &parameters);
-        let default = self.expr(default);
-        format!("{call}?.unwrap_or({default})")
+        self.access_default(sr, call, default)
     }
 
     fn is_fresh(&self, sr: StreamReference, parameters: Vec<Expr>) -> String {
@@ -195,6 +204,10 @@ impl DefaultExprFormatter for RustFormatter {
         }
     }
 
+    fn with_default(&self, expr: Expr, default: Expr) -> String {
+        format!("({}).unwrap_or({})", self.expr(expr), self.expr(default))
+    }
+
     fn if_then_else(&self, condition: Expr, consequence: Expr, alternative: Expr) -> String {
         let condition = self.expr(condition);
         let consequence = self.expr(consequence);
@@ -202,7 +215,19 @@ impl DefaultExprFormatter for RustFormatter {
         format!("(if {condition} {{ {consequence} }} else {{ {alternative} }})")
     }
 
+    fn unary(&self, op: Operator, operand: Expr) -> String {
+        if OverflowPolicy::governs(op) && matches!(operand.ty, Type::Int(_) | Type::UInt(_)) {
+            let ty = operand.ty.clone();
+            return self.integer_op(op, ty, self.expr(operand), None);
+        }
+        format!("({}{})", self.op(op), self.expr(operand))
+    }
+
     fn binary(&self, op: Operator, lhs: Expr, rhs: Expr) -> String {
+        if OverflowPolicy::governs(op) && matches!(lhs.ty, Type::Int(_) | Type::UInt(_)) {
+            let ty = lhs.ty.clone();
+            return self.integer_op(op, ty, self.expr(lhs), Some(rhs));
+        }
         match op {
             Operator::Pow => match (&lhs.ty, &rhs.ty) {
                 (Type::Float32 | Type::Float64, Type::Float32 | Type::Float64) => {
@@ -211,9 +236,6 @@ impl DefaultExprFormatter for RustFormatter {
                 (Type::Float32 | Type::Float64, Type::UInt(_) | Type::Int(_)) => {
                     format!("({}).powi({})", self.expr(lhs), self.expr(rhs))
                 }
-                (Type::UInt(_) | Type::Int(_), Type::UInt(_) | Type::Int(_)) => {
-                    format!("({}).pow({})", self.expr(lhs), self.expr(rhs))
-                }
                 _ => panic!("disallowed by type checker"),
             },
             _ => format!("({} {} {})", self.expr(lhs), self.op(op), self.expr(rhs)),
@@ -229,11 +251,49 @@ impl DefaultExprFormatter for RustFormatter {
             | Function::Cos
             | Function::Arccos
             | Function::Tan
-            | Function::Arctan => {
+            | Function::Arctan
+            | Function::Floor
+            | Function::Ceil
+            | Function::Round
+            | Function::Exp
+            | Function::Ln => {
                 let [lhs]: [Expr; 1] = args.try_into().unwrap();
                 format!("({}).{}()", self.expr(lhs), self.function(function))
             }
-            Function::Min | Function::Max => {
+            Function::Signum => {
+                let [arg]: [Expr; 1] = args.try_into().unwrap();
+                match arg.ty {
+                    // unsigned integers have no signum method
+                    Type::UInt(_) => format!("({}).min(1)", self.expr(arg)),
+                    // like the integers, a zero of either sign has the signum zero
+                    Type::Float32 | Type::Float64 => format!(
+                        "{{ let x = {}; if x == 0.0 {{ 0.0 }} else {{ x.signum() }} }}",
+                        self.expr(arg)
+                    ),
+                    _ => format!("({}).{}()", self.expr(arg), self.function(function)),
+                }
+            }
+            Function::Pow => {
+                let [lhs, rhs]: [Expr; 2] = args.try_into().unwrap();
+                ExprFormatter::binary(self, Operator::Pow, lhs, rhs)
+            }
+            Function::Clamp => {
+                let [value, min, max]: [Expr; 3] = args.try_into().unwrap();
+                // unlike the clamp method, this results in the minimum if it exceeds the maximum, as in the interpreter
+                format!(
+                    "({}).{}({}).{}({})",
+                    self.expr(value),
+                    self.function(Function::Min),
+                    self.expr(max),
+                    self.function(Function::Max),
+                    self.expr(min)
+                )
+            }
+            Function::External(_, _) => {
+                let args = args.into_iter().map(|arg| self.expr(arg)).join(", ");
+                format!("{}({args})", self.function(function))
+            }
+            Function::Min | Function::Max | Function::Log | Function::Atan2 => {
                 let [lhs, rhs]: [Expr; 2] = args.try_into().unwrap();
                 format!(
                     "({}).{}({})",
@@ -272,11 +332,97 @@ impl DefaultFunctionFormatter for RustFormatter {
     fn function_arctan(&self) -> String {
         "atan".into()
     }
+
+    fn function_external(&self, name: String, _signature: FunctionSignature) -> String {
+        self.external_functions
+            .get(&name)
+            .cloned()
+            .unwrap_or_else(|| {
+                self.unsupported(format!(
+                    "no implementation for the external function {name}"
+                ))
+            })
+    }
 }
 
 impl DefaultOperatorFormatter for RustFormatter {}
 
 impl RustFormatter {
+    /// Returns the code applying an arithmetic operator to integers, following the overflow policy of the specification
+    ///
+    /// The unary operator is given without a right-hand side.
+    /// Overflows under [OverflowPolicy::Trap] and divisions by zero are returned as an error of the monitor.
+    fn integer_op(&self, op: Operator, ty: Type, lhs: String, rhs: Option<Expr>) -> String {
+        let unsigned = matches!(ty, Type::UInt(_));
+        let Some(rhs) = rhs else {
+            let ty = self.ty(ty);
+            return match self.overflow {
+                OverflowPolicy::Wrap => format!("{ty}::wrapping_neg({lhs})"),
+                OverflowPolicy::Saturate if unsigned => {
+                    format!("{ty}::checked_neg({lhs}).unwrap_or(0)")
+                }
+                OverflowPolicy::Saturate => format!("{ty}::saturating_neg({lhs})"),
+                OverflowPolicy::Trap => format!(
+                    "{ty}::checked_neg({lhs}).ok_or({})?",
+                    MonitorError::overflow(op, self)
+                ),
+            };
+        };
+        if op == Operator::Pow {
+            let pow = IntegerPow {
+                ty,
+                exp: rhs.ty.clone(),
+            };
+            return format!("{}?", self.call_function(pow, &[lhs, self.expr(rhs)]));
+        }
+        let rhs = self.expr(rhs);
+        let ty = self.ty(ty);
+        let method = match op {
+            Operator::Add => "add",
+            Operator::Sub => "sub",
+            Operator::Mul => "mul",
+            Operator::Div => "div",
+            Operator::Rem => "rem",
+            _ => unreachable!("not governed by the overflow policy"),
+        };
+        // the operands of divisions are bound first, so that the divisor is evaluated once when checking it for zero
+        let args = match op {
+            Operator::Div | Operator::Rem => "lhs, rhs".to_string(),
+            _ => format!("{lhs}, {rhs}"),
+        };
+        let result = match (self.overflow, op) {
+            // the remainder of the smallest value and -1 is 0 for all policies
+            (_, Operator::Rem) => format!("{ty}::wrapping_rem({args})"),
+            (OverflowPolicy::Wrap, _) => format!("{ty}::wrapping_{method}({args})"),
+            (OverflowPolicy::Saturate, _) => format!("{ty}::saturating_{method}({args})"),
+            (OverflowPolicy::Trap, _) => format!(
+                "{ty}::checked_{method}({args}).ok_or({})?",
+                MonitorError::overflow(op, self)
+            ),
+        };
+        match op {
+            Operator::Div | Operator::Rem => format!(
+                "{{ let (lhs, rhs) = ({lhs}, {rhs}); if rhs == 0 {{ return Err({}); }} {result} }}",
+                MonitorError::division_by_zero(self)
+            ),
+            _ => result,
+        }
+    }
+
+    /// Returns the optional value of an access, or the value of the default if one is given
+    fn access_default(&self, sr: StreamReference, access: String, default: Option<Expr>) -> String {
+        // the values of an optional stream are optional themselves
+        let flatten = if matches!(self.lola_stream_type(sr), Type::Option(_)) {
+            ".flatten()"
+        } else {
+            ""
+        };
+        match default {
+            Some(default) => format!("{access}?{flatten}.unwrap_or({})", self.expr(default)),
+            None => format!("{access}?{flatten}"),
+        }
+    }
+
     pub(crate) fn get_stream_value(&self, sr: StreamReference, offset: &str) -> String {
         let stream = self.stream_name(sr);
         let mut res = format!("self.{}.{}", StreamMemoryStruct.argument_name(self), stream);
@@ -0,0 +1,118 @@
+use streamir_lib::{
+    formatter::types::TypeFormatter,
+    ir::{expressions::Operator, OverflowPolicy, Type},
+};
+
+use crate::{
+    constructs::{FunctionDefinition, RequirementKey},
+    error::MonitorError,
+    RustFormatter, RustType,
+};
+
+/// A function raising an integer to the power of an integer exponent, following the overflow policy of the specification
+///
+/// The exponent is not truncated, see [OverflowPolicy::binary] for the semantics.
+pub(crate) struct IntegerPow {
+    pub(crate) ty: Type,
+    pub(crate) exp: Type,
+}
+
+impl IntegerPow {
+    /// Returns the code computing the power for an exponent that does not fit into 32 bits
+    fn large_exponent(&self, f: &RustFormatter) -> String {
+        let ty = f.ty(self.ty.clone());
+        let signed = matches!(self.ty, Type::Int(_));
+        let minus_one = if signed {
+            "-1 if exp % 2 == 0 => Ok(1),\n-1 => Ok(-1),\n"
+        } else {
+            ""
+        };
+        match f.overflow {
+            OverflowPolicy::Wrap => format!(
+                "{{
+                let (mut base, mut exp, mut result) = (base, exp, 1);
+                while exp > 0 {{
+                if exp % 2 == 1 {{ result = {ty}::wrapping_mul(result, base); }}
+                base = {ty}::wrapping_mul(base, base);
+                exp /= 2;
+                }}
+                Ok(result)
+                }}"
+            ),
+            OverflowPolicy::Saturate => {
+                let min = if signed {
+                    format!("_ if base < 0 && exp % 2 == 1 => Ok({ty}::MIN),\n")
+                } else {
+                    String::new()
+                };
+                format!(
+                    "match base {{\n0 | 1 => Ok(base),\n{minus_one}{min}_ => Ok({ty}::MAX),\n}}"
+                )
+            }
+            OverflowPolicy::Trap => format!(
+                "match base {{\n0 | 1 => Ok(base),\n{minus_one}_ => Err({}),\n}}",
+                MonitorError::overflow(Operator::Pow, f)
+            ),
+        }
+    }
+}
+
+impl FunctionDefinition for IntegerPow {
+    fn name(&self, f: &RustFormatter) -> String {
+        format!("pow_{}_{}", f.ty(self.ty.clone()), f.ty(self.exp.clone()))
+    }
+
+    fn body(self, f: &RustFormatter) -> String {
+        let ty = f.ty(self.ty.clone());
+        let pow = match f.overflow {
+            OverflowPolicy::Wrap => format!("Ok({ty}::wrapping_pow(base, exp))"),
+            OverflowPolicy::Saturate => format!("Ok({ty}::saturating_pow(base, exp))"),
+            OverflowPolicy::Trap => format!(
+                "{ty}::checked_pow(base, exp).ok_or({})",
+                MonitorError::overflow(Operator::Pow, f)
+            ),
+        };
+        match self.exp {
+            Type::UInt(bits) if bits <= 32 => format!("let exp = u32::from(exp);\n{pow}"),
+            Type::UInt(_) => format!(
+                "match u32::try_from(exp) {{\nOk(exp) => {pow},\nErr(_) => {},\n}}",
+                self.large_exponent(f)
+            ),
+            _ => {
+                // the integer part of the reciprocal of the power
+                let reciprocal = match self.ty {
+                    Type::Int(_) => "1 => Ok(1),\n-1 if exp % 2 == 0 => Ok(1),\n-1 => Ok(-1),\n",
+                    _ => "1 => Ok(1),\n",
+                };
+                format!(
+                    "match u32::try_from(exp) {{\n\
+                    Ok(exp) => {pow},\n\
+                    Err(_) if exp < 0 => match base {{\n0 => Err({}),\n{reciprocal}_ => Ok(0),\n}},\n\
+                    Err(_) => {},\n\
+                    }}",
+                    MonitorError::division_by_zero(f),
+                    self.large_exponent(f)
+                )
+            }
+        }
+    }
+
+    fn arguments(&self, _f: &RustFormatter) -> Vec<(String, RustType)> {
+        vec![
+            ("base".into(), self.ty.clone().into()),
+            ("exp".into(), self.exp.clone().into()),
+        ]
+    }
+
+    fn key(&self) -> RequirementKey {
+        RequirementKey::IntegerPow(self.ty.clone(), self.exp.clone())
+    }
+
+    fn returns(&self, _f: &RustFormatter) -> Option<RustType> {
+        Some(RustType::from(self.ty.clone()).result())
+    }
+
+    fn file(&self, f: &RustFormatter) -> std::path::PathBuf {
+        f.main_file()
+    }
+}
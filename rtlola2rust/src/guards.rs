@@ -29,18 +29,7 @@ impl DefaultGuardFormatter for RustFormatter {
     }
 
     fn dynamic(&self, expr: Expr) -> String {
-        let args: Vec<_> = expr
-            .contains_parameter_access()
-            .map(|sr| {
-                (0..self.parameter_arguments(sr).len())
-                    .map(|i| self.cycle_parameter_name(i))
-                    .collect()
-            })
-            .unwrap_or_default();
-        format!(
-            "{}?",
-            self.call_self_function(DynamicGuard::new(expr, self), &args)
-        )
+        self.call_expr_function(expr)
     }
 
     fn global_freq(&self, duration: Duration) -> String {
@@ -86,9 +75,30 @@ impl DefaultGuardFormatter for RustFormatter {
     }
 }
 
-pub(crate) struct DynamicGuard(Expr, usize);
+impl RustFormatter {
+    /// Returns the call of the function evaluating the expression inside the cycle function,
+    /// passing the parameters of the current instance and the local variables the expression uses
+    pub(crate) fn call_expr_function(&self, expr: Expr) -> String {
+        let mut args: Vec<_> = expr
+            .contains_parameter_access()
+            .map(|sr| {
+                (0..self.parameter_arguments(sr).len())
+                    .map(|i| self.cycle_parameter_name(i))
+                    .collect()
+            })
+            .unwrap_or_default();
+        args.extend(self.variable_values(&expr));
+        format!(
+            "{}?",
+            self.call_self_function(ExprFunction::new(expr, self), &args)
+        )
+    }
+}
 
-impl DynamicGuard {
+/// A function evaluating an expression of a dynamic guard or a let statement
+pub(crate) struct ExprFunction(Expr, usize);
+
+impl ExprFunction {
     pub(crate) fn new(expr: Expr, f: &RustFormatter) -> Self {
         let mut expr_counter = f.expr_counter.lock().unwrap();
         let params = expr.contains_parameter_access();
@@ -104,7 +114,7 @@ impl DynamicGuard {
     }
 }
 
-impl FunctionDefinition for DynamicGuard {
+impl FunctionDefinition for ExprFunction {
     fn name(&self, f: &RustFormatter) -> String {
         f.expr_function_name(self.1)
     }
@@ -118,15 +128,17 @@ impl FunctionDefinition for DynamicGuard {
     }
 
     fn arguments(&self, f: &RustFormatter) -> Vec<(String, RustType)> {
-        if let Some(sr) = self.0.contains_parameter_access() {
+        let mut arguments = if let Some(sr) = self.0.contains_parameter_access() {
             f.parameter_arguments(sr)
         } else {
             Vec::new()
-        }
+        };
+        arguments.extend(f.variable_arguments(&self.0));
+        arguments
     }
 
     fn returns(&self, _f: &RustFormatter) -> Option<RustType> {
-        Some(RustType::Bool.result())
+        Some(RustType::from(self.0.ty.clone()).result())
     }
 
     fn body(self, f: &RustFormatter) -> String {
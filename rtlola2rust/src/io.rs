@@ -200,6 +200,9 @@ impl StructDefinition for VerdictStruct {
 
     fn fields(&self, f: &RustFormatter) -> Vec<(String, RustType)> {
         f.add_requirement(VerdictDisplay);
+        if f.verdict_streams.iter().any(|sr| f.stream_delay(*sr) > 0) {
+            f.add_requirement(VerdictDelays);
+        }
         f.verdict_streams
             .iter()
             .map(|sr| {
@@ -252,10 +255,10 @@ impl Requirement<RustFormatter> for VerdictDisplay {
                     format!(
                         "write!(f, \"{{}},\", self.{}.as_ref().map(|v|{}).unwrap_or_else(||\"#\".into()))?;",
                         name,
-                        if *formatter.lola_stream_type(*sr) == Type::String {
-                            r#"format!("\"{v}\"")"#
-                        } else {
-                            "v.to_string()"
+                        match formatter.lola_stream_type(*sr) {
+                            Type::String => r#"format!("\"{v}\"")"#,
+                            Type::Option(_) => r#"v.as_ref().map_or_else(|| "None".into(), |v| v.to_string())"#,
+                            _ => "v.to_string()",
                         }
                     )
                 }
@@ -277,6 +280,42 @@ impl Display for Verdict {{
     }
 }
 
+struct VerdictDelays;
+
+impl Requirement<RustFormatter> for VerdictDelays {
+    fn key(&self) -> RequirementKey {
+        RequirementKey::VerdictDelays
+    }
+
+    fn format(self, formatter: &RustFormatter) -> String {
+        let delays = formatter
+            .verdict_streams
+            .iter()
+            .filter(|sr| formatter.stream_delay(**sr) > 0)
+            .map(|sr| {
+                format!(
+                    "(\"{}\", {})",
+                    formatter.stream_name(*sr),
+                    formatter.stream_delay(*sr)
+                )
+            })
+            .collect::<Vec<_>>();
+        format!(
+            "
+impl Verdict {{
+    /// The delayed streams with the number of activations by which their values lag behind the time of the verdict
+    pub const DELAYS: [(&'static str, u32); {}] = [{}];
+}}",
+            delays.len(),
+            delays.join(", ")
+        )
+    }
+
+    fn file(&self, formatter: &RustFormatter) -> std::path::PathBuf {
+        formatter.main_file()
+    }
+}
+
 pub(crate) struct VerdictConstructor;
 
 impl FunctionDefinition for VerdictConstructor {
@@ -347,7 +386,10 @@ impl FunctionDefinition for VerdictHeaderFunction {
         let s = f
             .verdict_streams
             .iter()
-            .map(|sr| f.stream_name(*sr))
+            .map(|sr| match f.stream_delay(*sr) {
+                0 => f.stream_name(*sr),
+                delay => format!("{}.offset(by: -{delay})", f.stream_name(*sr)),
+            })
             .chain(Some("time".into()))
             .join(",");
         format!("println!(\"{s}\");")
@@ -50,7 +50,8 @@ use streamir_lib::{
         expressions::Expr,
         memory::{Memory, Parameter, StreamMemory},
         windows::Window,
-        LocalFreq, LocalFreqRef, StreamIr, StreamReference, Type, WindowReference,
+        LocalFreq, LocalFreqRef, OutputReference, OverflowPolicy, SourceMap, SourceSpan, StreamIr,
+        StreamReference, Type, WindowReference,
     },
 };
 use tera::Tera;
@@ -63,6 +64,7 @@ pub struct RustFormatter {
     sr2ty: HashMap<StreamReference, Type>,
     sr2parameters: HashMap<StreamReference, Vec<Parameter>>,
     sr2memory: HashMap<StreamReference, StreamMemory>,
+    sr2delay: HashMap<StreamReference, u32>,
     lfreq2lfreq: HashMap<LocalFreqRef, LocalFreq>,
     wref2window: HashMap<WindowReference, Window>,
     static_deadlines: Vec<Duration>,
@@ -75,9 +77,20 @@ pub struct RustFormatter {
     // this counter is incremented for each expression that is generated
     expr_counter: Mutex<HashMap<(Expr, Option<StreamReference>), usize>>,
     num_exprs: Mutex<usize>,
+    // the distinct expressions of each eval clause, which differ between copies of the eval statement
+    // if they use different local variables
+    eval_variants: Mutex<HashMap<(OutputReference, usize), Vec<Expr>>>,
     tera: Tera,
     main: MainFunction,
     verdict_streams: Vec<StreamReference>,
+    // the Rust paths implementing the external functions
+    external_functions: HashMap<String, String>,
+    // the constructs of the specification that can not be translated, reported after formatting the monitor
+    unsupported: Mutex<Vec<String>>,
+    // the locations of the streams in the specification, cited in comments of the generated code
+    sources: SourceMap,
+    // the behavior of integer operations whose result does not fit their type
+    overflow: OverflowPolicy,
 }
 
 impl StreamIrFormatter for RustFormatter {
@@ -88,16 +101,26 @@ impl StreamIrFormatter for RustFormatter {
     }
 
     fn format(self, ir: StreamIr) -> Self::Return {
+        self.add_constructs(ir)?;
+        self.generate_files()
+    }
+}
+
+impl RustFormatter {
+    /// Adds the constructs of the monitor for the StreamIR to the construct store
+    fn add_constructs(&self, ir: StreamIr) -> Result<(), ConstructWriteError> {
         let StreamIr { stmt, .. } = ir;
         let _ = self.call_self_function::<_, String>(CycleFunction(stmt), &[]);
         let _ = self.call_self_function::<_, String>(AcceptEventFunction, &[]);
         self.require_struct(MonitorStruct);
-        self.main.insert_requirement(&self);
-        self.generate_files()
+        self.main.insert_requirement(self);
+        let unsupported = self.unsupported.lock().unwrap();
+        if !unsupported.is_empty() {
+            return Err(ConstructWriteError::Unsupported(unsupported.join(", ")));
+        }
+        Ok(())
     }
-}
 
-impl RustFormatter {
     /// Construct a new RustFormatter for the given StreamIR, writing the files to `output_folder`, optionally overwriting existing files.
     ///
     /// The `main` arguments specifies the kind of main function to generate, while `verdict_streams` contains a list of (unparameterized) stream references
@@ -109,16 +132,22 @@ impl RustFormatter {
         main: MainFunction,
         verdict_streams: Vec<StreamReference>,
     ) -> Self {
-        let (sr2name, sr2ty, sr2parameters, sr2memory) = ir
+        let (sr2name, sr2ty, sr2parameters, sr2memory, sr2delay) = ir
             .sr2memory
             .iter()
             .map(|(sr, m)| {
-                let Memory { buffer, ty, name } = m;
+                let Memory {
+                    buffer,
+                    ty,
+                    name,
+                    delay,
+                } = m;
                 (
                     (*sr, name.clone()),
                     (*sr, ty.clone()),
                     (*sr, m.parameters().unwrap_or(&[]).to_owned()),
                     (*sr, buffer.clone()),
+                    (*sr, *delay),
                 )
             })
             .multiunzip();
@@ -150,6 +179,7 @@ impl RustFormatter {
             sr2ty,
             sr2parameters,
             sr2memory,
+            sr2delay,
             static_deadlines,
             dynamic_deadlines,
             lfreq2lfreq,
@@ -158,10 +188,43 @@ impl RustFormatter {
             output_folder,
             expr_counter: Mutex::new(HashMap::new()),
             num_exprs: Mutex::new(0),
+            eval_variants: Mutex::new(HashMap::new()),
             tera,
             overwrite,
             main,
             verdict_streams,
+            external_functions: HashMap::new(),
+            unsupported: Mutex::new(Vec::new()),
+            sources: ir.sources.clone(),
+            overflow: ir.overflow,
+        }
+    }
+
+    /// Sets the Rust paths implementing the external functions of the specification.
+    ///
+    /// The map assigns each name of an external function a path to a Rust function (e.g. `my_crate::foo`)
+    /// that receives the arguments by value and returns the result.
+    pub fn with_external_functions(mut self, external_functions: HashMap<String, String>) -> Self {
+        self.external_functions = external_functions;
+        self
+    }
+
+    /// Records that the construct can not be translated, which fails the translation
+    ///
+    /// Returns a placeholder for the code of the construct.
+    pub(crate) fn unsupported(&self, construct: String) -> String {
+        let mut unsupported = self.unsupported.lock().unwrap();
+        if !unsupported.contains(&construct) {
+            unsupported.push(construct);
+        }
+        String::new()
+    }
+
+    /// Precedes the code generated for a clause of a stream by a comment citing the clause in the specification
+    pub(crate) fn located(&self, code: String, sr: OutputReference, span: SourceSpan) -> String {
+        match self.sources.locate(span, sr.sr()) {
+            Some(location) => format!("// {location}\n{code}"),
+            None => code,
         }
     }
 
@@ -197,6 +260,10 @@ impl RustFormatter {
         &self.sr2memory[&sr]
     }
 
+    pub(crate) fn stream_delay(&self, sr: StreamReference) -> u32 {
+        self.sr2delay[&sr]
+    }
+
     pub(crate) fn stream_parameter(&self, sr: StreamReference) -> &[Parameter] {
         &self.sr2parameters[&sr]
     }
@@ -272,3 +339,168 @@ impl StructDefinition for MonitorStruct {
         _f.main_file()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Write,
+        path::PathBuf,
+        process::{Command, Stdio},
+    };
+
+    use streamir_lib::{
+        formatter::files::{ConstructWriteError, FilesFormatter},
+        ir::{StreamIr, StreamReference},
+        pipeline::Pipeline,
+    };
+
+    use crate::{MainFunction, RustFormatter};
+
+    /// Generates the monitor for the StreamIR, returning the code or the error
+    fn monitor(
+        ir: StreamIr,
+        main: MainFunction,
+        verdict_streams: Vec<StreamReference>,
+    ) -> Result<String, ConstructWriteError> {
+        let formatter = RustFormatter::new(&ir, PathBuf::new(), true, main, verdict_streams);
+        formatter.add_constructs(ir)?;
+        Ok(formatter
+            .generate_strings()?
+            .remove(&PathBuf::from("main.rs"))
+            .unwrap())
+    }
+
+    /// Generates the monitor for the StreamIR after the pipeline, returning the code
+    fn code(ir: &str, pipeline: &str) -> String {
+        let ir = StreamIr::from_text(ir).unwrap_or_else(|e| panic!("{e}"));
+        let ir = pipeline.parse::<Pipeline>().unwrap().run(ir).unwrap();
+        monitor(ir, MainFunction::NoMain, Vec::new()).unwrap()
+    }
+
+    /// Asserts that the generated code compiles as a library
+    fn assert_compiles(code: &str) {
+        let dir = std::env::temp_dir().join(format!("rtlola2rust-{}", std::process::id()));
+        let mut rustc = Command::new("rustc")
+            .args([
+                "--edition",
+                "2021",
+                "--crate-type",
+                "lib",
+                "--emit",
+                "metadata",
+            ])
+            .arg("--out-dir")
+            .arg(&dir)
+            .arg("-")
+            .stdin(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("rustc is available");
+        rustc
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(code.as_bytes())
+            .unwrap();
+        let output = rustc.wait_with_output().unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(
+            output.status.success(),
+            "{}\n{code}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    #[test]
+    fn let_in_iterate() {
+        let code = code(
+            "input in0 a: UInt(64) = static(single);
+output pout0 b(p: UInt(64)): UInt(64) = instances(single);
+output out0 c: Bool = static(single);
+stmt seq {
+    input in0;
+    if @in0 then spawn pout0 with (in0) fi;
+    if @in0 then
+        iterate [pout0] seq {
+            if Expr((param(pout0, 0) + in0) * (param(pout0, 0) + in0) > 3u64) then
+                shift pout0
+            fi;
+            eval_0 pout0 with param(pout0, 0) + in0
+        }
+    fi;
+    if @in0 then
+        seq {
+            eval_0 out0 with in0 + 1u64 > 2u64 && in0 + 1u64 < 10u64;
+            eval_0 out0 with in0 > 2u64
+        }
+    fi
+}",
+            "CommonSubexpressionElimination",
+        );
+        assert_compiles(&code);
+        assert!(code.contains("let var_0"), "{code}");
+        // the two evaluations of out0 see different variables
+        assert!(code.contains("fn eval_c_0_variant1"), "{code}");
+    }
+
+    #[test]
+    fn float_signum() {
+        let code = code(
+            "input in0 a: Float(64) = static(single);
+output out0 b: Float(64) = static(single);
+stmt seq {
+    input in0;
+    if @in0 then eval_0 out0 with signum(in0) fi
+}",
+            "",
+        );
+        assert_compiles(&code);
+    }
+
+    #[test]
+    fn delayed_verdict_streams() {
+        let ir = StreamIr::from_text(
+            "input in0 a: UInt(64) = static(single);
+output out0 b: UInt(64) = static(single) delayed(1);
+output out1 c: UInt(64) = static(single);
+stmt seq {
+    input in0;
+    if @in0 then
+        par {
+            eval_0 out0 with in0;
+            eval_0 out1 with in0
+        }
+    fi
+}",
+        )
+        .unwrap_or_else(|e| panic!("{e}"));
+        let verdict_streams = vec![
+            ir.stream_by_name("b").unwrap(),
+            ir.stream_by_name("c").unwrap(),
+        ];
+        let code = monitor(ir, MainFunction::CsvOffline, verdict_streams).unwrap();
+        assert!(
+            code.contains("println!(\"b.offset(by: -1),c,time\")"),
+            "{code}"
+        );
+        assert!(code.contains("[(\"b\", 1)]"), "{code}");
+    }
+
+    #[test]
+    fn missing_external_function() {
+        let ir = StreamIr::from_text(
+            "input in0 a: UInt(64) = static(single);
+output out0 b: UInt(64) = static(single);
+stmt seq {
+    input in0;
+    if @in0 then eval_0 out0 with extern<UInt(64)> scale(in0) fi
+}",
+        )
+        .unwrap_or_else(|e| panic!("{e}"));
+        let res = monitor(ir, MainFunction::NoMain, Vec::new());
+        assert!(
+            matches!(res, Err(ConstructWriteError::Unsupported(e)) if e.contains("scale")),
+            "expected an error naming the function"
+        );
+    }
+}
@@ -66,6 +66,11 @@ impl StructDefinition for StreamMemoryStruct {
                 };
                 (f.stream_name(sr), RustType::Other(ty))
             })
+            .chain(
+                f.streams()
+                    .filter(|sr| f.stream_delay(*sr) > 0)
+                    .map(|sr| (f.warmup_field_name(sr), RustType::Usize)),
+            )
             .collect()
     }
 
@@ -118,13 +123,18 @@ impl FunctionDefinition for StreamMemoryConstructor {
                         f.add_requirement(DynamicStreamBuffer);
                         DynamicStreamBuffer::constructor(*has_spawn)
                     }
-                    streamir_lib::ir::memory::StreamMemory::Instances { .. } => {
+                    streamir_lib::ir::memory::StreamMemory::Instances { bound, .. } => {
                         f.add_requirement(InstanceStreamBuffer);
-                        InstanceStreamBuffer::constructor()
+                        InstanceStreamBuffer::constructor(*bound)
                     }
                 };
                 format!("{}: {}", f.stream_name(sr), constructor)
             })
+            .chain(
+                f.streams()
+                    .filter(|sr| f.stream_delay(*sr) > 0)
+                    .map(|sr| format!("{}: {}", f.warmup_field_name(sr), f.stream_delay(sr))),
+            )
             .join(",\n");
         format!("Self {{\n{fields}\n}}")
     }
@@ -229,8 +239,11 @@ impl InstanceStreamBuffer {
         )
     }
 
-    fn constructor() -> String {
-        "InstanceStreamBuffer::new()".to_string()
+    fn constructor(bound: Option<usize>) -> String {
+        format!(
+            "InstanceStreamBuffer::with_capacity({})",
+            bound.unwrap_or_default()
+        )
     }
 }
 
@@ -67,6 +67,9 @@ function_names! {RustFormatter,
     /// The name of the argument holding the time of an event
     time_argument_name(): "time",
 
+    /// The name of the field counting the activations a delayed stream still skips
+    warmup_field_name(stream): "{stream}_warmup",
+
     /// The name of the function to clear all activations of streams at the end of the cycle
     clear_activations_function_name(): "clear_activations",
 
@@ -9,8 +9,8 @@ use streamir_lib::{
         statements::{DefaultStmtFormatter, StmtFormatter},
     },
     ir::{
-        expressions::Expr, Guard, InputReference, LocalFreq, LocalFreqRef, OutputReference, Stmt,
-        StreamReference, WindowReference,
+        expressions::Expr, Guard, InputReference, LocalFreq, LocalFreqRef, OutputReference,
+        SourceSpan, Stmt, StreamReference, VariableReference, WindowReference,
     },
 };
 
@@ -36,7 +36,12 @@ impl DefaultStmtFormatter for RustFormatter {
             .collect::<Vec<_>>();
         let instance = instance.iter().map(|s| s.as_str()).collect::<Vec<_>>();
         let buffer = self.get_stream_buffer_mut(sr, instance.as_slice());
-        format!("{buffer}.shift();")
+        if self.stream_delay(sr) > 0 {
+            let warmup = self.warmup_field(sr);
+            format!("if {warmup} == 0 {{\n{buffer}.shift();\n}}")
+        } else {
+            format!("{buffer}.shift();")
+        }
     }
 
     fn input(&self, sr: InputReference) -> String {
@@ -55,6 +60,7 @@ impl DefaultStmtFormatter for RustFormatter {
         with: Option<Vec<Expr>>,
         local_frequencies: Vec<LocalFreqRef>,
         windows: Vec<WindowReference>,
+        span: SourceSpan,
     ) -> String {
         let dynamic_queue_update: Vec<_> = local_frequencies
             .into_iter()
@@ -111,7 +117,7 @@ impl DefaultStmtFormatter for RustFormatter {
                 &dynamic_queue_update[..]
             ),
         };
-        if let Some(with) = with {
+        let code = if let Some(with) = with {
             let spawn_windows = windows
                 .into_iter()
                 .filter(|w| matches!(w, WindowReference::Sliding(_)))
@@ -197,22 +203,27 @@ impl DefaultStmtFormatter for RustFormatter {
                 argname = StreamMemoryStruct.argument_name(self),
                 sname = self.output_name(sr)
             )
-        }
+        };
+        self.located(code, sr, span)
     }
 
-    fn eval(&self, sr: OutputReference, with: Expr, idx: usize) -> String {
-        let eval = EvalStatement {
-            sr,
-            expr: with,
-            i: idx,
-        };
-        let instance = self
+    fn eval(&self, sr: OutputReference, with: Expr, idx: usize, span: SourceSpan) -> String {
+        let arguments = self
             .stream_parameter(sr.sr())
             .iter()
             .enumerate()
             .map(|(p, _)| self.cycle_parameter_name(p))
+            .chain(self.variable_values(&with))
             .collect::<Vec<_>>();
-        format!("{}?;", self.call_self_function(eval, &instance))
+        let eval = EvalStatement::new(sr, with, idx, self);
+        let call = format!("{}?;", self.call_self_function(eval, &arguments));
+        let code = if self.stream_delay(sr.sr()) > 0 {
+            let warmup = self.warmup_field(sr.sr());
+            format!("if {warmup} > 0 {{\n{warmup} -= 1;\n}} else {{\n{call}\n}}")
+        } else {
+            call
+        };
+        self.located(code, sr, span)
     }
 
     fn close(
@@ -220,8 +231,9 @@ impl DefaultStmtFormatter for RustFormatter {
         sr: OutputReference,
         _local_frequencies: Vec<LocalFreqRef>,
         _windows: Vec<WindowReference>,
+        span: SourceSpan,
     ) -> String {
-        match sr {
+        let code = match sr {
             OutputReference::Unparameterized(_) => {
                 format!(
                     "self.{closed_field}.push(StreamReference::{sref});",
@@ -237,7 +249,8 @@ impl DefaultStmtFormatter for RustFormatter {
                     params = self.cycle_parameters_variable(sr.sr())
                 )
             }
-        }
+        };
+        self.located(code, sr, span)
     }
 
     fn r#if(&self, guard: Guard, cons: Stmt, alt: Option<Stmt>) -> String {
@@ -276,6 +289,15 @@ impl DefaultStmtFormatter for RustFormatter {
             .join("\n");
         format!("{s}\n{}", self.stmt(inner))
     }
+
+    fn r#let(&self, var: VariableReference, expr: Expr, inner: Stmt) -> String {
+        format!(
+            "{{\nlet {} = {};\n{}\n}}",
+            self.variable(var, expr.ty.clone()),
+            self.call_expr_function(expr),
+            self.stmt(inner)
+        )
+    }
 }
 
 impl RustFormatter {
@@ -410,6 +432,14 @@ impl RustFormatter {
             .join("\n")
     }
 
+    fn warmup_field(&self, sr: StreamReference) -> String {
+        format!(
+            "self.{}.{}",
+            StreamMemoryStruct.argument_name(self),
+            self.warmup_field_name(sr)
+        )
+    }
+
     pub(crate) fn update_window(&self, wref: WindowReference, in_cycle: bool) -> String {
         let target = self.wref2window[&wref].target;
         if self
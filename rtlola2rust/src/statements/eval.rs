@@ -7,18 +7,44 @@ use streamir_lib::{
 use crate::constructs::FunctionDefinition;
 
 pub(super) struct EvalStatement {
-    pub(super) sr: OutputReference,
-    pub(super) expr: Expr,
-    pub(super) i: usize,
+    sr: OutputReference,
+    expr: Expr,
+    i: usize,
+    variant: usize,
+}
+
+impl EvalStatement {
+    /// Creates the function of the eval clause with the given expression
+    ///
+    /// Copies of an eval statement can use different local variables in their expression (or none),
+    /// so each distinct expression of a clause gets its own function.
+    pub(super) fn new(sr: OutputReference, expr: Expr, i: usize, f: &crate::RustFormatter) -> Self {
+        let mut eval_variants = f.eval_variants.lock().unwrap();
+        let variants = eval_variants.entry((sr, i)).or_default();
+        let variant = variants.iter().position(|e| *e == expr).unwrap_or_else(|| {
+            variants.push(expr.clone());
+            variants.len() - 1
+        });
+        Self {
+            sr,
+            expr,
+            i,
+            variant,
+        }
+    }
 }
 
 impl FunctionDefinition for EvalStatement {
     fn name(&self, f: &crate::RustFormatter) -> String {
-        f.eval_statement_function_name(self.sr.sr(), self.i)
+        let name = f.eval_statement_function_name(self.sr.sr(), self.i);
+        match self.variant {
+            0 => name,
+            variant => format!("{name}_variant{variant}"),
+        }
     }
 
     fn body(self, f: &crate::RustFormatter) -> String {
-        let EvalStatement { sr, expr, i: _ } = self;
+        let EvalStatement { sr, expr, .. } = self;
         let expr = f.expr(expr);
         let instance = f
             .stream_parameter(sr.sr())
@@ -38,12 +64,16 @@ impl FunctionDefinition for EvalStatement {
     }
 
     fn arguments(&self, f: &crate::RustFormatter) -> Vec<(String, crate::RustType)> {
-        f.parameter_arguments(self.sr.sr())
+        let mut arguments = f.parameter_arguments(self.sr.sr());
+        arguments.extend(f.variable_arguments(&self.expr));
+        arguments
     }
 
     fn key(&self) -> crate::constructs::RequirementKey {
         crate::constructs::RequirementKey::Statement(crate::constructs::StatementType::Eval(
-            self.sr, self.i,
+            self.sr,
+            self.i,
+            self.variant,
         ))
     }
 
@@ -10,9 +10,9 @@ pub(crate) struct InstanceStreamBuffer<
 impl<Parameter: Eq + std::hash::Hash + Clone, StreamType: Clone, const STREAM_SIZE: usize>
     InstanceStreamBuffer<Parameter, StreamType, STREAM_SIZE>
 {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn with_capacity(capacity: usize) -> Self {
         InstanceStreamBuffer {
-            stream_buffer: HashMap::new(),
+            stream_buffer: HashMap::with_capacity(capacity),
         }
     }
 
@@ -4,12 +4,10 @@ use rtlola2solidity::{
 };
 use std::path::PathBuf;
 use streamir_lib::{
-    optimize, parse,
-    rewrite_rules::{
-        CombineIf, CombineIterate, CombineNestedIf, CombineSeq, ImpliedGuards, IterateAssign,
-        MemoryOptimizations, MoveCommonGuardsOutside, MoveIfOutside, RemoveClose, RemoveIfs,
-        RemoveShift, RemoveSpawn, SimplifyGuard,
-    },
+    ir::OverflowPolicy,
+    parse,
+    pipeline::Pipeline,
+    rewrite_rules::{NarrowIntegers, Rewriter},
     translate, ParserConfig,
 };
 
@@ -29,6 +27,15 @@ struct Args {
     #[clap(long, short, value_enum, default_value_t = OptimizationLevel::PartialEval)]
     /// Whether to optimize the IR
     optimize: OptimizationLevel,
+    #[clap(long, conflicts_with = "optimize")]
+    /// The rewriting pipeline to apply: the name of a preset, a list of rules, or `@` followed by the path of a pipeline file
+    pipeline: Option<Pipeline>,
+    #[clap(long)]
+    /// Store integer output streams with fewer bits wherever their values provably fit, such that storage slots can be packed
+    narrow_integers: bool,
+    #[clap(long)]
+    /// How integer operations treat results that do not fit their type: `wrap` (the default), `saturate` or `trap`
+    overflow: Option<OverflowPolicy>,
     /// Whether a trigger throws revert or emits an event
     #[clap(long, value_enum, default_value_t=TriggerAction::EmitMultiple)]
     trigger_action: TriggerAction,
@@ -44,6 +51,9 @@ struct Args {
     /// Emit the given output streams at the end of each cycle evaluation (for testing purposes)
     #[clap(long)]
     output_streams: Vec<String>,
+    #[clap(long, value_enum)]
+    /// Print a report of the static memory footprint of the monitor to stdout
+    resource_report: Option<ReportFormat>,
 }
 
 #[derive(ValueEnum, Debug, Clone, Copy, Eq, PartialEq)]
@@ -54,17 +64,40 @@ enum OptimizationLevel {
     All,
 }
 
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum ReportFormat {
+    Text,
+    #[cfg(feature = "serde")]
+    Json,
+}
+
+impl OptimizationLevel {
+    fn pipeline(self) -> Pipeline {
+        let preset = match self {
+            OptimizationLevel::PartialEval => "partial-eval",
+            OptimizationLevel::Rewriting => "rewriting",
+            OptimizationLevel::Memory => "memory",
+            OptimizationLevel::All => "solidity",
+        };
+        Pipeline::preset(preset).unwrap()
+    }
+}
+
 fn main() -> anyhow::Result<()> {
     let Args {
         spec_path,
         config_file,
         contract_name,
         optimize: optimize_level,
+        pipeline,
+        narrow_integers,
+        overflow,
         trigger_action,
         output_file,
         overwrite,
         trigger_function_mode,
         output_streams,
+        resource_report,
     } = Args::parse();
 
     if trigger_action == TriggerAction::EmitMultiple
@@ -82,59 +115,28 @@ fn main() -> anyhow::Result<()> {
     //     parser_config.with_memory_bound_mode(MemoryBoundMode::Static)
     // };
 
-    let ir = parse(&parser_config).context("parsing specification to StreamIR")?;
+    let mut ir = parse(&parser_config).context("parsing specification to StreamIR")?;
+    if let Some(overflow) = overflow {
+        ir.set_overflow(overflow);
+    }
+
+    let pipeline = pipeline.unwrap_or_else(|| optimize_level.pipeline());
+    let mut optimized_ir = pipeline.run(ir).context("optimizing specification")?;
+    if narrow_integers {
+        let rule = NarrowIntegers::new(&optimized_ir);
+        optimized_ir = Rewriter::new(vec![Box::new(rule)])
+            .run(optimized_ir)
+            .context("narrowing integer streams")?;
+    }
 
-    let optimized_ir = match optimize_level {
-        OptimizationLevel::PartialEval => Ok(ir),
-        OptimizationLevel::Rewriting => optimize(
-            ir,
-            vec![
-                Box::new(CombineIf),
-                Box::new(SimplifyGuard),
-                Box::new(MoveCommonGuardsOutside),
-                Box::new(ImpliedGuards),
-                Box::new(SimplifyGuard),
-                Box::new(RemoveIfs),
-                Box::new(CombineSeq),
-                Box::new(MoveIfOutside),
-                Box::new(IterateAssign),
-                Box::new(CombineNestedIf),
-                Box::new(CombineIterate),
-                Box::new(RemoveIfs),
-            ],
-        ),
-        OptimizationLevel::Memory => optimize(
-            ir,
-            vec![
-                Box::new(RemoveShift),
-                Box::new(MemoryOptimizations),
-                Box::new(RemoveSpawn),
-                Box::new(RemoveClose),
-            ],
-        ),
-        OptimizationLevel::All => optimize(
-            ir,
-            vec![
-                Box::new(CombineIf),
-                Box::new(SimplifyGuard),
-                Box::new(MoveCommonGuardsOutside),
-                Box::new(ImpliedGuards),
-                Box::new(SimplifyGuard),
-                Box::new(RemoveIfs),
-                Box::new(CombineSeq),
-                Box::new(MoveIfOutside),
-                Box::new(IterateAssign),
-                Box::new(CombineNestedIf),
-                Box::new(CombineIterate),
-                Box::new(RemoveIfs),
-                Box::new(RemoveShift),
-                Box::new(MemoryOptimizations),
-                Box::new(RemoveSpawn),
-                Box::new(RemoveClose),
-            ],
-        ),
+    if let Some(format) = resource_report {
+        let report = optimized_ir.resource_report();
+        match format {
+            ReportFormat::Text => println!("{report}"),
+            #[cfg(feature = "serde")]
+            ReportFormat::Json => println!("{}", report.to_json()),
+        }
     }
-    .context("optimizing specification")?;
 
     let config = std::fs::read_to_string(config_file).context("reading config file")?;
     let config = InterfaceConfig::from_toml(&config).map_err(anyhow::Error::msg)?;
@@ -1,22 +1,54 @@
 pub(crate) mod get_access;
+mod overflow;
 
 use get_access::GetAccessFunction;
+use overflow::{OverflowFunction, SignedExponentPow};
 use std::fmt::Write;
 use streamir_lib::{
     formatter::{
         expressions::{
             DefaultConstantFormatter, DefaultExprFormatter, DefaultFunctionFormatter,
-            DefaultOperatorFormatter, ExprFormatter,
+            DefaultOperatorFormatter, ExprFormatter, OperatorFormatter,
         },
         files::FilesFormatter,
         types::TypeFormatter,
     },
-    ir::{expressions::Expr, OutputReference, StreamReference, Type, WindowReference},
+    ir::{
+        expressions::{Expr, FunctionSignature, Operator},
+        OutputReference, OverflowPolicy, StreamReference, Type, WindowReference,
+    },
 };
 
 use crate::{types::TupleType, SolidityFormatter};
 
 impl DefaultExprFormatter for SolidityFormatter {
+    fn unary(&self, op: Operator, operand: Expr) -> String {
+        if OverflowPolicy::governs(op) && matches!(operand.ty, Type::Int(_) | Type::UInt(_)) {
+            let ty = operand.ty.clone();
+            return self.integer_op(op, ty, self.expr(operand), None);
+        }
+        format!("({}{})", self.op(op), self.expr(operand))
+    }
+
+    fn binary(&self, op: Operator, lhs: Expr, rhs: Expr) -> String {
+        if OverflowPolicy::governs(op) && matches!(lhs.ty, Type::Int(_) | Type::UInt(_)) {
+            let ty = lhs.ty.clone();
+            let rhs = match (op, &rhs.ty) {
+                (Operator::Pow, Type::Int(_)) => {
+                    let pow = SignedExponentPow {
+                        ty,
+                        exp: rhs.ty.clone(),
+                    };
+                    return self.call_function(pow, vec![self.expr(lhs), self.expr(rhs)]);
+                }
+                (Operator::Pow, _) => format!("uint256({})", self.expr(rhs)),
+                _ => self.expr(rhs),
+            };
+            return self.integer_op(op, ty, self.expr(lhs), Some(rhs));
+        }
+        format!("({}{}{})", self.expr(lhs), self.op(op), self.expr(rhs))
+    }
+
     fn sync_access(&self, sr: StreamReference, parameters: Vec<Expr>) -> String {
         match sr {
             StreamReference::In(_) | StreamReference::Out(OutputReference::Unparameterized(_)) => {
@@ -36,9 +68,12 @@ impl DefaultExprFormatter for SolidityFormatter {
         &self,
         sr: StreamReference,
         offset: u32,
-        default: Expr,
+        default: Option<Expr>,
         parameters: Vec<Expr>,
     ) -> String {
+        let Some(default) = default else {
+            return self.unsupported("optional values");
+        };
         self.call_function(
             GetAccessFunction { sr },
             parameters
@@ -49,7 +84,15 @@ impl DefaultExprFormatter for SolidityFormatter {
         )
     }
 
-    fn hold_access(&self, sr: StreamReference, default: Expr, parameters: Vec<Expr>) -> String {
+    fn hold_access(
+        &self,
+        sr: StreamReference,
+        default: Option<Expr>,
+        parameters: Vec<Expr>,
+    ) -> String {
+        let Some(default) = default else {
+            return self.unsupported("optional values");
+        };
         self.call_function(
             GetAccessFunction { sr },
             parameters
@@ -60,7 +103,12 @@ impl DefaultExprFormatter for SolidityFormatter {
         )
     }
 
-    fn get_access(&self, _sr: StreamReference, _default: Expr, _parameters: Vec<Expr>) -> String {
+    fn get_access(
+        &self,
+        _sr: StreamReference,
+        _default: Option<Expr>,
+        _parameters: Vec<Expr>,
+    ) -> String {
         unimplemented!()
     }
 
@@ -92,6 +140,10 @@ impl DefaultExprFormatter for SolidityFormatter {
         format!("{}({})", self.ty(ty), self.expr(expr))
     }
 
+    fn with_default(&self, _expr: Expr, _default: Expr) -> String {
+        self.unsupported("optional values")
+    }
+
     fn tuple(&self, inner: Vec<Expr>) -> String {
         let inner_tys = inner.iter().map(|e| e.ty.clone()).collect();
         let tuple = TupleType(inner_tys);
@@ -101,6 +153,31 @@ impl DefaultExprFormatter for SolidityFormatter {
     }
 }
 
+impl SolidityFormatter {
+    /// Returns the code applying an arithmetic operator to integers, following the overflow policy of the specification
+    ///
+    /// The unary operator is given without a right-hand side.
+    fn integer_op(&self, op: Operator, ty: Type, lhs: String, rhs: Option<String>) -> String {
+        let unsigned = matches!(ty, Type::UInt(_));
+        match (self.overflow, op, rhs) {
+            // the remainder of the smallest value and -1 is 0, and the unsigned division cannot overflow
+            (_, Operator::Rem, Some(rhs)) => format!("({lhs}%{rhs})"),
+            (_, Operator::Div, Some(rhs)) if unsigned => format!("({lhs}/{rhs})"),
+            // the arithmetic of Solidity reverts on overflow
+            (OverflowPolicy::Trap, _, Some(rhs)) => format!("({lhs}{}{rhs})", self.op(op)),
+            (OverflowPolicy::Trap, _, None) if unsigned => format!("({}(0)-{lhs})", self.ty(ty)),
+            (OverflowPolicy::Trap, _, None) => format!("(-{lhs})"),
+            (OverflowPolicy::Saturate, Operator::Neg, None) if unsigned => {
+                format!("{}(0)", self.ty(ty))
+            }
+            (policy, _, rhs) => self.call_function(
+                OverflowFunction { op, policy, ty },
+                std::iter::once(lhs).chain(rhs).collect(),
+            ),
+        }
+    }
+}
+
 impl DefaultConstantFormatter for SolidityFormatter {
     fn constant_int(&self, i: i64, bits: u16) -> String {
         format!("int{bits}({i})")
@@ -111,6 +188,51 @@ impl DefaultConstantFormatter for SolidityFormatter {
     }
 }
 
-impl DefaultFunctionFormatter for SolidityFormatter {}
+// the functions that have no counterpart in solidity
+impl DefaultFunctionFormatter for SolidityFormatter {
+    fn function_floor(&self) -> String {
+        self.unsupported("the floor function")
+    }
+
+    fn function_ceil(&self) -> String {
+        self.unsupported("the ceil function")
+    }
+
+    fn function_round(&self) -> String {
+        self.unsupported("the round function")
+    }
+
+    fn function_exp(&self) -> String {
+        self.unsupported("the exp function")
+    }
+
+    fn function_ln(&self) -> String {
+        self.unsupported("the ln function")
+    }
+
+    fn function_log(&self) -> String {
+        self.unsupported("the log function")
+    }
+
+    fn function_pow(&self) -> String {
+        self.unsupported("the pow function")
+    }
+
+    fn function_signum(&self) -> String {
+        self.unsupported("the signum function")
+    }
+
+    fn function_clamp(&self) -> String {
+        self.unsupported("the clamp function")
+    }
+
+    fn function_atan2(&self) -> String {
+        self.unsupported("the atan2 function")
+    }
+
+    fn function_external(&self, name: String, _signature: FunctionSignature) -> String {
+        self.unsupported(&format!("the external function {name}"))
+    }
+}
 
 impl DefaultOperatorFormatter for SolidityFormatter {}
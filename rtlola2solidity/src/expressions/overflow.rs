@@ -0,0 +1,193 @@
+use streamir_lib::{
+    formatter::{expressions::OperatorFormatter, types::TypeFormatter},
+    ir::{expressions::Operator, OverflowPolicy, Type},
+};
+
+use crate::{
+    functions::{FunctionDefinition, FunctionStateMutability},
+    RequirementKey, SolidityFormatter,
+};
+
+/// A function applying an arithmetic operator to integers that wraps around or saturates on overflow
+///
+/// Trapping on overflow is the default of Solidity and needs no function,
+/// neither does the remainder, the unsigned division and the saturating unsigned negation.
+pub(crate) struct OverflowFunction {
+    pub op: Operator,
+    pub policy: OverflowPolicy,
+    pub ty: Type,
+}
+
+impl OverflowFunction {
+    fn signed(&self) -> bool {
+        matches!(self.ty, Type::Int(_))
+    }
+
+    /// Returns the body of the function saturating at the bounds of the type
+    fn saturating(&self, ty: &str) -> String {
+        let (min, max) = (format!("type({ty}).min"), format!("type({ty}).max"));
+        match (self.op, self.signed()) {
+            (Operator::Add, false) => {
+                format!("unchecked {{ {ty} r = a + b; return r < a ? {max} : r; }}")
+            }
+            (Operator::Add, true) => format!(
+                "unchecked {{ {ty} r = a + b;\n\
+                if (b > 0 && r < a) {{ return {max}; }}\n\
+                if (b < 0 && r > a) {{ return {min}; }}\n\
+                return r; }}"
+            ),
+            (Operator::Sub, false) => format!("return a < b ? {ty}(0) : a - b;"),
+            (Operator::Sub, true) => format!(
+                "unchecked {{ {ty} r = a - b;\n\
+                if (b < 0 && r < a) {{ return {max}; }}\n\
+                if (b > 0 && r > a) {{ return {min}; }}\n\
+                return r; }}"
+            ),
+            (Operator::Mul, false) => format!(
+                "if (a == 0) {{ return 0; }}\n\
+                unchecked {{ {ty} r = a * b; return r / a == b ? r : {max}; }}"
+            ),
+            (Operator::Mul, true) => format!(
+                "if (a == 0 || b == 0) {{ return 0; }}\n\
+                if ((a == -1 && b == {min}) || (b == -1 && a == {min})) {{ return {max}; }}\n\
+                unchecked {{ {ty} r = a * b;\n\
+                if (r / b != a) {{ return (a < 0) == (b < 0) ? {max} : {min}; }}\n\
+                return r; }}"
+            ),
+            (Operator::Div, true) => format!("return a == {min} && b == -1 ? {max} : a / b;"),
+            (Operator::Neg, true) => format!("return a == {min} ? {max} : -a;"),
+            (Operator::Pow, signed) => {
+                let (minus_one, sign) = if signed {
+                    (
+                        format!("if (a == -1) {{ return b % 2 == 0 ? {ty}(1) : {ty}(-1); }}\n"),
+                        format!("a < 0 && b % 2 == 1 ? {min} : "),
+                    )
+                } else {
+                    (String::new(), String::new())
+                };
+                // the magnitude of the base is at least 2 in the loop, so it stops after at most as many iterations as the type has bits
+                format!(
+                    "if (a == 0) {{ return b == 0 ? {ty}(1) : {ty}(0); }}\n\
+                    if (a == 1) {{ return 1; }}\n\
+                    {minus_one}\
+                    unchecked {{ {ty} r = 1;\n\
+                    for (uint256 i = 0; i < b; i++) {{\n\
+                    {ty} next = r * a;\n\
+                    if (next / a != r) {{ return {sign}{max}; }}\n\
+                    r = next;\n\
+                    }}\n\
+                    return r; }}"
+                )
+            }
+            (op, _) => unreachable!("{op:?} does not saturate with a function"),
+        }
+    }
+}
+
+impl FunctionDefinition for OverflowFunction {
+    fn name(&self, f: &SolidityFormatter) -> String {
+        let policy = match self.policy {
+            OverflowPolicy::Wrap => "wrapping",
+            OverflowPolicy::Saturate => "saturating",
+            OverflowPolicy::Trap => unreachable!("trapping is the default of Solidity"),
+        };
+        let op = match self.op {
+            Operator::Add => "add",
+            Operator::Sub => "sub",
+            Operator::Mul => "mul",
+            Operator::Div => "div",
+            Operator::Neg => "neg",
+            Operator::Pow => "pow",
+            op => unreachable!("{op:?} is not applied by a function"),
+        };
+        format!("{policy}_{op}_{}", f.ty(self.ty.clone()))
+    }
+
+    fn header(&self, f: &SolidityFormatter) -> String {
+        let ty = f.ty(self.ty.clone());
+        match self.op {
+            Operator::Neg => format!("{}({ty} a)", self.name(f)),
+            Operator::Pow => format!("{}({ty} a, uint256 b)", self.name(f)),
+            _ => format!("{}({ty} a, {ty} b)", self.name(f)),
+        }
+    }
+
+    fn body(self, f: &SolidityFormatter) -> String {
+        let ty = f.ty(self.ty.clone());
+        match self.policy {
+            OverflowPolicy::Wrap => {
+                let result = match self.op {
+                    Operator::Neg if !self.signed() => "0 - a".into(),
+                    Operator::Neg => "-a".into(),
+                    op => format!("a {} b", f.op(op)),
+                };
+                format!("unchecked {{ return {result}; }}")
+            }
+            _ => self.saturating(&ty),
+        }
+    }
+
+    fn key(&self) -> RequirementKey {
+        RequirementKey::OverflowFunction(self.op, self.policy, self.ty.clone())
+    }
+
+    fn returns(&self, _f: &SolidityFormatter) -> Vec<(Type, String)> {
+        vec![(self.ty.clone(), "result".into())]
+    }
+
+    fn mutability(&self) -> FunctionStateMutability {
+        FunctionStateMutability::Pure
+    }
+}
+
+/// A function raising an integer to the power of a signed exponent, following the overflow policy
+///
+/// A negative exponent results in the integer part of the reciprocal of the power, as in the other backends.
+pub(crate) struct SignedExponentPow {
+    pub ty: Type,
+    pub exp: Type,
+}
+
+impl FunctionDefinition for SignedExponentPow {
+    fn name(&self, f: &SolidityFormatter) -> String {
+        format!("pow_{}_{}", f.ty(self.ty.clone()), f.ty(self.exp.clone()))
+    }
+
+    fn header(&self, f: &SolidityFormatter) -> String {
+        format!(
+            "{}({} a, {} b)",
+            self.name(f),
+            f.ty(self.ty.clone()),
+            f.ty(self.exp.clone())
+        )
+    }
+
+    fn body(self, f: &SolidityFormatter) -> String {
+        let ty = f.ty(self.ty.clone());
+        let reciprocal = if matches!(self.ty, Type::Int(_)) {
+            format!("return r == -1 && b % 2 == 0 ? {ty}(1) : r;")
+        } else {
+            "return r;".into()
+        };
+        let pow = f.integer_op(
+            Operator::Pow,
+            self.ty,
+            "a".into(),
+            Some("uint256(int256(b))".into()),
+        );
+        // dividing by zero for the base 0
+        format!("if (b < 0) {{ {ty} r = {ty}(1) / a;\n{reciprocal} }}\nreturn {pow};")
+    }
+
+    fn key(&self) -> RequirementKey {
+        RequirementKey::SignedExponentPow(self.ty.clone(), self.exp.clone())
+    }
+
+    fn returns(&self, _f: &SolidityFormatter) -> Vec<(Type, String)> {
+        vec![(self.ty.clone(), "result".into())]
+    }
+
+    fn mutability(&self) -> FunctionStateMutability {
+        FunctionStateMutability::Pure
+    }
+}
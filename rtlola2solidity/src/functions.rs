@@ -99,6 +99,7 @@ pub(crate) trait FunctionDefinition {
 pub(crate) enum FunctionStateMutability {
     None, // no annotation
     View,
+    Pure,
 }
 
 impl std::fmt::Display for FunctionStateMutability {
@@ -106,6 +107,7 @@ impl std::fmt::Display for FunctionStateMutability {
         match self {
             FunctionStateMutability::None => Ok(()),
             FunctionStateMutability::View => write!(f, " view"),
+            FunctionStateMutability::Pure => write!(f, " pure"),
         }
     }
 }
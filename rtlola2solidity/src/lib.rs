@@ -26,6 +26,7 @@ mod types;
 use std::{
     collections::{HashMap, HashSet},
     path::PathBuf,
+    sync::Mutex,
 };
 
 use clap::ValueEnum;
@@ -37,8 +38,9 @@ use streamir_lib::{
         StreamIrFormatter,
     },
     ir::{
+        expressions::{Expr, Operator},
         memory::{Parameter, StreamMemory},
-        Accesses, OutputReference, StreamIr, StreamReference, Type,
+        Accesses, OutputReference, OverflowPolicy, StreamIr, StreamReference, Type,
     },
 };
 
@@ -59,6 +61,12 @@ pub struct SolidityFormatter {
     accesses: HashMap<StreamReference, Accesses>,
     triggers: HashMap<OutputReference, usize>,
     outputs_verdict: Vec<StreamReference>,
+    overflow: OverflowPolicy,
+    // the distinct expressions of each eval clause, which differ between copies of the eval statement
+    // if they use different local variables
+    eval_variants: Mutex<HashMap<(OutputReference, usize), Vec<Expr>>>,
+    // the constructs of the specification that can not be translated, reported after formatting the cycle functions
+    unsupported: Mutex<Vec<String>>,
 }
 
 #[derive(ValueEnum, Debug, Clone, Copy, Eq, PartialEq)]
@@ -92,7 +100,7 @@ pub enum RequirementKey {
     Memory(StreamReference),
     SpawnFunction(StreamReference),
     CloseFunction(StreamReference),
-    EvalFunction(StreamReference, usize),
+    EvalFunction(StreamReference, usize, usize),
     ShiftFunction(StreamReference),
     #[allow(dead_code)]
     SyncFunction(StreamReference),
@@ -102,6 +110,8 @@ pub enum RequirementKey {
     UnpartialCycle,
     VerdictEvent,
     Cycle(String),
+    OverflowFunction(Operator, OverflowPolicy, Type),
+    SignedExponentPow(Type, Type),
     CloseContract,
 }
 
@@ -153,6 +163,9 @@ impl SolidityFormatter {
             accesses: streamir.accesses.clone(),
             triggers,
             outputs_verdict,
+            overflow: streamir.overflow,
+            eval_variants: Mutex::new(HashMap::new()),
+            unsupported: Mutex::new(Vec::new()),
         }
     }
 
@@ -175,6 +188,17 @@ impl SolidityFormatter {
     fn file(&self) -> &PathBuf {
         &self.file
     }
+
+    /// Records that the construct is not supported in solidity, which fails the translation
+    ///
+    /// Returns a placeholder for the code of the construct.
+    fn unsupported(&self, construct: &str) -> String {
+        let mut unsupported = self.unsupported.lock().unwrap();
+        if !unsupported.iter().any(|c| c == construct) {
+            unsupported.push(construct.into());
+        }
+        String::new()
+    }
 }
 
 impl StreamIrFormatter for SolidityFormatter {
@@ -185,6 +209,21 @@ impl StreamIrFormatter for SolidityFormatter {
     }
 
     fn format(self, ir: StreamIr) -> Self::Return {
+        self.add_constructs(ir)?;
+        self.generate_files().unwrap();
+        Ok(())
+    }
+}
+
+impl SolidityFormatter {
+    /// Adds the constructs of the contract for the StreamIR to the construct store
+    fn add_constructs(&self, ir: StreamIr) -> Result<(), String> {
+        if let Some(m) = ir.sr2memory.values().find(|m| m.delay > 0) {
+            return Err(format!(
+                "Stream \"{}\" is delayed, but delayed streams are not supported in solidity.",
+                m.name
+            ));
+        }
         self.add_requirement_string(
             self.file(),
             RequirementKey::SolidityHeader,
@@ -198,8 +237,71 @@ impl StreamIrFormatter for SolidityFormatter {
             self.add_requirement(Memory(stream));
         }
         self.format_cycle_functions(&self.config, ir)?;
-
-        self.generate_files().unwrap();
+        let unsupported = self.unsupported.lock().unwrap();
+        if !unsupported.is_empty() {
+            return Err(format!(
+                "The specification uses constructs that are not supported in solidity: {}",
+                unsupported.join(", ")
+            ));
+        }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use streamir_lib::{formatter::files::FilesFormatter, ir::StreamIr, pipeline::Pipeline};
+
+    use crate::{
+        interface::InterfaceConfig, SolidityFormatter, TriggerAction, TriggerFunctionMode,
+    };
+
+    /// Generates the contract for the StreamIR after the pipeline, returning the code
+    fn contract(ir: &str, config: &str, pipeline: &str) -> String {
+        let ir = StreamIr::from_text(ir).unwrap_or_else(|e| panic!("{e}"));
+        let ir = pipeline.parse::<Pipeline>().unwrap().run(ir).unwrap();
+        let formatter = SolidityFormatter::new(
+            &ir,
+            InterfaceConfig::from_toml(config).unwrap(),
+            "Monitor".into(),
+            TriggerAction::Revert,
+            TriggerFunctionMode::Single,
+            PathBuf::from("monitor.sol"),
+            true,
+            Vec::new(),
+        );
+        formatter.add_constructs(ir).unwrap();
+        formatter
+            .generate_strings()
+            .unwrap()
+            .remove(&PathBuf::from("monitor.sol"))
+            .unwrap()
+    }
+
+    #[test]
+    fn eval_variants() {
+        let code = contract(
+            "input in0 a: UInt(64) = static(single);
+output out0 c: Bool = static(single);
+stmt seq {
+    input in0;
+    if @in0 then
+        seq {
+            eval_0 out0 with in0 + 1u64 > 2u64 && in0 + 1u64 < 10u64;
+            eval_0 out0 with in0 > 2u64
+        }
+    fi
+}",
+            "[[function]]
+name = \"update\"
+[[function.argument]]
+name = \"a\"",
+            "CommonSubexpressionElimination",
+        );
+        assert!(code.contains("function eval_c_0("), "{code}");
+        // the two evaluations of out0 see different variables
+        assert!(code.contains("function eval_c_0_variant1("), "{code}");
+    }
+}
@@ -61,7 +61,7 @@ impl SolidityFormatter {
                 StreamBuffer::Bounded(_) => format!("{name}_buffer[{name}_current] = {value};\n{name}_valid[{name}_current] = true;"),
                 StreamBuffer::UnBounded => unimplemented!("Unbounded streambuffer not implemented"),
             },
-            StreamMemory::Instances { buffer, parameter: _ } => {
+            StreamMemory::Instances { buffer, .. } => {
                 let parameter = self.param_access(sr);
                 match buffer {
                     StreamBuffer::SingleValue => {
@@ -137,7 +137,9 @@ impl Requirement<SolidityFormatter> for Memory {
                 "{}\nbool {name}_spawned;",
                 formatter.format_static_memory(self.0, buffer)
             ),
-            StreamMemory::Instances { buffer, parameter } => {
+            StreamMemory::Instances {
+                buffer, parameter, ..
+            } => {
                 let parameter_ty = parameter
                     .iter()
                     .map(|Parameter { name: _, ty }| formatter.ty(ty.clone()))
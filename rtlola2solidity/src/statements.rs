@@ -15,7 +15,8 @@ use streamir_lib::{
     ir::{
         expressions::{Constant, Expr, ExprKind},
         memory::{Parameter, StreamMemory},
-        InputReference, Origin, OutputReference, StreamAccessKind, StreamReference,
+        InputReference, Origin, OutputReference, SourceSpan, StreamAccessKind, StreamReference,
+        VariableReference,
     },
 };
 mod close;
@@ -56,6 +57,7 @@ impl DefaultStmtFormatter for SolidityFormatter {
         with: Option<Vec<Expr>>,
         local_frequencies: Vec<streamir_lib::ir::LocalFreqRef>,
         windows: Vec<streamir_lib::ir::WindowReference>,
+        _span: SourceSpan,
     ) -> String {
         assert!(windows.is_empty());
         assert!(local_frequencies.is_empty());
@@ -65,7 +67,7 @@ impl DefaultStmtFormatter for SolidityFormatter {
         format!("{};", self.call_function(SpawnFunction { sr }, parameter))
     }
 
-    fn eval(&self, sr: OutputReference, with: Expr, idx: usize) -> String {
+    fn eval(&self, sr: OutputReference, with: Expr, idx: usize, _span: SourceSpan) -> String {
         if let Some(trigger_idx) = self.triggers.get(&sr) {
             let ExprKind::Constant(Constant::Str(msg)) = &with.kind else {
                 panic!("Trigger messages have to be static")
@@ -101,16 +103,16 @@ impl DefaultStmtFormatter for SolidityFormatter {
             let parameter = parameter
                 .into_iter()
                 .chain(syn_accesses.iter().map(|sr| self.name(*sr).to_string()))
+                .chain(
+                    with.variables()
+                        .into_iter()
+                        .map(|(var, ty)| self.variable(var, ty)),
+                )
                 .collect();
 
             let ty = with.ty.clone();
             let c = self.call_function(
-                EvalFunction {
-                    sr,
-                    with,
-                    idx,
-                    syn_accesses,
-                },
+                EvalFunction::new(sr, with, idx, syn_accesses, self),
                 parameter,
             );
             format!(
@@ -126,6 +128,7 @@ impl DefaultStmtFormatter for SolidityFormatter {
         sr: OutputReference,
         local_frequencies: Vec<streamir_lib::ir::LocalFreqRef>,
         windows: Vec<streamir_lib::ir::WindowReference>,
+        _span: SourceSpan,
     ) -> String {
         assert!(local_frequencies.is_empty());
         assert!(windows.is_empty());
@@ -201,4 +204,14 @@ impl DefaultStmtFormatter for SolidityFormatter {
             self.stmt(inner)
         )
     }
+
+    fn r#let(&self, var: VariableReference, expr: Expr, inner: streamir_lib::ir::Stmt) -> String {
+        format!(
+            "{{\n{} {} = {};\n{}\n}}",
+            self.ty(expr.ty.clone()),
+            self.variable(var, expr.ty.clone()),
+            self.expr(expr),
+            self.stmt(inner)
+        )
+    }
 }
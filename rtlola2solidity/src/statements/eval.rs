@@ -8,10 +8,39 @@ use streamir_lib::{
 use crate::{functions::FunctionDefinition, RequirementKey, SolidityFormatter};
 
 pub(super) struct EvalFunction {
-    pub(super) sr: OutputReference,
-    pub(super) with: Expr,
-    pub(super) idx: usize,
-    pub(super) syn_accesses: Vec<StreamReference>,
+    sr: OutputReference,
+    with: Expr,
+    idx: usize,
+    variant: usize,
+    syn_accesses: Vec<StreamReference>,
+}
+
+impl EvalFunction {
+    /// Creates the function of the eval clause with the given expression
+    ///
+    /// Copies of an eval statement can use different local variables in their expression (or none),
+    /// so each distinct expression of a clause gets its own function.
+    pub(super) fn new(
+        sr: OutputReference,
+        with: Expr,
+        idx: usize,
+        syn_accesses: Vec<StreamReference>,
+        f: &SolidityFormatter,
+    ) -> Self {
+        let mut eval_variants = f.eval_variants.lock().unwrap();
+        let variants = eval_variants.entry((sr, idx)).or_default();
+        let variant = variants.iter().position(|e| *e == with).unwrap_or_else(|| {
+            variants.push(with.clone());
+            variants.len() - 1
+        });
+        Self {
+            sr,
+            with,
+            idx,
+            variant,
+            syn_accesses,
+        }
+    }
 }
 
 impl FunctionDefinition for EvalFunction {
@@ -29,15 +58,17 @@ impl FunctionDefinition for EvalFunction {
                     .join(",")
             })
             .unwrap_or_default();
-        format!(
-            "{}({}{local_streams})",
-            self.name(f),
-            if parameter.is_empty() || local_streams.is_empty() {
-                parameter
-            } else {
-                format!("{parameter}, ")
-            },
-        )
+        let variables = self
+            .with
+            .variables()
+            .into_iter()
+            .map(|(var, ty)| format!("{} {}", f.ty(ty.clone()), f.variable(var, ty)))
+            .join(", ");
+        let arguments = [parameter, local_streams, variables]
+            .into_iter()
+            .filter(|arguments| !arguments.is_empty())
+            .join(", ");
+        format!("{}({arguments})", self.name(f))
     }
 
     fn body(self, f: &SolidityFormatter) -> String {
@@ -50,11 +81,15 @@ impl FunctionDefinition for EvalFunction {
     }
 
     fn key(&self) -> RequirementKey {
-        RequirementKey::EvalFunction(self.sr.sr(), self.idx)
+        RequirementKey::EvalFunction(self.sr.sr(), self.idx, self.variant)
     }
 
     fn name(&self, f: &SolidityFormatter) -> String {
-        format!("eval_{}_{}", f.name(self.sr.sr()), self.idx)
+        let name = format!("eval_{}_{}", f.name(self.sr.sr()), self.idx);
+        match self.variant {
+            0 => name,
+            variant => format!("{name}_variant{variant}"),
+        }
     }
 
     fn returns(&self, f: &SolidityFormatter) -> Vec<(streamir_lib::ir::Type, String)> {
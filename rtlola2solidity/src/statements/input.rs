@@ -18,7 +18,7 @@ impl FunctionDefinition for EvalInputFunction {
     }
 
     fn key(&self) -> RequirementKey {
-        RequirementKey::EvalFunction(StreamReference::In(self.0), 0)
+        RequirementKey::EvalFunction(StreamReference::In(self.0), 0, 0)
     }
 
     fn name(&self, f: &SolidityFormatter) -> String {
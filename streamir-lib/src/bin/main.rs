@@ -1,36 +1,494 @@
 use std::{path::PathBuf, process::exit};
 
-use clap::{Parser, ValueEnum};
+use clap::{Parser, Subcommand, ValueEnum};
+use itertools::Itertools;
 use rtlola_frontend::{Handler, ParserConfig};
-use streamir_lib::{ir::DebugFormatter, parse, translate, ParseError};
+use similar::{ChangeTag, TextDiff};
+use streamir_lib::{
+    ir::{
+        expressions::Constant, DebugFormatter, DotFormatter, DotView, OverflowPolicy, StreamIr,
+        StreamReference, Task, Type, ValueRange,
+    },
+    optimize, parse_with_sources,
+    pipeline::Pipeline,
+    rewrite_rules::{EvaluateGuards, RewriteRule, SpecializeInputs},
+    translate,
+    work::WorkReport,
+    ParseError,
+};
 
 #[derive(Parser)]
+/// Explore the StreamIR of a specification
 struct Args {
     spec: PathBuf,
     #[clap(short, long)]
     optimize_all: bool,
+    #[clap(long, conflicts_with = "optimize_all")]
+    /// The rewriting pipeline to apply: the name of a preset, a list of rules, or `@` followed by the path of a pipeline file
+    pipeline: Option<Pipeline>,
+    #[clap(long)]
+    /// Print the StreamIR in the textual format that can be read back by `StreamIr::from_text`
+    text: bool,
+    #[clap(long, conflicts_with = "text")]
+    /// Show the location in the specification of each spawn, eval and close statement
+    locations: bool,
+    #[clap(long)]
+    /// How integer operations treat results that do not fit their type: `wrap` (the default), `saturate` or `trap`
+    overflow: Option<OverflowPolicy>,
+    #[clap(subcommand)]
+    command: Option<Command>,
 }
 
-#[derive(ValueEnum, Clone)]
-enum Optimization {}
+#[derive(Subcommand)]
+enum Command {
+    /// Apply the given rules and show the changes of each application of a rule that changed the StreamIR
+    ///
+    /// The pipeline is applied before the rules.
+    Rewrite {
+        /// The rules to apply: the name of a preset, a list of rules, or `@` followed by the path of a pipeline file
+        rules: Pipeline,
+        #[clap(long, value_enum, default_value_t = DiffStyle::Unified)]
+        diff: DiffStyle,
+        #[clap(long, default_value_t = 160)]
+        /// The width of the side-by-side diff
+        width: usize,
+    },
+    /// Print the StreamIR specialized to the event-based or time-based fragment by partially evaluating the guards
+    ///
+    /// The pipeline is applied after the specialization.
+    Specialize {
+        #[clap(long, value_enum, default_value_t = Fragment::Event)]
+        fragment: Fragment,
+        #[clap(long, value_delimiter = ',', conflicts_with = "fragment")]
+        /// Specialize the event-based fragment to events with new values for exactly the given input streams
+        inputs: Vec<String>,
+        #[clap(long, value_delimiter = ',')]
+        /// Specialize the StreamIR to input streams carrying a fixed value for the whole run, given as `name=value`,
+        /// and remove the output streams that become constant
+        values: Vec<String>,
+    },
+    /// Print the memory, windows, schedule and livetime equivalence classes of the streams
+    Info {
+        #[clap(long, value_enum, value_delimiter = ',')]
+        /// Only print the given sections
+        show: Vec<Section>,
+    },
+    /// Print the range of the values of each stream, constant outputs, triggers that never fire and possible runtime errors
+    Ranges {
+        #[clap(long, value_delimiter = ',')]
+        /// Assume that input streams only receive values in the given bounds, given as `name=min..max`
+        bounds: Vec<String>,
+    },
+    /// Print an upper bound on the statements executed for each set of inputs arriving together and at each deadline
+    ///
+    /// The pipeline is applied after specializing the StreamIR to the event or deadline, and defaults to the preset `all`.
+    Work {
+        #[clap(long, value_delimiter = ';')]
+        /// Only consider events with new values for the given sets of input streams, given as `a,b;c`
+        events: Vec<String>,
+    },
+    /// Print a graph of the StreamIR in the DOT format of Graphviz
+    Dot {
+        #[clap(long, value_enum, default_value_t = Graph::Dependencies)]
+        graph: Graph,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+enum DiffStyle {
+    Unified,
+    SideBySide,
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+enum Fragment {
+    Event,
+    Time,
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+enum Graph {
+    /// The streams with edges to the streams they access
+    Dependencies,
+    /// The tree of statements
+    Statements,
+}
+
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq)]
+enum Section {
+    Memory,
+    Windows,
+    Schedule,
+    Livetime,
+}
+
+fn print(ir: StreamIr, text: bool, locations: bool) -> String {
+    if text {
+        return ir.to_text();
+    }
+    let formatter = DebugFormatter::new(&ir).with_locations(locations);
+    translate(ir, formatter)
+}
+
+/// A line of a diff
+#[derive(Clone, Copy)]
+enum Line<'a> {
+    Both(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// The number of unchanged lines shown around a change
+const CONTEXT: usize = 3;
 
-fn print(config: &ParserConfig, optimize_all: bool) -> Result<String, ParseError> {
-    let streamir = parse(config)?;
-    let streamir = if optimize_all {
-        streamir_lib::optimize_all(streamir).expect("optimize error")
+/// Computes a line-based diff
+fn diff<'a>(before: &'a str, after: &'a str) -> Vec<Line<'a>> {
+    TextDiff::from_lines(before, after)
+        .iter_all_changes()
+        .map(|change| {
+            let line = change.value().trim_end_matches('\n');
+            match change.tag() {
+                ChangeTag::Equal => Line::Both(line),
+                ChangeTag::Delete => Line::Removed(line),
+                ChangeTag::Insert => Line::Added(line),
+            }
+        })
+        .collect()
+}
+
+fn format_diff(before: &str, after: &str, style: DiffStyle, width: usize) -> String {
+    let lines = diff(before, after);
+    let changed = lines
+        .iter()
+        .positions(|line| !matches!(line, Line::Both(_)))
+        .collect::<Vec<_>>();
+    let column = width.saturating_sub(3) / 2;
+    let mut res = Vec::new();
+    let mut last_shown = None;
+    for (idx, line) in lines.iter().enumerate() {
+        let near_change = changed.iter().any(|c| c.abs_diff(idx) <= CONTEXT);
+        if !near_change {
+            continue;
+        }
+        if last_shown.is_some_and(|last| last + 1 != idx) {
+            res.push("...".to_string());
+        }
+        last_shown = Some(idx);
+        let formatted = match (style, line) {
+            (DiffStyle::Unified, Line::Both(l)) => format!(" {l}"),
+            (DiffStyle::Unified, Line::Removed(l)) => format!("-{l}"),
+            (DiffStyle::Unified, Line::Added(l)) => format!("+{l}"),
+            (DiffStyle::SideBySide, Line::Both(l)) => {
+                format!("{:column$} | {}", truncate(l, column), truncate(l, column))
+            }
+            (DiffStyle::SideBySide, Line::Removed(l)) => {
+                format!("{:column$} <", truncate(l, column))
+            }
+            (DiffStyle::SideBySide, Line::Added(l)) => {
+                format!("{:column$} > {}", "", truncate(l, column))
+            }
+        };
+        res.push(formatted.trim_end().to_string());
+    }
+    res.join("\n")
+}
+
+fn truncate(line: &str, width: usize) -> String {
+    line.chars().take(width).collect()
+}
+
+fn rewrite(
+    ir: StreamIr,
+    rules: Pipeline,
+    text: bool,
+    locations: bool,
+    style: DiffStyle,
+    width: usize,
+) -> String {
+    let mut ir = ir;
+    let mut reports = Vec::new();
+    for rewriter in rules.rewriters() {
+        let result = rewriter.and_then(|rewriter| {
+            rewriter
+                .with_snapshots(move |rule, before, after| {
+                    let before = print(before.clone(), text, locations);
+                    let after = print(after.clone(), text, locations);
+                    println!("=== {rule}");
+                    println!("{}", format_diff(&before, &after, style, width));
+                })
+                .run_with_report(ir)
+        });
+        let (rewritten, report) = match result {
+            Ok(res) => res,
+            Err(e) => {
+                eprintln!("{e}");
+                exit(1)
+            }
+        };
+        ir = rewritten;
+        reports.push(report.to_string());
+    }
+    reports.join("\n")
+}
+
+fn specialize(
+    ir: &StreamIr,
+    fragment: Fragment,
+    inputs: Vec<String>,
+    values: Vec<String>,
+) -> Result<Vec<Box<dyn RewriteRule>>, String> {
+    let mut rules: Vec<Box<dyn RewriteRule>> = Vec::new();
+    if !values.is_empty() {
+        let values = values
+            .iter()
+            .map(|value| {
+                let (name, value) = value
+                    .split_once('=')
+                    .ok_or_else(|| format!("expected `name=value`, found `{value}`"))?;
+                let name = name.trim();
+                let ty = ir
+                    .stream_by_name(name)
+                    .map(|sr| &ir.stream_memory(sr).ty)
+                    .ok_or_else(|| {
+                        format!("input stream {name} does not exist in the specification")
+                    })?;
+                let value = parse_value(value.trim(), ty)
+                    .ok_or_else(|| format!("`{value}` is not a value of type {ty}"))?;
+                Ok((name.to_owned(), value))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        rules.push(Box::new(
+            SpecializeInputs::new(ir, values).map_err(|e| e.to_string())?,
+        ));
+    }
+    if inputs.is_empty() {
+        rules.push(match fragment {
+            Fragment::Event => Box::new(EvaluateGuards::only_event_based()),
+            Fragment::Time => Box::new(EvaluateGuards::only_time_based()),
+        });
+        return Ok(rules);
+    }
+    let inputs = inputs
+        .iter()
+        .map(|name| {
+            ir.inputs()
+                .map(StreamReference::In)
+                .find(|sr| ir.name(*sr) == name.trim())
+                .ok_or_else(|| format!("input stream {name} does not exist in the specification"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    rules.push(Box::new(EvaluateGuards::only_event_based()));
+    rules.push(Box::new(EvaluateGuards::only_streams(inputs)));
+    Ok(rules)
+}
+
+/// Parses the value of a stream with the given type
+fn parse_value(value: &str, ty: &Type) -> Option<Constant> {
+    match ty {
+        Type::UInt(bits) => value.parse().ok().map(|v| Constant::UInt(v, *bits)),
+        Type::Int(bits) => value.parse().ok().map(|v| Constant::Int(v, *bits)),
+        Type::Bool => value.parse().ok().map(Constant::Bool),
+        Type::Float32 => value.parse().ok().map(Constant::Float32),
+        Type::Float64 => value.parse().ok().map(Constant::Float64),
+        Type::String => Some(Constant::Str(value.to_owned())),
+        _ => None,
+    }
+}
+
+fn work(ir: &StreamIr, pipeline: &Pipeline, events: Vec<String>) -> Result<String, String> {
+    let report = if events.is_empty() {
+        WorkReport::new(ir, pipeline)
+    } else {
+        let events = events
+            .iter()
+            .map(|event| {
+                event
+                    .split(',')
+                    .map(|name| {
+                        ir.inputs()
+                            .find(|i| ir.name(StreamReference::In(*i)) == name.trim())
+                            .ok_or_else(|| {
+                                format!("input stream {name} does not exist in the specification")
+                            })
+                    })
+                    .collect()
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        WorkReport::with_events(ir, pipeline, events)
+    };
+    report
+        .map(|report| report.to_string())
+        .map_err(|e| e.to_string())
+}
+
+fn ranges(ir: &StreamIr, bounds: Vec<String>) -> Result<String, String> {
+    let bounds = bounds
+        .iter()
+        .map(|bound| {
+            let (name, range) = bound
+                .split_once('=')
+                .and_then(|(name, range)| Some((name.trim(), range.split_once("..")?)))
+                .ok_or_else(|| format!("expected `name=min..max`, found `{bound}`"))?;
+            let sr = ir
+                .stream_by_name(name)
+                .filter(|sr| matches!(sr, StreamReference::In(_)))
+                .ok_or_else(|| {
+                    format!("input stream {name} does not exist in the specification")
+                })?;
+            let ty = &ir.stream_memory(sr).ty;
+            let range = match (
+                ty,
+                parse_value(range.0.trim(), ty),
+                parse_value(range.1.trim(), ty),
+            ) {
+                (_, Some(Constant::UInt(min, _)), Some(Constant::UInt(max, _))) => {
+                    ValueRange::Int {
+                        min: min.into(),
+                        max: max.into(),
+                    }
+                }
+                (_, Some(Constant::Int(min, _)), Some(Constant::Int(max, _))) => ValueRange::Int {
+                    min: min.into(),
+                    max: max.into(),
+                },
+                (
+                    _,
+                    Some(Constant::Float32(min) | Constant::Float64(min)),
+                    Some(Constant::Float32(max) | Constant::Float64(max)),
+                ) => ValueRange::Float { min, max },
+                (ty, _, _) => return Err(format!("`{bound}` is not a range of type {ty}")),
+            };
+            Ok((sr, range))
+        })
+        .collect::<Result<_, String>>()?;
+    Ok(ir.value_ranges_with(&bounds).to_string())
+}
+
+fn info(ir: &StreamIr, show: Vec<Section>) -> String {
+    let show = if show.is_empty() {
+        vec![
+            Section::Memory,
+            Section::Windows,
+            Section::Schedule,
+            Section::Livetime,
+        ]
     } else {
-        streamir
+        show
     };
-    let formatter = DebugFormatter::new(&streamir);
-    Ok(translate(streamir, formatter))
+    show.into_iter()
+        .map(|section| {
+            let name = section.to_possible_value().unwrap().get_name().to_owned();
+            let lines = match section {
+                Section::Memory => memory_info(ir),
+                Section::Windows => windows_info(ir),
+                Section::Schedule => schedule_info(ir),
+                Section::Livetime => livetime_info(ir),
+            };
+            let lines = lines.iter().map(|line| format!("    {line}")).join("\n");
+            format!("{name}:\n{lines}")
+        })
+        .join("\n")
+}
+
+/// The type and the memory of each stream
+fn memory_info(ir: &StreamIr) -> Vec<String> {
+    ir.sr2memory
+        .iter()
+        .sorted_by_key(|(sr, _)| **sr)
+        .map(|(_, memory)| {
+            let delay = match memory.delay {
+                0 => String::new(),
+                delay => format!(", delay {delay}"),
+            };
+            format!("{}: {}, {:?}{delay}", memory.name, memory.ty, memory.buffer)
+        })
+        .collect()
+}
+
+/// The aggregation and the origin of each window
+fn windows_info(ir: &StreamIr) -> Vec<String> {
+    ir.wref2window
+        .values()
+        .sorted_by_key(|window| window.wref)
+        .map(|window| {
+            format!(
+                "{}: {:?} of {} in the {} of {} over {:?}: {}",
+                window.wref,
+                window.op,
+                ir.name(window.target),
+                window.origin,
+                ir.name(window.caller),
+                window.kind,
+                window.ty
+            )
+        })
+        .collect()
+}
+
+/// The local frequencies and the deadlines of the static schedule
+fn schedule_info(ir: &StreamIr) -> Vec<String> {
+    let local = ir
+        .lref2lfreq
+        .iter()
+        .sorted_by_key(|(lref, _)| **lref)
+        .map(|(lref, freq)| {
+            format!(
+                "local {lref}: every {:?} for {}",
+                freq.dur,
+                ir.name(freq.sr.sr())
+            )
+        });
+    let global = ir.static_schedule.iter().flat_map(|schedule| {
+        let deadlines = schedule.deadlines.iter().map(|deadline| {
+            let due = deadline
+                .due
+                .iter()
+                .map(|task| match task {
+                    Task::Spawn(sr) => format!("spawn {}", ir.name(sr.sr())),
+                    Task::Eval(sr) => format!("eval {}", ir.name(sr.sr())),
+                    Task::Close(sr) => format!("close {}", ir.name(sr.sr())),
+                })
+                .join(", ");
+            format!("after {:?}: {due}", deadline.pause)
+        });
+        std::iter::once(format!("hyper period {:?}", schedule.hyper_period)).chain(deadlines)
+    });
+    local.chain(global).collect()
+}
+
+/// The classes of streams with equivalent livetimes
+fn livetime_info(ir: &StreamIr) -> Vec<String> {
+    let mut classes: Vec<Vec<StreamReference>> = Vec::new();
+    for sr in ir.streams().sorted() {
+        match classes
+            .iter_mut()
+            .find(|class| ir.livetime_equivalences.is_equivalent(class[0], sr))
+        {
+            Some(class) => class.push(sr),
+            None => classes.push(vec![sr]),
+        }
+    }
+    classes
+        .into_iter()
+        .map(|class| format!("[{}]", class.into_iter().map(|sr| ir.name(sr)).join(", ")))
+        .collect()
 }
 
 fn main() {
-    let Args { spec, optimize_all } = Args::parse();
+    let Args {
+        spec,
+        optimize_all,
+        pipeline,
+        text,
+        locations,
+        overflow,
+        command,
+    } = Args::parse();
+    let pipeline = pipeline.or_else(|| optimize_all.then(|| Pipeline::preset("all").unwrap()));
 
     let config = ParserConfig::from_path(spec).unwrap();
-    match print(&config, optimize_all) {
-        Ok(s) => println!("{s}"),
+    let mut streamir = match parse_with_sources(&config) {
+        Ok(streamir) => streamir,
         Err(ParseError::FrontendError(e)) => {
             let handler = Handler::from(&config);
             handler.emit_error(&e);
@@ -40,5 +498,67 @@ fn main() {
             eprintln!("{other}");
             exit(1)
         }
+    };
+    if let Some(overflow) = overflow {
+        streamir.set_overflow(overflow);
     }
+    let apply = |ir: StreamIr, pipeline: &Option<Pipeline>| match pipeline {
+        Some(pipeline) => pipeline.run(ir).expect("optimize error"),
+        None => ir,
+    };
+
+    let output = match command {
+        None => print(apply(streamir, &pipeline), text, locations),
+        Some(Command::Rewrite { rules, diff, width }) => rewrite(
+            apply(streamir, &pipeline),
+            rules,
+            text,
+            locations,
+            diff,
+            width,
+        ),
+        Some(Command::Specialize {
+            fragment,
+            inputs,
+            values,
+        }) => {
+            let rules = match specialize(&streamir, fragment, inputs, values) {
+                Ok(rules) => rules,
+                Err(e) => {
+                    eprintln!("{e}");
+                    exit(1)
+                }
+            };
+            let specialized = optimize(streamir, rules).expect("optimize error");
+            print(apply(specialized, &pipeline), text, locations)
+        }
+        Some(Command::Info { show }) => info(&apply(streamir, &pipeline), show),
+        Some(Command::Ranges { bounds }) => match ranges(&apply(streamir, &pipeline), bounds) {
+            Ok(report) => report,
+            Err(e) => {
+                eprintln!("{e}");
+                exit(1)
+            }
+        },
+        Some(Command::Work { events }) => {
+            let pipeline = pipeline.unwrap_or_else(|| Pipeline::preset("all").unwrap());
+            match work(&streamir, &pipeline, events) {
+                Ok(report) => report,
+                Err(e) => {
+                    eprintln!("{e}");
+                    exit(1)
+                }
+            }
+        }
+        Some(Command::Dot { graph }) => {
+            let view = match graph {
+                Graph::Dependencies => DotView::Dependencies,
+                Graph::Statements => DotView::Statements,
+            };
+            let ir = apply(streamir, &pipeline);
+            let formatter = DotFormatter::new(&ir, view);
+            translate(ir, formatter)
+        }
+    };
+    println!("{output}");
 }
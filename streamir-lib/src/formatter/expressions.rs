@@ -3,8 +3,8 @@
 use itertools::Itertools;
 
 use crate::ir::{
-    expressions::{Constant, Expr, ExprKind, Function, Operator},
-    StreamReference, Type, WindowReference,
+    expressions::{Constant, Expr, ExprKind, Function, FunctionSignature, Operator},
+    StreamReference, Type, VariableReference, WindowReference,
 };
 
 /// A trait definining how the parts of the stream expression are represented in the target language.
@@ -34,7 +34,7 @@ pub trait ExprFormatter {
         &self,
         sr: StreamReference,
         offset: u32,
-        default: Expr,
+        default: Option<Expr>,
         parameters: Vec<Expr>,
     ) -> Self::Return;
 
@@ -42,13 +42,17 @@ pub trait ExprFormatter {
     fn hold_access(
         &self,
         sr: StreamReference,
-        default: Expr,
+        default: Option<Expr>,
         parameters: Vec<Expr>,
     ) -> Self::Return;
 
     /// Returns the code that is used to access a stream with a get access.
-    fn get_access(&self, sr: StreamReference, default: Expr, parameters: Vec<Expr>)
-        -> Self::Return;
+    fn get_access(
+        &self,
+        sr: StreamReference,
+        default: Option<Expr>,
+        parameters: Vec<Expr>,
+    ) -> Self::Return;
 
     /// Returns the code that is used to access a stream with is_fresh.
     fn is_fresh(&self, sr: StreamReference, parameters: Vec<Expr>) -> Self::Return;
@@ -80,9 +84,16 @@ pub trait ExprFormatter {
     /// Returns the code, that is used to call a function.
     fn function_call(&self, function: Function, args: Vec<Expr>) -> Self::Return;
 
+    /// Returns the code, that is used to access a variable bound by a [Stmt::Let](crate::ir::Stmt::Let).
+    fn variable(&self, var: VariableReference, ty: Type) -> Self::Return;
+
+    /// Returns the code, that replaces a missing optional value by a default value.
+    fn with_default(&self, expr: Expr, default: Expr) -> Self::Return;
+
     /// Formats any expression in the target language.
     fn expr(&self, expr: Expr) -> Self::Return {
-        match expr.kind {
+        let Expr { ty, kind, .. } = expr;
+        match kind {
             ExprKind::Constant(c) => self.constant(c),
             ExprKind::BinaryOperation(op, lhs, rhs) => self.binary(op, *lhs, *rhs),
             ExprKind::UnaryOperation(op, operand) => self.unary(op, *operand),
@@ -97,18 +108,18 @@ pub trait ExprFormatter {
                 offset,
                 default,
                 parameters,
-            } => self.offset_access(target, offset, *default, parameters),
+            } => self.offset_access(target, offset, default.map(|b| *b), parameters),
             ExprKind::HoldStreamAccess {
                 target,
                 default,
                 parameters,
-            } => self.hold_access(target, *default, parameters),
+            } => self.hold_access(target, default.map(|b| *b), parameters),
             ExprKind::IsFresh { target, parameters } => self.is_fresh(target, parameters),
             ExprKind::GetAccess {
                 target,
                 default,
                 parameters,
-            } => self.get_access(target, *default, parameters),
+            } => self.get_access(target, default.map(|b| *b), parameters),
             ExprKind::WindowAccess {
                 target: _,
                 window,
@@ -127,6 +138,8 @@ pub trait ExprFormatter {
             ExprKind::LambdaParameterAccess(window_reference, idx) => {
                 self.lambda_parameter_access(window_reference, idx)
             }
+            ExprKind::Variable(var) => self.variable(var, ty),
+            ExprKind::Default { expr, default } => self.with_default(*expr, *default),
         }
     }
 }
@@ -172,15 +185,25 @@ where
         &self,
         sr: StreamReference,
         offset: u32,
-        default: Expr,
+        default: Option<Expr>,
         parameters: Vec<Expr>,
     ) -> String;
 
     /// Returns the code, that is used to access the given stream asyncronously.
-    fn hold_access(&self, sr: StreamReference, default: Expr, parameters: Vec<Expr>) -> String;
+    fn hold_access(
+        &self,
+        sr: StreamReference,
+        default: Option<Expr>,
+        parameters: Vec<Expr>,
+    ) -> String;
 
     /// Returns the code that is used to access a stream with a get access.
-    fn get_access(&self, sr: StreamReference, default: Expr, parameters: Vec<Expr>) -> String;
+    fn get_access(
+        &self,
+        sr: StreamReference,
+        default: Option<Expr>,
+        parameters: Vec<Expr>,
+    ) -> String;
 
     /// Returns the code that is used to access a stream with is_fresh.
     fn is_fresh(&self, sr: StreamReference, parameters: Vec<Expr>) -> String;
@@ -203,6 +226,9 @@ where
     /// Returns the code, that is used to cast an Expr to a given type.
     fn cast(&self, ty: Type, expr: Expr) -> String;
 
+    /// Returns the code, that replaces a missing optional value by a default value.
+    fn with_default(&self, expr: Expr, default: Expr) -> String;
+
     /// Returns the code, that constructs a tuple out of its elements.
     fn tuple(&self, inner: Vec<Expr>) -> String {
         format!("({})", inner.into_iter().map(|e| self.expr(e)).join(", "))
@@ -218,6 +244,11 @@ where
         let args = args.into_iter().map(|e| self.expr(e)).join(", ");
         format!("{}({})", self.function(function), args)
     }
+
+    /// Returns the code, that is used to access a variable bound by a [Stmt::Let](crate::ir::Stmt::Let).
+    fn variable(&self, var: VariableReference, _ty: Type) -> String {
+        format!("var_{var}")
+    }
 }
 
 impl<X> ExprFormatter for X
@@ -250,7 +281,7 @@ where
         &self,
         stream: StreamReference,
         offset: u32,
-        default: Expr,
+        default: Option<Expr>,
         parameters: Vec<Expr>,
     ) -> Self::Return {
         <Self as DefaultExprFormatter>::offset_access(self, stream, offset, default, parameters)
@@ -259,7 +290,7 @@ where
     fn hold_access(
         &self,
         stream: StreamReference,
-        default: Expr,
+        default: Option<Expr>,
         parameters: Vec<Expr>,
     ) -> Self::Return {
         <Self as DefaultExprFormatter>::hold_access(self, stream, default, parameters)
@@ -268,7 +299,7 @@ where
     fn get_access(
         &self,
         stream: StreamReference,
-        default: Expr,
+        default: Option<Expr>,
         parameters: Vec<Expr>,
     ) -> Self::Return {
         <Self as DefaultExprFormatter>::get_access(self, stream, default, parameters)
@@ -313,6 +344,13 @@ where
     fn function_call(&self, function: Function, args: Vec<Expr>) -> Self::Return {
         <Self as DefaultExprFormatter>::function_call(self, function, args)
     }
+    fn variable(&self, var: VariableReference, ty: Type) -> Self::Return {
+        <Self as DefaultExprFormatter>::variable(self, var, ty)
+    }
+
+    fn with_default(&self, expr: Expr, default: Expr) -> Self::Return {
+        <Self as DefaultExprFormatter>::with_default(self, expr, default)
+    }
 }
 
 /// A formatter defining how operators are represented in the target language.
@@ -659,6 +697,39 @@ pub trait FunctionFormatter {
     /// Returns the name of the max function in the target language.
     fn function_max(&self) -> Self::Return;
 
+    /// Returns the name of the floor function in the target language.
+    fn function_floor(&self) -> Self::Return;
+
+    /// Returns the name of the ceil function in the target language.
+    fn function_ceil(&self) -> Self::Return;
+
+    /// Returns the name of the rounding function in the target language.
+    fn function_round(&self) -> Self::Return;
+
+    /// Returns the name of the exponential function in the target language.
+    fn function_exp(&self) -> Self::Return;
+
+    /// Returns the name of the natural logarithm in the target language.
+    fn function_ln(&self) -> Self::Return;
+
+    /// Returns the name of the logarithm function in the target language.
+    fn function_log(&self) -> Self::Return;
+
+    /// Returns the name of the power function in the target language.
+    fn function_pow(&self) -> Self::Return;
+
+    /// Returns the name of the signum function in the target language.
+    fn function_signum(&self) -> Self::Return;
+
+    /// Returns the name of the clamp function in the target language.
+    fn function_clamp(&self) -> Self::Return;
+
+    /// Returns the name of the atan2 function in the target language.
+    fn function_atan2(&self) -> Self::Return;
+
+    /// Returns the name of the external function with the given name and signature in the target language.
+    fn function_external(&self, name: String, signature: FunctionSignature) -> Self::Return;
+
     /// Formats a function in the target language.
     fn function(&self, f: Function) -> Self::Return {
         match f {
@@ -672,6 +743,17 @@ pub trait FunctionFormatter {
             Function::Arctan => self.function_arctan(),
             Function::Min => self.function_min(),
             Function::Max => self.function_max(),
+            Function::Floor => self.function_floor(),
+            Function::Ceil => self.function_ceil(),
+            Function::Round => self.function_round(),
+            Function::Exp => self.function_exp(),
+            Function::Ln => self.function_ln(),
+            Function::Log => self.function_log(),
+            Function::Pow => self.function_pow(),
+            Function::Signum => self.function_signum(),
+            Function::Clamp => self.function_clamp(),
+            Function::Atan2 => self.function_atan2(),
+            Function::External(name, signature) => self.function_external(name, signature),
         }
     }
 }
@@ -727,6 +809,61 @@ pub trait DefaultFunctionFormatter {
     fn function_max(&self) -> String {
         "max".into()
     }
+
+    /// Returns the name of the floor function in the target language.
+    fn function_floor(&self) -> String {
+        "floor".into()
+    }
+
+    /// Returns the name of the ceil function in the target language.
+    fn function_ceil(&self) -> String {
+        "ceil".into()
+    }
+
+    /// Returns the name of the rounding function in the target language.
+    fn function_round(&self) -> String {
+        "round".into()
+    }
+
+    /// Returns the name of the exponential function in the target language.
+    fn function_exp(&self) -> String {
+        "exp".into()
+    }
+
+    /// Returns the name of the natural logarithm in the target language.
+    fn function_ln(&self) -> String {
+        "ln".into()
+    }
+
+    /// Returns the name of the logarithm function in the target language.
+    fn function_log(&self) -> String {
+        "log".into()
+    }
+
+    /// Returns the name of the power function in the target language.
+    fn function_pow(&self) -> String {
+        "pow".into()
+    }
+
+    /// Returns the name of the signum function in the target language.
+    fn function_signum(&self) -> String {
+        "signum".into()
+    }
+
+    /// Returns the name of the clamp function in the target language.
+    fn function_clamp(&self) -> String {
+        "clamp".into()
+    }
+
+    /// Returns the name of the atan2 function in the target language.
+    fn function_atan2(&self) -> String {
+        "atan2".into()
+    }
+
+    /// Returns the name of the external function with the given name and signature in the target language.
+    fn function_external(&self, name: String, _signature: FunctionSignature) -> String {
+        name
+    }
 }
 
 impl<X> FunctionFormatter for X
@@ -774,6 +911,50 @@ where
     fn function_max(&self) -> Self::Return {
         <Self as DefaultFunctionFormatter>::function_max(self)
     }
+
+    fn function_floor(&self) -> Self::Return {
+        <Self as DefaultFunctionFormatter>::function_floor(self)
+    }
+
+    fn function_ceil(&self) -> Self::Return {
+        <Self as DefaultFunctionFormatter>::function_ceil(self)
+    }
+
+    fn function_round(&self) -> Self::Return {
+        <Self as DefaultFunctionFormatter>::function_round(self)
+    }
+
+    fn function_exp(&self) -> Self::Return {
+        <Self as DefaultFunctionFormatter>::function_exp(self)
+    }
+
+    fn function_ln(&self) -> Self::Return {
+        <Self as DefaultFunctionFormatter>::function_ln(self)
+    }
+
+    fn function_log(&self) -> Self::Return {
+        <Self as DefaultFunctionFormatter>::function_log(self)
+    }
+
+    fn function_pow(&self) -> Self::Return {
+        <Self as DefaultFunctionFormatter>::function_pow(self)
+    }
+
+    fn function_signum(&self) -> Self::Return {
+        <Self as DefaultFunctionFormatter>::function_signum(self)
+    }
+
+    fn function_clamp(&self) -> Self::Return {
+        <Self as DefaultFunctionFormatter>::function_clamp(self)
+    }
+
+    fn function_atan2(&self) -> Self::Return {
+        <Self as DefaultFunctionFormatter>::function_atan2(self)
+    }
+
+    fn function_external(&self, name: String, signature: FunctionSignature) -> Self::Return {
+        <Self as DefaultFunctionFormatter>::function_external(self, name, signature)
+    }
 }
 
 /// A trait defining how constants are represented in the target language. (See also [DefaultConstantFormatter])
@@ -39,6 +39,9 @@ pub enum ConstructWriteError {
     #[error("IO Error")]
     /// Another io error happened.
     IO(#[from] std::io::Error),
+    #[error("The specification can not be translated: {0}")]
+    /// A construct of the specification has no translation in the target language.
+    Unsupported(String),
 }
 
 /// A formatter that uses the [ConstructStore] to compile different files.
@@ -91,6 +94,19 @@ where
         Ok(())
     }
 
+    /// Consumes the formatter and returns the content of the output files instead of writing them.
+    fn generate_strings(self) -> Result<BTreeMap<PathBuf, String>, ConstructWriteError> {
+        let mut store = self.get_construct_store().0.lock().unwrap();
+        store
+            .drain()
+            .map(|(path, content)| {
+                let mut code = Vec::new();
+                self.write_file(&mut code, content.0.into_values())?;
+                Ok((path, String::from_utf8_lossy(&code).into_owned()))
+            })
+            .collect()
+    }
+
     /// Write a single file
     fn write_file<W: Write>(
         &self,
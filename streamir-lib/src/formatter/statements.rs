@@ -3,8 +3,8 @@
 use itertools::Itertools;
 
 use crate::ir::{
-    expressions::Expr, Guard, InputReference, LocalFreqRef, OutputReference, Stmt, StreamReference,
-    WindowReference,
+    expressions::Expr, Guard, InputReference, LocalFreqRef, OutputReference, SourceSpan, Stmt,
+    StreamReference, VariableReference, WindowReference,
 };
 
 /// A trait for formatting StreamIR statements in the target language (see [DefaultStmtFormatter] for a
@@ -35,10 +35,11 @@ pub trait StmtFormatter {
         with: Option<Vec<Expr>>,
         local_frequencies: Vec<LocalFreqRef>,
         windows: Vec<WindowReference>,
+        span: SourceSpan,
     ) -> Self::Return;
 
     /// The representation of [Stmt::Eval] in the target language
-    fn eval(&self, sr: OutputReference, with: Expr, idx: usize) -> Self::Return;
+    fn eval(&self, sr: OutputReference, with: Expr, idx: usize, span: SourceSpan) -> Self::Return;
 
     /// The representation of [Stmt::Close] in the target language
     fn close(
@@ -46,6 +47,7 @@ pub trait StmtFormatter {
         sr: OutputReference,
         local_frequencies: Vec<LocalFreqRef>,
         windows: Vec<WindowReference>,
+        span: SourceSpan,
     ) -> Self::Return;
 
     /// The representation of [Stmt::If] in the target language
@@ -62,6 +64,9 @@ pub trait StmtFormatter {
         inner: Stmt,
     ) -> Self::Return;
 
+    /// The representation of [Stmt::Let] in the target language
+    fn r#let(&self, var: VariableReference, expr: Expr, inner: Stmt) -> Self::Return;
+
     /// The representation of a statement in the target language
     fn stmt(&self, stmt: Stmt) -> Self::Return {
         match stmt {
@@ -75,13 +80,20 @@ pub trait StmtFormatter {
                 with,
                 local_frequencies,
                 windows,
-            } => self.spawn(sr, with, local_frequencies, windows),
-            Stmt::Eval { sr, with, idx } => self.eval(sr, with, idx),
+                span,
+            } => self.spawn(sr, with, local_frequencies, windows, span),
+            Stmt::Eval {
+                sr,
+                with,
+                idx,
+                span,
+            } => self.eval(sr, with, idx, span),
             Stmt::Close {
                 sr,
                 local_frequencies,
                 windows,
-            } => self.close(sr, local_frequencies, windows),
+                span,
+            } => self.close(sr, local_frequencies, windows, span),
             Stmt::If(if_stmt) => {
                 let (guard, cons, alt) = if_stmt.destruct();
                 self.r#if(guard, cons, alt)
@@ -92,6 +104,7 @@ pub trait StmtFormatter {
                 sr,
                 stmt,
             } => self.assign(sr, parameter_expr, *stmt),
+            Stmt::Let { var, expr, stmt } => self.r#let(var, expr, *stmt),
         }
     }
 }
@@ -119,10 +132,11 @@ where
         with: Option<Vec<Expr>>,
         local_frequencies: Vec<LocalFreqRef>,
         windows: Vec<WindowReference>,
+        span: SourceSpan,
     ) -> String;
 
     /// The representation of [Stmt::Eval] in the target language
-    fn eval(&self, sr: OutputReference, with: Expr, idx: usize) -> String;
+    fn eval(&self, sr: OutputReference, with: Expr, idx: usize, span: SourceSpan) -> String;
 
     /// The representation of [Stmt::Close] in the target language
     fn close(
@@ -130,6 +144,7 @@ where
         sr: OutputReference,
         local_frequencies: Vec<LocalFreqRef>,
         windows: Vec<WindowReference>,
+        span: SourceSpan,
     ) -> String;
 
     /// The representation of [Stmt::If] in the target language
@@ -141,6 +156,9 @@ where
     /// The representation of [Stmt::Assign] in the target language
     fn assign(&self, sr: Vec<OutputReference>, parameter_expr: Vec<Expr>, inner: Stmt) -> String;
 
+    /// The representation of [Stmt::Let] in the target language
+    fn r#let(&self, var: VariableReference, expr: Expr, inner: Stmt) -> String;
+
     /// The representation of [Stmt::Seq] in the target language
     fn seq(&self, inner: Vec<Stmt>) -> String {
         inner.into_iter().map(|stmt| self.stmt(stmt)).join("\n")
@@ -181,12 +199,13 @@ impl<F: DefaultStmtFormatter> StmtFormatter for F {
         with: Option<Vec<Expr>>,
         local_frequencies: Vec<LocalFreqRef>,
         windows: Vec<WindowReference>,
+        span: SourceSpan,
     ) -> Self::Return {
-        <Self as DefaultStmtFormatter>::spawn(self, sr, with, local_frequencies, windows)
+        <Self as DefaultStmtFormatter>::spawn(self, sr, with, local_frequencies, windows, span)
     }
 
-    fn eval(&self, sr: OutputReference, with: Expr, idx: usize) -> Self::Return {
-        <Self as DefaultStmtFormatter>::eval(self, sr, with, idx)
+    fn eval(&self, sr: OutputReference, with: Expr, idx: usize, span: SourceSpan) -> Self::Return {
+        <Self as DefaultStmtFormatter>::eval(self, sr, with, idx, span)
     }
 
     fn close(
@@ -194,8 +213,9 @@ impl<F: DefaultStmtFormatter> StmtFormatter for F {
         sr: OutputReference,
         local_frequencies: Vec<LocalFreqRef>,
         windows: Vec<WindowReference>,
+        span: SourceSpan,
     ) -> Self::Return {
-        <Self as DefaultStmtFormatter>::close(self, sr, local_frequencies, windows)
+        <Self as DefaultStmtFormatter>::close(self, sr, local_frequencies, windows, span)
     }
 
     fn r#if(&self, guard: Guard, cons: Stmt, alt: Option<Stmt>) -> Self::Return {
@@ -214,4 +234,7 @@ impl<F: DefaultStmtFormatter> StmtFormatter for F {
     ) -> Self::Return {
         <Self as DefaultStmtFormatter>::assign(self, sr, parameter_expr, inner)
     }
+    fn r#let(&self, var: VariableReference, expr: Expr, inner: Stmt) -> Self::Return {
+        <Self as DefaultStmtFormatter>::r#let(self, var, expr, inner)
+    }
 }
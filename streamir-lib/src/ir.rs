@@ -11,40 +11,74 @@ pub use lowering::livetime_equivalences::LivetimeEquivalences;
 use memory::{Memory, StreamMemory};
 use rtlola_frontend::mir::{self};
 use windows::Window;
+mod bdd;
 mod display;
+mod dot;
 pub mod expressions;
+mod instances;
 mod lowering;
+#[cfg(feature = "serde")]
+mod map_entries;
 pub mod memory;
+mod overflow;
 #[cfg(test)]
 pub(crate) mod parse;
 mod print;
+mod ranges;
+mod resources;
 mod schedule;
+mod sources;
+pub mod text;
+mod verify;
 pub mod windows;
+pub use bdd::{Bdd, GuardBdd};
+pub use dot::{DotFormatter, DotView};
 pub use lowering::LoweringError;
+pub(crate) use lowering::Spans;
+pub use overflow::{Overflow, OverflowPolicy, UnknownOverflowPolicy};
 pub use print::DebugFormatter;
+pub use ranges::{Hazard, HazardKind, ValueRange, ValueRanges};
+pub use resources::{ResourceReport, StreamResources, WindowResources};
 pub use schedule::{Deadline, StaticSchedule, Task};
+pub use sources::{Location, SourceMap, SourceSpan, Span};
+pub use verify::VerifyError;
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// The internal representation of the StreamIR.
 pub struct StreamIr {
     /// The outermost statement of the StreamIR program.
     pub stmt: Stmt,
     /// The memory representation of each stream.
+    #[cfg_attr(feature = "serde", serde(with = "map_entries"))]
     pub sr2memory: HashMap<StreamReference, Memory>,
     /// Information on windows in the specification.
+    #[cfg_attr(feature = "serde", serde(with = "map_entries"))]
     pub wref2window: HashMap<WindowReference, Window>,
     /// A mapping from references to information of local frequencies.
+    #[cfg_attr(feature = "serde", serde(with = "map_entries"))]
     pub lref2lfreq: HashMap<LocalFreqRef, LocalFreq>,
     /// Equivalence classes for the livetime information of streams.
     pub livetime_equivalences: LivetimeEquivalences,
     /// The precomputed schedule for global periodic output streams
     pub static_schedule: Option<StaticSchedule>,
     /// The mapping of all output references that represent triggers to the corresponding trigger reference
+    #[cfg_attr(feature = "serde", serde(with = "map_entries"))]
     pub triggers: HashMap<OutputReference, usize>,
     /// The collection of streams this stream accesses non-transitively.  Includes this stream's spawn, evaluation condition, and close expressions.
+    #[cfg_attr(feature = "serde", serde(with = "map_entries"))]
     pub accesses: HashMap<StreamReference, Accesses>,
     /// The collection of streams that access the current stream non-transitively
+    #[cfg_attr(feature = "serde", serde(with = "map_entries"))]
     pub accessed_by: HashMap<StreamReference, Accesses>,
+    /// The specification file and the locations of the stream declarations
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub sources: SourceMap,
+    /// How the arithmetic operators treat integer results that do not fit their type
+    ///
+    /// Changed with [StreamIr::set_overflow], which keeps the instance bounds depending on it up to date.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub overflow: OverflowPolicy,
 }
 
 impl StreamIr {
@@ -53,6 +87,14 @@ impl StreamIr {
         &self.sr2memory[&sr]
     }
 
+    /// Sets the policy of the integer arithmetic on overflow
+    ///
+    /// The bounds on the alive instances of parameterized streams depend on the policy and are recomputed.
+    pub fn set_overflow(&mut self, overflow: OverflowPolicy) {
+        self.overflow = overflow;
+        self.bound_instances();
+    }
+
     /// Returns the name of the given stream.
     pub fn name(&self, sr: StreamReference) -> &str {
         &self.stream_memory(sr).name
@@ -135,6 +177,7 @@ impl StreamIr {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// A statement of the StreamIR.
 pub enum Stmt {
     /// A no-op operation
@@ -157,6 +200,8 @@ pub enum Stmt {
         local_frequencies: Vec<LocalFreqRef>,
         /// A list of windows that need to start with the spawn of the instance
         windows: Vec<WindowReference>,
+        /// The location of the spawn clause in the specification
+        span: SourceSpan,
     },
     /// A statement computing the new value of an output stream and writing the value to memory
     Eval {
@@ -166,6 +211,8 @@ pub enum Stmt {
         with: Expr,
         /// The index of the eval clause this statment originated from
         idx: usize,
+        /// The location of the eval clause in the specification
+        span: SourceSpan,
     },
     /// A statement closing an instance of an output stream
     /// To close a specific instance, the statement has to be inside an iterate/assign statement
@@ -176,6 +223,8 @@ pub enum Stmt {
         local_frequencies: Vec<LocalFreqRef>,
         /// A list of windows that need to be closed
         windows: Vec<WindowReference>,
+        /// The location of the close clause in the specification
+        span: SourceSpan,
     },
     /// A conditional statement
     If(IfStmt),
@@ -195,9 +244,19 @@ pub enum Stmt {
         /// The inner statement that is executed once with the calculated instance
         stmt: Box<Stmt>,
     },
+    /// A statement binding the value of a stream expression to a variable
+    Let {
+        /// The variable the value is bound to
+        var: VariableReference,
+        /// The expression computing the value
+        expr: Expr,
+        /// The statement in which the variable is bound
+        stmt: Box<Stmt>,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// An conditional statement in the StreamIR.
 pub struct IfStmt {
     /// The condition
@@ -232,6 +291,7 @@ impl IfStmt {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// The condition of an conditional statement
 pub enum Guard {
     /// Is true when the given stream received a new value in the current evaluation cycle
@@ -266,50 +326,6 @@ pub enum Guard {
     FastOr(Vec<StreamReference>),
 }
 
-impl Guard {
-    pub(crate) fn eq_liveness(
-        &self,
-        other: &Self,
-        livetime_equivalences: &LivetimeEquivalences,
-    ) -> bool {
-        match (self, other) {
-            (Self::Stream(l0), Self::Stream(r0)) => l0 == r0,
-            (Self::Alive(l0), Self::Alive(r0)) => livetime_equivalences.is_equivalent(*l0, *r0),
-            (Self::Dynamic(l0), Self::Dynamic(r0)) => l0 == r0,
-            (Self::GlobalFreq(l0), Self::GlobalFreq(r0)) => l0 == r0,
-            (Self::LocalFreq(l0), Self::LocalFreq(r0)) => l0 == r0,
-            (
-                Self::And {
-                    lhs: l_lhs,
-                    rhs: l_rhs,
-                },
-                Self::And {
-                    lhs: r_lhs,
-                    rhs: r_rhs,
-                },
-            ) => {
-                l_lhs.eq_liveness(r_lhs, livetime_equivalences)
-                    && l_rhs.eq_liveness(r_rhs, livetime_equivalences)
-            }
-            (
-                Self::Or {
-                    lhs: l_lhs,
-                    rhs: l_rhs,
-                },
-                Self::Or {
-                    lhs: r_lhs,
-                    rhs: r_rhs,
-                },
-            ) => {
-                l_lhs.eq_liveness(r_lhs, livetime_equivalences)
-                    && l_rhs.eq_liveness(r_rhs, livetime_equivalences)
-            }
-            (Self::Constant(l0), Self::Constant(r0)) => l0 == r0,
-            _ => false,
-        }
-    }
-}
-
 impl Hash for Guard {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         match self {
@@ -321,8 +337,9 @@ impl Hash for Guard {
                 state.write_u8(2);
                 sr.hash(state);
             }
-            Guard::Dynamic(_) => {
+            Guard::Dynamic(expr) => {
                 state.write_u8(3);
+                expr.hash(state);
             }
             Guard::GlobalFreq(duration) => {
                 state.write_u8(4);
@@ -362,6 +379,7 @@ impl Hash for Guard {
 pub type LocalFreqRef = usize;
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// The information of a local frequency
 pub struct LocalFreq {
     /// The frequency
@@ -383,8 +401,15 @@ impl Eq for LocalFreq {}
 /// Allows for referencing an input stream within the specification.
 pub type InputReference = usize;
 
+/// Allows for referencing a variable bound by a [Stmt::Let].
+///
+/// Variables are lexically scoped: a variable is visible in the inner statement of the binding
+/// and an inner binding of the same variable shadows the outer one.
+pub type VariableReference = usize;
+
 /// Allows for referencing an output stream within the specification.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum OutputReference {
     /// Un unparameterized stream
     Unparameterized(usize),
@@ -416,6 +441,7 @@ impl OutputReference {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// A reference of an input or output stream
 pub enum StreamReference {
     /// The reference is for an input stream
@@ -443,6 +469,7 @@ impl StreamReference {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// The origin of a stream expression (the clauses of an output stream)
 pub enum Origin {
     /// The expression was in the spawn clause
@@ -467,6 +494,7 @@ impl From<mir::Origin> for Origin {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// A reference of a window in the specification
 pub enum WindowReference {
     /// The window is a sliding window
@@ -490,6 +518,7 @@ impl WindowReference {
 
 /// Represents the type of a stream
 #[derive(Clone, Debug, Hash, Eq, PartialEq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Type {
     /// An signed integer with a specific number of bits
     Int(u16),
@@ -524,6 +553,24 @@ impl Type {
             self
         }
     }
+
+    /// Returns the number of bytes required to store a value of the type, based on its bit width
+    ///
+    /// Returns None for strings and bytestrings, whose size is not known statically.
+    /// Optional values require an additional byte for the tag.
+    pub fn size(&self) -> Option<usize> {
+        match self {
+            Type::Int(bits) | Type::UInt(bits) | Type::Fixed(bits) | Type::UFixed(bits) => {
+                Some((*bits as usize).div_ceil(8))
+            }
+            Type::Bool => Some(1),
+            Type::Float32 => Some(4),
+            Type::Float64 => Some(8),
+            Type::Option(inner) => inner.size().map(|size| size + 1),
+            Type::Tuple(items) => items.iter().map(Type::size).sum(),
+            Type::String | Type::Bytes => None,
+        }
+    }
 }
 
 impl StreamIr {
@@ -550,7 +597,9 @@ impl Stmt {
             Stmt::Parallel(stmts) | Stmt::Seq(stmts) => {
                 stmts.iter().flat_map(|s| s.all_global_freqs()).collect()
             }
-            Stmt::Iterate { stmt, .. } | Stmt::Assign { stmt, .. } => stmt.all_global_freqs(),
+            Stmt::Iterate { stmt, .. } | Stmt::Assign { stmt, .. } | Stmt::Let { stmt, .. } => {
+                stmt.all_global_freqs()
+            }
             Stmt::If(IfStmt { guard, cons, alt }) => cons
                 .all_global_freqs()
                 .into_iter()
@@ -586,6 +635,7 @@ pub type Accesses = Vec<(StreamReference, Vec<(Origin, StreamAccessKind)>)>;
 
 /// Representation of the different stream accesses
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum StreamAccessKind {
     /// Represents the synchronous access
     Sync,
@@ -615,6 +665,7 @@ pub enum StreamAccessKind {
 
 /// Offset used in the lookup expression
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Offset {
     /// A strictly positive discrete offset, e.g., `4`, or `42`
     Future(u32),
@@ -661,7 +712,7 @@ impl Stmt {
                 cons,
                 alt,
             }) => cons.contains_interate(sr) || alt.contains_interate(sr),
-            Stmt::Assign { stmt, .. } => stmt.contains_interate(sr),
+            Stmt::Assign { stmt, .. } | Stmt::Let { stmt, .. } => stmt.contains_interate(sr),
         }
     }
 }
@@ -0,0 +1,513 @@
+//! Reduced ordered binary decision diagrams (BDDs) over the boolean skeleton of guards.
+//!
+//! The atoms of a guard (new values of streams, alive checks, frequencies and dynamic conditions) are the variables of the BDD,
+//! ordered by their first occurrence.
+//! Alive checks of streams with equivalent livetimes refer to the same variable, and the shortcuts [Guard::FastAnd] and
+//! [Guard::FastOr] are expanded into their atoms.
+//! As each boolean function has a unique reduced BDD, two guards are equivalent exactly if their BDDs are identical.
+//!
+//! Guards contain no negation and therefore describe monotone functions.
+//! The smallest sum of products of a monotone function consists of all its prime implicants, which only contain positive atoms,
+//! such that a BDD can always be translated back into a guard.
+
+use std::collections::HashMap;
+
+use itertools::Itertools;
+
+use super::{Guard, LivetimeEquivalences};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// A reference to a node of a [GuardBdd]
+pub struct Bdd(usize);
+
+impl Bdd {
+    /// The BDD of guards that are never satisfied
+    pub const FALSE: Bdd = Bdd(0);
+    /// The BDD of guards that are always satisfied
+    pub const TRUE: Bdd = Bdd(1);
+}
+
+/// The variable of the terminal nodes, which is larger than the variables of all atoms
+const TERMINAL: usize = usize::MAX;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Node {
+    var: usize,
+    low: Bdd,
+    high: Bdd,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Op {
+    And,
+    Or,
+}
+
+/// A conjunction of atoms, given by their variables in ascending order
+type Cube = Vec<usize>;
+
+#[derive(Debug)]
+/// Builds the BDDs of guards over a common set of atoms
+///
+/// BDDs of the same [GuardBdd] can be compared and combined with each other.
+pub struct GuardBdd<'a> {
+    livetime_equivalences: &'a LivetimeEquivalences,
+    atoms: Vec<Guard>,
+    variables: HashMap<Guard, usize>,
+    nodes: Vec<Node>,
+    unique: HashMap<Node, Bdd>,
+    apply_cache: HashMap<(Op, Bdd, Bdd), Bdd>,
+    primes_cache: HashMap<Bdd, Vec<Cube>>,
+}
+
+impl<'a> GuardBdd<'a> {
+    /// Constructs an empty BDD, identifying alive checks of streams with equivalent livetimes
+    pub fn new(livetime_equivalences: &'a LivetimeEquivalences) -> Self {
+        let terminal = |value| Node {
+            var: TERMINAL,
+            low: value,
+            high: value,
+        };
+        Self {
+            livetime_equivalences,
+            atoms: Vec::new(),
+            variables: HashMap::new(),
+            nodes: vec![terminal(Bdd::FALSE), terminal(Bdd::TRUE)],
+            unique: HashMap::new(),
+            apply_cache: HashMap::new(),
+            primes_cache: HashMap::new(),
+        }
+    }
+
+    /// Returns the BDD of the guard
+    pub fn from_guard(&mut self, guard: &Guard) -> Bdd {
+        match guard {
+            Guard::Constant(true) => Bdd::TRUE,
+            Guard::Constant(false) => Bdd::FALSE,
+            Guard::Stream(_)
+            | Guard::Alive(_)
+            | Guard::Dynamic(_)
+            | Guard::GlobalFreq(_)
+            | Guard::LocalFreq(_) => self.atom(guard),
+            Guard::And { lhs, rhs } => {
+                let lhs = self.from_guard(lhs);
+                let rhs = self.from_guard(rhs);
+                self.and(lhs, rhs)
+            }
+            Guard::Or { lhs, rhs } => {
+                let lhs = self.from_guard(lhs);
+                let rhs = self.from_guard(rhs);
+                self.or(lhs, rhs)
+            }
+            Guard::FastAnd(srs) => srs.iter().fold(Bdd::TRUE, |res, sr| {
+                let atom = self.atom(&Guard::Stream(*sr));
+                self.and(res, atom)
+            }),
+            Guard::FastOr(srs) => srs.iter().fold(Bdd::FALSE, |res, sr| {
+                let atom = self.atom(&Guard::Stream(*sr));
+                self.or(res, atom)
+            }),
+        }
+    }
+
+    /// Returns the conjunction of both BDDs
+    pub fn and(&mut self, lhs: Bdd, rhs: Bdd) -> Bdd {
+        self.apply(Op::And, lhs, rhs)
+    }
+
+    /// Returns the disjunction of both BDDs
+    pub fn or(&mut self, lhs: Bdd, rhs: Bdd) -> Bdd {
+        self.apply(Op::Or, lhs, rhs)
+    }
+
+    /// Returns whether the first BDD implies the second
+    pub fn implies(&mut self, lhs: Bdd, rhs: Bdd) -> bool {
+        self.and(lhs, rhs) == lhs
+    }
+
+    /// Removes the atoms satisfying the predicate from the BDD by existential quantification
+    ///
+    /// The result holds whenever the BDD holds for some value of the removed atoms.
+    pub fn forget(&mut self, bdd: Bdd, predicate: impl Fn(&Guard) -> bool) -> Bdd {
+        let vars = self.atoms.iter().positions(predicate).collect::<Vec<_>>();
+        vars.into_iter().fold(bdd, |bdd, var| {
+            let mut cache = HashMap::new();
+            self.exists(bdd, var, &mut cache)
+        })
+    }
+
+    /// Returns the smallest guard of the BDD, with atoms occurring in multiple conjunctions factored out
+    pub fn to_guard(&mut self, bdd: Bdd) -> Guard {
+        let cubes = self.primes(bdd);
+        self.factor(cubes)
+    }
+
+    /// Returns a guard that is equivalent to the BDD whenever the context holds
+    ///
+    /// Atoms and conjunctions that are already implied by the context are left out of the guard.
+    pub fn simplify(&mut self, bdd: Bdd, context: Bdd) -> Guard {
+        let target = self.and(bdd, context);
+        let mut cubes = self.primes(bdd);
+        // weaken the conjunctions as long as the result agrees with the BDD under the context
+        for i in 0..cubes.len() {
+            let mut j = 0;
+            while j < cubes[i].len() {
+                let mut candidate = cubes.clone();
+                candidate[i].remove(j);
+                if self.agrees(&candidate, context, target) {
+                    cubes = candidate;
+                } else {
+                    j += 1;
+                }
+            }
+        }
+        // remove conjunctions that are not needed under the context
+        let mut i = 0;
+        while i < cubes.len() {
+            let mut candidate = cubes.clone();
+            candidate.remove(i);
+            if self.agrees(&candidate, context, target) {
+                cubes = candidate;
+            } else {
+                i += 1;
+            }
+        }
+        self.factor(cubes.into_iter().sorted().dedup().collect())
+    }
+
+    fn atom(&mut self, guard: &Guard) -> Bdd {
+        let livetime_equivalences = self.livetime_equivalences;
+        let existing = match guard {
+            Guard::Alive(sr) => self.atoms.iter().position(|atom| {
+                matches!(atom, Guard::Alive(other) if livetime_equivalences.is_equivalent(*other, *sr))
+            }),
+            guard => self.variables.get(guard).copied(),
+        };
+        let var = existing.unwrap_or_else(|| {
+            self.atoms.push(guard.clone());
+            self.variables.insert(guard.clone(), self.atoms.len() - 1);
+            self.atoms.len() - 1
+        });
+        self.node(var, Bdd::FALSE, Bdd::TRUE)
+    }
+
+    /// Returns the unique node with the given variable and successors
+    fn node(&mut self, var: usize, low: Bdd, high: Bdd) -> Bdd {
+        if low == high {
+            return low;
+        }
+        let node = Node { var, low, high };
+        if let Some(bdd) = self.unique.get(&node) {
+            return *bdd;
+        }
+        let bdd = Bdd(self.nodes.len());
+        self.nodes.push(node);
+        self.unique.insert(node, bdd);
+        bdd
+    }
+
+    /// Returns the BDD with the variable set to false and to true
+    fn cofactors(&self, bdd: Bdd, var: usize) -> (Bdd, Bdd) {
+        let node = self.nodes[bdd.0];
+        if node.var == var {
+            (node.low, node.high)
+        } else {
+            (bdd, bdd)
+        }
+    }
+
+    fn apply(&mut self, op: Op, lhs: Bdd, rhs: Bdd) -> Bdd {
+        match (op, lhs, rhs) {
+            (Op::And, Bdd::FALSE, _) | (Op::And, _, Bdd::FALSE) => return Bdd::FALSE,
+            (Op::Or, Bdd::TRUE, _) | (Op::Or, _, Bdd::TRUE) => return Bdd::TRUE,
+            (Op::And, Bdd::TRUE, other)
+            | (Op::And, other, Bdd::TRUE)
+            | (Op::Or, Bdd::FALSE, other)
+            | (Op::Or, other, Bdd::FALSE) => return other,
+            _ if lhs == rhs => return lhs,
+            _ => {}
+        }
+        // both operations are commutative
+        let key = (op, lhs.min(rhs), lhs.max(rhs));
+        if let Some(res) = self.apply_cache.get(&key) {
+            return *res;
+        }
+        let var = self.nodes[lhs.0].var.min(self.nodes[rhs.0].var);
+        let (lhs_low, lhs_high) = self.cofactors(lhs, var);
+        let (rhs_low, rhs_high) = self.cofactors(rhs, var);
+        let low = self.apply(op, lhs_low, rhs_low);
+        let high = self.apply(op, lhs_high, rhs_high);
+        let res = self.node(var, low, high);
+        self.apply_cache.insert(key, res);
+        res
+    }
+
+    fn exists(&mut self, bdd: Bdd, var: usize, cache: &mut HashMap<Bdd, Bdd>) -> Bdd {
+        let node = self.nodes[bdd.0];
+        if node.var == TERMINAL || node.var > var {
+            return bdd;
+        }
+        if node.var == var {
+            return self.or(node.low, node.high);
+        }
+        if let Some(res) = cache.get(&bdd) {
+            return *res;
+        }
+        let low = self.exists(node.low, var, cache);
+        let high = self.exists(node.high, var, cache);
+        let res = self.node(node.var, low, high);
+        cache.insert(bdd, res);
+        res
+    }
+
+    /// Returns whether the BDD holds if exactly the atoms of the conjunction hold
+    fn holds(&self, bdd: Bdd, cube: &[usize]) -> bool {
+        let mut bdd = bdd;
+        loop {
+            match bdd {
+                Bdd::FALSE => return false,
+                Bdd::TRUE => return true,
+                _ => {
+                    let node = self.nodes[bdd.0];
+                    bdd = if cube.contains(&node.var) {
+                        node.high
+                    } else {
+                        node.low
+                    };
+                }
+            }
+        }
+    }
+
+    /// Returns the prime implicants of the (monotone) BDD in lexicographic order
+    fn primes(&mut self, bdd: Bdd) -> Vec<Cube> {
+        match bdd {
+            Bdd::FALSE => return Vec::new(),
+            Bdd::TRUE => return vec![Vec::new()],
+            _ => {}
+        }
+        if let Some(primes) = self.primes_cache.get(&bdd) {
+            return primes.clone();
+        }
+        let Node { var, low, high } = self.nodes[bdd.0];
+        // as the function is monotone, it is `low || (var && high)` with `low` implying `high`
+        let with_var = self
+            .primes(high)
+            .into_iter()
+            .filter(|cube| !self.holds(low, cube))
+            .map(|cube| std::iter::once(var).chain(cube).collect())
+            .collect::<Vec<_>>();
+        let primes = self
+            .primes(low)
+            .into_iter()
+            .chain(with_var)
+            .sorted()
+            .collect::<Vec<_>>();
+        self.primes_cache.insert(bdd, primes.clone());
+        primes
+    }
+
+    /// Returns whether the disjunction of the conjunctions is equivalent to the target under the context
+    fn agrees(&mut self, cubes: &[Cube], context: Bdd, target: Bdd) -> bool {
+        let disjunction = cubes.iter().fold(Bdd::FALSE, |res, cube| {
+            let conjunction = cube.iter().fold(Bdd::TRUE, |res, var| {
+                let atom = self.node(*var, Bdd::FALSE, Bdd::TRUE);
+                self.and(res, atom)
+            });
+            self.or(res, conjunction)
+        });
+        self.and(disjunction, context) == target
+    }
+
+    /// Translates the disjunction of conjunctions into a guard, factoring out the atom occurring in most conjunctions
+    ///
+    /// Dynamic atoms can fail to evaluate unless the other atoms of their conjunction hold, e.g., if they access a stream without a new value.
+    /// They are therefore placed after all other atoms of their conjunction and only factored out of conjunctions without further dynamic atoms.
+    fn factor(&self, cubes: Vec<Cube>) -> Guard {
+        if cubes.is_empty() {
+            return Guard::Constant(false);
+        }
+        if cubes.iter().any(Vec::is_empty) {
+            return Guard::Constant(true);
+        }
+        let is_dynamic = |var: usize| matches!(self.atoms[var], Guard::Dynamic(_));
+        let factorable = |var: usize| {
+            !is_dynamic(var)
+                || cubes
+                    .iter()
+                    .filter(|cube| cube.contains(&var))
+                    .all(|cube| cube.iter().filter(|v| is_dynamic(**v)).count() == 1)
+        };
+        let Some((var, _)) = cubes
+            .iter()
+            .flatten()
+            .counts()
+            .into_iter()
+            .filter(|(var, count)| *count > 1 && factorable(**var))
+            .max_by_key(|(var, count)| (!is_dynamic(**var), *count, std::cmp::Reverse(**var)))
+        else {
+            return disjunction(cubes.iter().map(|cube| {
+                conjunction(
+                    cube.iter()
+                        .sorted_by_key(|var| is_dynamic(**var))
+                        .map(|var| self.atoms[*var].clone()),
+                )
+            }));
+        };
+        let var = *var;
+        let (with_var, without_var): (Vec<_>, Vec<_>) =
+            cubes.into_iter().partition(|cube| cube.contains(&var));
+        let with_var = with_var
+            .into_iter()
+            .map(|cube| cube.into_iter().filter(|v| *v != var).collect())
+            .collect();
+        let factored = if is_dynamic(var) {
+            conjunction([self.factor(with_var), self.atoms[var].clone()])
+        } else {
+            conjunction([self.atoms[var].clone(), self.factor(with_var)])
+        };
+        if without_var.is_empty() {
+            factored
+        } else {
+            disjunction([factored, self.factor(without_var)])
+        }
+    }
+}
+
+/// Returns the right-nested conjunction of the guards, leaving out constant true guards
+fn conjunction(guards: impl IntoIterator<Item = Guard>) -> Guard {
+    guards
+        .into_iter()
+        .filter(|guard| *guard != Guard::Constant(true))
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .reduce(|rhs, lhs| Guard::And {
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+        })
+        .unwrap_or(Guard::Constant(true))
+}
+
+/// Returns the right-nested disjunction of the guards
+fn disjunction(guards: impl IntoIterator<Item = Guard>) -> Guard {
+    guards
+        .into_iter()
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .reduce(|rhs, lhs| Guard::Or {
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+        })
+        .unwrap_or(Guard::Constant(false))
+}
+
+impl Guard {
+    /// Returns the number of atoms of the guard, which is a measure for the cost of evaluating it
+    pub fn num_atoms(&self) -> usize {
+        match self {
+            Guard::Stream(_)
+            | Guard::Alive(_)
+            | Guard::Dynamic(_)
+            | Guard::GlobalFreq(_)
+            | Guard::LocalFreq(_) => 1,
+            Guard::Constant(_) => 0,
+            Guard::And { lhs, rhs } | Guard::Or { lhs, rhs } => lhs.num_atoms() + rhs.num_atoms(),
+            Guard::FastAnd(srs) | Guard::FastOr(srs) => srs.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use winnow::Parser;
+
+    use crate::ir::{parse::parse_guard, Guard};
+
+    use super::{Bdd, GuardBdd};
+
+    fn guard(s: &str) -> Guard {
+        parse_guard.parse(s).unwrap()
+    }
+
+    fn with_bdd(f: impl FnOnce(&mut GuardBdd)) {
+        let ir = crate::ir::parse::parse_ir("input 0");
+        let mut bdd = GuardBdd::new(&ir.livetime_equivalences);
+        f(&mut bdd)
+    }
+
+    #[test]
+    fn equivalence() {
+        with_bdd(|bdd| {
+            let lhs = bdd.from_guard(&guard("@0 && (@1 || Expr(s0))"));
+            let rhs = bdd.from_guard(&guard("(Expr(s0) || @1) && @0"));
+            assert_eq!(lhs, rhs);
+            let fast = bdd.from_guard(&guard("FastAnd(0, 1)"));
+            let and = bdd.from_guard(&guard("@1 && @0"));
+            assert_eq!(fast, and);
+            assert_ne!(lhs, and);
+            // alive checks of inputs are equivalent
+            let alive = bdd.from_guard(&guard("?0 && ?1"));
+            assert_eq!(alive, bdd.from_guard(&guard("?2")));
+            assert_eq!(bdd.from_guard(&guard("@0 || true")), Bdd::TRUE);
+        })
+    }
+
+    #[test]
+    fn implication() {
+        with_bdd(|bdd| {
+            let and = bdd.from_guard(&guard("@0 && @1"));
+            let or = bdd.from_guard(&guard("@1 || Global(1)"));
+            assert!(bdd.implies(and, or));
+            assert!(!bdd.implies(or, and));
+            assert!(bdd.implies(Bdd::FALSE, and));
+        })
+    }
+
+    #[test]
+    fn minimal_guard() {
+        with_bdd(|bdd| {
+            // absorption and duplicated atoms
+            let absorbed = bdd.from_guard(&guard("(@0 || (@0 && @1)) && (@0 && @0)"));
+            assert_eq!(bdd.to_guard(absorbed), guard("@0"));
+            let factored = bdd.from_guard(&guard("(@0 && @1) || (@2 || (@0 && Local(3)))"));
+            let res = bdd.to_guard(factored);
+            assert_eq!(res, guard("(@0 && (@1 || Local(3))) || @2"));
+            assert_eq!(bdd.from_guard(&res), factored);
+        })
+    }
+
+    #[test]
+    fn dynamic_atoms_last() {
+        with_bdd(|bdd| {
+            let g = bdd.from_guard(&guard("Expr(s0) && @0"));
+            assert_eq!(bdd.to_guard(g), guard("@0 && Expr(s0)"));
+            // the dynamic atom is factored out behind the atoms guarding it
+            let g = bdd.from_guard(&guard("(@0 && Expr(s0)) || (@1 && Expr(s0))"));
+            assert_eq!(bdd.to_guard(g), guard("(@0 || @1) && Expr(s0)"));
+            // the dynamic atoms might depend on each other
+            let g = bdd.from_guard(&guard(
+                "(@0 && Expr(s0) && Expr(s1)) || (@1 && Expr(s0) && Expr(s1))",
+            ));
+            assert_eq!(
+                bdd.to_guard(g),
+                guard("(@0 && Expr(s0) && Expr(s1)) || (@1 && Expr(s0) && Expr(s1))")
+            );
+        })
+    }
+
+    #[test]
+    fn simplify() {
+        with_bdd(|bdd| {
+            let context = bdd.from_guard(&guard("@1 && Expr(s0)"));
+            let g = bdd.from_guard(&guard("@0 && (@1 || @2) && Expr(s0)"));
+            assert_eq!(bdd.simplify(g, context), guard("@0"));
+            let g = bdd.from_guard(&guard("@0 || @1"));
+            assert_eq!(bdd.simplify(g, context), Guard::Constant(true));
+            let context = bdd.forget(context, |atom| matches!(atom, Guard::Dynamic(_)));
+            let g = bdd.from_guard(&guard("@1 && Expr(s0)"));
+            assert_eq!(bdd.simplify(g, context), guard("Expr(s0)"));
+        })
+    }
+}
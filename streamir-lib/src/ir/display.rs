@@ -1,6 +1,6 @@
 use itertools::Itertools;
 
-use super::Type;
+use super::{Offset, Origin, StreamAccessKind, Type, WindowReference};
 
 impl std::fmt::Display for Type {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -19,3 +19,40 @@ impl std::fmt::Display for Type {
         }
     }
 }
+
+impl std::fmt::Display for WindowReference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WindowReference::Sliding(i) => write!(f, "sw{i}"),
+            WindowReference::Discrete(i) => write!(f, "dw{i}"),
+            WindowReference::Instance(i) => write!(f, "iw{i}"),
+        }
+    }
+}
+
+impl std::fmt::Display for Origin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Origin::Spawn => write!(f, "spawn"),
+            Origin::EvalWhen(i) => write!(f, "eval_when({i})"),
+            Origin::EvalWith(i) => write!(f, "eval_with({i})"),
+            Origin::Close => write!(f, "close"),
+        }
+    }
+}
+
+impl std::fmt::Display for StreamAccessKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StreamAccessKind::Sync => write!(f, "sync"),
+            StreamAccessKind::DiscreteWindow(w) => write!(f, "discrete_window({w})"),
+            StreamAccessKind::SlidingWindow(w) => write!(f, "sliding_window({w})"),
+            StreamAccessKind::InstanceAggregation(w) => write!(f, "instance_aggregation({w})"),
+            StreamAccessKind::Hold => write!(f, "hold"),
+            StreamAccessKind::Offset(Offset::Past(o)) => write!(f, "offset(past {o})"),
+            StreamAccessKind::Offset(Offset::Future(o)) => write!(f, "offset(future {o})"),
+            StreamAccessKind::Get => write!(f, "get"),
+            StreamAccessKind::Fresh => write!(f, "fresh"),
+        }
+    }
+}
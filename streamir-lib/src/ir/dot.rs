@@ -0,0 +1,286 @@
+//! Exports views of the StreamIR as graphs in the DOT format of Graphviz.
+
+use itertools::Itertools;
+
+use crate::formatter::{
+    expressions::ExprFormatter, guards::GuardFormatter, statements::StmtFormatter,
+    StreamIrFormatter,
+};
+
+use super::{
+    print::StreamIrPrinter, DebugFormatter, IfStmt, OutputReference, Stmt, StreamIr,
+    StreamReference,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The view of the StreamIR shown by the [DotFormatter]
+pub enum DotView {
+    /// The streams as nodes, with an edge from each stream to the streams it accesses.
+    ///
+    /// The edges are labelled with the origin and kind of the accesses.
+    Dependencies,
+    /// The tree of statements, with guards as decision nodes and parallel blocks as clusters
+    Statements,
+}
+
+#[derive(Debug, Clone)]
+/// A [StreamIrFormatter] producing a graph in the DOT format of Graphviz
+pub struct DotFormatter {
+    view: DotView,
+    debug: DebugFormatter,
+}
+
+impl DotFormatter {
+    /// Creates a new formatter for the given view of the StreamIR
+    pub fn new(ir: &StreamIr, view: DotView) -> Self {
+        Self {
+            view,
+            debug: DebugFormatter::new(ir),
+        }
+    }
+}
+
+impl StreamIrFormatter for DotFormatter {
+    type Return = String;
+
+    fn id(&self) -> String {
+        "dot-formatter".into()
+    }
+
+    fn format(self, ir: StreamIr) -> Self::Return {
+        match self.view {
+            DotView::Dependencies => dependencies(&ir),
+            DotView::Statements => {
+                let mut graph = StmtGraph {
+                    ir: &ir,
+                    printer: self.debug.printer(),
+                    lines: Vec::new(),
+                    nodes: 0,
+                    clusters: 0,
+                };
+                graph.stmt(ir.stmt.clone(), 1);
+                format!(
+                    "digraph statements {{\n    node [shape=box];\n{}\n}}",
+                    graph.lines.join("\n")
+                )
+            }
+        }
+    }
+}
+
+/// The identifier of the node of a stream, as in the textual representation of the StreamIR
+fn node(sr: StreamReference) -> String {
+    match sr {
+        StreamReference::In(i) => format!("in{i}"),
+        StreamReference::Out(OutputReference::Unparameterized(i)) => format!("out{i}"),
+        StreamReference::Out(OutputReference::Parameterized(i)) => format!("pout{i}"),
+    }
+}
+
+fn escape(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn dependencies(ir: &StreamIr) -> String {
+    let nodes = ir.streams().sorted().map(|sr| {
+        let shape = match sr {
+            StreamReference::In(_) => "ellipse",
+            StreamReference::Out(o) if ir.triggers.contains_key(&o) => "octagon",
+            StreamReference::Out(_) => "box",
+        };
+        format!(
+            "    {} [label=\"{}\", shape={shape}];",
+            node(sr),
+            escape(ir.name(sr))
+        )
+    });
+    let edges = ir
+        .accesses
+        .iter()
+        .sorted_by_key(|(sr, _)| **sr)
+        .flat_map(|(sr, accesses)| {
+            accesses.iter().map(|(target, kinds)| {
+                let label = kinds
+                    .iter()
+                    .map(|(origin, kind)| format!("{origin}: {kind}"))
+                    .join("\n");
+                format!(
+                    "    {} -> {} [label=\"{}\"];",
+                    node(*sr),
+                    node(*target),
+                    escape(&label)
+                )
+            })
+        });
+    format!(
+        "digraph dependencies {{\n{}\n}}",
+        nodes.chain(edges).join("\n")
+    )
+}
+
+/// Collects the nodes and edges of the statement tree
+struct StmtGraph<'a> {
+    ir: &'a StreamIr,
+    printer: StreamIrPrinter<'a>,
+    lines: Vec<String>,
+    nodes: usize,
+    clusters: usize,
+}
+
+impl StmtGraph<'_> {
+    fn push(&mut self, line: String, depth: usize) {
+        self.lines.push(format!("{}{line}", "    ".repeat(depth)));
+    }
+
+    fn node(&mut self, label: &str, attributes: &str, depth: usize) -> String {
+        let id = format!("n{}", self.nodes);
+        self.nodes += 1;
+        self.push(
+            format!("{id} [label=\"{}\"{attributes}];", escape(label)),
+            depth,
+        );
+        id
+    }
+
+    fn edge(&mut self, from: &str, to: &str, label: Option<&str>, depth: usize) {
+        match label {
+            Some(label) => self.push(format!("{from} -> {to} [label=\"{label}\"];"), depth),
+            None => self.push(format!("{from} -> {to};"), depth),
+        }
+    }
+
+    fn names(&self, srs: &[OutputReference]) -> String {
+        srs.iter().map(|sr| self.ir.name(sr.sr())).join(", ")
+    }
+
+    /// Adds the nodes of the statement and returns the identifier of its root
+    fn stmt(&mut self, stmt: Stmt, depth: usize) -> String {
+        match stmt {
+            Stmt::Seq(inner) => {
+                let id = self.node("seq", "", depth);
+                for (idx, stmt) in inner.into_iter().enumerate() {
+                    let child = self.stmt(stmt, depth);
+                    self.edge(&id, &child, Some(&(idx + 1).to_string()), depth);
+                }
+                id
+            }
+            Stmt::Parallel(inner) => {
+                self.push(format!("subgraph cluster_{} {{", self.clusters), depth);
+                self.clusters += 1;
+                self.push("style=dashed;".into(), depth + 1);
+                let id = self.node("par", "", depth + 1);
+                for stmt in inner {
+                    let child = self.stmt(stmt, depth + 1);
+                    self.edge(&id, &child, None, depth + 1);
+                }
+                self.push("}".into(), depth);
+                id
+            }
+            Stmt::If(IfStmt { guard, cons, alt }) => {
+                let id = self.node(&self.printer.guard(guard), ", shape=diamond", depth);
+                let cons = self.stmt(*cons, depth);
+                self.edge(&id, &cons, Some("then"), depth);
+                if !matches!(*alt, Stmt::Skip) {
+                    let alt = self.stmt(*alt, depth);
+                    self.edge(&id, &alt, Some("else"), depth);
+                }
+                id
+            }
+            Stmt::Iterate { sr, stmt } => {
+                let label = format!("iterate [{}]", self.names(&sr));
+                let id = self.node(&label, "", depth);
+                let inner = self.stmt(*stmt, depth);
+                self.edge(&id, &inner, None, depth);
+                id
+            }
+            Stmt::Assign {
+                sr,
+                parameter_expr,
+                stmt,
+            } => {
+                let parameter_expr = parameter_expr
+                    .into_iter()
+                    .map(|p| self.printer.expr(p))
+                    .join(", ");
+                let label = format!("assign [{}] ({parameter_expr})", self.names(&sr));
+                let id = self.node(&label, "", depth);
+                let inner = self.stmt(*stmt, depth);
+                self.edge(&id, &inner, None, depth);
+                id
+            }
+            Stmt::Let { var, expr, stmt } => {
+                let label = format!("let var_{var} = {}", self.printer.expr(expr));
+                let id = self.node(&label, "", depth);
+                let inner = self.stmt(*stmt, depth);
+                self.edge(&id, &inner, None, depth);
+                id
+            }
+            leaf => {
+                let label = self.printer.stmt(leaf);
+                self.node(label.trim(), ", style=rounded", depth)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ir::StreamIr, translate};
+
+    use super::{DotFormatter, DotView};
+
+    const TEXT: &str = "input in0 a: UInt(64) = static(single);
+output out0 b: UInt(64) = static(bounded(2));
+output out1 \"trigger 0\": String = static(single);
+trigger out1 = 0;
+accesses out0 { in0 [eval_with(0) sync]; out0 [eval_with(0) offset(past 1)] };
+accesses out1 { out0 [eval_with(0) hold] };
+stmt seq {
+    if @in0 then
+        input in0
+    fi;
+    if @in0 then
+        par {
+            seq {
+                shift out0;
+                eval_0 out0 with in0 + out0.offset(1, or: 0u64)
+            };
+            eval_0 out1 with \"a \\\"quoted\\\" message\"
+        }
+    else
+        skip
+    fi
+}";
+
+    fn dot(view: DotView) -> String {
+        let ir = StreamIr::from_text(TEXT).unwrap();
+        translate(ir.clone(), DotFormatter::new(&ir, view))
+    }
+
+    #[test]
+    fn dependencies() {
+        let dot = dot(DotView::Dependencies);
+        assert!(dot.starts_with("digraph dependencies {"));
+        assert!(dot.contains("in0 [label=\"a\", shape=ellipse];"));
+        assert!(dot.contains("out1 [label=\"trigger 0\", shape=octagon];"));
+        assert!(dot.contains("out0 -> in0 [label=\"eval_with(0): sync\"];"));
+        assert!(dot.contains("out0 -> out0 [label=\"eval_with(0): offset(past 1)\"];"));
+        assert!(dot.contains("out1 -> out0 [label=\"eval_with(0): hold\"];"));
+    }
+
+    #[test]
+    fn statements() {
+        let dot = dot(DotView::Statements);
+        assert!(dot.starts_with("digraph statements {"));
+        assert_eq!(dot.matches("shape=diamond").count(), 2);
+        assert_eq!(dot.matches("subgraph cluster_").count(), 1);
+        assert!(dot.contains("[label=\"par\"]"));
+        assert!(dot.contains("\\\"quoted\\\""));
+        // the empty alternative is not shown
+        assert!(!dot.contains("label=\"else\""));
+        assert!(!dot.contains("skip"));
+    }
+}
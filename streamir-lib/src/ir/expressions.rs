@@ -1,18 +1,22 @@
 //! Contains the internal representation of stream expressions inside the StreamIR
 
-use super::{StreamReference, Type, WindowReference};
+use super::{SourceSpan, StreamReference, Type, VariableReference, WindowReference};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Represents a stream expression
 pub struct Expr {
     /// The type of the expression
     pub ty: Type,
     /// The kind of the expression
     pub kind: ExprKind,
+    /// The location of the clause in the specification, if the expression is the outermost expression of a clause
+    pub span: SourceSpan,
 }
 
 /// Represents the different kinds a expression can have
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ExprKind {
     /// A Constant
     Constant(Constant),
@@ -35,8 +39,8 @@ pub enum ExprKind {
         target: StreamReference,
         /// the offset
         offset: u32,
-        /// the default value of the access
-        default: Box<Expr>,
+        /// the default value of the access, if no default is given the access results in an optional value
+        default: Option<Box<Expr>>,
         /// and these parameters
         parameters: Vec<Expr>,
     },
@@ -44,8 +48,8 @@ pub enum ExprKind {
     HoldStreamAccess {
         /// with that stream reference
         target: StreamReference,
-        /// the default value of the access
-        default: Box<Expr>,
+        /// the default value of the access, if no default is given the access results in an optional value
+        default: Option<Box<Expr>>,
         /// and these parameters
         parameters: Vec<Expr>,
     },
@@ -60,8 +64,8 @@ pub enum ExprKind {
     GetAccess {
         /// the target of the access
         target: StreamReference,
-        /// the default value of the access
-        default: Box<Expr>,
+        /// the default value of the access, if no default is given the access results in an optional value
+        default: Option<Box<Expr>>,
         /// and these parameters
         parameters: Vec<Expr>,
     },
@@ -88,15 +92,107 @@ pub enum ExprKind {
     TupleAccess(Box<Expr>, usize),
     /// The access to a parameter of a lambda expression
     LambdaParameterAccess(WindowReference, usize),
+    /// The access to a variable bound by a [Stmt::Let](super::Stmt::Let)
+    Variable(VariableReference),
+    /// Replaces a missing optional value by a default value
+    Default {
+        /// the expression with an optional value
+        expr: Box<Expr>,
+        /// the value used if the expression has no value
+        default: Box<Expr>,
+    },
 }
 
 impl std::hash::Hash for ExprKind {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         core::mem::discriminant(self).hash(state);
+        match self {
+            ExprKind::Constant(c) => c.hash(state),
+            ExprKind::BinaryOperation(op, lhs, rhs) => {
+                op.hash(state);
+                lhs.hash(state);
+                rhs.hash(state);
+            }
+            ExprKind::UnaryOperation(op, expr) => {
+                op.hash(state);
+                expr.hash(state);
+            }
+            ExprKind::Ite(cond, cons, alt) => {
+                cond.hash(state);
+                cons.hash(state);
+                alt.hash(state);
+            }
+            ExprKind::SyncStreamAccess { target, parameters }
+            | ExprKind::IsFresh { target, parameters } => {
+                target.hash(state);
+                parameters.hash(state);
+            }
+            ExprKind::OffsetStreamAccess {
+                target,
+                offset,
+                default,
+                parameters,
+            } => {
+                target.hash(state);
+                offset.hash(state);
+                default.hash(state);
+                parameters.hash(state);
+            }
+            ExprKind::HoldStreamAccess {
+                target,
+                default,
+                parameters,
+            }
+            | ExprKind::GetAccess {
+                target,
+                default,
+                parameters,
+            } => {
+                target.hash(state);
+                default.hash(state);
+                parameters.hash(state);
+            }
+            ExprKind::WindowAccess {
+                target,
+                window,
+                parameters,
+                default,
+            } => {
+                target.hash(state);
+                window.hash(state);
+                parameters.hash(state);
+                default.hash(state);
+            }
+            ExprKind::Cast(ty, expr) => {
+                ty.hash(state);
+                expr.hash(state);
+            }
+            // the stream reference is ignored by the equality as well
+            ExprKind::ParameterAccess(_, idx) => idx.hash(state),
+            ExprKind::FunctionCall(function, args) => {
+                function.hash(state);
+                args.hash(state);
+            }
+            ExprKind::Tuple(items) => items.hash(state),
+            ExprKind::TupleAccess(expr, idx) => {
+                expr.hash(state);
+                idx.hash(state);
+            }
+            ExprKind::LambdaParameterAccess(wref, idx) => {
+                wref.hash(state);
+                idx.hash(state);
+            }
+            ExprKind::Variable(var) => var.hash(state),
+            ExprKind::Default { expr, default } => {
+                expr.hash(state);
+                default.hash(state);
+            }
+        }
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Copy)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// Represent the different functions usable in expressions
 pub enum Function {
     /// The square root function
@@ -119,10 +215,45 @@ pub enum Function {
     Min,
     /// The maximum function
     Max,
+    /// The floor function
+    Floor,
+    /// The ceil function
+    Ceil,
+    /// The rounding function
+    Round,
+    /// The exponential function
+    Exp,
+    /// The natural logarithm
+    Ln,
+    /// The logarithm of the first argument to the base given by the second argument
+    Log,
+    /// The power function
+    Pow,
+    /// The signum function
+    Signum,
+    /// Restricts the first argument to the interval given by the second and third argument
+    Clamp,
+    /// The four-quadrant arctan function of the first argument divided by the second argument
+    Atan2,
+    /// A project-specific pure function with the given name and signature
+    ///
+    /// The backends map the name to a native implementation.
+    External(String, FunctionSignature),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The signature of an external function
+pub struct FunctionSignature {
+    /// The types of the arguments
+    pub args: Vec<Type>,
+    /// The return type
+    pub ret: Type,
 }
 
 /// Represents a constant of a stream expression
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Constant {
     /// A string
     Str(String),
@@ -140,8 +271,51 @@ pub enum Constant {
     Tuple(Vec<Constant>),
 }
 
+impl std::hash::Hash for Constant {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        core::mem::discriminant(self).hash(state);
+        match self {
+            Constant::Str(s) => s.hash(state),
+            Constant::Bool(b) => b.hash(state),
+            Constant::UInt(v, bits) => {
+                v.hash(state);
+                bits.hash(state);
+            }
+            Constant::Int(v, bits) => {
+                v.hash(state);
+                bits.hash(state);
+            }
+            Constant::Float32(f) | Constant::Float64(f) => {
+                // equal floats have the same bits, except for the positive and negative zero
+                let f = if *f == 0.0 { 0.0 } else { *f };
+                f.to_bits().hash(state)
+            }
+            Constant::Tuple(items) => items.hash(state),
+        }
+    }
+}
+
+impl Constant {
+    /// Returns whether the constant is a value of the given type
+    pub fn has_type(&self, ty: &Type) -> bool {
+        match (self, ty) {
+            (Constant::Str(_), Type::String)
+            | (Constant::Bool(_), Type::Bool)
+            | (Constant::Float32(_), Type::Float32)
+            | (Constant::Float64(_), Type::Float64) => true,
+            (Constant::UInt(_, bits), Type::UInt(ty_bits))
+            | (Constant::Int(_, bits), Type::Int(ty_bits)) => bits == ty_bits,
+            (Constant::Tuple(items), Type::Tuple(tys)) => {
+                items.len() == tys.len() && items.iter().zip(tys).all(|(c, ty)| c.has_type(ty))
+            }
+            _ => false,
+        }
+    }
+}
+
 /// Represents a binary or unary operation in a stream expression
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Operator {
     /// Binary negation
     Not,
@@ -288,6 +462,17 @@ impl PartialEq for ExprKind {
             (Self::LambdaParameterAccess(l0, l1), Self::LambdaParameterAccess(r0, r1)) => {
                 l0 == r0 && l1 == r1
             }
+            (Self::Variable(l0), Self::Variable(r0)) => l0 == r0,
+            (
+                Self::Default {
+                    expr: l_expr,
+                    default: l_default,
+                },
+                Self::Default {
+                    expr: r_expr,
+                    default: r_default,
+                },
+            ) => l_expr == r_expr && l_default == r_default,
             _ => false,
         }
     }
@@ -315,12 +500,6 @@ impl Expr {
                 target: _,
                 parameters,
             }
-            | ExprKind::WindowAccess {
-                target: _,
-                window: _,
-                parameters,
-                default: None,
-            }
             | ExprKind::SyncStreamAccess {
                 target: _,
                 parameters,
@@ -339,7 +518,7 @@ impl Expr {
                 target: _,
                 window: _,
                 parameters,
-                default: Some(default),
+                default,
             }
             | ExprKind::OffsetStreamAccess {
                 target: _,
@@ -348,13 +527,211 @@ impl Expr {
                 parameters,
             } => parameters
                 .iter()
-                .find_map(Self::contains_parameter_access)
+                .chain(default.as_deref())
+                .find_map(Self::contains_parameter_access),
+            ExprKind::Default { expr, default } => expr
+                .contains_parameter_access()
                 .or_else(|| default.contains_parameter_access()),
             ExprKind::FunctionCall(_, exprs) | ExprKind::Tuple(exprs) => {
                 exprs.iter().find_map(Self::contains_parameter_access)
             }
             ExprKind::TupleAccess(expr, _) => expr.contains_parameter_access(),
-            ExprKind::LambdaParameterAccess(_, _) => None,
+            ExprKind::LambdaParameterAccess(_, _) | ExprKind::Variable(_) => None,
         }
     }
+
+    /// Returns the direct subexpressions of the expression
+    pub fn children(&self) -> Vec<&Expr> {
+        match &self.kind {
+            ExprKind::Constant(_)
+            | ExprKind::ParameterAccess(_, _)
+            | ExprKind::LambdaParameterAccess(_, _)
+            | ExprKind::Variable(_) => Vec::new(),
+            ExprKind::BinaryOperation(_, lhs, rhs) => vec![lhs.as_ref(), rhs.as_ref()],
+            ExprKind::UnaryOperation(_, expr)
+            | ExprKind::Cast(_, expr)
+            | ExprKind::TupleAccess(expr, _) => vec![expr.as_ref()],
+            ExprKind::Ite(cond, cons, alt) => vec![cond.as_ref(), cons.as_ref(), alt.as_ref()],
+            ExprKind::SyncStreamAccess {
+                target: _,
+                parameters,
+            }
+            | ExprKind::IsFresh {
+                target: _,
+                parameters,
+            } => parameters.iter().collect(),
+            ExprKind::OffsetStreamAccess {
+                target: _,
+                offset: _,
+                default,
+                parameters,
+            }
+            | ExprKind::HoldStreamAccess {
+                target: _,
+                default,
+                parameters,
+            }
+            | ExprKind::GetAccess {
+                target: _,
+                default,
+                parameters,
+            }
+            | ExprKind::WindowAccess {
+                target: _,
+                window: _,
+                parameters,
+                default,
+            } => parameters.iter().chain(default.as_deref()).collect(),
+            ExprKind::Default { expr, default } => vec![expr.as_ref(), default.as_ref()],
+            ExprKind::FunctionCall(_, exprs) | ExprKind::Tuple(exprs) => exprs.iter().collect(),
+        }
+    }
+
+    /// Returns mutable references to the direct subexpressions of the expression
+    pub(crate) fn children_mut(&mut self) -> Vec<&mut Expr> {
+        match &mut self.kind {
+            ExprKind::Constant(_)
+            | ExprKind::ParameterAccess(_, _)
+            | ExprKind::LambdaParameterAccess(_, _)
+            | ExprKind::Variable(_) => Vec::new(),
+            ExprKind::BinaryOperation(_, lhs, rhs) => vec![lhs.as_mut(), rhs.as_mut()],
+            ExprKind::UnaryOperation(_, expr)
+            | ExprKind::Cast(_, expr)
+            | ExprKind::TupleAccess(expr, _) => vec![expr.as_mut()],
+            ExprKind::Ite(cond, cons, alt) => vec![cond.as_mut(), cons.as_mut(), alt.as_mut()],
+            ExprKind::SyncStreamAccess {
+                target: _,
+                parameters,
+            }
+            | ExprKind::IsFresh {
+                target: _,
+                parameters,
+            } => parameters.iter_mut().collect(),
+            ExprKind::OffsetStreamAccess {
+                target: _,
+                offset: _,
+                default,
+                parameters,
+            }
+            | ExprKind::HoldStreamAccess {
+                target: _,
+                default,
+                parameters,
+            }
+            | ExprKind::GetAccess {
+                target: _,
+                default,
+                parameters,
+            }
+            | ExprKind::WindowAccess {
+                target: _,
+                window: _,
+                parameters,
+                default,
+            } => parameters
+                .iter_mut()
+                .chain(default.as_deref_mut())
+                .collect(),
+            ExprKind::Default { expr, default } => vec![expr.as_mut(), default.as_mut()],
+            ExprKind::FunctionCall(_, exprs) | ExprKind::Tuple(exprs) => exprs.iter_mut().collect(),
+        }
+    }
+
+    /// Returns the variables accessed in the expression together with their type, sorted by the variable reference
+    pub fn variables(&self) -> Vec<(VariableReference, Type)> {
+        let mut variables = Vec::new();
+        self.collect_variables(&mut variables);
+        variables.sort_unstable_by_key(|(var, _)| *var);
+        variables.dedup_by_key(|(var, _)| *var);
+        variables
+    }
+
+    fn collect_variables(&self, variables: &mut Vec<(VariableReference, Type)>) {
+        match &self.kind {
+            ExprKind::Variable(var) => variables.push((*var, self.ty.clone())),
+            _ => self
+                .children()
+                .into_iter()
+                .for_each(|child| child.collect_variables(variables)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::{DefaultHasher, Hash, Hasher};
+
+    use super::*;
+    use crate::ir::{OutputReference, Span};
+
+    fn hash(expr: &Expr) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        expr.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn constant(c: Constant) -> Expr {
+        Expr {
+            ty: Type::Float64,
+            kind: ExprKind::Constant(c),
+            span: SourceSpan::UNKNOWN,
+        }
+    }
+
+    fn add(lhs: Expr, rhs: Expr) -> Expr {
+        Expr {
+            ty: Type::Float64,
+            kind: ExprKind::BinaryOperation(Operator::Add, Box::new(lhs), Box::new(rhs)),
+            span: SourceSpan::UNKNOWN,
+        }
+    }
+
+    fn access(sr: usize) -> Expr {
+        Expr {
+            ty: Type::Float64,
+            kind: ExprKind::SyncStreamAccess {
+                target: StreamReference::In(sr),
+                parameters: Vec::new(),
+            },
+            span: SourceSpan::UNKNOWN,
+        }
+    }
+
+    #[test]
+    fn structural_hash() {
+        let a = add(access(0), constant(Constant::Float64(1.0)));
+        let b = add(access(0), constant(Constant::Float64(1.0)));
+        assert_eq!(a, b);
+        assert_eq!(hash(&a), hash(&b));
+        let different = [
+            add(access(1), constant(Constant::Float64(1.0))),
+            add(access(0), constant(Constant::Float64(2.0))),
+            add(constant(Constant::Float64(1.0)), access(0)),
+        ];
+        for other in different {
+            assert_ne!(a, other);
+            assert_ne!(hash(&a), hash(&other));
+        }
+        let located = Expr {
+            span: Span { line: 2, col: 8 }.into(),
+            ..b
+        };
+        assert_eq!(a, located);
+        assert_eq!(hash(&a), hash(&located));
+    }
+
+    #[test]
+    fn hash_consistent_with_eq() {
+        let zero = constant(Constant::Float64(0.0));
+        let negative_zero = constant(Constant::Float64(-0.0));
+        assert_eq!(zero, negative_zero);
+        assert_eq!(hash(&zero), hash(&negative_zero));
+        let parameter = |sr| Expr {
+            ty: Type::UInt(64),
+            kind: ExprKind::ParameterAccess(OutputReference::Parameterized(sr).sr(), 0),
+            span: SourceSpan::UNKNOWN,
+        };
+        assert_eq!(parameter(0), parameter(1));
+        assert_eq!(hash(&parameter(0)), hash(&parameter(1)));
+    }
 }
@@ -0,0 +1,297 @@
+//! An analysis bounding the number of instances of a parameterized stream that are alive at the same time.
+//!
+//! Two arguments give an upper bound, of which the smaller one is stored in the memory of the stream:
+//! - As spawning an instance that is already alive has no effect, there are at most as many instances as values of the parameters.
+//!   These are limited by the types of the parameters, e.g., a `Bool` has two values and a `UInt(4)` sixteen,
+//!   and by the range of the values the spawn expressions evaluate to (see [StreamIr::value_ranges]).
+//! - If all instances are closed after the spawn statements in every cycle in which the stream can be spawned,
+//!   the alive instances were all spawned in the current cycle, i.e., there is at most one per spawn statement.
+//!   Whether the closes cover the spawns is decided on the guards of the statements, of which only the atoms are taken into account
+//!   whose value does not change within a cycle and is the same for all instances.
+
+use std::collections::{HashMap, HashSet};
+
+use super::{
+    bdd::{Bdd, GuardBdd},
+    expressions::{Constant, ExprKind},
+    memory::StreamMemory,
+    ranges::ValueRange,
+    Guard, IfStmt, OutputReference, Stmt, StreamIr, StreamReference, Type,
+};
+
+/// The largest bound derived from the values of the parameters
+///
+/// Larger numbers of instances are not worth allocating a fixed-size table.
+const MAX_INSTANCES: u128 = 1 << 16;
+
+impl StreamIr {
+    /// Computes an upper bound on the number of alive instances for each parameterized output stream
+    ///
+    /// Streams for which no bound is found are missing from the result.
+    pub fn instance_bounds(&self) -> HashMap<OutputReference, usize> {
+        let ranges = self.value_ranges();
+        let mut lifecycles = Lifecycles {
+            bdd: GuardBdd::new(&self.livetime_equivalences),
+            spawns: HashMap::new(),
+            repeated: HashSet::new(),
+            closes: HashMap::new(),
+        };
+        lifecycles.stmt(&self.stmt, Bdd::TRUE, Bdd::TRUE, &[], false);
+        self.parameterized_outputs()
+            .filter_map(|sr| {
+                let parameters = self.stream_memory(sr.sr()).parameters()?;
+                let values = parameters
+                    .iter()
+                    .enumerate()
+                    .try_fold(1u128, |values, (i, parameter)| {
+                        values.checked_mul(values_in(ranges.parameter(sr, i), &parameter.ty)?)
+                    })
+                    .filter(|values| *values <= MAX_INSTANCES);
+                let spawns = lifecycles
+                    .spawned_per_cycle(sr)
+                    .map(|spawns| spawns as u128);
+                values
+                    .into_iter()
+                    .chain(spawns)
+                    .min()
+                    .map(|bound| (sr, bound as usize))
+            })
+            .collect()
+    }
+
+    /// Stores the bounds computed by [StreamIr::instance_bounds] in the memory of the parameterized streams
+    ///
+    /// Bounds that are already stored are replaced, and removed for the streams without a bound.
+    /// As the ranges of the spawn expressions depend on the overflow policy, the bounds are recomputed by [StreamIr::set_overflow].
+    pub fn bound_instances(&mut self) {
+        let mut bounds = self.instance_bounds();
+        for (sr, memory) in &mut self.sr2memory {
+            if let (StreamReference::Out(sr), StreamMemory::Instances { bound, .. }) =
+                (sr, &mut memory.buffer)
+            {
+                *bound = bounds.remove(sr);
+            }
+        }
+    }
+}
+
+/// The spawn statements and the closes of all instances of the streams, with the guards under which they are executed
+struct Lifecycles<'a> {
+    bdd: GuardBdd<'a>,
+    /// The guards of the spawn statements of each stream, assuming that the atoms changing within a cycle hold
+    spawns: HashMap<OutputReference, Vec<Bdd>>,
+    /// The streams with a spawn statement that might be executed multiple times in a cycle
+    repeated: HashSet<OutputReference>,
+    /// The disjunction of the guards under which all instances of each stream are closed after its spawn statements,
+    /// assuming that the atoms changing within a cycle or depending on the instance do not hold
+    closes: HashMap<OutputReference, Bdd>,
+}
+
+impl Lifecycles<'_> {
+    /// Collects the spawns and closes of the statement, executed under the given guards
+    ///
+    /// `spawn` is the guard for the spawn statements and `close` the one for the closes,
+    /// `iterated` are the streams whose instances are closed by a close statement.
+    fn stmt(
+        &mut self,
+        stmt: &Stmt,
+        spawn: Bdd,
+        close: Bdd,
+        iterated: &[OutputReference],
+        repeated: bool,
+    ) {
+        match stmt {
+            Stmt::Skip | Stmt::Shift(_) | Stmt::Input(_) | Stmt::Eval { .. } => {}
+            Stmt::Seq(stmts) | Stmt::Parallel(stmts) => stmts
+                .iter()
+                .for_each(|stmt| self.stmt(stmt, spawn, close, iterated, repeated)),
+            Stmt::Spawn { sr, .. } => {
+                self.spawns.entry(*sr).or_default().push(spawn);
+                if repeated {
+                    self.repeated.insert(*sr);
+                }
+                // closes before the spawn do not affect the spawned instance
+                self.closes.remove(sr);
+            }
+            Stmt::Close { sr, .. } => {
+                if iterated.contains(sr) {
+                    let closes = self.closes.get(sr).copied().unwrap_or(Bdd::FALSE);
+                    let closes = self.bdd.or(closes, close);
+                    self.closes.insert(*sr, closes);
+                }
+            }
+            Stmt::If(IfStmt { guard, cons, alt }) => {
+                let weakened = self.stable(guard, true);
+                let strengthened = self.stable(guard, false);
+                let cons_spawn = self.bdd.and(spawn, weakened);
+                let cons_close = self.bdd.and(close, strengthened);
+                self.stmt(cons, cons_spawn, cons_close, iterated, repeated);
+                // guards can not be negated, so the closes in the alternative are not taken into account
+                self.stmt(alt, spawn, Bdd::FALSE, iterated, repeated);
+            }
+            Stmt::Iterate { sr, stmt } => self.stmt(stmt, spawn, close, sr, true),
+            Stmt::Assign { stmt, .. } => self.stmt(stmt, spawn, close, &[], repeated),
+            Stmt::Let { stmt, .. } => self.stmt(stmt, spawn, close, iterated, repeated),
+        }
+    }
+
+    /// Returns the BDD of the guard in which the atoms that can change within a cycle or depend on the instance are replaced by the constant
+    ///
+    /// As guards contain no negation, replacing atoms by `true` weakens the guard and replacing them by `false` strengthens it.
+    fn stable(&mut self, guard: &Guard, replacement: bool) -> Bdd {
+        let constant = |b| if b { Bdd::TRUE } else { Bdd::FALSE };
+        match guard {
+            Guard::Stream(StreamReference::In(_)) | Guard::GlobalFreq(_) | Guard::Constant(_) => {
+                self.bdd.from_guard(guard)
+            }
+            Guard::Dynamic(expr) => match &expr.kind {
+                ExprKind::Constant(Constant::Bool(b)) => constant(*b),
+                _ => constant(replacement),
+            },
+            Guard::Stream(StreamReference::Out(_)) | Guard::Alive(_) | Guard::LocalFreq(_) => {
+                constant(replacement)
+            }
+            Guard::And { lhs, rhs } => {
+                let (lhs, rhs) = (self.stable(lhs, replacement), self.stable(rhs, replacement));
+                self.bdd.and(lhs, rhs)
+            }
+            Guard::Or { lhs, rhs } => {
+                let (lhs, rhs) = (self.stable(lhs, replacement), self.stable(rhs, replacement));
+                self.bdd.or(lhs, rhs)
+            }
+            Guard::FastAnd(srs) => srs.iter().fold(Bdd::TRUE, |res, sr| {
+                let atom = self.stable(&Guard::Stream(*sr), replacement);
+                self.bdd.and(res, atom)
+            }),
+            Guard::FastOr(srs) => srs.iter().fold(Bdd::FALSE, |res, sr| {
+                let atom = self.stable(&Guard::Stream(*sr), replacement);
+                self.bdd.or(res, atom)
+            }),
+        }
+    }
+
+    /// Returns the number of spawn statements of the stream if all of its instances are closed
+    /// in every cycle in which it can be spawned
+    fn spawned_per_cycle(&mut self, sr: OutputReference) -> Option<usize> {
+        if self.repeated.contains(&sr) {
+            return None;
+        }
+        let spawns = self.spawns.get(&sr).cloned().unwrap_or_default();
+        let closes = self.closes.get(&sr).copied().unwrap_or(Bdd::FALSE);
+        spawns
+            .iter()
+            .all(|spawn| self.bdd.implies(*spawn, closes))
+            .then_some(spawns.len())
+    }
+}
+
+/// Returns the number of values of the type in the range, or None if they can not be counted
+fn values_in(range: ValueRange, ty: &Type) -> Option<u128> {
+    match (range, ty) {
+        (ValueRange::Empty, _) => Some(0),
+        // the range does not contain the missing value
+        (_, Type::Option(_)) => values(ty),
+        (ValueRange::Int { min, max }, _) => max
+            .checked_sub(min)
+            .and_then(|diff| diff.checked_add(1))
+            .map(|values| values as u128),
+        (
+            ValueRange::Bool {
+                can_be_false,
+                can_be_true,
+            },
+            _,
+        ) => Some(u128::from(can_be_false) + u128::from(can_be_true)),
+        (ValueRange::Float { min, max }, _) if min == max => Some(1),
+        (ValueRange::Float { .. } | ValueRange::Unknown, _) => values(ty),
+    }
+}
+
+/// Returns the number of values of the type, or None if they can not be counted
+fn values(ty: &Type) -> Option<u128> {
+    match ty {
+        Type::Bool => Some(2),
+        Type::Int(bits) | Type::UInt(bits) if *bits < 128 => Some(1 << bits),
+        Type::Option(inner) => values(inner)?.checked_add(1),
+        Type::Tuple(tys) => tys
+            .iter()
+            .try_fold(1u128, |values, ty| values.checked_mul(self::values(ty)?)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ir::{parse::parse_text, OutputReference, OverflowPolicy, StreamIr};
+
+    const STREAMS: &str = "input in0 a: UInt(64) = static(single);
+input in1 b: Bool = static(single);
+output pout0 c(p: Bool): UInt(64) = instances(single);
+output pout1 d(p: UInt(64)): UInt(64) = instances(single);
+output pout2 e(p: UInt(64)): UInt(64) = instances(single);
+output pout3 f(p: UInt(64)): UInt(64) = instances(single);
+output pout4 g(p: UInt(64), q: UInt(4)): UInt(64) = instances(single);
+";
+
+    #[test]
+    fn bounds() {
+        let mut ir = parse_text(
+            STREAMS,
+            "seq {
+                input in0;
+                input in1;
+                if @in0 then
+                    par {
+                        spawn pout0 with (in1);
+                        spawn pout1 with (in0 % 10u64);
+                        spawn pout2 with (in0);
+                        spawn pout3 with (in0);
+                        spawn pout4 with (in0, 3u8)
+                    }
+                fi;
+                iterate [pout2] if @in0 then close pout2 fi;
+                iterate [pout3] if Expr(param(pout3, 0) > 5u64) then close pout3 fi
+            }",
+        );
+        let bound = |ir: &StreamIr, i: usize| {
+            ir.stream_memory(OutputReference::Parameterized(i).sr())
+                .buffer
+                .instance_bound()
+        };
+        ir.bound_instances();
+        // the values of the parameter
+        assert_eq!(bound(&ir, 0), Some(2));
+        assert_eq!(bound(&ir, 1), Some(10));
+        // all instances are closed whenever one is spawned
+        assert_eq!(bound(&ir, 2), Some(1));
+        // the close depends on the instance
+        assert_eq!(bound(&ir, 3), None);
+        // the constant parameter does not help against the unbounded one
+        assert_eq!(bound(&ir, 4), None);
+        assert!(ir.to_text().contains("instances(single, max 2)"));
+    }
+
+    #[test]
+    fn overflow() {
+        let mut ir = StreamIr::from_text(
+            "input in0 a: UInt(8) = static(single);
+output pout0 b(p: UInt(8)): UInt(8) = instances(single);
+stmt seq {
+    input in0;
+    if @in0 then spawn pout0 with (in0 + 1u8) fi
+}",
+        )
+        .unwrap_or_else(|e| panic!("{e}"));
+        let bound = |ir: &StreamIr| {
+            ir.stream_memory(OutputReference::Parameterized(0).sr())
+                .buffer
+                .instance_bound()
+        };
+        ir.bound_instances();
+        // the sum can wrap around to 0
+        assert_eq!(bound(&ir), Some(256));
+        ir.set_overflow(OverflowPolicy::Trap);
+        // the sum never wraps around to 0
+        assert_eq!(bound(&ir), Some(255));
+    }
+}
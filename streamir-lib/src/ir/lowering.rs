@@ -1,7 +1,12 @@
+mod delays;
 mod expressions;
 pub(crate) mod livetime_equivalences;
+mod spans;
 
+use delays::Delays;
 use livetime_equivalences::LivetimeEquivalences;
+pub(crate) use spans::Spans;
+use spans::{ExprSpans, Shape, StreamSpans};
 use std::{collections::HashMap, iter, time::Duration};
 use thiserror::Error;
 use uom::num_traits::Inv;
@@ -17,9 +22,9 @@ use rtlola_frontend::mir::{self, Expression, ExpressionKind, PacingType, Stream}
 use super::schedule::StaticSchedule;
 use super::windows::{InstanceSelection, Window, WindowKind, WindowOperation};
 use super::{
-    expressions::Expr,
+    expressions::{Expr, ExprKind, Function},
     memory::{Memory, Parameter, StreamBuffer, StreamMemory},
-    Guard, IfStmt, Stmt, StreamIr, StreamReference, Type, WindowReference,
+    Guard, IfStmt, SourceMap, Stmt, StreamIr, StreamReference, Type, WindowReference,
 };
 use super::{LocalFreq, LocalFreqRef, Offset, Origin};
 use super::{OutputReference, StreamAccessKind};
@@ -28,6 +33,20 @@ impl TryFrom<rtlola_frontend::RtLolaMir> for StreamIr {
     type Error = LoweringError;
 
     fn try_from(value: rtlola_frontend::RtLolaMir) -> Result<Self, Self::Error> {
+        StreamIr::lower(value, &Spans::default(), &[])
+    }
+}
+
+impl StreamIr {
+    /// Lowers the MIR into the StreamIR, locating the statements, windows and clauses by the `spans` of the specification
+    ///
+    /// Calls of functions that are not built into the StreamIR are lowered to external functions
+    /// if their name is one of the `external_functions`, and are rejected otherwise.
+    pub(crate) fn lower(
+        value: rtlola_frontend::RtLolaMir,
+        spans: &Spans,
+        external_functions: &[String],
+    ) -> Result<Self, LoweringError> {
         let schedule = value
             .compute_schedule()
             .map_err(LoweringError::ComputeSchedule)?;
@@ -43,6 +62,23 @@ impl TryFrom<rtlola_frontend::RtLolaMir> for StreamIr {
             global_tags: _,
         } = value;
 
+        let delays = Delays::new(&outputs)?;
+
+        let trigger_idx: HashMap<_, _> = triggers
+            .iter()
+            .map(|t| (t.output_reference, t.trigger_reference))
+            .collect();
+        let stream_spans: HashMap<mir::StreamReference, StreamSpans> = inputs
+            .iter()
+            .map(|i| (i.reference, spans.stream(&i.name, None)))
+            .chain(outputs.iter().map(|o| {
+                (
+                    o.reference,
+                    spans.stream(&o.name, trigger_idx.get(&o.reference).copied()),
+                )
+            }))
+            .collect();
+
         let mut cur_unparameterized = 0;
         let mut cur_parameterized = 0;
         let sr2sr: HashMap<_, _> = inputs
@@ -77,12 +113,15 @@ impl TryFrom<rtlola_frontend::RtLolaMir> for StreamIr {
                         sr2sr[&sr],
                         accesses
                             .iter()
-                            .map(|(sr, a)| {
+                            .map(|(target, a)| {
                                 (
-                                    sr2sr[sr],
+                                    sr2sr[target],
                                     a.iter()
                                         .map(|(o, a)| {
-                                            (Origin::from(*o), StreamAccessKind::from(*a))
+                                            (
+                                                Origin::from(*o),
+                                                delays.access(sr, *target, *a).into(),
+                                            )
                                         })
                                         .collect(),
                                 )
@@ -93,10 +132,14 @@ impl TryFrom<rtlola_frontend::RtLolaMir> for StreamIr {
                         sr2sr[&sr],
                         accessed_by
                             .iter()
-                            .map(|(sr, a)| {
+                            .map(|(accessor, a)| {
                                 (
-                                    sr2sr[sr],
-                                    a.iter().map(|(o, a)| ((*o).into(), (*a).into())).collect(),
+                                    sr2sr[accessor],
+                                    a.iter()
+                                        .map(|(o, a)| {
+                                            ((*o).into(), delays.access(*accessor, sr, *a).into())
+                                        })
+                                        .collect(),
                                 )
                             })
                             .collect::<Vec<(StreamReference, _)>>(),
@@ -108,12 +151,19 @@ impl TryFrom<rtlola_frontend::RtLolaMir> for StreamIr {
 
         let livetime_equivalences = LivetimeEquivalences::new(&outputs, &sr2sr);
         let static_schedule = StaticSchedule::new(schedule, &sr2sr);
+        let sources = SourceMap {
+            file: None,
+            streams: stream_spans
+                .iter()
+                .filter_map(|(sr, spans)| spans.declaration.0.map(|span| (sr2sr[sr], span)))
+                .collect(),
+        };
 
         let (sr2memory_inputs, input_stmts): (HashMap<_, _>, Vec<_>) = inputs
             .into_iter()
             .map(|i| {
                 let sr = sr2sr[&i.reference];
-                let (mem, stmts) = StreamIr::lower_input(i, &sr2sr);
+                let (mem, stmts) = StreamIr::lower_input(i, &sr2sr, &delays);
                 ((sr, mem), stmts)
             })
             .unzip();
@@ -122,7 +172,13 @@ impl TryFrom<rtlola_frontend::RtLolaMir> for StreamIr {
             .into_iter()
             .map(|o| {
                 let sr = sr2sr[&o.reference];
-                let (mem, stmts) = StreamIr::lower_output(o, &sr2sr, &mut lref2lfreq)?;
+                let (mem, stmts) = StreamIr::lower_output(
+                    o,
+                    &sr2sr,
+                    &mut lref2lfreq,
+                    &delays,
+                    &stream_spans[&o.reference],
+                )?;
                 Ok(((sr, mem), stmts))
             })
             .collect::<Result<Vec<_>, _>>()?
@@ -149,7 +205,7 @@ impl TryFrom<rtlola_frontend::RtLolaMir> for StreamIr {
             .map(|swin| {
                 Ok((
                     swin.reference.into(),
-                    StreamIr::lower_sliding_window(swin, &sr2sr, &mut lref2lfreq)?,
+                    StreamIr::lower_sliding_window(swin, &sr2sr, &mut lref2lfreq, &stream_spans)?,
                 ))
             })
             .collect::<Vec<_>>()
@@ -160,7 +216,12 @@ impl TryFrom<rtlola_frontend::RtLolaMir> for StreamIr {
                     .map(|dwin| {
                         Ok((
                             dwin.reference.into(),
-                            StreamIr::lower_discrete_window(dwin, &sr2sr, &mut lref2lfreq)?,
+                            StreamIr::lower_discrete_window(
+                                dwin,
+                                &sr2sr,
+                                &mut lref2lfreq,
+                                &stream_spans,
+                            )?,
                         ))
                     })
                     .collect::<Vec<_>>(),
@@ -171,7 +232,12 @@ impl TryFrom<rtlola_frontend::RtLolaMir> for StreamIr {
                     .map(|iwin| {
                         Ok((
                             iwin.reference.into(),
-                            StreamIr::lower_instance_aggregation(iwin, &sr2sr, &mut lref2lfreq)?,
+                            StreamIr::lower_instance_aggregation(
+                                iwin,
+                                &sr2sr,
+                                &mut lref2lfreq,
+                                &stream_spans,
+                            )?,
                         ))
                     })
                     .collect::<Vec<_>>(),
@@ -183,7 +249,7 @@ impl TryFrom<rtlola_frontend::RtLolaMir> for StreamIr {
             .map(|t| (sr2sr[&t.output_reference].out_idx(), t.trigger_reference))
             .collect();
 
-        Ok(StreamIr {
+        let mut ir = StreamIr {
             stmt: layers,
             sr2memory,
             wref2window,
@@ -193,7 +259,67 @@ impl TryFrom<rtlola_frontend::RtLolaMir> for StreamIr {
             triggers,
             accesses,
             accessed_by,
-        })
+            sources,
+            overflow: Default::default(),
+        };
+        Self::check_functions(&ir.stmt, external_functions)?;
+        ir.bound_instances();
+        Ok(ir)
+    }
+
+    /// Checks that all external functions called by the statement are declared in `external_functions`
+    fn check_functions(stmt: &Stmt, external_functions: &[String]) -> Result<(), LoweringError> {
+        fn check_expr(expr: &Expr, external_functions: &[String]) -> Result<(), LoweringError> {
+            if let ExprKind::FunctionCall(Function::External(name, _), _) = &expr.kind {
+                if !external_functions.contains(name) {
+                    return Err(LoweringError::UnsupportedFunction(name.clone()));
+                }
+            }
+            expr.children()
+                .into_iter()
+                .try_for_each(|child| check_expr(child, external_functions))
+        }
+        fn check_guard(guard: &Guard, external_functions: &[String]) -> Result<(), LoweringError> {
+            match guard {
+                Guard::Dynamic(expr) => check_expr(expr, external_functions),
+                Guard::And { lhs, rhs } | Guard::Or { lhs, rhs } => {
+                    check_guard(lhs, external_functions)?;
+                    check_guard(rhs, external_functions)
+                }
+                _ => Ok(()),
+            }
+        }
+        match stmt {
+            Stmt::Skip | Stmt::Shift(_) | Stmt::Input(_) | Stmt::Close { .. } => Ok(()),
+            Stmt::Spawn { with, .. } => with
+                .iter()
+                .flatten()
+                .try_for_each(|expr| check_expr(expr, external_functions)),
+            Stmt::Eval { with, .. } => check_expr(with, external_functions),
+            Stmt::Seq(stmts) | Stmt::Parallel(stmts) => stmts
+                .iter()
+                .try_for_each(|stmt| Self::check_functions(stmt, external_functions)),
+            Stmt::If(IfStmt { guard, cons, alt }) => {
+                check_guard(guard, external_functions)?;
+                Self::check_functions(cons, external_functions)?;
+                Self::check_functions(alt, external_functions)
+            }
+            Stmt::Iterate { stmt, .. } => Self::check_functions(stmt, external_functions),
+            Stmt::Assign {
+                parameter_expr,
+                stmt,
+                ..
+            } => {
+                parameter_expr
+                    .iter()
+                    .try_for_each(|expr| check_expr(expr, external_functions))?;
+                Self::check_functions(stmt, external_functions)
+            }
+            Stmt::Let { expr, stmt, .. } => {
+                check_expr(expr, external_functions)?;
+                Self::check_functions(stmt, external_functions)
+            }
+        }
     }
 }
 
@@ -209,6 +335,8 @@ impl From<mir::MemorizationBound> for StreamBuffer {
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum MyLayer {
     Layer(mir::Layer),
+    /// The layers of streams with a delay, evaluated after all streams without a delay
+    Delayed(u32, mir::Layer),
     Close,
 }
 
@@ -232,12 +360,18 @@ impl StreamIr {
     fn lower_input(
         input: mir::InputStream,
         sr2sr: &HashMap<mir::StreamReference, StreamReference>,
+        delays: &Delays,
     ) -> (Memory, Stmt) {
         let sr = sr2sr[&input.reference];
         let memory = Memory {
-            buffer: StreamMemory::Static(input.memory_bound.into()),
+            buffer: StreamMemory::Static(
+                delays
+                    .memory_bound(input.reference, input.memory_bound)
+                    .into(),
+            ),
             ty: input.ty.into(),
             name: input.name,
+            delay: 0,
         };
         let stmt = Stmt::seq([Stmt::Shift(sr), Stmt::Input(sr.in_idx())]).filter(Guard::Stream(sr));
         (memory, stmt)
@@ -247,6 +381,8 @@ impl StreamIr {
         output: mir::OutputStream,
         sr2sr: &HashMap<mir::StreamReference, StreamReference>,
         lref2lfreq: &mut HashMap<LocalFreqRef, LocalFreq>,
+        delays: &Delays,
+        spans: &StreamSpans,
     ) -> Result<(Memory, [(MyLayer, Stmt); 4]), LoweringError> {
         let has_spawn = output.is_spawned();
         let has_close = output.is_closed();
@@ -271,6 +407,9 @@ impl StreamIr {
         } = output;
         let sr = sr2sr[&reference];
         let parameter = params.into_iter().map(|p| p.into()).collect::<Vec<_>>();
+        let delay = delays.delay(reference);
+        let memory_bound = delays.memory_bound(reference, memory_bound);
+        let eval = delays.eval(reference, eval)?;
 
         let spawned_and_closed_windows = aggregates
             .iter()
@@ -281,7 +420,8 @@ impl StreamIr {
                 }
             })
             .collect::<Vec<_>>();
-        let (shift, eval, mut eval_freq) = StreamIr::lower_eval(sr, eval, sr2sr, lref2lfreq)?;
+        let (shift, eval, mut eval_freq) =
+            StreamIr::lower_eval(sr, eval, sr2sr, lref2lfreq, spans)?;
         let shift = shift.iterate(sr, &parameter, is_dynamic);
         let eval = eval.iterate(sr, &parameter, is_dynamic);
         let (close, close_freq) = StreamIr::lower_close(
@@ -291,6 +431,7 @@ impl StreamIr {
             lref2lfreq,
             eval_freq.clone(),
             spawned_and_closed_windows.clone(),
+            spans,
         )?;
         let close = if has_close {
             close.iterate(sr, &parameter, is_dynamic)
@@ -306,6 +447,7 @@ impl StreamIr {
                 lref2lfreq,
                 eval_freq,
                 spawned_and_closed_windows,
+                spans,
             )?
         } else {
             Stmt::Skip
@@ -321,6 +463,7 @@ impl StreamIr {
             (false, true) => StreamMemory::Instances {
                 buffer: memory_bound.into(),
                 parameter,
+                bound: None,
             },
             (false, false) => unreachable!("parameterized always has spawn"),
         };
@@ -329,13 +472,21 @@ impl StreamIr {
             buffer,
             ty: ty.into(),
             name,
+            delay,
+        };
+        let delayed = |l: mir::Layer| {
+            if delay > 0 {
+                MyLayer::Delayed(delay, l)
+            } else {
+                l.into()
+            }
         };
         Ok((
             mem,
             [
-                (layer.spawn_layer().into(), spawn),
-                (layer.shift_layer().into(), shift),
-                (layer.evaluation_layer().into(), eval),
+                (delayed(layer.spawn_layer()), spawn),
+                (delayed(layer.shift_layer()), shift),
+                (delayed(layer.evaluation_layer()), eval),
                 (MyLayer::Close, close),
             ],
         ))
@@ -348,13 +499,21 @@ impl StreamIr {
         lref2lfreq: &mut HashMap<LocalFreqRef, LocalFreq>,
         local_freqs: Vec<LocalFreqRef>,
         windows: Vec<WindowReference>,
+        spans: &StreamSpans,
     ) -> Result<Stmt, LoweringError> {
         let mir::Spawn {
             expression,
             pacing,
             condition,
         } = spawn;
-        let (guard, lref) = Self::lower_guard(pacing, condition, sr, sr2sr, lref2lfreq)?;
+        let (guard, lref) = Self::lower_guard(
+            pacing,
+            condition,
+            &spans.spawn.condition,
+            sr,
+            sr2sr,
+            lref2lfreq,
+        )?;
         if lref.is_some() {
             Err(LoweringError::LocalFreq)
         } else {
@@ -364,17 +523,23 @@ impl StreamIr {
                     .map(|Expression { ty, kind }| match kind {
                         ExpressionKind::Tuple(inner) => inner
                             .into_iter()
-                            .map(|expr| convert_stream_expression(expr, None, sr2sr))
+                            .enumerate()
+                            .map(|(i, expr)| {
+                                let spans = spans.spawn.expression.operand(Shape::Tuple, i);
+                                convert_stream_expression(expr, None, &spans, sr2sr)
+                            })
                             .collect(),
                         other => Ok(vec![convert_stream_expression(
                             Expression { ty, kind: other },
                             None,
+                            &spans.spawn.expression,
                             sr2sr,
                         )?]),
                     })
                     .transpose()?,
                 local_frequencies: local_freqs,
                 windows,
+                span: spans.spawn.clause,
             }
             .filter(guard))
         }
@@ -385,6 +550,7 @@ impl StreamIr {
         eval: mir::Eval,
         sr2sr: &HashMap<mir::StreamReference, StreamReference>,
         lref2lfreq: &mut HashMap<LocalFreqRef, LocalFreq>,
+        spans: &StreamSpans,
     ) -> Result<(Stmt, Stmt, Vec<LocalFreqRef>), LoweringError> {
         let mir::Eval {
             clauses,
@@ -392,10 +558,12 @@ impl StreamIr {
         } = eval;
         let shift = |_expr: Expression, _idx: usize| Ok(Stmt::Shift(sr));
         let eval = |expr: Expression, idx: usize| {
+            let spans = spans.eval_expression(Origin::EvalWith(idx));
             Ok::<Stmt, LoweringError>(Stmt::Eval {
                 sr: sr.out_idx(),
-                with: convert_stream_expression(expr, None, sr2sr)?,
+                with: convert_stream_expression(expr, None, &spans, sr2sr)?,
                 idx,
+                span: spans.span,
             })
         };
         let mut local_freqs = Vec::new();
@@ -413,8 +581,14 @@ impl StreamIr {
                             pacing,
                         },
                     )| {
-                        let (guard, lref) =
-                            Self::lower_guard(pacing, condition, sr, sr2sr, lref2lfreq)?;
+                        let (guard, lref) = Self::lower_guard(
+                            pacing,
+                            condition,
+                            &spans.eval_expression(Origin::EvalWhen(idx)),
+                            sr,
+                            sr2sr,
+                            lref2lfreq,
+                        )?;
                         local_freqs.extend(lref);
                         Ok(f(expression, idx)?.filter(guard))
                     },
@@ -431,8 +605,14 @@ impl StreamIr {
                         pacing,
                     },
                 )| {
-                    let (guard, lref) =
-                        Self::lower_guard(pacing, condition, sr, sr2sr, lref2lfreq)?;
+                    let (guard, lref) = Self::lower_guard(
+                        pacing,
+                        condition,
+                        &spans.eval_expression(Origin::EvalWhen(idx)),
+                        sr,
+                        sr2sr,
+                        lref2lfreq,
+                    )?;
                     local_freqs.extend(lref);
                     Ok(f(expression, idx)?.filter_else(guard, alt?))
                 },
@@ -452,12 +632,22 @@ impl StreamIr {
         lref2lfreq: &mut HashMap<LocalFreqRef, LocalFreq>,
         mut eval_local_freqs: Vec<LocalFreqRef>,
         windows: Vec<WindowReference>,
+        spans: &StreamSpans,
     ) -> Result<(Stmt, Option<LocalFreqRef>), LoweringError> {
         let mir::Close {
             condition, pacing, ..
         } = close;
         let (condition, lfreq) = condition
-            .map(|g| Self::lower_guard(pacing, Some(g), sr, sr2sr, lref2lfreq))
+            .map(|g| {
+                Self::lower_guard(
+                    pacing,
+                    Some(g),
+                    &spans.close.condition,
+                    sr,
+                    sr2sr,
+                    lref2lfreq,
+                )
+            })
             .unwrap_or_else(|| Ok((Guard::Constant(false), None)))?;
         eval_local_freqs.extend(lfreq);
         Ok((
@@ -465,6 +655,7 @@ impl StreamIr {
                 sr: sr.out_idx(),
                 local_frequencies: eval_local_freqs,
                 windows,
+                span: spans.close.clause,
             }
             .filter(condition),
             lfreq,
@@ -474,16 +665,15 @@ impl StreamIr {
     fn lower_guard(
         pacing: PacingType,
         condition: Option<Expression>,
+        spans: &ExprSpans,
         source: StreamReference,
         sr2sr: &HashMap<mir::StreamReference, StreamReference>,
         lref1lfreq: &mut HashMap<LocalFreqRef, LocalFreq>,
     ) -> Result<(Guard, Option<LocalFreqRef>), LoweringError> {
         let (pacing, lref) = Guard::from_pt(pacing, source, sr2sr, lref1lfreq);
         if let Some(condition) = condition {
-            Ok((
-                pacing.and(convert_stream_expression(condition, None, sr2sr)?.into()),
-                lref,
-            ))
+            let condition = convert_stream_expression(condition, None, spans, sr2sr)?;
+            Ok((pacing.and(condition.into()), lref))
         } else {
             Ok((pacing, lref))
         }
@@ -493,6 +683,7 @@ impl StreamIr {
         sliding_window: mir::SlidingWindow,
         sr2sr: &HashMap<mir::StreamReference, StreamReference>,
         lref2lfreq: &mut HashMap<LocalFreqRef, LocalFreq>,
+        spans: &HashMap<mir::StreamReference, StreamSpans>,
     ) -> Result<Window, LoweringError> {
         let mir::SlidingWindow {
             target,
@@ -521,6 +712,7 @@ impl StreamIr {
             },
             origin_pacing: Guard::from_pt(pacing, sr2sr[&caller], sr2sr, lref2lfreq).0,
             origin: origin.into(),
+            span: spans[&caller].origin(origin.into()),
         })
     }
 
@@ -528,6 +720,7 @@ impl StreamIr {
         discrete_window: mir::DiscreteWindow,
         sr2sr: &HashMap<mir::StreamReference, StreamReference>,
         lref2lfreq: &mut HashMap<LocalFreqRef, LocalFreq>,
+        spans: &HashMap<mir::StreamReference, StreamSpans>,
     ) -> Result<Window, LoweringError> {
         let mir::DiscreteWindow {
             target,
@@ -552,6 +745,7 @@ impl StreamIr {
             },
             origin_pacing: Guard::from_pt(pacing, sr2sr[&caller], sr2sr, lref2lfreq).0,
             origin: origin.into(),
+            span: spans[&caller].origin(origin.into()),
         })
     }
 
@@ -559,6 +753,7 @@ impl StreamIr {
         instance_aggregation: mir::InstanceAggregation,
         sr2sr: &HashMap<mir::StreamReference, StreamReference>,
         lref2lfreq: &mut HashMap<LocalFreqRef, LocalFreq>,
+        spans: &HashMap<mir::StreamReference, StreamSpans>,
     ) -> Result<Window, LoweringError> {
         let mir::InstanceAggregation {
             target,
@@ -577,10 +772,15 @@ impl StreamIr {
             caller: sr2sr[&caller],
             ty: ty.into(),
             kind: WindowKind::Instances {
-                selection: InstanceSelection::from_mir_selection(selection, sr2sr)?,
+                selection: InstanceSelection::from_mir_selection(
+                    selection,
+                    &ExprSpans::at(spans[&caller].origin(origin.into())),
+                    sr2sr,
+                )?,
             },
             origin_pacing: Guard::from_pt(pacing, sr2sr[&caller], sr2sr, lref2lfreq).0,
             origin: origin.into(),
+            span: spans[&caller].origin(origin.into()),
         })
     }
 }
@@ -844,6 +1044,7 @@ impl From<mir::Offset> for Offset {
 impl InstanceSelection {
     fn from_mir_selection(
         value: mir::InstanceSelection,
+        spans: &ExprSpans,
         sr2sr: &HashMap<mir::StreamReference, StreamReference>,
     ) -> Result<Self, LoweringError> {
         match value {
@@ -852,13 +1053,13 @@ impl InstanceSelection {
             mir::InstanceSelection::FilteredFresh { parameters, cond } => {
                 Ok(InstanceSelection::FilteredFresh {
                     parameters: parameters.into_iter().map(|p| p.into()).collect(),
-                    cond: convert_stream_expression(*cond, None, sr2sr)?,
+                    cond: convert_stream_expression(*cond, None, spans, sr2sr)?,
                 })
             }
             mir::InstanceSelection::FilteredAll { parameters, cond } => {
                 Ok(InstanceSelection::FilteredAll {
                     parameters: parameters.into_iter().map(|p| p.into()).collect(),
-                    cond: convert_stream_expression(*cond, None, sr2sr)?,
+                    cond: convert_stream_expression(*cond, None, spans, sr2sr)?,
                 })
             }
         }
@@ -868,14 +1069,30 @@ impl InstanceSelection {
 #[derive(Debug, Clone, Error)]
 /// An error that can happen during the lowering of the RtLolaMIR into the StreamIR
 pub enum LoweringError {
-    #[error("specification contains optional expression that is not immediately followed by a default value")]
-    /// The specification contains an optional expression that is not immediately followed by a default value
-    DefaultRequired,
-    #[error("specification contains a future access")]
-    /// The specification contains a future access
+    #[error("specification contains a future access outside of an eval clause")]
+    /// The specification contains a future access in a spawn or close expression,
+    /// which can not be lowered by delaying the evaluation of the accessing stream
     FutureAccess,
+    #[error("stream {0} depends on its own future values")]
+    /// The stream transitively accesses its own future values, so no delay of the stream is large enough
+    DelayCycle(String),
+    #[error("the parameterized stream {0} can not be delayed")]
+    /// The stream would need to be delayed, but it is parameterized, spawned or closed
+    DelayedParameterized(String),
+    #[error("the filtered stream {0} can not be delayed")]
+    /// The stream would need to be delayed, but it has an eval clause with a filter
+    DelayedFilter(String),
+    #[error("the delayed stream {0} can not aggregate a sliding window")]
+    /// The stream would need to be delayed, but it accesses a sliding window, which aggregates by real time
+    DelayedWindow(String),
+    #[error("the delayed stream {0} asynchronously accesses a stream with a different delay")]
+    /// The stream would need to be delayed, but it accesses a stream with a different delay by a hold, get, fresh or window access
+    DelayedAsyncAccess(String),
+    #[error("can not delay a synchronous access of type {0}")]
+    /// A synchronous access of a delayed stream needs a default value, but no value of the accessed type can be constructed
+    DelayedAccessType(String),
     #[error("specification contains the unsupported function {0}")]
-    /// The specification contains an unsupported function
+    /// The specification contains a function that is neither built into the StreamIR nor declared as an external function
     UnsupportedFunction(String),
     #[error("Local frequency in an invalid position")]
     /// The specification contains a local frequency in an invalid position
@@ -0,0 +1,325 @@
+//! Lowers future offset accesses by delaying the evaluation of the accessing streams.
+//!
+//! A stream accessing `x.offset(by: n)` is evaluated `n` activations later than `x`.
+//! All other accesses of the delayed stream are moved into the past by its delay,
+//! so that the stream observes the same values as in the original specification.
+//! A stream inherits the delay of every stream it accesses.
+
+use std::collections::HashMap;
+
+use rtlola_frontend::mir::{self, Stream};
+
+use super::LoweringError;
+
+/// The delays of the streams of a specification
+pub(super) struct Delays {
+    /// The delay of each delayed stream; all other streams have a delay of zero
+    delays: HashMap<mir::StreamReference, u32>,
+    /// The number of values each stream needs to store after the accesses were delayed
+    bounds: HashMap<mir::StreamReference, u32>,
+}
+
+impl Delays {
+    pub(super) fn new(outputs: &[mir::OutputStream]) -> Result<Self, LoweringError> {
+        // no stream can be delayed by more than the sum of all future offsets without depending on its own future
+        let max_delay: u32 = outputs
+            .iter()
+            .flat_map(accesses)
+            .map(|(_target, kind)| match kind {
+                mir::StreamAccessKind::Offset(mir::Offset::Future(o)) => o,
+                _ => 0,
+            })
+            .sum();
+
+        let mut delays = Delays {
+            delays: HashMap::new(),
+            bounds: HashMap::new(),
+        };
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for output in outputs {
+                let delay = accesses(output)
+                    .map(|(target, kind)| match kind {
+                        mir::StreamAccessKind::Offset(mir::Offset::Future(o)) => {
+                            delays.delay(target) + o
+                        }
+                        _ => delays.delay(target),
+                    })
+                    .max()
+                    .unwrap_or(0);
+                if delay > max_delay {
+                    return Err(LoweringError::DelayCycle(output.name.clone()));
+                }
+                if delay > delays.delay(output.reference) {
+                    delays.delays.insert(output.reference, delay);
+                    changed = true;
+                }
+            }
+        }
+
+        for output in outputs {
+            delays.check(output)?;
+            for (target, kind) in accesses(output) {
+                if let mir::StreamAccessKind::Offset(mir::Offset::Past(o)) =
+                    delays.access(output.reference, target, kind)
+                {
+                    let bound = delays.bounds.entry(target).or_default();
+                    *bound = (*bound).max(o + 1);
+                }
+            }
+        }
+        Ok(delays)
+    }
+
+    /// Returns the number of activations by which the evaluation of the stream is delayed
+    pub(super) fn delay(&self, sr: mir::StreamReference) -> u32 {
+        self.delays.get(&sr).copied().unwrap_or(0)
+    }
+
+    /// Checks that the stream only uses accesses that can be moved to its delayed timeline
+    fn check(&self, output: &mir::OutputStream) -> Result<(), LoweringError> {
+        let delay = self.delay(output.reference);
+        if delay == 0 {
+            return Ok(());
+        }
+        if output.is_parameterized() || output.is_spawned() || output.is_closed() {
+            return Err(LoweringError::DelayedParameterized(output.name.clone()));
+        }
+        if output
+            .eval
+            .clauses
+            .iter()
+            .any(|clause| clause.condition.is_some())
+        {
+            return Err(LoweringError::DelayedFilter(output.name.clone()));
+        }
+        for (target, kind) in accesses(output) {
+            match kind {
+                mir::StreamAccessKind::Sync | mir::StreamAccessKind::Offset(_) => {}
+                mir::StreamAccessKind::SlidingWindow(_) => {
+                    return Err(LoweringError::DelayedWindow(output.name.clone()))
+                }
+                mir::StreamAccessKind::Hold
+                | mir::StreamAccessKind::Get
+                | mir::StreamAccessKind::Fresh
+                | mir::StreamAccessKind::DiscreteWindow(_)
+                | mir::StreamAccessKind::InstanceAggregation(_) => {
+                    if self.delay(target) != delay {
+                        return Err(LoweringError::DelayedAsyncAccess(output.name.clone()));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the access of `accessor` to `target` on the delayed timeline
+    ///
+    /// Synchronous and offset accesses refer to values that are older by the difference between both delays.
+    /// A future offset that equals this difference becomes a synchronous access.
+    pub(super) fn access(
+        &self,
+        accessor: mir::StreamReference,
+        target: mir::StreamReference,
+        kind: mir::StreamAccessKind,
+    ) -> mir::StreamAccessKind {
+        let shift = self.delay(accessor) - self.delay(target);
+        match kind {
+            mir::StreamAccessKind::Sync if shift > 0 => {
+                mir::StreamAccessKind::Offset(mir::Offset::Past(shift))
+            }
+            mir::StreamAccessKind::Offset(mir::Offset::Future(o)) if o == shift => {
+                mir::StreamAccessKind::Sync
+            }
+            mir::StreamAccessKind::Offset(mir::Offset::Future(o)) => {
+                mir::StreamAccessKind::Offset(mir::Offset::Past(shift - o))
+            }
+            mir::StreamAccessKind::Offset(mir::Offset::Past(o)) => {
+                mir::StreamAccessKind::Offset(mir::Offset::Past(o + shift))
+            }
+            other => other,
+        }
+    }
+
+    /// Returns the memory bound of the stream, enlarged such that all delayed accesses can be served
+    pub(super) fn memory_bound(
+        &self,
+        sr: mir::StreamReference,
+        bound: mir::MemorizationBound,
+    ) -> mir::MemorizationBound {
+        match (bound, self.bounds.get(&sr)) {
+            (mir::MemorizationBound::Bounded(b), Some(required)) => {
+                mir::MemorizationBound::Bounded(b.max(*required))
+            }
+            (bound, _) => bound,
+        }
+    }
+
+    /// Moves all accesses in the eval clauses of the stream to the delayed timeline
+    pub(super) fn eval(
+        &self,
+        sr: mir::StreamReference,
+        eval: mir::Eval,
+    ) -> Result<mir::Eval, LoweringError> {
+        if self.delay(sr) == 0 {
+            return Ok(eval);
+        }
+        let mir::Eval {
+            clauses,
+            eval_pacing,
+        } = eval;
+        let clauses = clauses
+            .into_iter()
+            .map(|clause| {
+                Ok(mir::EvalClause {
+                    expression: self.expression(sr, clause.expression)?,
+                    ..clause
+                })
+            })
+            .collect::<Result<_, LoweringError>>()?;
+        Ok(mir::Eval {
+            clauses,
+            eval_pacing,
+        })
+    }
+
+    fn expression(
+        &self,
+        sr: mir::StreamReference,
+        expr: mir::Expression,
+    ) -> Result<mir::Expression, LoweringError> {
+        let mir::Expression { kind, ty } = expr;
+        let delay = |expr: mir::Expression| self.expression(sr, expr).map(Box::new);
+        let delay_all = |exprs: Vec<mir::Expression>| {
+            exprs
+                .into_iter()
+                .map(|expr| self.expression(sr, expr))
+                .collect::<Result<Vec<_>, LoweringError>>()
+        };
+        let kind = match kind {
+            mir::ExpressionKind::StreamAccess {
+                target,
+                parameters,
+                access_kind,
+            } => {
+                let parameters = delay_all(parameters)?;
+                let delayed_kind = match self.access(sr, target, access_kind) {
+                    // a synchronous access can not result in the optional value of the future access,
+                    // so the current value is accessed by an offset of zero instead
+                    mir::StreamAccessKind::Sync if matches!(ty, mir::Type::Option(_)) => {
+                        mir::StreamAccessKind::Offset(mir::Offset::Past(0))
+                    }
+                    kind => kind,
+                };
+                let access = mir::ExpressionKind::StreamAccess {
+                    target,
+                    parameters,
+                    access_kind: delayed_kind,
+                };
+                // an access to an optional stream needs no default, the delayed access keeps its type
+                let needs_default = access_kind == mir::StreamAccessKind::Sync
+                    && matches!(delayed_kind, mir::StreamAccessKind::Offset(_))
+                    && !matches!(ty, mir::Type::Option(_));
+                if needs_default {
+                    // the default is never used, as the stream is not evaluated until all accessed values exist
+                    let default = placeholder(&ty)
+                        .ok_or_else(|| LoweringError::DelayedAccessType(format!("{ty:?}")))?;
+                    mir::ExpressionKind::Default {
+                        expr: Box::new(mir::Expression {
+                            kind: access,
+                            ty: mir::Type::Option(Box::new(ty.clone())),
+                        }),
+                        default: Box::new(mir::Expression {
+                            kind: mir::ExpressionKind::LoadConstant(default),
+                            ty: ty.clone(),
+                        }),
+                    }
+                } else {
+                    access
+                }
+            }
+            mir::ExpressionKind::ArithLog(op, operands) => {
+                mir::ExpressionKind::ArithLog(op, delay_all(operands)?)
+            }
+            mir::ExpressionKind::Ite {
+                condition,
+                consequence,
+                alternative,
+            } => mir::ExpressionKind::Ite {
+                condition: delay(*condition)?,
+                consequence: delay(*consequence)?,
+                alternative: delay(*alternative)?,
+            },
+            mir::ExpressionKind::Tuple(elements) => {
+                mir::ExpressionKind::Tuple(delay_all(elements)?)
+            }
+            mir::ExpressionKind::TupleAccess(tuple, i) => {
+                mir::ExpressionKind::TupleAccess(delay(*tuple)?, i)
+            }
+            mir::ExpressionKind::Function(name, args) => {
+                mir::ExpressionKind::Function(name, delay_all(args)?)
+            }
+            mir::ExpressionKind::Convert { expr } => mir::ExpressionKind::Convert {
+                expr: delay(*expr)?,
+            },
+            mir::ExpressionKind::Default { expr, default } => match *expr {
+                // the delayed future access always has a value, so it is accessed synchronously without the default
+                mir::Expression {
+                    kind:
+                        mir::ExpressionKind::StreamAccess {
+                            target,
+                            parameters,
+                            access_kind:
+                                access_kind @ mir::StreamAccessKind::Offset(mir::Offset::Future(_)),
+                        },
+                    ..
+                } if self.access(sr, target, access_kind) == mir::StreamAccessKind::Sync => {
+                    mir::ExpressionKind::StreamAccess {
+                        target,
+                        parameters: delay_all(parameters)?,
+                        access_kind: mir::StreamAccessKind::Sync,
+                    }
+                }
+                expr => mir::ExpressionKind::Default {
+                    expr: delay(expr)?,
+                    default: delay(*default)?,
+                },
+            },
+            other @ (mir::ExpressionKind::LoadConstant(_)
+            | mir::ExpressionKind::ParameterAccess(_, _)
+            | mir::ExpressionKind::LambdaParameterAccess { .. }) => other,
+        };
+        Ok(mir::Expression { kind, ty })
+    }
+}
+
+fn accesses(
+    output: &mir::OutputStream,
+) -> impl Iterator<Item = (mir::StreamReference, mir::StreamAccessKind)> + '_ {
+    output
+        .accesses
+        .iter()
+        .flat_map(|(target, kinds)| kinds.iter().map(|(_origin, kind)| (*target, *kind)))
+}
+
+/// Returns an arbitrary constant of the given type
+fn placeholder(ty: &mir::Type) -> Option<mir::Constant> {
+    match ty {
+        mir::Type::Bool => Some(mir::Constant::Bool(false)),
+        mir::Type::Int(_) => Some(mir::Constant::Int(0)),
+        mir::Type::UInt(_) => Some(mir::Constant::UInt(0)),
+        mir::Type::Float(_) => Some(mir::Constant::Float(0.0)),
+        mir::Type::String => Some(mir::Constant::Str(String::new())),
+        mir::Type::Tuple(inner) => inner
+            .iter()
+            .map(placeholder)
+            .collect::<Option<_>>()
+            .map(mir::Constant::Tuple),
+        mir::Type::Option(_)
+        | mir::Type::Fixed(_)
+        | mir::Type::UFixed(_)
+        | mir::Type::Bytes
+        | mir::Type::Function { .. } => None,
+    }
+}
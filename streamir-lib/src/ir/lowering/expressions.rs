@@ -3,21 +3,27 @@ use std::collections::HashMap;
 use rtlola_frontend::mir::{self, StreamAccessKind};
 
 use crate::ir::{
-    expressions::{Constant, Expr, ExprKind, Function, Operator},
+    expressions::{Constant, Expr, ExprKind, Function, FunctionSignature, Operator},
     StreamReference, Type,
 };
 
-use super::LoweringError;
+use super::{
+    spans::{ExprSpans, Shape},
+    LoweringError,
+};
 
+/// Lowers the MIR expression, locating it and its subexpressions by the `spans` of the expression
 pub(super) fn convert_stream_expression(
     expr: mir::Expression,
     default: Option<Expr>,
+    spans: &ExprSpans,
     sr2sr: &HashMap<mir::StreamReference, StreamReference>,
 ) -> Result<Expr, LoweringError> {
     let mir::Expression { ty, kind } = expr;
 
+    // a default turns the optional result of the access into a value
     let lir_ty = match ty {
-        mir::Type::Option(inner) => *inner,
+        mir::Type::Option(inner) if default.is_some() => *inner,
         other => other,
     }
     .into();
@@ -27,45 +33,54 @@ pub(super) fn convert_stream_expression(
             Ok(ExprKind::Constant(translate_constant(c, &lir_ty)))
         }
 
-        mir::ExpressionKind::ArithLog(op, exprs) => translate_operator(op, exprs, sr2sr),
+        mir::ExpressionKind::ArithLog(op, exprs) => translate_operator(op, exprs, spans, sr2sr),
 
         mir::ExpressionKind::Ite {
             condition,
             consequence,
             alternative,
-        } => translate_ite(*condition, *consequence, *alternative, sr2sr),
+        } => translate_ite(*condition, *consequence, *alternative, spans, sr2sr),
 
         mir::ExpressionKind::StreamAccess {
             target,
             parameters,
             access_kind: StreamAccessKind::Sync,
-        } => translate_sync_access(target, parameters, sr2sr),
+        } => translate_sync_access(target, parameters, spans, sr2sr),
 
         mir::ExpressionKind::StreamAccess {
             target,
             parameters,
             access_kind: StreamAccessKind::Offset(offset),
-        } => translate_offset_access(
-            target,
-            parameters,
-            offset,
-            default.ok_or(LoweringError::DefaultRequired)?,
-            sr2sr,
-        ),
+        } => translate_offset_access(target, parameters, offset, default, spans, sr2sr),
         mir::ExpressionKind::StreamAccess {
             target,
             parameters,
             access_kind: StreamAccessKind::Hold,
-        } => translate_hold_access(
-            target,
-            parameters,
-            default.ok_or(LoweringError::DefaultRequired)?,
-            sr2sr,
-        ),
+        } => translate_hold_access(target, parameters, default, spans, sr2sr),
 
         mir::ExpressionKind::Default { expr, default } => {
-            let default_expr = convert_stream_expression(*default, None, sr2sr)?;
-            return convert_stream_expression(*expr, Some(default_expr), sr2sr);
+            // a default without a counterpart in the syntax tree was added by the lowering of future offsets
+            let expr_spans = if spans.is(Shape::Default) {
+                spans.operand(Shape::Default, 0)
+            } else {
+                spans.clone()
+            };
+            let default_expr = convert_stream_expression(
+                *default,
+                None,
+                &spans.operand(Shape::Default, 1),
+                sr2sr,
+            )?;
+            if takes_default(&expr.kind) {
+                // the access with the default is located at the whole default expression
+                let spans = expr_spans.located_at(spans.span);
+                return convert_stream_expression(*expr, Some(default_expr), &spans, sr2sr);
+            }
+            let expr = convert_stream_expression(*expr, None, &expr_spans, sr2sr)?;
+            Ok(ExprKind::Default {
+                expr: Box::new(expr),
+                default: Box::new(default_expr),
+            })
         }
         mir::ExpressionKind::StreamAccess {
             target,
@@ -74,32 +89,33 @@ pub(super) fn convert_stream_expression(
                 StreamAccessKind::SlidingWindow(window)
                 | StreamAccessKind::DiscreteWindow(window)
                 | StreamAccessKind::InstanceAggregation(window),
-        } => translate_window(target, parameters, window, default, sr2sr),
+        } => translate_window(target, parameters, window, default, spans, sr2sr),
 
         mir::ExpressionKind::StreamAccess {
             target,
             parameters,
             access_kind: StreamAccessKind::Fresh,
-        } => translate_is_fresh(target, parameters, sr2sr),
+        } => translate_is_fresh(target, parameters, spans, sr2sr),
 
         mir::ExpressionKind::StreamAccess {
             target,
             parameters,
             access_kind: StreamAccessKind::Get,
-        } => translate_get(
-            target,
-            parameters,
-            default.ok_or(LoweringError::DefaultRequired)?,
-            sr2sr,
-        ),
+        } => translate_get(target, parameters, default, spans, sr2sr),
 
         mir::ExpressionKind::Convert { expr: inner_exp } => {
-            translate_convert(*inner_exp, &lir_ty, sr2sr)
+            // an implicit conversion has no counterpart in the syntax tree
+            let inner_spans = if spans.is(Shape::Call) {
+                spans.operand(Shape::Call, 0)
+            } else {
+                spans.clone()
+            };
+            translate_convert(*inner_exp, &lir_ty, &inner_spans, sr2sr)
         }
 
         mir::ExpressionKind::Function(func, inner_exp) if func == "cast" => {
             let inner_exp = inner_exp.into_iter().next().unwrap();
-            translate_convert(inner_exp, &lir_ty, sr2sr)
+            translate_convert(inner_exp, &lir_ty, &spans.operand(Shape::Call, 0), sr2sr)
         }
 
         mir::ExpressionKind::ParameterAccess(target, parameter) => {
@@ -107,6 +123,13 @@ pub(super) fn convert_stream_expression(
         }
 
         mir::ExpressionKind::Function(func, inner_exps) => {
+            let args = inner_exps
+                .into_iter()
+                .enumerate()
+                .map(|(i, expr)| {
+                    convert_stream_expression(expr, None, &spans.operand(Shape::Call, i), sr2sr)
+                })
+                .collect::<Result<Vec<_>, LoweringError>>()?;
             let f = match func.as_str() {
                 "sqrt" => Function::Sqrt,
                 "sin" => Function::Sin,
@@ -118,24 +141,45 @@ pub(super) fn convert_stream_expression(
                 "abs" => Function::Abs,
                 "min" => Function::Min,
                 "max" => Function::Max,
-                name => return Err(LoweringError::UnsupportedFunction(name.into())),
+                "floor" => Function::Floor,
+                "ceil" => Function::Ceil,
+                "round" => Function::Round,
+                "exp" => Function::Exp,
+                "log" if args.len() == 2 => Function::Log,
+                "ln" | "log" => Function::Ln,
+                "pow" => Function::Pow,
+                "signum" => Function::Signum,
+                "clamp" => Function::Clamp,
+                "atan2" => Function::Atan2,
+                // all other functions are external functions, which are only accepted if they are declared when lowering
+                name => Function::External(
+                    name.into(),
+                    FunctionSignature {
+                        args: args.iter().map(|arg| arg.ty.clone()).collect(),
+                        ret: lir_ty.clone(),
+                    },
+                ),
             };
-            let args = inner_exps
-                .into_iter()
-                .map(|expr| convert_stream_expression(expr, None, sr2sr))
-                .collect::<Result<Vec<_>, LoweringError>>()?;
             Ok(ExprKind::FunctionCall(f, args))
         }
 
         mir::ExpressionKind::Tuple(inner_exps) => {
             let inner_exps = inner_exps
                 .into_iter()
-                .map(|expr| convert_stream_expression(expr, None, sr2sr))
+                .enumerate()
+                .map(|(i, expr)| {
+                    convert_stream_expression(expr, None, &spans.operand(Shape::Tuple, i), sr2sr)
+                })
                 .collect::<Result<Vec<_>, LoweringError>>()?;
             Ok(ExprKind::Tuple(inner_exps))
         }
         mir::ExpressionKind::TupleAccess(tuple_expr, i) => {
-            let tuple_expr = convert_stream_expression(*tuple_expr, None, sr2sr)?;
+            let tuple_expr = convert_stream_expression(
+                *tuple_expr,
+                None,
+                &spans.operand(Shape::TupleAccess, 0),
+                sr2sr,
+            )?;
             Ok(ExprKind::TupleAccess(Box::new(tuple_expr), i))
         }
         mir::ExpressionKind::LambdaParameterAccess { wref, pref } => {
@@ -146,9 +190,41 @@ pub(super) fn convert_stream_expression(
     Ok(Expr {
         ty: lir_ty,
         kind: lir_kind,
+        span: spans.span,
     })
 }
 
+/// Lowers the parameters of a stream access, locating them by the `spans` of the access
+fn convert_parameters(
+    parameters: Vec<mir::Expression>,
+    spans: &ExprSpans,
+    sr2sr: &HashMap<mir::StreamReference, StreamReference>,
+) -> Result<Vec<Expr>, LoweringError> {
+    parameters
+        .into_iter()
+        .enumerate()
+        .map(|(i, parameter)| {
+            convert_stream_expression(parameter, None, &spans.parameter(i), sr2sr)
+        })
+        .collect()
+}
+
+/// Returns whether the expression is an access that can directly use a default value
+fn takes_default(kind: &mir::ExpressionKind) -> bool {
+    matches!(
+        kind,
+        mir::ExpressionKind::StreamAccess {
+            access_kind: StreamAccessKind::Offset(_)
+                | StreamAccessKind::Hold
+                | StreamAccessKind::Get
+                | StreamAccessKind::SlidingWindow(_)
+                | StreamAccessKind::DiscreteWindow(_)
+                | StreamAccessKind::InstanceAggregation(_),
+            ..
+        }
+    )
+}
+
 fn translate_constant(c: mir::Constant, ty: &Type) -> Constant {
     match (c, ty) {
         (mir::Constant::Str(s), _) => Constant::Str(s),
@@ -172,6 +248,7 @@ fn translate_constant(c: mir::Constant, ty: &Type) -> Constant {
 fn translate_operator(
     op: mir::ArithLogOp,
     operands: Vec<mir::Expression>,
+    spans: &ExprSpans,
     sr2sr: &HashMap<mir::StreamReference, StreamReference>,
 ) -> Result<ExprKind, LoweringError> {
     let operator = match op {
@@ -204,7 +281,12 @@ fn translate_operator(
             let operand = operands.into_iter().next().unwrap();
             ExprKind::UnaryOperation(
                 operator,
-                Box::new(convert_stream_expression(operand, None, sr2sr)?),
+                Box::new(convert_stream_expression(
+                    operand,
+                    None,
+                    &spans.operand(Shape::Operation, 0),
+                    sr2sr,
+                )?),
             )
         }
         2 => {
@@ -214,8 +296,18 @@ fn translate_operator(
             };
             ExprKind::BinaryOperation(
                 operator,
-                Box::new(convert_stream_expression(lhs, None, sr2sr)?),
-                Box::new(convert_stream_expression(rhs, None, sr2sr)?),
+                Box::new(convert_stream_expression(
+                    lhs,
+                    None,
+                    &spans.operand(Shape::Operation, 0),
+                    sr2sr,
+                )?),
+                Box::new(convert_stream_expression(
+                    rhs,
+                    None,
+                    &spans.operand(Shape::Operation, 1),
+                    sr2sr,
+                )?),
             )
         }
         _ => unreachable!(),
@@ -226,26 +318,28 @@ fn translate_ite(
     condition: mir::Expression,
     consequence: mir::Expression,
     alternative: mir::Expression,
+    spans: &ExprSpans,
     sr2sr: &HashMap<mir::StreamReference, StreamReference>,
 ) -> Result<ExprKind, LoweringError> {
+    let convert = |expr: mir::Expression, i: usize| {
+        convert_stream_expression(expr, None, &spans.operand(Shape::Ite, i), sr2sr).map(Box::new)
+    };
     Ok(ExprKind::Ite(
-        Box::new(convert_stream_expression(condition, None, sr2sr)?),
-        Box::new(convert_stream_expression(consequence, None, sr2sr)?),
-        Box::new(convert_stream_expression(alternative, None, sr2sr)?),
+        convert(condition, 0)?,
+        convert(consequence, 1)?,
+        convert(alternative, 2)?,
     ))
 }
 
 fn translate_sync_access(
     target: mir::StreamReference,
     parameters: Vec<mir::Expression>,
+    spans: &ExprSpans,
     sr2sr: &HashMap<mir::StreamReference, StreamReference>,
 ) -> Result<ExprKind, LoweringError> {
     Ok(ExprKind::SyncStreamAccess {
         target: sr2sr[&target],
-        parameters: parameters
-            .into_iter()
-            .map(|expr| convert_stream_expression(expr, None, sr2sr))
-            .collect::<Result<Vec<_>, LoweringError>>()?,
+        parameters: convert_parameters(parameters, spans, sr2sr)?,
     })
 }
 
@@ -253,7 +347,8 @@ fn translate_offset_access(
     target: mir::StreamReference,
     parameters: Vec<mir::Expression>,
     offset: mir::Offset,
-    default: Expr,
+    default: Option<Expr>,
+    spans: &ExprSpans,
     sr2sr: &HashMap<mir::StreamReference, StreamReference>,
 ) -> Result<ExprKind, LoweringError> {
     let offset = match offset {
@@ -264,27 +359,22 @@ fn translate_offset_access(
     Ok(ExprKind::OffsetStreamAccess {
         target: sr2sr[&target],
         offset,
-        default: Box::new(default),
-        parameters: parameters
-            .into_iter()
-            .map(|parameter| convert_stream_expression(parameter, None, sr2sr))
-            .collect::<Result<Vec<_>, LoweringError>>()?,
+        default: default.map(Box::new),
+        parameters: convert_parameters(parameters, spans, sr2sr)?,
     })
 }
 
 fn translate_hold_access(
     target: mir::StreamReference,
     parameters: Vec<mir::Expression>,
-    default: Expr,
+    default: Option<Expr>,
+    spans: &ExprSpans,
     sr2sr: &HashMap<mir::StreamReference, StreamReference>,
 ) -> Result<ExprKind, LoweringError> {
     Ok(ExprKind::HoldStreamAccess {
         target: sr2sr[&target],
-        default: Box::new(default),
-        parameters: parameters
-            .into_iter()
-            .map(|parameter| convert_stream_expression(parameter, None, sr2sr))
-            .collect::<Result<Vec<_>, LoweringError>>()?,
+        default: default.map(Box::new),
+        parameters: convert_parameters(parameters, spans, sr2sr)?,
     })
 }
 
@@ -293,6 +383,7 @@ fn translate_window(
     parameters: Vec<mir::Expression>,
     window: mir::WindowReference,
     default: Option<Expr>,
+    spans: &ExprSpans,
     sr2sr: &HashMap<mir::StreamReference, StreamReference>,
 ) -> Result<ExprKind, LoweringError> {
     let default = default.map(Box::new);
@@ -300,10 +391,7 @@ fn translate_window(
     Ok(ExprKind::WindowAccess {
         target: sr2sr[&target],
         window: window.into(),
-        parameters: parameters
-            .into_iter()
-            .map(|x| convert_stream_expression(x, None, sr2sr))
-            .collect::<Result<Vec<_>, LoweringError>>()?,
+        parameters: convert_parameters(parameters, spans, sr2sr)?,
         default,
     })
 }
@@ -311,11 +399,12 @@ fn translate_window(
 fn translate_convert(
     expr: mir::Expression,
     convert_to: &Type,
+    spans: &ExprSpans,
     sr2sr: &HashMap<mir::StreamReference, StreamReference>,
 ) -> Result<ExprKind, LoweringError> {
     Ok(ExprKind::Cast(
         convert_to.clone(),
-        Box::new(convert_stream_expression(expr, None, sr2sr)?),
+        Box::new(convert_stream_expression(expr, None, spans, sr2sr)?),
     ))
 }
 
@@ -330,29 +419,25 @@ fn translate_parameter_access(
 fn translate_is_fresh(
     target: mir::StreamReference,
     parameters: Vec<mir::Expression>,
+    spans: &ExprSpans,
     sr2sr: &HashMap<mir::StreamReference, StreamReference>,
 ) -> Result<ExprKind, LoweringError> {
     Ok(ExprKind::IsFresh {
         target: sr2sr[&target],
-        parameters: parameters
-            .into_iter()
-            .map(|x| convert_stream_expression(x, None, sr2sr))
-            .collect::<Result<Vec<_>, LoweringError>>()?,
+        parameters: convert_parameters(parameters, spans, sr2sr)?,
     })
 }
 
 fn translate_get(
     target: mir::StreamReference,
     parameters: Vec<mir::Expression>,
-    default: Expr,
+    default: Option<Expr>,
+    spans: &ExprSpans,
     sr2sr: &HashMap<mir::StreamReference, StreamReference>,
 ) -> Result<ExprKind, LoweringError> {
     Ok(ExprKind::GetAccess {
         target: sr2sr[&target],
-        default: Box::new(default),
-        parameters: parameters
-            .into_iter()
-            .map(|x| convert_stream_expression(x, None, sr2sr))
-            .collect::<Result<Vec<_>, LoweringError>>()?,
+        default: default.map(Box::new),
+        parameters: convert_parameters(parameters, spans, sr2sr)?,
     })
 }
@@ -7,6 +7,11 @@ use crate::ir::{OutputReference, StreamReference};
 
 /// Contains information about the equivalences of stream livetimes
 #[derive(Default, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "SerializedEquivalences", into = "SerializedEquivalences")
+)]
 pub struct LivetimeEquivalences {
     pub(crate) idx: HashMap<OutputReference, usize>,
     pub(crate) sets: DisjointSet,
@@ -109,9 +114,12 @@ impl std::fmt::Debug for LivetimeEquivalences {
             .map(|set| {
                 set.into_iter()
                     .map(|s| {
+                        if s == self.input_idx {
+                            return "inputs".to_string();
+                        }
                         self.idx
                             .iter()
-                            .find_map(|(k, v)| (*v == s).then_some(k))
+                            .find_map(|(k, v)| (*v == s).then(|| format!("{k:?}")))
                             .unwrap()
                     })
                     .collect::<Vec<_>>()
@@ -121,6 +129,55 @@ impl std::fmt::Debug for LivetimeEquivalences {
     }
 }
 
+/// The serialized form of [LivetimeEquivalences], storing the equivalence classes instead of the disjoint set.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SerializedEquivalences {
+    idx: Vec<(OutputReference, usize)>,
+    classes: Vec<Vec<usize>>,
+    input_idx: usize,
+}
+
+#[cfg(feature = "serde")]
+impl From<LivetimeEquivalences> for SerializedEquivalences {
+    fn from(value: LivetimeEquivalences) -> Self {
+        let LivetimeEquivalences {
+            idx,
+            sets,
+            input_idx,
+        } = value;
+        let mut idx = idx.into_iter().collect::<Vec<_>>();
+        idx.sort_unstable();
+        Self {
+            idx,
+            classes: sets.sets(),
+            input_idx,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<SerializedEquivalences> for LivetimeEquivalences {
+    fn from(value: SerializedEquivalences) -> Self {
+        let SerializedEquivalences {
+            idx,
+            classes,
+            input_idx,
+        } = value;
+        let mut sets = DisjointSet::with_len(classes.iter().map(Vec::len).sum());
+        for class in &classes {
+            for other in class.iter().skip(1) {
+                sets.join(class[0], *other);
+            }
+        }
+        Self {
+            idx: idx.into_iter().collect(),
+            sets,
+            input_idx,
+        }
+    }
+}
+
 fn compare_expr_option(e1: Option<&mir::Expression>, e2: Option<&mir::Expression>) -> bool {
     match (e1, e2) {
         (Some(e1), Some(e2)) => compare_expr(e1, e2),
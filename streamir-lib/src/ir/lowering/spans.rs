@@ -0,0 +1,277 @@
+//! Takes the locations of the streams and their clauses from the syntax tree of the frontend.
+//!
+//! The MIR carries no spans, so the spans are collected from the syntax tree of the same specification
+//! and matched to the streams of the MIR by name, or by their order for triggers.
+//! The spans of the subexpressions of a clause are matched to the subexpressions of the MIR by the structure of the expression.
+
+use std::{collections::HashMap, iter};
+
+use rtlola_frontend::{
+    ast::{Expression, ExpressionKind, OutputKind},
+    RtLolaAst, Span as FrontendSpan,
+};
+
+use crate::ir::{Origin, SourceSpan, Span};
+
+/// The syntactic form of an expression, which determines how its operands correspond to the subexpressions in the MIR
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(super) enum Shape {
+    /// An expression without operands, or one whose operands have no counterpart in the MIR
+    #[default]
+    Leaf,
+    /// A unary or binary operation with its operands
+    Operation,
+    /// An if-then-else with the condition, the consequence and the alternative
+    Ite,
+    /// A default with the optional expression and the default value
+    Default,
+    /// A tuple with its elements
+    Tuple,
+    /// A tuple access with the accessed tuple
+    TupleAccess,
+    /// A function call with its arguments, where the receiver of a method call is the first argument
+    Call,
+    /// A stream access or window aggregation with the parameters of the accessed stream
+    Access,
+}
+
+/// The spans of an expression and its operands
+#[derive(Debug, Clone, Default)]
+pub(super) struct ExprSpans {
+    /// The span of the expression
+    pub(super) span: SourceSpan,
+    /// The syntactic form of the expression
+    shape: Shape,
+    /// The spans of the operands of the expression
+    operands: Vec<ExprSpans>,
+}
+
+impl ExprSpans {
+    /// Returns the spans of an expression at the given span without operands in the syntax tree
+    pub(super) fn at(span: SourceSpan) -> Self {
+        ExprSpans {
+            span,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the spans with the span of the whole expression replaced by the given span
+    pub(super) fn located_at(self, span: SourceSpan) -> Self {
+        ExprSpans { span, ..self }
+    }
+
+    /// Returns whether the expression has the given syntactic form
+    pub(super) fn is(&self, shape: Shape) -> bool {
+        self.shape == shape
+    }
+
+    /// Returns the spans of the `i`-th operand if the expression has the given syntactic form
+    ///
+    /// Otherwise, the subexpression of the MIR has no counterpart in the syntax tree
+    /// and is located at the whole expression.
+    pub(super) fn operand(&self, shape: Shape, i: usize) -> ExprSpans {
+        match self.operands.get(i) {
+            Some(operand) if self.shape == shape => operand.clone(),
+            _ => ExprSpans::at(self.span),
+        }
+    }
+
+    /// Returns the spans of the `i`-th parameter of a stream access
+    ///
+    /// The parameters of a synchronous access are the arguments of a call of the stream.
+    pub(super) fn parameter(&self, i: usize) -> ExprSpans {
+        let shape = if self.is(Shape::Call) {
+            Shape::Call
+        } else {
+            Shape::Access
+        };
+        self.operand(shape, i)
+    }
+
+    /// Collects the spans of the expression from the syntax tree of the specification `spec`
+    fn new(spec: &str, expr: &Expression) -> Self {
+        let new = |expr: &Expression| Self::new(spec, expr);
+        let (shape, operands) = match &expr.kind {
+            ExpressionKind::ParenthesizedExpression(_, inner, _) => return new(inner),
+            ExpressionKind::Unary(_, operand) => (Shape::Operation, vec![new(operand)]),
+            ExpressionKind::Binary(_, lhs, rhs) => (Shape::Operation, vec![new(lhs), new(rhs)]),
+            ExpressionKind::Ite(condition, consequence, alternative) => (
+                Shape::Ite,
+                vec![new(condition), new(consequence), new(alternative)],
+            ),
+            ExpressionKind::Default(expr, default) => {
+                (Shape::Default, vec![new(expr), new(default)])
+            }
+            ExpressionKind::Tuple(elements) => (Shape::Tuple, elements.iter().map(new).collect()),
+            ExpressionKind::Field(tuple, _) => (Shape::TupleAccess, vec![new(tuple)]),
+            ExpressionKind::Function(_, _, args) => (Shape::Call, args.iter().map(new).collect()),
+            ExpressionKind::Method(receiver, _, _, args) => (
+                Shape::Call,
+                iter::once(receiver.as_ref()).chain(args).map(new).collect(),
+            ),
+            ExpressionKind::StreamAccess(target, _)
+            | ExpressionKind::Offset(target, _)
+            | ExpressionKind::DiscreteWindowAggregation { expr: target, .. }
+            | ExpressionKind::SlidingWindowAggregation { expr: target, .. }
+            | ExpressionKind::InstanceAggregation { expr: target, .. } => {
+                // the parameters of the accessed stream are the arguments of its call
+                let target = new(target);
+                let parameters = if target.is(Shape::Call) {
+                    target.operands
+                } else {
+                    Vec::new()
+                };
+                (Shape::Access, parameters)
+            }
+            _ => (Shape::Leaf, Vec::new()),
+        };
+        ExprSpans {
+            span: Spans::span(spec, &expr.span),
+            shape,
+            operands,
+        }
+    }
+}
+
+/// The spans of a spawn, eval or close clause
+#[derive(Debug, Clone, Default)]
+pub(super) struct ClauseSpans {
+    /// The span of the whole clause
+    pub(super) clause: SourceSpan,
+    /// The spans of the condition of the clause
+    pub(super) condition: ExprSpans,
+    /// The spans of the expression of the clause
+    pub(super) expression: ExprSpans,
+}
+
+/// The spans of the declaration and the clauses of a stream
+#[derive(Debug, Clone, Default)]
+pub(super) struct StreamSpans {
+    /// The span of the name of the stream, or of the trigger keyword
+    pub(super) declaration: SourceSpan,
+    /// The spans of the spawn clause
+    pub(super) spawn: ClauseSpans,
+    /// The spans of the eval clauses, in the order of the clauses
+    pub(super) eval: Vec<ClauseSpans>,
+    /// The spans of the close clause
+    pub(super) close: ClauseSpans,
+}
+
+impl StreamSpans {
+    /// Returns the span of the clause with the given origin
+    pub(super) fn origin(&self, origin: Origin) -> SourceSpan {
+        match origin {
+            Origin::Spawn => self.spawn.clause,
+            Origin::EvalWhen(_) | Origin::EvalWith(_) => self.eval_expression(origin).span,
+            Origin::Close => self.close.clause,
+        }
+    }
+
+    /// Returns the spans of the filter or the expression of the eval clause with the given origin
+    ///
+    /// If the location of the whole expression is unknown, it is located at the clause.
+    pub(super) fn eval_expression(&self, origin: Origin) -> ExprSpans {
+        let (eval, expression) = match origin {
+            Origin::EvalWhen(i) => (self.eval.get(i), self.eval.get(i).map(|e| &e.condition)),
+            Origin::EvalWith(i) => (self.eval.get(i), self.eval.get(i).map(|e| &e.expression)),
+            Origin::Spawn | Origin::Close => (None, None),
+        };
+        let expression = expression.cloned().unwrap_or_default();
+        let span = expression
+            .span
+            .or(eval.map_or(SourceSpan::UNKNOWN, |eval| eval.clause));
+        expression.located_at(span)
+    }
+}
+
+/// The spans of the streams of a specification
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Spans {
+    /// The spans of the inputs and named outputs by their name
+    streams: HashMap<String, StreamSpans>,
+    /// The spans of the triggers in the order of their declaration
+    triggers: Vec<StreamSpans>,
+}
+
+impl Spans {
+    /// Collects the spans from the syntax tree of the specification `spec`
+    pub(crate) fn new(ast: &RtLolaAst, spec: &str) -> Self {
+        let span = |span: &FrontendSpan| Self::span(spec, span);
+        let clause = |clause: &FrontendSpan,
+                      condition: Option<&Expression>,
+                      expression: Option<&Expression>| ClauseSpans {
+            clause: span(clause),
+            condition: condition.map_or_else(ExprSpans::default, |e| ExprSpans::new(spec, e)),
+            expression: expression.map_or_else(ExprSpans::default, |e| ExprSpans::new(spec, e)),
+        };
+        let mut spans = Spans::default();
+        for input in &ast.inputs {
+            let stream = StreamSpans {
+                declaration: span(&input.name.span),
+                ..Default::default()
+            };
+            spans.streams.insert(input.name.name.clone(), stream);
+        }
+        for output in &ast.outputs {
+            let stream = StreamSpans {
+                declaration: match &output.kind {
+                    OutputKind::NamedOutput(name) => span(&name.span),
+                    OutputKind::Trigger => span(&output.span),
+                },
+                spawn: output
+                    .spawn
+                    .as_ref()
+                    .map_or_else(ClauseSpans::default, |spawn| {
+                        clause(
+                            &spawn.span,
+                            spawn.condition.as_ref(),
+                            spawn.expression.as_ref(),
+                        )
+                    }),
+                eval: output
+                    .eval
+                    .iter()
+                    .map(|eval| {
+                        clause(
+                            &eval.span,
+                            eval.condition.as_ref(),
+                            eval.eval_expression.as_ref(),
+                        )
+                    })
+                    .collect(),
+                close: output
+                    .close
+                    .as_ref()
+                    .map_or_else(ClauseSpans::default, |close| {
+                        clause(&close.span, Some(&close.condition), None)
+                    }),
+            };
+            match &output.kind {
+                OutputKind::NamedOutput(name) => {
+                    spans.streams.insert(name.name.clone(), stream);
+                }
+                OutputKind::Trigger => spans.triggers.push(stream),
+            }
+        }
+        spans
+    }
+
+    /// Converts the byte offsets of the frontend into a line and column
+    fn span(spec: &str, span: &FrontendSpan) -> SourceSpan {
+        match span {
+            FrontendSpan::Direct { start, .. } => Span::from_offset(spec, *start).into(),
+            FrontendSpan::Indirect(span) => Self::span(spec, span),
+            FrontendSpan::Unknown => SourceSpan::UNKNOWN,
+        }
+    }
+
+    /// Returns the spans of the stream with the given name, or of the trigger with the given index
+    pub(super) fn stream(&self, name: &str, trigger: Option<usize>) -> StreamSpans {
+        match trigger {
+            Some(idx) => self.triggers.get(idx),
+            None => self.streams.get(name),
+        }
+        .cloned()
+        .unwrap_or_default()
+    }
+}
@@ -0,0 +1,29 @@
+//! (De-)serializes a map as a list of key-value pairs sorted by key.
+//!
+//! Most of the maps in the StreamIR are keyed by enums that carry data (e.g. [StreamReference](super::StreamReference)),
+//! which many formats (such as JSON) do not allow as map keys.
+
+use std::{collections::HashMap, hash::Hash};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+pub(super) fn serialize<K, V, S>(map: &HashMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    K: Serialize + Ord,
+    V: Serialize,
+    S: Serializer,
+{
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort_unstable_by_key(|(k, _)| *k);
+    entries.serialize(serializer)
+}
+
+pub(super) fn deserialize<'de, K, V, D>(deserializer: D) -> Result<HashMap<K, V>, D::Error>
+where
+    K: Deserialize<'de> + Eq + Hash,
+    V: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    let entries: Vec<(K, V)> = Vec::deserialize(deserializer)?;
+    Ok(entries.into_iter().collect())
+}
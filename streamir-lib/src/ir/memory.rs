@@ -5,6 +5,7 @@ use std::ops::Add;
 use super::Type;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// All memory information of a stream
 pub struct Memory {
     /// The buffer of the stream
@@ -13,9 +14,16 @@ pub struct Memory {
     pub ty: Type,
     /// The name of the stream
     pub name: String,
+    /// The number of activations by which the evaluation of the stream lags behind
+    ///
+    /// A stream with a delay of `n` computes at its `i`-th activation the value belonging to its `(i-n)`-th activation,
+    /// so that future offset accesses refer to values that were already computed.
+    /// During the first `n` activations, the stream is neither shifted nor evaluated.
+    pub delay: u32,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// The kind of a stream's memory
 pub enum StreamMemory {
     /// No memory is needed for this stream
@@ -37,10 +45,14 @@ pub enum StreamMemory {
         buffer: StreamBuffer,
         /// The parameter of the stream
         parameter: Vec<Parameter>,
+        /// An upper bound on the number of instances that are alive at the same time, or None if it is not known
+        #[cfg_attr(feature = "serde", serde(default))]
+        bound: Option<usize>,
     },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// The kind of buffer for a stream's instance
 pub enum StreamBuffer {
     /// Only the newest value needs to be stored
@@ -63,6 +75,7 @@ impl StreamBuffer {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// A single parameter of a parameterized stream
 pub struct Parameter {
     /// The name of the parameter
@@ -87,10 +100,7 @@ impl StreamMemory {
     /// Returns the parameters of a stream if it is parameterized (or None otherwise)
     pub fn parameters(&self) -> Option<&[Parameter]> {
         match self {
-            StreamMemory::Instances {
-                buffer: _,
-                parameter,
-            } => Some(parameter),
+            StreamMemory::Instances { parameter, .. } => Some(parameter),
             _ => None,
         }
     }
@@ -98,16 +108,22 @@ impl StreamMemory {
     /// Returns the number of parameters of a parameterized streams or 0 for unparameterized streams
     pub fn num_parameters(&self) -> usize {
         match self {
-            StreamMemory::Instances {
-                buffer: _,
-                parameter,
-            } => parameter.len(),
+            StreamMemory::Instances { parameter, .. } => parameter.len(),
             _ => 0,
         }
     }
 }
 
 impl StreamMemory {
+    /// Returns the upper bound on the number of instances alive at the same time.
+    /// Returns None for unparameterized streams and if the bound is not known.
+    pub fn instance_bound(&self) -> Option<usize> {
+        match self {
+            StreamMemory::Instances { bound, .. } => *bound,
+            _ => None,
+        }
+    }
+
     /// Returns the [StreamBuffer] of the memory. Returns [None] if no memory is required
     pub fn buffer(&self) -> Option<&StreamBuffer> {
         match self {
@@ -0,0 +1,323 @@
+//! The behavior of integer operations whose result does not fit their type.
+
+use std::{cmp::Ordering, fmt::Display, str::FromStr};
+
+use thiserror::Error;
+
+use super::{expressions::Operator, ranges::int_bounds, Type};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// How the arithmetic operators treat integer results that do not fit the width of their type
+///
+/// The policy applies to `+`, `-`, `*`, `/`, `%`, `**` (including the `pow` function) and the unary `-`,
+/// and is implemented identically by all backends (see [OverflowPolicy::binary] for the reference semantics).
+pub enum OverflowPolicy {
+    /// The result wraps around at the width of the type (two's complement)
+    #[default]
+    Wrap,
+    /// The result is clamped to the smallest or largest value of the type
+    Saturate,
+    /// The overflow is a runtime error of the monitor
+    Trap,
+}
+
+#[derive(Debug, Error)]
+#[error("unknown overflow policy `{0}`, expected `wrap`, `saturate` or `trap`")]
+/// The name of an overflow policy was not recognized
+pub struct UnknownOverflowPolicy(pub String);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("attempt to {} with overflow", verb(*.0))]
+/// An integer operation overflowed under [OverflowPolicy::Trap]
+///
+/// The message matches the one of the corresponding integer operation in Rust.
+pub struct Overflow(pub Operator);
+
+/// Returns the verb describing the operation in the message of an [Overflow]
+fn verb(op: Operator) -> &'static str {
+    match op {
+        Operator::Add => "add",
+        Operator::Sub => "subtract",
+        Operator::Div => "divide",
+        Operator::Rem => "calculate the remainder",
+        Operator::Neg => "negate",
+        _ => "multiply",
+    }
+}
+
+impl Display for OverflowPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OverflowPolicy::Wrap => write!(f, "wrap"),
+            OverflowPolicy::Saturate => write!(f, "saturate"),
+            OverflowPolicy::Trap => write!(f, "trap"),
+        }
+    }
+}
+
+impl FromStr for OverflowPolicy {
+    type Err = UnknownOverflowPolicy;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "wrap" => Ok(OverflowPolicy::Wrap),
+            "saturate" => Ok(OverflowPolicy::Saturate),
+            "trap" => Ok(OverflowPolicy::Trap),
+            other => Err(UnknownOverflowPolicy(other.into())),
+        }
+    }
+}
+
+impl OverflowPolicy {
+    /// Returns whether the policy applies to the operator
+    pub fn governs(op: Operator) -> bool {
+        matches!(
+            op,
+            Operator::Add
+                | Operator::Sub
+                | Operator::Mul
+                | Operator::Div
+                | Operator::Rem
+                | Operator::Pow
+                | Operator::Neg
+        )
+    }
+
+    /// Applies the binary operator to integer operands of the given type, following the policy if the result does not fit.
+    ///
+    /// The exponent of `**` is never truncated: a negative exponent results in the integer part of the reciprocal of the power,
+    /// i.e., 0 unless the base is 1 or -1, and a division by zero for the base 0.
+    /// The remainder is always defined if the divisor is not zero, e.g., the remainder of the smallest value and -1 is 0.
+    ///
+    /// # Panics
+    /// Panics on a division by zero (which is no overflow) and if the operator is not governed by the policy.
+    pub fn binary(self, op: Operator, lhs: i128, rhs: i128, ty: &Type) -> Result<i128, Overflow> {
+        // the exact result, or the direction in which it exceeds the range of i128
+        let (exact, wrapped) = match op {
+            Operator::Add => (
+                lhs.checked_add(rhs).ok_or(lhs.cmp(&0)),
+                lhs.wrapping_add(rhs),
+            ),
+            Operator::Sub => (
+                lhs.checked_sub(rhs).ok_or(lhs.cmp(&-1)),
+                lhs.wrapping_sub(rhs),
+            ),
+            Operator::Mul => (
+                lhs.checked_mul(rhs).ok_or(if (lhs < 0) == (rhs < 0) {
+                    Ordering::Greater
+                } else {
+                    Ordering::Less
+                }),
+                lhs.wrapping_mul(rhs),
+            ),
+            Operator::Div | Operator::Rem if rhs == 0 => panic!("attempt to divide by zero"),
+            Operator::Div => (
+                lhs.checked_div(rhs).ok_or(Ordering::Greater),
+                lhs.wrapping_div(rhs),
+            ),
+            Operator::Rem => (Ok(lhs.checked_rem(rhs).unwrap_or(0)), 0),
+            Operator::Pow if rhs < 0 => {
+                let reciprocal = match lhs {
+                    0 => panic!("attempt to divide by zero"),
+                    1 => 1,
+                    -1 if rhs % 2 == 0 => 1,
+                    -1 => -1,
+                    _ => 0,
+                };
+                (Ok(reciprocal), reciprocal)
+            }
+            Operator::Pow => {
+                let exact = match u32::try_from(rhs) {
+                    Ok(exp) => lhs.checked_pow(exp),
+                    // only the powers of 0, 1 and -1 fit with an exponent beyond 32 bits
+                    Err(_) if (-1..=1).contains(&lhs) => Some(wrapping_pow(lhs, rhs as u128)),
+                    Err(_) => None,
+                };
+                (
+                    exact.ok_or(if lhs < 0 && rhs % 2 == 1 {
+                        Ordering::Less
+                    } else {
+                        Ordering::Greater
+                    }),
+                    wrapping_pow(lhs, rhs as u128),
+                )
+            }
+            _ => panic!("the overflow policy does not apply to {op:?}"),
+        };
+        self.fit(op, exact, wrapped, ty)
+    }
+
+    /// Negates an integer operand of the given type, following the policy if the result does not fit
+    pub fn negate(self, operand: i128, ty: &Type) -> Result<i128, Overflow> {
+        self.fit(
+            Operator::Neg,
+            operand.checked_neg().ok_or(Ordering::Greater),
+            operand.wrapping_neg(),
+            ty,
+        )
+    }
+
+    fn fit(
+        self,
+        op: Operator,
+        exact: Result<i128, Ordering>,
+        wrapped: i128,
+        ty: &Type,
+    ) -> Result<i128, Overflow> {
+        let (signed, bits) = match ty.inner_ty() {
+            Type::Int(bits) => (true, *bits),
+            Type::UInt(bits) => (false, *bits),
+            other => panic!("the overflow policy does not apply to values of type {other}"),
+        };
+        let (min, max) = int_bounds(signed, bits);
+        let direction = match exact {
+            Ok(value) if value < min => Ordering::Less,
+            Ok(value) if value > max => Ordering::Greater,
+            Ok(value) => return Ok(value),
+            Err(direction) => direction,
+        };
+        match self {
+            OverflowPolicy::Trap => Err(Overflow(op)),
+            OverflowPolicy::Saturate if direction == Ordering::Less => Ok(min),
+            OverflowPolicy::Saturate => Ok(max),
+            OverflowPolicy::Wrap => Ok(wrap(wrapped, signed, bits)),
+        }
+    }
+}
+
+/// Raises the base to the power of the exponent, wrapping around at the width of `i128`
+fn wrapping_pow(mut base: i128, mut exp: u128) -> i128 {
+    let mut result: i128 = 1;
+    while exp > 0 {
+        if exp % 2 == 1 {
+            result = result.wrapping_mul(base);
+        }
+        base = base.wrapping_mul(base);
+        exp /= 2;
+    }
+    result
+}
+
+/// Returns the value modulo `2^bits`, in the range of the signed or unsigned integers of that width
+fn wrap(value: i128, signed: bool, bits: u16) -> i128 {
+    if bits >= 127 {
+        return value;
+    }
+    let modulus = 1i128 << bits;
+    let value = value.rem_euclid(modulus);
+    if signed && value >= modulus / 2 {
+        value - modulus
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ir::{expressions::Operator, Type};
+
+    use super::{Overflow, OverflowPolicy};
+
+    #[test]
+    fn policies() {
+        let u8 = Type::UInt(8);
+        let i8 = Type::Int(8);
+        assert_eq!(
+            OverflowPolicy::Wrap.binary(Operator::Add, 250, 10, &u8),
+            Ok(4)
+        );
+        assert_eq!(
+            OverflowPolicy::Saturate.binary(Operator::Add, 250, 10, &u8),
+            Ok(255)
+        );
+        assert_eq!(
+            OverflowPolicy::Trap.binary(Operator::Add, 250, 10, &u8),
+            Err(Overflow(Operator::Add))
+        );
+        assert_eq!(
+            OverflowPolicy::Trap.binary(Operator::Add, 250, 5, &u8),
+            Ok(255)
+        );
+        assert_eq!(
+            OverflowPolicy::Wrap.binary(Operator::Sub, 1, 2, &u8),
+            Ok(255)
+        );
+        assert_eq!(
+            OverflowPolicy::Saturate.binary(Operator::Sub, 1, 2, &u8),
+            Ok(0)
+        );
+        assert_eq!(
+            OverflowPolicy::Wrap.binary(Operator::Mul, 100, 3, &i8),
+            Ok(44)
+        );
+        assert_eq!(
+            OverflowPolicy::Saturate.binary(Operator::Mul, -100, 3, &i8),
+            Ok(-128)
+        );
+        assert_eq!(
+            OverflowPolicy::Wrap.binary(Operator::Div, -128, -1, &i8),
+            Ok(-128)
+        );
+        assert_eq!(
+            OverflowPolicy::Saturate.binary(Operator::Div, -128, -1, &i8),
+            Ok(127)
+        );
+        assert_eq!(
+            OverflowPolicy::Trap.binary(Operator::Rem, -128, -1, &i8),
+            Ok(0)
+        );
+        assert_eq!(
+            OverflowPolicy::Saturate.binary(Operator::Pow, -2, 9, &i8),
+            Ok(-128)
+        );
+        assert_eq!(
+            OverflowPolicy::Wrap.binary(Operator::Pow, 3, 5, &u8),
+            Ok(243)
+        );
+        // the exponent is not truncated to 32 bits
+        assert_eq!(
+            OverflowPolicy::Trap.binary(Operator::Pow, 2, 1 << 32, &u8),
+            Err(Overflow(Operator::Pow))
+        );
+        assert_eq!(
+            OverflowPolicy::Saturate.binary(Operator::Pow, -2, (1 << 32) + 1, &i8),
+            Ok(-128)
+        );
+        assert_eq!(
+            OverflowPolicy::Wrap.binary(Operator::Pow, 3, 1 << 32, &u8),
+            Ok(1)
+        );
+        assert_eq!(
+            OverflowPolicy::Trap.binary(Operator::Pow, -1, (1 << 40) + 1, &i8),
+            Ok(-1)
+        );
+        assert_eq!(
+            OverflowPolicy::Trap.binary(Operator::Pow, 2, -1, &i8),
+            Ok(0)
+        );
+        assert_eq!(
+            OverflowPolicy::Trap.binary(Operator::Pow, -1, -3, &i8),
+            Ok(-1)
+        );
+        assert_eq!(OverflowPolicy::Saturate.negate(-128, &i8), Ok(127));
+        assert_eq!(OverflowPolicy::Wrap.negate(1, &u8), Ok(255));
+        // the exact result exceeds even 128 bits
+        assert_eq!(
+            OverflowPolicy::Saturate.binary(Operator::Pow, 3, 200, &Type::UInt(64)),
+            Ok(u64::MAX.into())
+        );
+        assert_eq!(
+            OverflowPolicy::Trap
+                .binary(Operator::Mul, i64::MIN.into(), 2, &Type::Int(64))
+                .unwrap_err()
+                .to_string(),
+            "attempt to multiply with overflow"
+        );
+        assert_eq!(
+            "saturate".parse::<OverflowPolicy>().unwrap(),
+            OverflowPolicy::Saturate
+        );
+        assert!("clamp".parse::<OverflowPolicy>().is_err());
+    }
+}
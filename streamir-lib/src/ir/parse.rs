@@ -12,7 +12,8 @@ use winnow::{
 use super::{
     expressions::{Constant, Expr, ExprKind, Operator},
     memory::{Memory, Parameter, StreamBuffer, StreamMemory},
-    Guard, LivetimeEquivalences, LocalFreq, OutputReference, Stmt, StreamIr, StreamReference, Type,
+    Guard, LivetimeEquivalences, LocalFreq, OutputReference, SourceSpan, Stmt, StreamIr,
+    StreamReference, Type,
 };
 
 fn iws<Input, Output, Error, ParseNext>(parser: ParseNext) -> impl Parser<Input, Output, Error>
@@ -51,7 +52,8 @@ fn parse_stmt_prime(input: &mut &str) -> winnow::ModalResult<Stmt> {
         preceded("eval", cut_err(seq!(_: multispace1, dec_int, _: multispace1, parse_expr))).context(StrContext::Label("eval")).map(|(i, expr): (i32,_)| Stmt::Eval {
             sr: OutputReference::Unparameterized(i as usize),
             with: expr,
-            idx: 0
+            idx: 0,
+            span: SourceSpan::UNKNOWN
         }),
         preceded("iterate", cut_err(seq!(_: multispace1, dec_int, _: multispace1, parse_stmt_prime))).context(StrContext::Label("iterate")).map(|(i,stmt): (i32,_)| Stmt::Iterate {
             sr: vec![OutputReference::Unparameterized(i as usize)],
@@ -133,14 +135,17 @@ fn parse_expr(input: &mut &str) -> winnow::ModalResult<Expr> {
             "&&" => Ok(Expr {
                 ty: Type::Bool,
                 kind: ExprKind::BinaryOperation(Operator::And, Box::new(lhs), Box::new(rhs)),
+                span: SourceSpan::UNKNOWN,
             }),
             "||" => Ok(Expr {
                 ty: Type::Bool,
                 kind: ExprKind::BinaryOperation(Operator::Or, Box::new(lhs), Box::new(rhs)),
+                span: SourceSpan::UNKNOWN,
             }),
             "==" => Ok(Expr {
                 ty: Type::Bool,
                 kind: ExprKind::BinaryOperation(Operator::Eq, Box::new(lhs), Box::new(rhs)),
+                span: SourceSpan::UNKNOWN,
             }),
             _ => unreachable!(),
         }
@@ -155,6 +160,7 @@ fn parse_expr_term(input: &mut &str) -> winnow::ModalResult<Expr> {
         seq!(_: "p", dec_uint).map(|(p,): (u64,)| Expr {
             ty: Type::Bool,
             kind: ExprKind::ParameterAccess(StreamReference::In(0), p as usize),
+            span: SourceSpan::UNKNOWN,
         }),
         seq!(_: alt(("s", "o")), dec_uint).map(|(p,): (u64,)| Expr {
             ty: Type::Bool,
@@ -162,6 +168,7 @@ fn parse_expr_term(input: &mut &str) -> winnow::ModalResult<Expr> {
                 target: StreamReference::In(p as usize),
                 parameters: vec![],
             },
+            span: SourceSpan::UNKNOWN,
         }),
         delimited("(", parse_expr, ")"),
     )))
@@ -178,6 +185,7 @@ fn parse_bool(input: &mut &str) -> winnow::ModalResult<Expr> {
         .map(|v| Expr {
             ty: Type::Bool,
             kind: ExprKind::Constant(Constant::Bool(v)),
+            span: SourceSpan::UNKNOWN,
         })
         .parse_next(input)
 }
@@ -192,6 +200,12 @@ pub(crate) fn parse_stmt(mut s: &str) -> Stmt {
     }
 }
 
+/// Parses the stream declarations followed by the statement from the text format
+#[cfg(test)]
+pub(crate) fn parse_text(declarations: &str, stmt: &str) -> StreamIr {
+    StreamIr::from_text(&format!("{declarations}stmt {stmt}")).unwrap_or_else(|e| panic!("{e}"))
+}
+
 pub(crate) fn parse_ir(s: &str) -> StreamIr {
     let stmt = parse_stmt(s);
     StreamIr {
@@ -216,9 +230,11 @@ pub(crate) fn parse_ir(s: &str) -> StreamIr {
                                     ty: Type::Bool,
                                 })
                                 .collect(),
+                            bound: None,
                         },
                         ty: Type::Bool,
                         name,
+                        delay: 0,
                     },
                 )
             })
@@ -247,5 +263,7 @@ pub(crate) fn parse_ir(s: &str) -> StreamIr {
         triggers: HashMap::new(),
         accessed_by: HashMap::new(),
         accesses: HashMap::new(),
+        sources: Default::default(),
+        overflow: Default::default(),
     }
 }
@@ -15,8 +15,8 @@ use crate::formatter::{
 };
 
 use super::{
-    Expr, Guard, LocalFreq, LocalFreqRef, OutputReference, Stmt, StreamIr, StreamReference, Type,
-    WindowReference,
+    Expr, Guard, LocalFreq, LocalFreqRef, OutputReference, SourceMap, SourceSpan, Stmt, StreamIr,
+    StreamReference, Type, VariableReference, WindowReference,
 };
 
 /// A [StreamIrFormatter] trait implementation to display the StreamIR in a nicely formatted string
@@ -26,6 +26,8 @@ pub struct DebugFormatter {
     sr2parameter: HashMap<StreamReference, Vec<String>>,
     window_targets: HashMap<WindowReference, StreamReference>,
     lref2lfreq: HashMap<LocalFreqRef, LocalFreq>,
+    sources: SourceMap,
+    locations: bool,
 }
 
 impl DebugFormatter {
@@ -59,8 +61,27 @@ impl DebugFormatter {
             sr2parameter,
             window_targets,
             lref2lfreq,
+            sources: ir.sources.clone(),
+            locations: false,
         }
     }
+
+    /// Appends the location in the specification to the spawn, eval and close statements (see [SourceMap])
+    pub fn with_locations(mut self, locations: bool) -> Self {
+        self.locations = locations;
+        self
+    }
+
+    /// Returns a printer for the parts of the StreamIR
+    pub(super) fn printer(&self) -> StreamIrPrinter<'_> {
+        StreamIrPrinter::new(
+            &self.sr2name,
+            &self.sr2parameter,
+            &self.window_targets,
+            &self.lref2lfreq,
+            self.locations.then_some(&self.sources),
+        )
+    }
 }
 
 impl StreamIrFormatter for DebugFormatter {
@@ -71,22 +92,17 @@ impl StreamIrFormatter for DebugFormatter {
     }
 
     fn format(self, ir: StreamIr) -> Self::Return {
-        StreamIrPrinter::new(
-            &self.sr2name,
-            &self.sr2parameter,
-            &self.window_targets,
-            &self.lref2lfreq,
-        )
-        .stmt(ir.stmt)
+        self.printer().stmt(ir.stmt)
     }
 }
 
-struct StreamIrPrinter<'a> {
+pub(super) struct StreamIrPrinter<'a> {
     whitespace_counter: usize,
     sr2name: &'a HashMap<StreamReference, String>,
     sr2parameter: &'a HashMap<StreamReference, Vec<String>>,
     window_targets: &'a HashMap<WindowReference, StreamReference>,
     lref2lfreq: &'a HashMap<LocalFreqRef, LocalFreq>,
+    sources: Option<&'a SourceMap>,
 }
 
 impl<'a> StreamIrPrinter<'a> {
@@ -95,6 +111,7 @@ impl<'a> StreamIrPrinter<'a> {
         sr2parameter: &'a HashMap<StreamReference, Vec<String>>,
         window_targets: &'a HashMap<WindowReference, StreamReference>,
         lref2lfreq: &'a HashMap<LocalFreqRef, LocalFreq>,
+        sources: Option<&'a SourceMap>,
     ) -> Self {
         Self {
             whitespace_counter: 0,
@@ -102,6 +119,7 @@ impl<'a> StreamIrPrinter<'a> {
             sr2parameter,
             window_targets,
             lref2lfreq,
+            sources,
         }
     }
 
@@ -112,6 +130,18 @@ impl<'a> StreamIrPrinter<'a> {
             sr2parameter: self.sr2parameter,
             window_targets: self.window_targets,
             lref2lfreq: self.lref2lfreq,
+            sources: self.sources,
+        }
+    }
+
+    /// Appends the location of the clause to the line, if locations are shown and the clause could be located
+    fn located(&self, line: String, sr: OutputReference, span: SourceSpan) -> String {
+        match self
+            .sources
+            .and_then(|sources| sources.locate(span, sr.sr()))
+        {
+            Some(location) => format!("{line}  // {location}"),
+            None => line,
         }
     }
 
@@ -137,6 +167,14 @@ impl<'a> StreamIrPrinter<'a> {
             parameters.into_iter().map(|p| self.expr(p)).join(",")
         )
     }
+
+    fn defaults(&self, access: String, default: Option<Expr>) -> String {
+        if let Some(default) = default {
+            format!("{access}.defaults(to: {})", self.expr(default))
+        } else {
+            access
+        }
+    }
 }
 
 impl DefaultStmtFormatter for StreamIrPrinter<'_> {
@@ -158,6 +196,7 @@ impl DefaultStmtFormatter for StreamIrPrinter<'_> {
         with: Option<Vec<Expr>>,
         _local_frequencies: Vec<LocalFreqRef>,
         _windows: Vec<WindowReference>,
+        span: SourceSpan,
     ) -> String {
         let with = with
             .map(|with| {
@@ -167,15 +206,17 @@ impl DefaultStmtFormatter for StreamIrPrinter<'_> {
                 )
             })
             .unwrap_or_default();
-        self.whitespace(&format!("spawn {} {with}", self.name(sr.sr())))
+        let line = self.whitespace(&format!("spawn {} {with}", self.name(sr.sr())));
+        self.located(line, sr, span)
     }
 
-    fn eval(&self, sr: OutputReference, with: Expr, idx: usize) -> String {
-        self.whitespace(&format!(
+    fn eval(&self, sr: OutputReference, with: Expr, idx: usize, span: SourceSpan) -> String {
+        let line = self.whitespace(&format!(
             "eval_{idx} {} with {}",
             self.name(sr.sr()),
             self.expr(with)
-        ))
+        ));
+        self.located(line, sr, span)
     }
 
     fn close(
@@ -183,8 +224,10 @@ impl DefaultStmtFormatter for StreamIrPrinter<'_> {
         sr: OutputReference,
         _local_frequencies: Vec<LocalFreqRef>,
         _windows: Vec<WindowReference>,
+        span: SourceSpan,
     ) -> String {
-        self.whitespace(&format!("close {}", self.name(sr.sr())))
+        let line = self.whitespace(&format!("close {}", self.name(sr.sr())));
+        self.located(line, sr, span)
     }
 
     fn r#if(&self, guard: Guard, cons: Stmt, alt: Option<Stmt>) -> String {
@@ -220,6 +263,13 @@ impl DefaultStmtFormatter for StreamIrPrinter<'_> {
         ))
     }
 
+    fn r#let(&self, var: VariableReference, expr: Expr, inner: Stmt) -> String {
+        let inner_formatter = self.indent();
+        let inner = inner_formatter.stmt(inner);
+        let expr = self.expr(expr);
+        self.whitespace(&format!("let var_{var} = {expr} in\n{inner}"))
+    }
+
     fn seq(&self, inner: Vec<Stmt>) -> String {
         let inner_strings = inner
             .into_iter()
@@ -358,24 +408,34 @@ impl DefaultExprFormatter for StreamIrPrinter<'_> {
         &self,
         sr: StreamReference,
         offset: u32,
-        default: Expr,
+        default: Option<Expr>,
         parameters: Vec<Expr>,
     ) -> String {
-        let sr = self.stream_access(sr, parameters);
-        let dft = self.expr(default);
-        format!("{sr}.offset(by: -{offset}).defaults(to: {dft})")
+        let c = format!(
+            "{}.offset(by: -{offset})",
+            self.stream_access(sr, parameters)
+        );
+        self.defaults(c, default)
     }
 
-    fn hold_access(&self, sr: StreamReference, default: Expr, parameters: Vec<Expr>) -> String {
-        let sr = self.stream_access(sr, parameters);
-        let dft = self.expr(default);
-        format!("{sr}.hold().defaults(to: {dft})")
+    fn hold_access(
+        &self,
+        sr: StreamReference,
+        default: Option<Expr>,
+        parameters: Vec<Expr>,
+    ) -> String {
+        let c = format!("{}.hold()", self.stream_access(sr, parameters));
+        self.defaults(c, default)
     }
 
-    fn get_access(&self, sr: StreamReference, default: Expr, parameters: Vec<Expr>) -> String {
-        let sr = self.stream_access(sr, parameters);
-        let dft = self.expr(default);
-        format!("{sr}.get().defaults(to: {dft})")
+    fn get_access(
+        &self,
+        sr: StreamReference,
+        default: Option<Expr>,
+        parameters: Vec<Expr>,
+    ) -> String {
+        let c = format!("{}.get()", self.stream_access(sr, parameters));
+        self.defaults(c, default)
     }
 
     fn is_fresh(&self, sr: StreamReference, parameters: Vec<Expr>) -> String {
@@ -386,31 +446,19 @@ impl DefaultExprFormatter for StreamIrPrinter<'_> {
     fn sliding_window_access(&self, window_idx: usize, default: Option<Expr>) -> String {
         let target_name = self.name(self.window_targets[&WindowReference::Sliding(window_idx)]);
         let c = format!("{target_name}.aggregate_sliding({window_idx})");
-        if let Some(default) = default {
-            format!("{c}.defaults(to: {})", self.expr(default))
-        } else {
-            c
-        }
+        self.defaults(c, default)
     }
 
     fn discrete_window_access(&self, window_idx: usize, default: Option<Expr>) -> String {
         let target_name = self.name(self.window_targets[&WindowReference::Discrete(window_idx)]);
         let c = format!("{target_name}.aggregate_discrete({window_idx})");
-        if let Some(default) = default {
-            format!("{c}.defaults(to: {})", self.expr(default))
-        } else {
-            c
-        }
+        self.defaults(c, default)
     }
 
     fn instance_aggregation(&self, window_idx: usize, default: Option<Expr>) -> String {
         let target_name = self.name(self.window_targets[&WindowReference::Instance(window_idx)]);
         let c = format!("{target_name}.aggregate_instances({window_idx})");
-        if let Some(default) = default {
-            format!("{c}.defaults(to: {})", self.expr(default))
-        } else {
-            c
-        }
+        self.defaults(c, default)
     }
 
     fn parameter_access(&self, sr: StreamReference, p: usize) -> String {
@@ -429,6 +477,10 @@ impl DefaultExprFormatter for StreamIrPrinter<'_> {
             self.expr(expr)
         )
     }
+
+    fn with_default(&self, expr: Expr, default: Expr) -> String {
+        format!("({}).defaults(to: {})", self.expr(expr), self.expr(default))
+    }
 }
 
 impl DefaultFunctionFormatter for StreamIrPrinter<'_> {}
@@ -497,7 +549,10 @@ impl StreamIr {
 mod tests {
     use rtlola_frontend::{parse, ParserConfig};
 
-    use crate::ir::StreamIr;
+    use crate::{
+        formatter::StreamIrFormatter,
+        ir::{DebugFormatter, StreamIr},
+    };
 
     #[test]
     fn test() {
@@ -509,4 +564,25 @@ mod tests {
         let streamir: StreamIr = mir.try_into().unwrap();
         println!("{}", streamir.display());
     }
+
+    #[test]
+    fn locations() {
+        let ir = StreamIr::from_text(
+            "input in0 a: UInt(64) = static(single);
+output out0 b: UInt(64) = static(single);
+source \"spec.lola\";
+stmt seq {
+    input in0;
+    eval_0 out0 with in0 + 1u64 at 2:10
+}",
+        )
+        .unwrap();
+        let plain = DebugFormatter::new(&ir).format(ir.clone());
+        assert!(!plain.contains("spec.lola"));
+        let located = DebugFormatter::new(&ir).with_locations(true).format(ir);
+        assert_eq!(
+            located.lines().last().unwrap(),
+            "eval_0 b with (a()+1)  // spec.lola:2:10"
+        );
+    }
 }
@@ -0,0 +1,1279 @@
+//! An interval analysis computing the range of the values of each stream.
+//!
+//! The analysis starts from the types of the input streams (or bounds given by the user) and propagates the ranges
+//! through the expressions of the spawn and eval statements until a fixed point is reached.
+//! The ranges of streams that keep growing, such as counters, are widened to the bounds of their type.
+//! Statements behind guards that can never hold do not contribute to the ranges,
+//! which reveals triggers that can never fire.
+//! Along the way, possible divisions by zero and integer overflows are reported.
+
+use std::{collections::HashMap, fmt::Display};
+
+use itertools::Itertools;
+
+use super::{
+    expressions::{Constant, Expr, ExprKind, Function, Operator},
+    windows::WindowOperation,
+    Guard, IfStmt, Origin, OutputReference, OverflowPolicy, Stmt, StreamIr, StreamReference, Type,
+    VariableReference,
+};
+
+/// The number of rounds after which growing ranges are widened to the bounds of their type
+const WIDENING_ROUNDS: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// A set of values, over-approximated by an interval
+pub enum ValueRange {
+    /// No value, e.g., of a stream that is never evaluated
+    Empty,
+    /// The integers between the bounds (inclusive)
+    Int {
+        /// The smallest value
+        min: i128,
+        /// The largest value
+        max: i128,
+    },
+    /// The floating point numbers between the bounds (inclusive), which might be infinite
+    Float {
+        /// The smallest value
+        min: f64,
+        /// The largest value
+        max: f64,
+    },
+    /// A subset of the booleans
+    Bool {
+        /// Whether the value can be false
+        can_be_false: bool,
+        /// Whether the value can be true
+        can_be_true: bool,
+    },
+    /// Any value, for types whose values are not tracked (e.g. strings or tuples)
+    Unknown,
+}
+
+impl ValueRange {
+    /// Returns the range of all values of the type, where optional types are represented by the range of their value
+    pub fn of_type(ty: &Type) -> Self {
+        match ty.inner_ty() {
+            Type::Int(bits) => {
+                let (min, max) = int_bounds(true, *bits);
+                ValueRange::Int { min, max }
+            }
+            Type::UInt(bits) => {
+                let (min, max) = int_bounds(false, *bits);
+                ValueRange::Int { min, max }
+            }
+            Type::Float32 | Type::Float64 => ValueRange::Float {
+                min: f64::NEG_INFINITY,
+                max: f64::INFINITY,
+            },
+            Type::Bool => ValueRange::Bool {
+                can_be_false: true,
+                can_be_true: true,
+            },
+            Type::String
+            | Type::Fixed(_)
+            | Type::UFixed(_)
+            | Type::Option(_)
+            | Type::Tuple(_)
+            | Type::Bytes => ValueRange::Unknown,
+        }
+    }
+
+    /// Returns the range consisting of the constant
+    pub fn constant(c: &Constant) -> Self {
+        match c {
+            Constant::UInt(v, _) => ValueRange::Int {
+                min: *v as i128,
+                max: *v as i128,
+            },
+            Constant::Int(v, _) => ValueRange::Int {
+                min: *v as i128,
+                max: *v as i128,
+            },
+            Constant::Float32(v) | Constant::Float64(v) => ValueRange::Float { min: *v, max: *v },
+            Constant::Bool(b) => ValueRange::Bool {
+                can_be_false: !b,
+                can_be_true: *b,
+            },
+            Constant::Str(_) | Constant::Tuple(_) => ValueRange::Unknown,
+        }
+    }
+
+    /// Returns the smallest range containing both ranges
+    pub fn join(self, other: Self) -> Self {
+        match (self, other) {
+            (ValueRange::Empty, r) | (r, ValueRange::Empty) => r,
+            (ValueRange::Int { min: l0, max: l1 }, ValueRange::Int { min: r0, max: r1 }) => {
+                ValueRange::Int {
+                    min: l0.min(r0),
+                    max: l1.max(r1),
+                }
+            }
+            (
+                ValueRange::Bool {
+                    can_be_false: lf,
+                    can_be_true: lt,
+                },
+                ValueRange::Bool {
+                    can_be_false: rf,
+                    can_be_true: rt,
+                },
+            ) => ValueRange::Bool {
+                can_be_false: lf || rf,
+                can_be_true: lt || rt,
+            },
+            (l, r) => match (l.float_bounds(), r.float_bounds()) {
+                (Some((l0, l1)), Some((r0, r1))) => ValueRange::Float {
+                    min: l0.min(r0),
+                    max: l1.max(r1),
+                },
+                _ => ValueRange::Unknown,
+            },
+        }
+    }
+
+    /// Returns whether the range consists of a single value
+    pub fn is_constant(&self) -> bool {
+        match self {
+            ValueRange::Int { min, max } => min == max,
+            ValueRange::Float { min, max } => min == max,
+            ValueRange::Bool {
+                can_be_false,
+                can_be_true,
+            } => can_be_false != can_be_true,
+            ValueRange::Empty | ValueRange::Unknown => false,
+        }
+    }
+
+    /// Returns whether all values of the range are values of the type
+    pub fn fits(&self, ty: &Type) -> bool {
+        match (self, ValueRange::of_type(ty)) {
+            (ValueRange::Empty, _) => true,
+            (ValueRange::Int { min, max }, ValueRange::Int { min: lo, max: hi }) => {
+                lo <= *min && *max <= hi
+            }
+            (ValueRange::Float { .. }, ValueRange::Float { .. })
+            | (ValueRange::Bool { .. }, ValueRange::Bool { .. }) => true,
+            _ => false,
+        }
+    }
+
+    fn float_bounds(&self) -> Option<(f64, f64)> {
+        match self {
+            ValueRange::Int { min, max } => Some((*min as f64, *max as f64)),
+            ValueRange::Float { min, max } => Some((*min, *max)),
+            _ => None,
+        }
+    }
+
+    fn bools(&self) -> Option<(bool, bool)> {
+        match self {
+            ValueRange::Bool {
+                can_be_false,
+                can_be_true,
+            } => Some((*can_be_false, *can_be_true)),
+            _ => None,
+        }
+    }
+
+    fn float(min: f64, max: f64) -> Self {
+        if min.is_nan() || max.is_nan() {
+            ValueRange::Float {
+                min: f64::NEG_INFINITY,
+                max: f64::INFINITY,
+            }
+        } else {
+            ValueRange::Float { min, max }
+        }
+    }
+
+    fn bool(can_be_false: bool, can_be_true: bool) -> Self {
+        ValueRange::Bool {
+            can_be_false,
+            can_be_true,
+        }
+    }
+
+    /// Widens the bounds that grew compared to the previous range to the bounds of the type
+    fn widen(self, previous: Self, ty: &Type) -> Self {
+        match (previous, self, ValueRange::of_type(ty)) {
+            (
+                ValueRange::Int { min: p0, max: p1 },
+                ValueRange::Int { min, max },
+                ValueRange::Int { min: lo, max: hi },
+            ) => ValueRange::Int {
+                min: if min < p0 { lo } else { min },
+                max: if max > p1 { hi } else { max },
+            },
+            (
+                ValueRange::Float { min: p0, max: p1 },
+                ValueRange::Float { min, max },
+                ValueRange::Float { min: lo, max: hi },
+            ) => ValueRange::Float {
+                min: if min < p0 { lo } else { min },
+                max: if max > p1 { hi } else { max },
+            },
+            (_, range, _) => range,
+        }
+    }
+}
+
+impl Display for ValueRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValueRange::Empty => write!(f, "empty"),
+            ValueRange::Int { min, max } => write!(f, "[{min}, {max}]"),
+            ValueRange::Float { min, max } => write!(f, "[{min}, {max}]"),
+            ValueRange::Bool {
+                can_be_false,
+                can_be_true,
+            } => {
+                let values = [(*can_be_false, "false"), (*can_be_true, "true")]
+                    .into_iter()
+                    .filter(|(possible, _)| *possible)
+                    .map(|(_, value)| value)
+                    .join(", ");
+                write!(f, "{{{values}}}")
+            }
+            ValueRange::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// Returns the smallest and largest integer of the given width
+pub(super) fn int_bounds(signed: bool, bits: u16) -> (i128, i128) {
+    let bits = u32::from(bits.max(1));
+    match (signed, bits) {
+        (true, 128..) => (i128::MIN, i128::MAX),
+        (true, bits) => (-(1 << (bits - 1)), (1 << (bits - 1)) - 1),
+        (false, 127..) => (0, i128::MAX),
+        (false, bits) => (0, (1 << bits) - 1),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+/// A runtime error that might happen when evaluating an expression
+pub enum HazardKind {
+    /// An integer division or remainder with a divisor that might be zero
+    DivisionByZero,
+    /// An integer operation whose result might not fit the type
+    Overflow(Type),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+/// A possible runtime error in a clause of an output stream
+pub struct Hazard {
+    /// The output stream
+    pub sr: OutputReference,
+    /// The clause containing the expression
+    pub origin: Origin,
+    /// The kind of error
+    pub kind: HazardKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// The result of the value range analysis, as computed by [StreamIr::value_ranges]
+pub struct ValueRanges {
+    /// The range of the values of each stream
+    pub streams: HashMap<StreamReference, ValueRange>,
+    /// The possible runtime errors in the clauses of the output streams, ordered by stream
+    pub hazards: Vec<Hazard>,
+    parameters: HashMap<(StreamReference, usize), ValueRange>,
+    names: HashMap<StreamReference, String>,
+    triggers: Vec<OutputReference>,
+}
+
+impl ValueRanges {
+    /// Returns the range of the values of the stream
+    pub fn range(&self, sr: StreamReference) -> ValueRange {
+        self.streams[&sr]
+    }
+
+    /// Returns the range of the values the `i`-th parameter of a stream is spawned with
+    ///
+    /// The range is empty if the stream is never spawned.
+    pub fn parameter(&self, sr: OutputReference, i: usize) -> ValueRange {
+        self.parameters
+            .get(&(sr.sr(), i))
+            .copied()
+            .unwrap_or(ValueRange::Empty)
+    }
+
+    /// Returns the output streams that always have the same value, ordered by their reference
+    pub fn constant_outputs(&self) -> Vec<OutputReference> {
+        self.streams
+            .iter()
+            .filter_map(|(sr, range)| match sr {
+                StreamReference::Out(o) if range.is_constant() => Some(*o),
+                _ => None,
+            })
+            .sorted()
+            .collect()
+    }
+
+    /// Returns the triggers that can never fire, as they are never evaluated
+    pub fn never_firing(&self) -> Vec<OutputReference> {
+        self.triggers
+            .iter()
+            .filter(|t| self.streams[&t.sr()] == ValueRange::Empty)
+            .copied()
+            .collect()
+    }
+}
+
+impl Display for ValueRanges {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "streams:")?;
+        for (sr, range) in self.streams.iter().sorted_by_key(|(sr, _)| **sr) {
+            writeln!(f, "    {}: {range}", self.names[sr])?;
+        }
+        let names = |srs: Vec<OutputReference>| srs.iter().map(|o| &self.names[&o.sr()]).join(", ");
+        let constant = names(self.constant_outputs());
+        if !constant.is_empty() {
+            writeln!(f, "constant: {constant}")?;
+        }
+        let never_firing = names(self.never_firing());
+        if !never_firing.is_empty() {
+            writeln!(f, "never firing: {never_firing}")?;
+        }
+        for Hazard { sr, origin, kind } in &self.hazards {
+            let name = &self.names[&sr.sr()];
+            match kind {
+                HazardKind::DivisionByZero => {
+                    writeln!(f, "possible division by zero in {origin} of {name}")?
+                }
+                HazardKind::Overflow(ty) => {
+                    writeln!(f, "possible overflow of {ty} in {origin} of {name}")?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl StreamIr {
+    /// Computes the range of the values of each stream, assuming that input streams receive any value of their type
+    pub fn value_ranges(&self) -> ValueRanges {
+        self.value_ranges_with(&HashMap::new())
+    }
+
+    /// Computes the range of the values of each stream, assuming that the given input streams only receive values in the given range
+    ///
+    /// The ranges of the remaining input streams are given by their type, ranges given for output streams are ignored.
+    pub fn value_ranges_with(&self, inputs: &HashMap<StreamReference, ValueRange>) -> ValueRanges {
+        let mut analysis = Analysis {
+            ir: self,
+            streams: self
+                .streams()
+                .map(|sr| {
+                    let range = match sr {
+                        StreamReference::In(_) => inputs
+                            .get(&sr)
+                            .copied()
+                            .unwrap_or_else(|| ValueRange::of_type(&self.stream_memory(sr).ty)),
+                        StreamReference::Out(_) => ValueRange::Empty,
+                    };
+                    (sr, range)
+                })
+                .collect(),
+            parameters: HashMap::new(),
+        };
+        let mut round = 0;
+        let hazards = loop {
+            let mut next = Pass {
+                streams: analysis.streams.clone(),
+                parameters: analysis.parameters.clone(),
+                hazards: Vec::new(),
+            };
+            analysis.stmt(&self.stmt, &mut HashMap::new(), &mut next);
+            round += 1;
+            if round > WIDENING_ROUNDS {
+                for (sr, range) in next.streams.iter_mut() {
+                    *range = range.widen(analysis.streams[sr], &self.stream_memory(*sr).ty);
+                }
+                for ((sr, i), range) in next.parameters.iter_mut() {
+                    let ty = &self.stream_memory(*sr).parameters().unwrap()[*i].ty;
+                    if let Some(previous) = analysis.parameters.get(&(*sr, *i)) {
+                        *range = range.widen(*previous, ty);
+                    }
+                }
+            }
+            if next.streams == analysis.streams && next.parameters == analysis.parameters {
+                break next.hazards;
+            }
+            analysis.streams = next.streams;
+            analysis.parameters = next.parameters;
+        };
+        ValueRanges {
+            streams: analysis.streams,
+            hazards: hazards.into_iter().sorted().dedup().collect(),
+            parameters: analysis.parameters,
+            names: self
+                .sr2memory
+                .iter()
+                .map(|(sr, memory)| (*sr, memory.name.clone()))
+                .collect(),
+            triggers: self.triggers.keys().sorted().copied().collect(),
+        }
+    }
+}
+
+/// The ranges computed by a single round of the analysis
+struct Pass {
+    streams: HashMap<StreamReference, ValueRange>,
+    parameters: HashMap<(StreamReference, usize), ValueRange>,
+    hazards: Vec<Hazard>,
+}
+
+impl Pass {
+    /// Reports the hazard in each of the clauses
+    fn report(&mut self, clauses: Clauses, kind: HazardKind) {
+        self.hazards
+            .extend(clauses.iter().map(|(sr, origin)| Hazard {
+                sr: *sr,
+                origin: *origin,
+                kind: kind.clone(),
+            }));
+    }
+}
+
+struct Analysis<'a> {
+    ir: &'a StreamIr,
+    /// The ranges of the streams computed by the previous round
+    streams: HashMap<StreamReference, ValueRange>,
+    /// The ranges of the parameters of the streams computed by the previous round
+    parameters: HashMap<(StreamReference, usize), ValueRange>,
+}
+
+/// The clauses an expression is evaluated for, to which hazards are attributed
+///
+/// Expressions of let statements, assign statements and dynamic guards belong to the clauses of the statements using them.
+type Clauses<'c> = &'c [(OutputReference, Origin)];
+
+impl Analysis<'_> {
+    fn stmt(&self, stmt: &Stmt, env: &mut HashMap<VariableReference, ValueRange>, next: &mut Pass) {
+        match stmt {
+            Stmt::Skip | Stmt::Shift(_) | Stmt::Input(_) | Stmt::Close { .. } => {}
+            Stmt::Seq(stmts) | Stmt::Parallel(stmts) => {
+                stmts.iter().for_each(|stmt| self.stmt(stmt, env, next))
+            }
+            Stmt::Spawn { sr, with, .. } => {
+                for (i, expr) in with.iter().flatten().enumerate() {
+                    let range = self.expr(expr, env, &[(*sr, Origin::Spawn)], next);
+                    let parameter = next
+                        .parameters
+                        .entry((sr.sr(), i))
+                        .or_insert(ValueRange::Empty);
+                    *parameter = parameter.join(range);
+                }
+            }
+            Stmt::Eval { sr, with, idx, .. } => {
+                let range = self.expr(with, env, &[(*sr, Origin::EvalWith(*idx))], next);
+                let stream = next.streams.get_mut(&sr.sr()).unwrap();
+                *stream = stream.join(range);
+            }
+            Stmt::If(IfStmt { guard, cons, alt }) => {
+                let guarded = [clauses(cons, true), clauses(alt, true)].concat();
+                let (may_fail, may_hold) = self.guard(guard, env, &guarded, next);
+                if may_hold {
+                    self.stmt(cons, env, next);
+                }
+                if may_fail {
+                    self.stmt(alt, env, next);
+                }
+            }
+            Stmt::Iterate { stmt, .. } => self.stmt(stmt, env, next),
+            Stmt::Assign {
+                parameter_expr,
+                stmt,
+                ..
+            } => {
+                let assigned = clauses(stmt, false);
+                parameter_expr.iter().for_each(|expr| {
+                    self.expr(expr, env, &assigned, next);
+                });
+                self.stmt(stmt, env, next)
+            }
+            Stmt::Let { var, expr, stmt } => {
+                let mut using = Vec::new();
+                clauses_using(stmt, &[*var], &mut using);
+                // the expression is evaluated even if no statement uses the variable
+                if using.is_empty() {
+                    using = clauses(stmt, false);
+                }
+                let range = self.expr(expr, env, &using, next);
+                let shadowed = env.insert(*var, range);
+                self.stmt(stmt, env, next);
+                match shadowed {
+                    Some(range) => env.insert(*var, range),
+                    None => env.remove(var),
+                };
+            }
+        }
+    }
+
+    /// Returns whether the guard might not hold and whether it might hold
+    fn guard(
+        &self,
+        guard: &Guard,
+        env: &HashMap<VariableReference, ValueRange>,
+        clauses: Clauses,
+        next: &mut Pass,
+    ) -> (bool, bool) {
+        match guard {
+            Guard::Constant(b) => (!b, *b),
+            Guard::Dynamic(expr) => match self.expr(expr, env, clauses, next) {
+                // the guard can not be evaluated (yet)
+                ValueRange::Empty => (false, false),
+                range => range.bools().unwrap_or((true, true)),
+            },
+            Guard::And { lhs, rhs } => {
+                let (lf, lt) = self.guard(lhs, env, clauses, next);
+                let (rf, rt) = self.guard(rhs, env, clauses, next);
+                (lf || rf, lt && rt)
+            }
+            Guard::Or { lhs, rhs } => {
+                let (lf, lt) = self.guard(lhs, env, clauses, next);
+                let (rf, rt) = self.guard(rhs, env, clauses, next);
+                (lf && rf, lt || rt)
+            }
+            Guard::Stream(_)
+            | Guard::Alive(_)
+            | Guard::GlobalFreq(_)
+            | Guard::LocalFreq(_)
+            | Guard::FastAnd(_)
+            | Guard::FastOr(_) => (true, true),
+        }
+    }
+
+    fn expr(
+        &self,
+        expr: &Expr,
+        env: &HashMap<VariableReference, ValueRange>,
+        clauses: Clauses,
+        next: &mut Pass,
+    ) -> ValueRange {
+        let mut sub = |expr: &Expr| self.expr(expr, env, clauses, next);
+        let range = match &expr.kind {
+            ExprKind::Constant(c) => ValueRange::constant(c),
+            ExprKind::BinaryOperation(op, lhs, rhs) => {
+                let (lhs, rhs) = (sub(lhs), sub(rhs));
+                return self.binary(*op, lhs, rhs, &expr.ty, clauses, next);
+            }
+            ExprKind::UnaryOperation(op, inner) => {
+                let inner = sub(inner);
+                return self.unary(*op, inner, &expr.ty, clauses, next);
+            }
+            ExprKind::Ite(cond, cons, alt) => {
+                let (cond, cons, alt) = (sub(cond), sub(cons), sub(alt));
+                match cond.bools() {
+                    _ if cond == ValueRange::Empty => ValueRange::Empty,
+                    Some((false, true)) => cons,
+                    Some((true, false)) => alt,
+                    _ => cons.join(alt),
+                }
+            }
+            ExprKind::SyncStreamAccess { target, parameters } => {
+                parameters.iter().for_each(|p| {
+                    sub(p);
+                });
+                self.streams[target]
+            }
+            ExprKind::OffsetStreamAccess {
+                target,
+                default,
+                parameters,
+                ..
+            }
+            | ExprKind::HoldStreamAccess {
+                target,
+                default,
+                parameters,
+            }
+            | ExprKind::GetAccess {
+                target,
+                default,
+                parameters,
+            } => {
+                parameters.iter().for_each(|p| {
+                    sub(p);
+                });
+                match default {
+                    Some(default) => self.streams[target].join(sub(default)),
+                    None => self.streams[target],
+                }
+            }
+            ExprKind::IsFresh { parameters, .. } => {
+                parameters.iter().for_each(|p| {
+                    sub(p);
+                });
+                ValueRange::bool(true, true)
+            }
+            ExprKind::WindowAccess {
+                window,
+                parameters,
+                default,
+                ..
+            } => {
+                parameters.iter().for_each(|p| {
+                    sub(p);
+                });
+                let range = self.window(self.ir.wref2window[window].op, window, &expr.ty);
+                match default {
+                    Some(default) => range.join(sub(default)),
+                    None => range,
+                }
+            }
+            ExprKind::Cast(ty, inner) => cast(sub(inner), ty),
+            ExprKind::ParameterAccess(sr, i) => self
+                .parameters
+                .get(&(*sr, *i))
+                .copied()
+                .unwrap_or_else(|| ValueRange::of_type(&expr.ty)),
+            ExprKind::FunctionCall(function, args) => {
+                let args = args.iter().map(sub).collect::<Vec<_>>();
+                if args.contains(&ValueRange::Empty) {
+                    ValueRange::Empty
+                } else {
+                    function_range(function, &args).unwrap_or_else(|| ValueRange::of_type(&expr.ty))
+                }
+            }
+            ExprKind::Tuple(elements) => {
+                elements.iter().for_each(|e| {
+                    sub(e);
+                });
+                ValueRange::Unknown
+            }
+            ExprKind::TupleAccess(inner, _) => {
+                sub(inner);
+                ValueRange::of_type(&expr.ty)
+            }
+            ExprKind::LambdaParameterAccess(..) => ValueRange::of_type(&expr.ty),
+            ExprKind::Variable(var) => env
+                .get(var)
+                .copied()
+                .unwrap_or_else(|| ValueRange::of_type(&expr.ty)),
+            ExprKind::Default { expr, default } => sub(expr).join(sub(default)),
+        };
+        range
+    }
+
+    fn window(
+        &self,
+        op: WindowOperation,
+        window: &super::WindowReference,
+        ty: &Type,
+    ) -> ValueRange {
+        let target = self.streams[&self.ir.wref2window[window].target];
+        match op {
+            WindowOperation::Min
+            | WindowOperation::Max
+            | WindowOperation::Last
+            | WindowOperation::Average
+            | WindowOperation::NthPercentile(_) => cast(target, ty),
+            WindowOperation::Count => match ValueRange::of_type(ty) {
+                ValueRange::Int { max, .. } => ValueRange::Int { min: 0, max },
+                range => range,
+            },
+            WindowOperation::Sum
+            | WindowOperation::Product
+            | WindowOperation::Integral
+            | WindowOperation::Conjunction
+            | WindowOperation::Disjunction
+            | WindowOperation::Variance
+            | WindowOperation::Covariance
+            | WindowOperation::StandardDeviation => ValueRange::of_type(ty),
+        }
+    }
+
+    fn unary(
+        &self,
+        op: Operator,
+        inner: ValueRange,
+        ty: &Type,
+        clauses: Clauses,
+        next: &mut Pass,
+    ) -> ValueRange {
+        match (op, inner) {
+            (_, ValueRange::Empty) => ValueRange::Empty,
+            (Operator::Not, ValueRange::Bool { .. }) => {
+                let (can_be_false, can_be_true) = inner.bools().unwrap();
+                ValueRange::bool(can_be_true, can_be_false)
+            }
+            (Operator::Neg, ValueRange::Int { min, max }) => {
+                self.checked(-max, -min, ty, clauses, next)
+            }
+            (Operator::Neg, ValueRange::Float { min, max }) => ValueRange::float(-max, -min),
+            _ => ValueRange::of_type(ty),
+        }
+    }
+
+    fn binary(
+        &self,
+        op: Operator,
+        lhs: ValueRange,
+        rhs: ValueRange,
+        ty: &Type,
+        clauses: Clauses,
+        next: &mut Pass,
+    ) -> ValueRange {
+        if lhs == ValueRange::Empty || rhs == ValueRange::Empty {
+            return ValueRange::Empty;
+        }
+        match op {
+            Operator::Eq
+            | Operator::Ne
+            | Operator::Lt
+            | Operator::Le
+            | Operator::Gt
+            | Operator::Ge => {
+                return compare(op, lhs, rhs);
+            }
+            Operator::And | Operator::Or => {
+                return match (lhs.bools(), rhs.bools()) {
+                    (Some((lf, lt)), Some((rf, rt))) if op == Operator::And => {
+                        ValueRange::bool(lf || rf, lt && rt)
+                    }
+                    (Some((lf, lt)), Some((rf, rt))) => ValueRange::bool(lf && rf, lt || rt),
+                    _ => ValueRange::of_type(ty),
+                };
+            }
+            _ => {}
+        }
+        match (lhs, rhs) {
+            (ValueRange::Int { min: l0, max: l1 }, ValueRange::Int { min: r0, max: r1 }) => {
+                if matches!(op, Operator::Div | Operator::Rem) && r0 <= 0 && 0 <= r1 {
+                    next.report(clauses, HazardKind::DivisionByZero);
+                }
+                match int_binary(op, (l0, l1), (r0, r1)) {
+                    Some(None) => ValueRange::Empty,
+                    Some(Some((min, max))) => self.checked(min, max, ty, clauses, next),
+                    None => ValueRange::of_type(ty),
+                }
+            }
+            (ValueRange::Float { .. }, ValueRange::Float { .. }) => {
+                let ((l0, l1), (r0, r1)) =
+                    (lhs.float_bounds().unwrap(), rhs.float_bounds().unwrap());
+                let corners = |f: fn(f64, f64) -> f64| {
+                    let corners = [f(l0, r0), f(l0, r1), f(l1, r0), f(l1, r1)];
+                    if corners.iter().any(|c| c.is_nan()) {
+                        ValueRange::of_type(ty)
+                    } else {
+                        ValueRange::float(
+                            corners.into_iter().fold(f64::INFINITY, f64::min),
+                            corners.into_iter().fold(f64::NEG_INFINITY, f64::max),
+                        )
+                    }
+                };
+                match op {
+                    Operator::Add => ValueRange::float(l0 + r0, l1 + r1),
+                    Operator::Sub => ValueRange::float(l0 - r1, l1 - r0),
+                    Operator::Mul => corners(|a, b| a * b),
+                    Operator::Div if r0 > 0.0 || r1 < 0.0 => corners(|a, b| a / b),
+                    _ => ValueRange::of_type(ty),
+                }
+            }
+            _ => ValueRange::of_type(ty),
+        }
+    }
+
+    /// Returns the integer range if it fits the type, or the values resulting from the overflow policy otherwise, which is reported as an overflow
+    fn checked(
+        &self,
+        min: i128,
+        max: i128,
+        ty: &Type,
+        clauses: Clauses,
+        next: &mut Pass,
+    ) -> ValueRange {
+        let range = ValueRange::Int { min, max };
+        if range.fits(ty) {
+            return range;
+        }
+        next.report(clauses, HazardKind::Overflow(ty.inner_ty().clone()));
+        let ValueRange::Int { min: lo, max: hi } = ValueRange::of_type(ty) else {
+            return ValueRange::of_type(ty);
+        };
+        match self.ir.overflow {
+            OverflowPolicy::Wrap => ValueRange::of_type(ty),
+            OverflowPolicy::Saturate => ValueRange::Int {
+                min: min.clamp(lo, hi),
+                max: max.clamp(lo, hi),
+            },
+            // the operation never results in a value
+            OverflowPolicy::Trap if min > hi || max < lo => ValueRange::Empty,
+            OverflowPolicy::Trap => ValueRange::Int {
+                min: min.max(lo),
+                max: max.min(hi),
+            },
+        }
+    }
+}
+
+/// Returns the clauses of the statements in `stmt`, where the clauses of eval statements are their filter if `filter` holds
+fn clauses(stmt: &Stmt, filter: bool) -> Vec<(OutputReference, Origin)> {
+    match stmt {
+        Stmt::Skip | Stmt::Shift(_) | Stmt::Input(_) => Vec::new(),
+        Stmt::Spawn { sr, .. } => vec![(*sr, Origin::Spawn)],
+        Stmt::Close { sr, .. } => vec![(*sr, Origin::Close)],
+        Stmt::Eval { sr, idx, .. } if filter => vec![(*sr, Origin::EvalWhen(*idx))],
+        Stmt::Eval { sr, idx, .. } => vec![(*sr, Origin::EvalWith(*idx))],
+        Stmt::Seq(stmts) | Stmt::Parallel(stmts) => stmts
+            .iter()
+            .flat_map(|stmt| clauses(stmt, filter))
+            .collect(),
+        Stmt::If(IfStmt { cons, alt, .. }) => {
+            [clauses(cons, filter), clauses(alt, filter)].concat()
+        }
+        Stmt::Iterate { stmt, .. } | Stmt::Assign { stmt, .. } | Stmt::Let { stmt, .. } => {
+            clauses(stmt, filter)
+        }
+    }
+}
+
+/// Collects the clauses of the statements in `stmt` that read one of the variables, directly or through other variables
+fn clauses_using(
+    stmt: &Stmt,
+    vars: &[VariableReference],
+    using: &mut Vec<(OutputReference, Origin)>,
+) {
+    let uses = |expr: &Expr| expr.variables().iter().any(|(var, _)| vars.contains(var));
+    match stmt {
+        Stmt::Skip | Stmt::Shift(_) | Stmt::Input(_) | Stmt::Close { .. } => {}
+        Stmt::Spawn { sr, with, .. } => {
+            if with.iter().flatten().any(uses) {
+                using.push((*sr, Origin::Spawn));
+            }
+        }
+        Stmt::Eval { sr, with, idx, .. } => {
+            if uses(with) {
+                using.push((*sr, Origin::EvalWith(*idx)));
+            }
+        }
+        Stmt::Seq(stmts) | Stmt::Parallel(stmts) => stmts
+            .iter()
+            .for_each(|stmt| clauses_using(stmt, vars, using)),
+        Stmt::If(IfStmt { guard, cons, alt }) => {
+            if guard_uses(guard, &uses) {
+                using.extend(clauses(cons, true));
+                using.extend(clauses(alt, true));
+            }
+            clauses_using(cons, vars, using);
+            clauses_using(alt, vars, using);
+        }
+        Stmt::Iterate { stmt, .. } => clauses_using(stmt, vars, using),
+        Stmt::Assign {
+            parameter_expr,
+            stmt,
+            ..
+        } => {
+            if parameter_expr.iter().any(uses) {
+                using.extend(clauses(stmt, false));
+            }
+            clauses_using(stmt, vars, using);
+        }
+        Stmt::Let { var, expr, stmt } => {
+            // the variable is read through the inner one, or shadowed by it
+            let vars = if uses(expr) {
+                vars.iter().chain([var]).copied().collect::<Vec<_>>()
+            } else {
+                vars.iter().filter(|v| *v != var).copied().collect()
+            };
+            clauses_using(stmt, &vars, using);
+        }
+    }
+}
+
+/// Returns whether one of the expressions of the dynamic guards in `guard` satisfies `uses`
+fn guard_uses(guard: &Guard, uses: &impl Fn(&Expr) -> bool) -> bool {
+    match guard {
+        Guard::Dynamic(expr) => uses(expr),
+        Guard::And { lhs, rhs } | Guard::Or { lhs, rhs } => {
+            guard_uses(lhs, uses) || guard_uses(rhs, uses)
+        }
+        _ => false,
+    }
+}
+
+/// Returns the range of an integer operation before wrapping it to the type
+///
+/// Returns None if the operation is not tracked and `Some(None)` if the operation always fails.
+#[allow(clippy::option_option)]
+fn int_binary(
+    op: Operator,
+    (l0, l1): (i128, i128),
+    (r0, r1): (i128, i128),
+) -> Option<Option<(i128, i128)>> {
+    let hull = |values: &[i128]| {
+        (
+            values.iter().copied().min().unwrap(),
+            values.iter().copied().max().unwrap(),
+        )
+    };
+    // the divisors without zero, split into the negative and positive ones
+    let divisors = [(r0, r1.min(-1)), (r0.max(1), r1)]
+        .into_iter()
+        .filter(|(lo, hi)| lo <= hi)
+        .collect::<Vec<_>>();
+    let range = match op {
+        Operator::Add => (l0.saturating_add(r0), l1.saturating_add(r1)),
+        Operator::Sub => (l0.saturating_sub(r1), l1.saturating_sub(r0)),
+        Operator::Mul => hull(&[
+            l0.saturating_mul(r0),
+            l0.saturating_mul(r1),
+            l1.saturating_mul(r0),
+            l1.saturating_mul(r1),
+        ]),
+        Operator::Div => {
+            let quotients = divisors
+                .iter()
+                .flat_map(|(lo, hi)| [l0 / lo, l0 / hi, l1 / lo, l1 / hi])
+                .collect::<Vec<_>>();
+            if quotients.is_empty() {
+                return Some(None);
+            }
+            hull(&quotients)
+        }
+        Operator::Rem => {
+            let Some(m) = divisors
+                .iter()
+                .flat_map(|(lo, hi)| [lo.saturating_abs(), hi.saturating_abs()])
+                .max()
+            else {
+                return Some(None);
+            };
+            // the remainder is smaller than the divisor and has the sign of the dividend
+            (
+                if l0 < 0 { l0.max(1 - m) } else { 0 },
+                if l1 > 0 { l1.min(m - 1) } else { 0 },
+            )
+        }
+        Operator::Pow if l0 >= 0 && r0 >= 0 => {
+            let pow = |base: i128, exp: i128| {
+                // only the powers of 0 and 1 stay small for an exponent beyond 32 bits
+                u32::try_from(exp).map_or(if base <= 1 { base } else { i128::MAX }, |exp| {
+                    base.saturating_pow(exp)
+                })
+            };
+            // zero to the power of zero is one
+            (pow(l0, r0).min(pow(l0, r1)), pow(l1, r1).max(pow(l1, r0)))
+        }
+        _ => return None,
+    };
+    Some(Some(range))
+}
+
+/// Returns the range of a comparison of two values
+fn compare(op: Operator, lhs: ValueRange, rhs: ValueRange) -> ValueRange {
+    let (Some((l0, l1)), Some((r0, r1))) = (lhs.float_bounds(), rhs.float_bounds()) else {
+        return ValueRange::bool(true, true);
+    };
+    // whether the comparison can be false and whether it can be true
+    let (can_be_false, can_be_true) = match op {
+        Operator::Lt => (l1 >= r0, l0 < r1),
+        Operator::Le => (l1 > r0, l0 <= r1),
+        Operator::Gt => (l0 <= r1, l1 > r0),
+        Operator::Ge => (l0 < r1, l1 >= r0),
+        Operator::Eq => (!(l0 == l1 && r0 == r1 && l0 == r0), l0 <= r1 && r0 <= l1),
+        Operator::Ne => (l0 <= r1 && r0 <= l1, !(l0 == l1 && r0 == r1 && l0 == r0)),
+        _ => unreachable!("not a comparison"),
+    };
+    ValueRange::bool(can_be_false, can_be_true)
+}
+
+/// Returns the range of the value converted to the type as by a cast
+fn cast(range: ValueRange, ty: &Type) -> ValueRange {
+    let target = ValueRange::of_type(ty);
+    match (range, target) {
+        (ValueRange::Empty, _) => ValueRange::Empty,
+        (ValueRange::Int { .. }, ValueRange::Int { .. }) if range.fits(ty) => range,
+        // floats are truncated and saturated
+        (ValueRange::Float { min, max }, ValueRange::Int { min: lo, max: hi }) => {
+            let saturate = |v: f64| (v.trunc() as i128).clamp(lo, hi);
+            ValueRange::Int {
+                min: saturate(min),
+                max: saturate(max),
+            }
+        }
+        (ValueRange::Int { min, max }, ValueRange::Float { .. }) => {
+            ValueRange::float(min as f64, max as f64)
+        }
+        (ValueRange::Float { .. }, ValueRange::Float { .. }) => range,
+        (_, target) => target,
+    }
+}
+
+/// Returns the range of the result of a function, or None if the function is not tracked
+fn function_range(function: &Function, args: &[ValueRange]) -> Option<ValueRange> {
+    let monotone = |f: fn(f64) -> f64| {
+        let (min, max) = args[0].float_bounds()?;
+        Some(match args[0] {
+            ValueRange::Int { .. } => ValueRange::Int {
+                min: f(min) as i128,
+                max: f(max) as i128,
+            },
+            _ => ValueRange::float(f(min), f(max)),
+        })
+    };
+    let min_max = |lhs: ValueRange, rhs: ValueRange, min: bool| -> Option<ValueRange> {
+        let pick = |a: f64, b: f64| if min { a.min(b) } else { a.max(b) };
+        match (lhs, rhs) {
+            (ValueRange::Int { min: l0, max: l1 }, ValueRange::Int { min: r0, max: r1 }) => {
+                Some(if min {
+                    ValueRange::Int {
+                        min: l0.min(r0),
+                        max: l1.min(r1),
+                    }
+                } else {
+                    ValueRange::Int {
+                        min: l0.max(r0),
+                        max: l1.max(r1),
+                    }
+                })
+            }
+            (ValueRange::Float { min: l0, max: l1 }, ValueRange::Float { min: r0, max: r1 }) => {
+                Some(ValueRange::float(pick(l0, r0), pick(l1, r1)))
+            }
+            _ => None,
+        }
+    };
+    match function {
+        Function::Abs => match args[0] {
+            ValueRange::Int { min, max } if min >= 0 => Some(ValueRange::Int { min, max }),
+            ValueRange::Int { min, max } if max <= 0 => Some(ValueRange::Int {
+                min: -max,
+                max: -min,
+            }),
+            ValueRange::Int { min, max } => Some(ValueRange::Int {
+                min: 0,
+                max: max.max(-min),
+            }),
+            ValueRange::Float { min, max } if min >= 0.0 => Some(ValueRange::float(min, max)),
+            ValueRange::Float { min, max } if max <= 0.0 => Some(ValueRange::float(-max, -min)),
+            ValueRange::Float { min, max } => Some(ValueRange::float(0.0, max.max(-min))),
+            _ => None,
+        },
+        Function::Min => min_max(args[0], args[1], true),
+        Function::Max => min_max(args[0], args[1], false),
+        // the maximum is applied first, so the result is the minimum if it exceeds the maximum
+        Function::Clamp => min_max(min_max(args[0], args[2], true)?, args[1], false),
+        Function::Sqrt => match args[0] {
+            ValueRange::Float { min, max } if min >= 0.0 => {
+                Some(ValueRange::float(min.sqrt(), max.sqrt()))
+            }
+            _ => None,
+        },
+        Function::Floor => monotone(f64::floor),
+        Function::Ceil => monotone(f64::ceil),
+        Function::Round => monotone(f64::round),
+        Function::Signum => match args[0] {
+            ValueRange::Int { min, max } => Some(ValueRange::Int {
+                min: min.signum(),
+                max: max.signum(),
+            }),
+            ValueRange::Float { min, max } => Some(ValueRange::float(
+                if min == 0.0 { 0.0 } else { min.signum() },
+                if max == 0.0 { 0.0 } else { max.signum() },
+            )),
+            _ => None,
+        },
+        Function::Exp => match args[0] {
+            ValueRange::Float { min, max } => Some(ValueRange::float(min.exp(), max.exp())),
+            _ => None,
+        },
+        Function::Arctan => match args[0] {
+            ValueRange::Float { min, max } => Some(ValueRange::float(min.atan(), max.atan())),
+            _ => None,
+        },
+        Function::Sin | Function::Cos => match args[0] {
+            ValueRange::Float { .. } => Some(ValueRange::float(-1.0, 1.0)),
+            _ => None,
+        },
+        Function::Arcsin
+        | Function::Arccos
+        | Function::Tan
+        | Function::Ln
+        | Function::Log
+        | Function::Pow
+        | Function::Atan2
+        | Function::External(..) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::ir::{
+        expressions::Function, parse::parse_text, Origin, OutputReference, OverflowPolicy,
+        StreamIr, StreamReference, Type,
+    };
+
+    use super::{function_range, Hazard, HazardKind, ValueRange};
+
+    const STREAMS: &str = "input in0 a: UInt(8) = static(single);
+input in1 b: Int(64) = static(single);
+output out0 c: UInt(64) = static(single);
+output out1 d: UInt(8) = static(single);
+output out2 e: Int(64) = static(single);
+output out3 f: Bool = static(single);
+output out4 g: String = static(single);
+trigger out4 = 0;
+";
+
+    fn out(i: usize) -> StreamReference {
+        OutputReference::Unparameterized(i).sr()
+    }
+
+    #[test]
+    fn propagation() {
+        let ir = parse_text(
+            STREAMS,
+            "seq {
+                input in0;
+                eval_0 out0 with cast<UInt(64)>(in0) * 2u64 + 1u64;
+                eval_0 out2 with ite(in1 > 0i64, 10i64, -10i64);
+                if Expr(out0 > 600u64) then
+                    eval_0 out4 with \"impossible\"
+                fi;
+                eval_0 out3 with out0 >= 1u64
+            }",
+        );
+        let ranges = ir.value_ranges();
+        assert_eq!(
+            ranges.range(StreamReference::In(0)),
+            ValueRange::Int { min: 0, max: 255 }
+        );
+        assert_eq!(ranges.range(out(0)), ValueRange::Int { min: 1, max: 511 });
+        assert_eq!(ranges.range(out(2)), ValueRange::Int { min: -10, max: 10 });
+        assert_eq!(
+            ranges.range(out(3)),
+            ValueRange::Bool {
+                can_be_false: false,
+                can_be_true: true
+            }
+        );
+        assert_eq!(ranges.range(out(4)), ValueRange::Empty);
+        assert_eq!(
+            ranges.constant_outputs(),
+            vec![OutputReference::Unparameterized(3)]
+        );
+        assert_eq!(
+            ranges.never_firing(),
+            vec![OutputReference::Unparameterized(4)]
+        );
+        assert!(ranges.hazards.is_empty(), "{:?}", ranges.hazards);
+    }
+
+    #[test]
+    fn widening() {
+        // a counter grows until the bounds of its type
+        let ir = parse_text(
+            STREAMS,
+            "seq {
+                input in0;
+                shift out1;
+                eval_0 out1 with out1.offset(1, or: 0u8) + 1u8
+            }",
+        );
+        let ranges = ir.value_ranges();
+        // wrapping on overflow, the counter can wrap around to 0
+        assert_eq!(ranges.range(out(1)), ValueRange::Int { min: 0, max: 255 });
+        let trapping = StreamIr {
+            overflow: OverflowPolicy::Trap,
+            ..ir.clone()
+        };
+        // trapping on overflow, the counter never wraps around to 0
+        assert_eq!(
+            trapping.value_ranges().range(out(1)),
+            ValueRange::Int { min: 1, max: 255 }
+        );
+        assert_eq!(
+            ranges.hazards,
+            vec![Hazard {
+                sr: OutputReference::Unparameterized(1),
+                origin: Origin::EvalWith(0),
+                kind: HazardKind::Overflow(Type::UInt(8)),
+            }]
+        );
+    }
+
+    #[test]
+    fn hazards() {
+        let ir = parse_text(
+            STREAMS,
+            "seq {
+                eval_0 out2 with 100i64 / in1;
+                eval_0 out1 with in0 - 1u8
+            }",
+        );
+        let ranges = ir.value_ranges();
+        let kinds = ranges
+            .hazards
+            .iter()
+            .map(|h| (h.sr, h.kind.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                (
+                    OutputReference::Unparameterized(1),
+                    HazardKind::Overflow(Type::UInt(8))
+                ),
+                (
+                    OutputReference::Unparameterized(2),
+                    HazardKind::DivisionByZero
+                ),
+            ]
+        );
+        // with bounds on the inputs, neither can happen
+        let bounds = HashMap::from([
+            (StreamReference::In(0), ValueRange::Int { min: 1, max: 10 }),
+            (StreamReference::In(1), ValueRange::Int { min: 5, max: 50 }),
+        ]);
+        let ranges = ir.value_ranges_with(&bounds);
+        assert!(ranges.hazards.is_empty(), "{:?}", ranges.hazards);
+        assert_eq!(ranges.range(out(1)), ValueRange::Int { min: 0, max: 9 });
+        assert_eq!(ranges.range(out(2)), ValueRange::Int { min: 2, max: 20 });
+        assert!(ranges.to_string().contains("    e: [2, 20]\n"));
+    }
+
+    #[test]
+    fn hazards_in_lets_and_guards() {
+        let ir = parse_text(
+            STREAMS,
+            "let var0 = 100i64 / in1
+            seq {
+                eval_0 out2 with var0 + 1i64;
+                if Expr(in0 - 1u8 > 3u8) then
+                    eval_0 out3 with true
+                fi
+            }",
+        );
+        let hazards = ir
+            .value_ranges()
+            .hazards
+            .into_iter()
+            .map(|h| (h.sr, h.origin))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            hazards,
+            vec![
+                (OutputReference::Unparameterized(2), Origin::EvalWith(0)),
+                (OutputReference::Unparameterized(3), Origin::EvalWhen(0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn signum() {
+        let signum = |min, max| function_range(&Function::Signum, &[ValueRange::float(min, max)]);
+        // like the integers, a zero of either sign has the signum zero
+        assert_eq!(signum(0.0, 0.0), Some(ValueRange::float(0.0, 0.0)));
+        assert_eq!(signum(-0.0, 3.0), Some(ValueRange::float(0.0, 1.0)));
+        assert_eq!(signum(-3.0, 3.0), Some(ValueRange::float(-1.0, 1.0)));
+        assert_eq!(signum(0.5, 3.0), Some(ValueRange::float(1.0, 1.0)));
+        assert_eq!(signum(-3.0, -0.5), Some(ValueRange::float(-1.0, -1.0)));
+    }
+}
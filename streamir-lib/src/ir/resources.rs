@@ -0,0 +1,403 @@
+//! A static estimate of the memory required by a monitor for the StreamIR.
+//!
+//! The estimate is computed from the buffers of the streams, the buckets of the windows and the bit widths of the types.
+//! Streams spawning multiple instances, as well as the windows of such streams, are reported with their cost per instance
+//! and count towards the total with their bound on the number of instances.
+
+use std::fmt::Display;
+
+use itertools::Itertools;
+
+use super::{
+    memory::StreamMemory,
+    windows::{WindowKind, WindowOperation},
+    StreamIr, StreamReference, Window,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// The static memory footprint of a monitor, as computed by [StreamIr::resource_report]
+pub struct ResourceReport {
+    /// The memory of each stream, ordered by the stream references
+    pub streams: Vec<StreamResources>,
+    /// The memory of each window, ordered by the window references
+    pub windows: Vec<WindowResources>,
+    /// The number of deadlines of the static schedule for global periodic streams
+    pub deadlines: usize,
+    /// The number of local frequencies, each scheduled dynamically for the instances of a stream
+    pub local_frequencies: usize,
+    /// The number of bytes required by all streams and windows, counting each instance of a parameterized stream or window
+    ///
+    /// None if one of them has an unbounded buffer, values whose size is not known statically,
+    /// or multiple instances without a known bound.
+    pub total_bytes: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// The memory of a single stream
+pub struct StreamResources {
+    /// The name of the stream
+    pub name: String,
+    /// The number of values stored for each instance, or None if the buffer is unbounded
+    pub values: Option<usize>,
+    /// The number of bytes of a single value, or None if the size is not known statically
+    pub value_bytes: Option<usize>,
+    /// The number of bytes of the buffer of each instance
+    pub bytes: Option<usize>,
+    /// Whether the stream spawns multiple instances, each requiring the memory of the buffer
+    pub per_instance: bool,
+    /// The largest number of instances alive at the same time, if the stream spawns multiple instances and the bound is known
+    pub instances: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// The memory of a single window
+pub struct WindowResources {
+    /// The reference of the window, as in the textual representation of the StreamIR
+    pub window: String,
+    /// The name of the aggregated stream
+    pub target: String,
+    /// The number of buckets of the window
+    ///
+    /// Instance aggregations are computed over the buffers of the target stream and have no buckets.
+    pub buckets: usize,
+    /// The number of bytes of a single bucket, or None if the size is not known statically
+    pub bucket_bytes: Option<usize>,
+    /// The number of bytes of all buckets of the window
+    pub bytes: Option<usize>,
+    /// Whether the caller or the target of the window spawns multiple instances, each requiring its own window
+    pub per_instance: bool,
+    /// The largest number of windows alive at the same time, if the window is required per instance and the bound is known
+    pub instances: Option<usize>,
+}
+
+impl StreamIr {
+    /// Computes the static memory footprint of a monitor for the StreamIR
+    pub fn resource_report(&self) -> ResourceReport {
+        let streams = self
+            .streams()
+            .sorted()
+            .map(|sr| self.stream_resources(sr))
+            .collect::<Vec<_>>();
+        let windows = self
+            .wref2window
+            .iter()
+            .sorted_by_key(|(wref, _)| **wref)
+            .map(|(_, window)| self.window_resources(window))
+            .collect::<Vec<_>>();
+        let total_bytes = streams
+            .iter()
+            .map(|stream| total(stream.bytes, stream.per_instance, stream.instances))
+            .chain(
+                windows
+                    .iter()
+                    .map(|window| total(window.bytes, window.per_instance, window.instances)),
+            )
+            .sum();
+        ResourceReport {
+            streams,
+            windows,
+            deadlines: self
+                .static_schedule
+                .as_ref()
+                .map_or(0, |schedule| schedule.deadlines.len()),
+            local_frequencies: self.lref2lfreq.len(),
+            total_bytes,
+        }
+    }
+
+    fn stream_resources(&self, sr: StreamReference) -> StreamResources {
+        let memory = self.stream_memory(sr);
+        let values = memory
+            .buffer
+            .buffer()
+            .map_or(Some(0), |buffer| buffer.bound());
+        let value_bytes = memory.ty.size();
+        let bytes = match (values, value_bytes) {
+            (Some(0), _) => Some(0),
+            (Some(values), Some(value_bytes)) => Some(values * value_bytes),
+            _ => None,
+        };
+        StreamResources {
+            name: memory.name.clone(),
+            values,
+            value_bytes,
+            bytes,
+            per_instance: self.is_parameterized(sr),
+            instances: memory.buffer.instance_bound(),
+        }
+    }
+
+    fn window_resources(&self, window: &Window) -> WindowResources {
+        let buckets = match &window.kind {
+            WindowKind::Sliding { bucket_count, .. } => *bucket_count,
+            WindowKind::Discrete { num_values, .. } => *num_values,
+            WindowKind::Instances { .. } => 0,
+        };
+        let bucket_bytes = bucket_size(window);
+        let bytes = if buckets == 0 {
+            Some(0)
+        } else {
+            bucket_bytes.map(|size| buckets * size)
+        };
+        // each instance of the caller, and each instance of the target whose values are stored in buckets, requires its own window
+        let parameterized = std::iter::once(window.caller)
+            .chain((buckets > 0).then_some(window.target))
+            .filter(|sr| self.is_parameterized(*sr))
+            .collect::<Vec<_>>();
+        WindowResources {
+            window: window.wref.to_string(),
+            target: self.name(window.target).into(),
+            buckets,
+            bucket_bytes,
+            bytes,
+            per_instance: !parameterized.is_empty(),
+            instances: if parameterized.is_empty() {
+                None
+            } else {
+                parameterized
+                    .iter()
+                    .map(|sr| self.stream_memory(*sr).buffer.instance_bound())
+                    .product()
+            },
+        }
+    }
+
+    fn is_parameterized(&self, sr: StreamReference) -> bool {
+        matches!(
+            self.stream_memory(sr).buffer,
+            StreamMemory::Instances { .. }
+        )
+    }
+}
+
+/// Returns the number of bytes of a single bucket of the window
+///
+/// A bucket stores the partial aggregate of its values.
+/// Averages, integrals and statistical aggregations additionally store a 64 bit counter or timestamp,
+/// while percentiles require all values of the bucket and have no static size.
+fn bucket_size(window: &Window) -> Option<usize> {
+    const COUNTER: usize = 8;
+    let value = window.ty.inner_ty().size()?;
+    match window.op {
+        WindowOperation::Sum
+        | WindowOperation::Product
+        | WindowOperation::Conjunction
+        | WindowOperation::Disjunction
+        | WindowOperation::Min
+        | WindowOperation::Max
+        | WindowOperation::Count
+        | WindowOperation::Last => Some(value),
+        WindowOperation::Average => Some(value + COUNTER),
+        // the integral so far and the last value with its timestamp
+        WindowOperation::Integral => Some(2 * value + COUNTER),
+        // the mean and the sum of squared differences
+        WindowOperation::Variance | WindowOperation::StandardDeviation => Some(2 * value + COUNTER),
+        // the means of both values and the sums of products
+        WindowOperation::Covariance => Some(4 * value + COUNTER),
+        WindowOperation::NthPercentile(_) => None,
+    }
+}
+
+/// Returns the number of bytes of all instances of a stream or window
+fn total(bytes: Option<usize>, per_instance: bool, instances: Option<usize>) -> Option<usize> {
+    match (bytes, per_instance) {
+        (Some(0), _) => Some(0),
+        (bytes, false) => bytes,
+        (bytes, true) => bytes
+            .zip(instances)
+            .map(|(bytes, instances)| bytes * instances),
+    }
+}
+
+fn bytes(bytes: Option<usize>) -> String {
+    bytes.map_or_else(|| "unknown".into(), |bytes| format!("{bytes} bytes"))
+}
+
+impl ResourceReport {
+    /// Returns the names of the streams with unbounded buffers
+    pub fn unbounded(&self) -> impl Iterator<Item = &str> + '_ {
+        self.streams
+            .iter()
+            .filter(|stream| stream.values.is_none())
+            .map(|stream| stream.name.as_str())
+    }
+
+    /// Returns the names of the streams and the references of the windows whose memory is required per instance
+    pub fn per_instance(&self) -> impl Iterator<Item = &str> + '_ {
+        self.streams
+            .iter()
+            .filter(|stream| stream.per_instance)
+            .map(|stream| stream.name.as_str())
+            .chain(
+                self.windows
+                    .iter()
+                    .filter(|window| window.per_instance)
+                    .map(|window| window.window.as_str()),
+            )
+    }
+
+    #[cfg(feature = "serde")]
+    /// Returns the report in the JSON format
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("the report can be serialized")
+    }
+}
+
+impl Display for ResourceReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "streams:")?;
+        for stream in &self.streams {
+            writeln!(
+                f,
+                "    {}: {} x {} = {}{}",
+                stream.name,
+                stream
+                    .values
+                    .map_or_else(|| "unbounded".into(), |values| values.to_string()),
+                bytes(stream.value_bytes),
+                bytes(stream.bytes),
+                match (stream.per_instance, stream.instances) {
+                    (true, Some(instances)) =>
+                        format!(" per instance, at most {instances} instances"),
+                    (true, None) => " per instance".into(),
+                    (false, _) => String::new(),
+                }
+            )?;
+        }
+        writeln!(f, "windows:")?;
+        for window in &self.windows {
+            writeln!(
+                f,
+                "    {} of {}: {} buckets x {} = {}{}",
+                window.window,
+                window.target,
+                window.buckets,
+                bytes(window.bucket_bytes),
+                bytes(window.bytes),
+                match (window.per_instance, window.instances) {
+                    (true, Some(instances)) =>
+                        format!(" per instance, at most {instances} instances"),
+                    (true, None) => " per instance".into(),
+                    (false, _) => String::new(),
+                }
+            )?;
+        }
+        writeln!(
+            f,
+            "deadlines: {} static, {} local frequencies",
+            self.deadlines, self.local_frequencies
+        )?;
+        let unbounded = self.unbounded().join(", ");
+        if !unbounded.is_empty() {
+            writeln!(f, "unbounded: {unbounded}")?;
+        }
+        let per_instance = self.per_instance().join(", ");
+        if !per_instance.is_empty() {
+            writeln!(f, "per instance: {per_instance}")?;
+        }
+        write!(f, "total: {}", bytes(self.total_bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ir::StreamIr;
+
+    const TEXT: &str = "input in0 a: UInt(32) = static(bounded(3));
+input in1 b: Float(64) = static(single);
+output out0 c: Option<Int(16)> = dynamic(single, spawn, close);
+output out1 d: Bool = none;
+output pout0 e(p: UInt(64)): (Float(32),Bool) = instances(bounded(2), max 4);
+output out2 f: String = static(unbounded);
+local 0 = 1500ms on out0;
+window sw0: Float(64) = average of in1 over sliding(1s, 10 x 100ms, nowait) in out0.eval_with(0) when @in1;
+window dw0: UInt(64) = sum of in0 over discrete(5, wait) in pout0.eval_with(0) when @in0;
+window iw0: Float(64) = sum of pout0 over instances(all) in out1.eval_with(0) when @in0;
+schedule 2s { 1500ms [eval out0]; 500ms [eval out0] };
+stmt skip";
+
+    #[test]
+    fn report() {
+        let ir = StreamIr::from_text(TEXT).unwrap_or_else(|e| panic!("{e}"));
+        let report = ir.resource_report();
+        let bytes = report
+            .streams
+            .iter()
+            .map(|s| (s.name.as_str(), s.bytes, s.per_instance))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            bytes,
+            vec![
+                ("a", Some(12), false),
+                ("b", Some(8), false),
+                ("c", Some(3), false),
+                ("d", Some(0), false),
+                ("f", None, false),
+                ("e", Some(10), true),
+            ]
+        );
+        let windows = report
+            .windows
+            .iter()
+            .map(|w| (w.window.as_str(), w.buckets, w.bytes, w.per_instance))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            windows,
+            vec![
+                ("sw0", 10, Some(160), false),
+                ("dw0", 5, Some(40), true),
+                ("iw0", 0, Some(0), false),
+            ]
+        );
+        assert_eq!(report.deadlines, 2);
+        assert_eq!(report.local_frequencies, 1);
+        assert_eq!(report.unbounded().collect::<Vec<_>>(), vec!["f"]);
+        assert_eq!(report.per_instance().collect::<Vec<_>>(), vec!["e", "dw0"]);
+        assert_eq!(report.streams[5].instances, Some(4));
+        assert_eq!(report.windows[1].instances, Some(4));
+        // the string stream has no static size
+        assert_eq!(report.total_bytes, None);
+        let text = report.to_string();
+        assert!(text.contains("    a: 3 x 4 bytes = 12 bytes\n"));
+        assert!(text.contains("    e: 2 x 5 bytes = 10 bytes per instance, at most 4 instances\n"));
+        assert!(text.contains("    f: unbounded x unknown = unknown\n"));
+        assert!(text.contains("    sw0 of b: 10 buckets x 16 bytes = 160 bytes\n"));
+        assert!(text.contains(
+            "    dw0 of a: 5 buckets x 8 bytes = 40 bytes per instance, at most 4 instances\n"
+        ));
+        assert!(text.ends_with("total: unknown"));
+    }
+
+    #[test]
+    fn total() {
+        let ir = StreamIr::from_text(
+            "input in0 a: UInt(8) = static(bounded(4));
+output out0 b: Float(32) = static(single);
+stmt skip",
+        )
+        .unwrap();
+        let report = ir.resource_report();
+        assert_eq!(report.total_bytes, Some(8));
+        assert!(report.to_string().ends_with("total: 8 bytes"));
+    }
+
+    #[test]
+    fn total_of_instances() {
+        let text = "input in0 a: UInt(16) = static(bounded(4));
+output pout0 b(p: UInt(8)): UInt(16) = instances(bounded(2), max 3);
+window dw0: UInt(16) = sum of in0 over discrete(2, wait) in pout0.eval_with(0) when @in0;
+stmt skip";
+        let report = StreamIr::from_text(text).unwrap().resource_report();
+        // 8 bytes for a, and 4 bytes for each of the at most 3 instances of b and of dw0
+        assert_eq!(report.total_bytes, Some(32));
+
+        // without a bound on the instances, the total is unknown
+        let text = text.replace(", max 3", "");
+        let report = StreamIr::from_text(&text).unwrap().resource_report();
+        assert_eq!(report.windows[0].instances, None);
+        assert_eq!(report.total_bytes, None);
+    }
+}
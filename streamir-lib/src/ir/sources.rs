@@ -0,0 +1,294 @@
+//! The locations of the streams and their clauses in the specification.
+//!
+//! The spans are taken from the syntax tree of the frontend while lowering the specification (see [crate::parse_with_sources]).
+//! Spawn, eval and close statements and windows carry the [SourceSpan] of the clause they originate from,
+//! expressions carry the span of the subexpression they are lowered from,
+//! while the [SourceMap] records the specification file and the declarations of the streams.
+
+use std::{collections::HashMap, fmt::Display, hash::Hash};
+
+use super::{Stmt, StreamReference, Window};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A position in the specification, with the line and column starting at 1
+pub struct Span {
+    /// The line of the position
+    pub line: usize,
+    /// The column of the position, counted in characters
+    pub col: usize,
+}
+
+impl Span {
+    /// Returns the position of the byte offset in the specification
+    pub(crate) fn from_offset(spec: &str, offset: usize) -> Self {
+        let before = &spec[..offset.min(spec.len())];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        Span {
+            line: before.matches('\n').count() + 1,
+            col: before[line_start..].chars().count() + 1,
+        }
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The span of a statement, window or expression in the specification, if it was lowered from one
+///
+/// Spans take no part in comparisons, such that rewriting treats the same construct from different clauses alike.
+pub struct SourceSpan(pub Option<Span>);
+
+impl SourceSpan {
+    /// The span of a construct without a location in the specification
+    pub const UNKNOWN: SourceSpan = SourceSpan(None);
+
+    /// Returns this span if it is known, and `other` otherwise
+    pub fn or(self, other: SourceSpan) -> SourceSpan {
+        SourceSpan(self.0.or(other.0))
+    }
+}
+
+impl From<Span> for SourceSpan {
+    fn from(span: Span) -> Self {
+        SourceSpan(Some(span))
+    }
+}
+
+impl PartialEq for SourceSpan {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for SourceSpan {}
+
+impl Hash for SourceSpan {
+    fn hash<H: std::hash::Hasher>(&self, _state: &mut H) {}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A position in the specification file, displayed as `file:line:col`
+pub struct Location<'a> {
+    /// The path of the specification, if it was read from a file
+    pub file: Option<&'a str>,
+    /// The position in the specification
+    pub span: Span,
+}
+
+impl Display for Location<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.file {
+            Some(file) => write!(f, "{file}:{}", self.span),
+            None => write!(f, "{}", self.span),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The specification file and the locations of the stream declarations.
+///
+/// The map is empty if the StreamIR was not parsed from a specification (see [crate::parse_with_sources]).
+pub struct SourceMap {
+    /// The path of the specification, if it was read from a file
+    pub file: Option<String>,
+    /// The location of the declaration of each stream
+    #[cfg_attr(feature = "serde", serde(with = "super::map_entries"))]
+    pub streams: HashMap<StreamReference, Span>,
+}
+
+impl SourceMap {
+    /// Returns whether no locations are known
+    pub fn is_empty(&self) -> bool {
+        self.streams.is_empty()
+    }
+
+    fn location(&self, span: Span) -> Location<'_> {
+        Location {
+            file: self.file.as_deref(),
+            span,
+        }
+    }
+
+    /// Returns the location of the declaration of the stream
+    pub fn stream(&self, sr: StreamReference) -> Option<Location<'_>> {
+        self.streams.get(&sr).map(|span| self.location(*span))
+    }
+
+    /// Returns the location of a statement, window or expression of the stream.
+    ///
+    /// Falls back to the declaration of the stream if the span is unknown.
+    pub fn locate(&self, span: SourceSpan, sr: StreamReference) -> Option<Location<'_>> {
+        span.0
+            .map(|span| self.location(span))
+            .or_else(|| self.stream(sr))
+    }
+
+    /// Returns the location of the clause or stream declaration the statement originated from
+    pub fn stmt(&self, stmt: &Stmt) -> Option<Location<'_>> {
+        match stmt {
+            Stmt::Shift(sr) => self.stream(*sr),
+            Stmt::Input(i) => self.stream(StreamReference::In(*i)),
+            Stmt::Spawn { sr, span, .. }
+            | Stmt::Eval { sr, span, .. }
+            | Stmt::Close { sr, span, .. } => self.locate(*span, sr.sr()),
+            Stmt::Skip
+            | Stmt::Seq(_)
+            | Stmt::Parallel(_)
+            | Stmt::If(_)
+            | Stmt::Iterate { .. }
+            | Stmt::Assign { .. }
+            | Stmt::Let { .. } => None,
+        }
+    }
+
+    /// Returns the location of the clause the window is accessed in
+    pub fn window(&self, window: &Window) -> Option<Location<'_>> {
+        self.locate(window.span, window.caller)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{
+        ir::{
+            expressions::{Expr, ExprKind},
+            IfStmt, Origin, OutputReference, Stmt, StreamReference,
+        },
+        parse_with_sources, ParserConfig,
+    };
+
+    use super::Span;
+
+    /// Collects the spans of the spawn, eval and close statements
+    fn spans(stmt: &Stmt, found: &mut HashMap<(OutputReference, Origin), Option<Span>>) {
+        match stmt {
+            Stmt::Spawn { sr, span, .. } => {
+                found.insert((*sr, Origin::Spawn), span.0);
+            }
+            Stmt::Eval {
+                sr,
+                with,
+                idx,
+                span,
+            } => {
+                assert_eq!(with.span.0, span.0);
+                found.insert((*sr, Origin::EvalWith(*idx)), span.0);
+            }
+            Stmt::Close { sr, span, .. } => {
+                found.insert((*sr, Origin::Close), span.0);
+            }
+            Stmt::Seq(stmts) | Stmt::Parallel(stmts) => {
+                stmts.iter().for_each(|stmt| spans(stmt, found))
+            }
+            Stmt::If(IfStmt { cons, alt, .. }) => {
+                spans(cons, found);
+                spans(alt, found);
+            }
+            Stmt::Iterate { stmt, .. } | Stmt::Assign { stmt, .. } | Stmt::Let { stmt, .. } => {
+                spans(stmt, found)
+            }
+            Stmt::Skip | Stmt::Shift(_) | Stmt::Input(_) => {}
+        }
+    }
+
+    /// Returns the expression of the first eval clause of the stream
+    fn eval_with(stmt: &Stmt, stream: OutputReference) -> Option<&Expr> {
+        match stmt {
+            Stmt::Eval { sr, with, idx, .. } if *sr == stream && *idx == 0 => Some(with),
+            Stmt::Seq(stmts) | Stmt::Parallel(stmts) => {
+                stmts.iter().find_map(|stmt| eval_with(stmt, stream))
+            }
+            Stmt::If(IfStmt { cons, alt, .. }) => {
+                eval_with(cons, stream).or_else(|| eval_with(alt, stream))
+            }
+            Stmt::Iterate { stmt, .. } | Stmt::Assign { stmt, .. } | Stmt::Let { stmt, .. } => {
+                eval_with(stmt, stream)
+            }
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn locations() {
+        let spec = "// a comment mentioning output c
+input a: UInt64, b: UInt64
+output c := a + b
+output d(p: UInt64): UInt64
+  spawn with a
+  eval when p == b with p /* eval */
+  eval with p + c
+  close when a > 10
+output e @1Hz := a.aggregate(over: 1s, using: sum)
+trigger c > 5 \"too large\"";
+        let ir = parse_with_sources(&ParserConfig::for_string(spec.into())).unwrap();
+        let a = StreamReference::In(0);
+        let b = StreamReference::In(1);
+        let c = OutputReference::Unparameterized(0);
+        let d = OutputReference::Parameterized(0);
+        let e = OutputReference::Unparameterized(1);
+        let trigger = OutputReference::Unparameterized(2);
+        let span = |line, col| Some(Span { line, col });
+        assert_eq!(ir.sources.streams.get(&a).copied(), span(2, 7));
+        assert_eq!(ir.sources.streams.get(&b).copied(), span(2, 18));
+        assert_eq!(ir.sources.streams.get(&c.sr()).copied(), span(3, 8));
+        assert_eq!(ir.sources.streams.get(&d.sr()).copied(), span(4, 8));
+        assert_eq!(ir.sources.streams.get(&trigger.sr()).copied(), span(10, 1));
+
+        let mut found = HashMap::new();
+        spans(&ir.stmt, &mut found);
+        assert_eq!(found[&(c, Origin::EvalWith(0))], span(3, 13));
+        assert_eq!(found[&(d, Origin::Spawn)], span(5, 3));
+        assert_eq!(found[&(d, Origin::EvalWith(0))], span(6, 25));
+        assert_eq!(found[&(d, Origin::EvalWith(1))], span(7, 13));
+        assert_eq!(found[&(d, Origin::Close)], span(8, 3));
+        assert_eq!(found[&(e, Origin::EvalWith(0))], span(9, 18));
+
+        let window = ir.wref2window.values().next().unwrap();
+        assert_eq!(ir.sources.window(window).unwrap().to_string(), "9:18");
+        // statements without a span of their own fall back to the declaration
+        assert_eq!(
+            ir.sources.stmt(&Stmt::Shift(c.sr())).unwrap().to_string(),
+            "3:8"
+        );
+    }
+
+    #[test]
+    fn expression_locations() {
+        let spec = "input a: UInt64, b: UInt64
+output c := (a + b) * a
+output d := if a > b then a else b";
+        let ir = parse_with_sources(&ParserConfig::for_string(spec.into())).unwrap();
+        let span = |line, col| Some(Span { line, col });
+        let operands = |expr: &Expr| match &expr.kind {
+            ExprKind::BinaryOperation(_, lhs, rhs) => (lhs.span.0, rhs.span.0),
+            kind => panic!("expected a binary operation, got {kind:?}"),
+        };
+
+        let c = eval_with(&ir.stmt, OutputReference::Unparameterized(0)).unwrap();
+        assert_eq!(c.span.0, span(2, 13));
+        assert_eq!(operands(c), (span(2, 14), span(2, 23)));
+        let ExprKind::BinaryOperation(_, sum, _) = &c.kind else {
+            unreachable!()
+        };
+        assert_eq!(operands(sum), (span(2, 14), span(2, 18)));
+
+        let d = eval_with(&ir.stmt, OutputReference::Unparameterized(1)).unwrap();
+        assert_eq!(d.span.0, span(3, 13));
+        let ExprKind::Ite(condition, consequence, alternative) = &d.kind else {
+            panic!("expected an if-then-else, got {:?}", d.kind)
+        };
+        assert_eq!(condition.span.0, span(3, 16));
+        assert_eq!(operands(condition), (span(3, 16), span(3, 20)));
+        assert_eq!(consequence.span.0, span(3, 27));
+        assert_eq!(alternative.span.0, span(3, 34));
+    }
+}
@@ -0,0 +1,447 @@
+//! A textual representation of the complete StreamIR.
+//!
+//! In contrast to the [DebugFormatter](super::DebugFormatter), the text format is lossless:
+//! [StreamIr::to_text] emits all parts of the StreamIR (statements, memory, windows, local frequencies,
+//! triggers, livetime equivalences, the static schedule, the stream accesses, the overflow policy and the source locations) and [StreamIr::from_text]
+//! reads them back into an equivalent StreamIR.
+//! This allows for writing StreamIR programs by hand or storing an optimized StreamIR next to the specification.
+//!
+//! A file consists of a list of declarations, each terminated by a `;`, followed by the statement of the program.
+//! Everything after `//` until the end of the line is a comment.
+//! Declarations can only refer to streams, windows and local frequencies that were declared before.
+//! The memory of a parameterized stream can state an upper bound on its alive instances, as in `instances(single, max 4)`.
+//! Streams whose evaluation is delayed (see [Memory::delay](super::memory::Memory::delay)) carry a `delayed(n)` suffix.
+//! The `overflow` declaration sets the [OverflowPolicy](super::OverflowPolicy) if it is not `wrap`.
+//! The `source` and `location` declarations record the specification file and the `line:col` of the stream declarations (see [SourceMap](super::SourceMap)).
+//! Spawn, eval and close statements and windows end with `at line:col` if the location of their clause is known;
+//! the expression of an eval statement shares the location of the statement.
+//! ```text
+//! input in0 a: UInt(64) = static(single);
+//! output out0 b: UInt(64) = dynamic(bounded(2), spawn, close);
+//! output pout0 c(p: UInt(64)): Bool = instances(single);
+//! output out1 d: UInt(64) = static(single) delayed(1);
+//! trigger out0 = 0;
+//! local 0 = 500ms on out0;
+//! window sw0: UInt(64) = sum of in0 over sliding(1s, 10 x 100ms, nowait) in out0.eval_with(0) when @in0 at 2:17;
+//! livetime [inputs, pout0] [out0];
+//! schedule 1s { 1s [eval out0] };
+//! accesses out0 { in0 [eval_with(0) sync, eval_with(0) offset(past 1)] };
+//! accessed_by in0 { out0 [eval_with(0) sync, eval_with(0) offset(past 1)] };
+//! overflow trap;
+//! source "spec.lola";
+//! location out0 = 2:8;
+//! stmt seq {
+//!     input in0;
+//!     if @in0 && ?out0 then
+//!         eval_0 out0 with in0.offset(1, or: 0u64) + in0.aggregate(sw0) at 2:17
+//!     fi
+//! }
+//! ```
+//!
+//! Stream references are written as `in<i>`, `out<i>` (unparameterized outputs) or `pout<i>` (parameterized outputs),
+//! window references as `sw<i>`, `dw<i>` or `iw<i>` and local frequencies by their index.
+//! Durations are integers with one of the units `s`, `ms`, `us` or `ns`.
+//!
+//! Guards are built from `@sr` (stream received a new value), `?sr` (stream is alive), `Global(dur)`, `Local(i)`,
+//! `Expr(expr)`, `FastAnd(sr, ...)`, `FastOr(sr, ...)`, `true`, `false`, `&&` and `||`.
+//! A local variable is bound by `let var<i> = e` followed by the statement in which it is visible.
+//!
+//! Expressions use the usual infix operators, the functions `sqrt`, `abs`, `sin`, `arcsin`, `cos`, `arccos`, `tan`, `arctan`, `min`, `max`,
+//! `floor`, `ceil`, `round`, `exp`, `ln`, `log`, `pow`, `signum`, `clamp`, `atan2`, external functions `extern<T> name(args)` returning a `T`,
+//! and the constructs `ite(c, a, b)`, `cast<T>(e)`, `tuple(a, b)`, `e.0`, `param(sr, i)`, `lambda(wref, i)` and `var<i>`.
+//! Stream accesses are written as `sr` or `sr(params)`, optionally followed by `.offset(n, or: e)`, `.hold(or: e)`,
+//! `.get(or: e)`, `.is_fresh()` or `.aggregate(wref)` / `.aggregate(wref, or: e)`.
+//! Without the `or:` part, an offset, hold or get access results in an optional value,
+//! which can be replaced by a default value with `e.defaults(to: d)`.
+//! Constants carry their type as a suffix (`1u64`, `-2i8`, `1.5f64`), strings are quoted and constant tuples are written as `const(1u8, true)`.
+//! The type of an expression is only written explicitly as `(e : T)` if it can not be inferred from the expression itself.
+
+use std::{collections::HashMap, str::FromStr};
+
+use thiserror::Error;
+
+use super::{
+    expressions::{Constant, ExprKind, Function, Operator},
+    StreamIr, StreamReference, Type, VariableReference, WindowReference,
+};
+
+mod parser;
+mod printer;
+
+#[derive(Debug, Clone, Error)]
+#[error("{0}")]
+/// An error that can happen when reading a StreamIR from its textual representation
+pub struct TextParseError(String);
+
+impl StreamIr {
+    /// Returns the textual representation of the StreamIR
+    ///
+    /// The result can be read back with [StreamIr::from_text].
+    pub fn to_text(&self) -> String {
+        printer::TextPrinter::new(self).print(self)
+    }
+
+    /// Reads a StreamIR from its textual representation
+    pub fn from_text(s: &str) -> Result<Self, TextParseError> {
+        parser::parse(s)
+    }
+}
+
+impl FromStr for StreamIr {
+    type Err = TextParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_text(s)
+    }
+}
+
+/// The types that are known when printing or parsing an expression.
+///
+/// The type of an expression is only part of the text if it differs from the type inferred by this context.
+#[derive(Debug, Clone, Default)]
+struct TypeContext {
+    streams: HashMap<StreamReference, Type>,
+    parameters: HashMap<StreamReference, Vec<Type>>,
+    windows: HashMap<WindowReference, Type>,
+    variables: HashMap<VariableReference, Type>,
+}
+
+impl TypeContext {
+    fn infer(&self, kind: &ExprKind) -> Option<Type> {
+        match kind {
+            ExprKind::Constant(c) => Some(Self::constant(c)),
+            ExprKind::BinaryOperation(op, lhs, _) => match op {
+                Operator::And
+                | Operator::Or
+                | Operator::Eq
+                | Operator::Lt
+                | Operator::Le
+                | Operator::Ne
+                | Operator::Ge
+                | Operator::Gt => Some(Type::Bool),
+                _ => Some(lhs.ty.clone()),
+            },
+            ExprKind::UnaryOperation(_, operand) => Some(operand.ty.clone()),
+            ExprKind::Ite(_, cons, _) => Some(cons.ty.clone()),
+            ExprKind::SyncStreamAccess { target, .. } => self.streams.get(target).cloned(),
+            ExprKind::OffsetStreamAccess {
+                default: Some(default),
+                ..
+            }
+            | ExprKind::HoldStreamAccess {
+                default: Some(default),
+                ..
+            }
+            | ExprKind::GetAccess {
+                default: Some(default),
+                ..
+            }
+            | ExprKind::Default { default, .. } => Some(default.ty.clone()),
+            ExprKind::OffsetStreamAccess {
+                target,
+                default: None,
+                ..
+            }
+            | ExprKind::HoldStreamAccess {
+                target,
+                default: None,
+                ..
+            }
+            | ExprKind::GetAccess {
+                target,
+                default: None,
+                ..
+            } => self
+                .streams
+                .get(target)
+                .map(|ty| Type::Option(Box::new(ty.inner_ty().clone()))),
+            ExprKind::IsFresh { .. } => Some(Type::Bool),
+            ExprKind::WindowAccess {
+                default: Some(default),
+                ..
+            } => Some(default.ty.clone()),
+            ExprKind::WindowAccess {
+                window,
+                default: None,
+                ..
+            } => self.windows.get(window).cloned(),
+            ExprKind::Cast(ty, _) => Some(ty.clone()),
+            ExprKind::ParameterAccess(sr, idx) => self
+                .parameters
+                .get(sr)
+                .and_then(|parameters| parameters.get(*idx))
+                .cloned(),
+            ExprKind::FunctionCall(Function::External(_, signature), _) => {
+                Some(signature.ret.clone())
+            }
+            ExprKind::FunctionCall(_, args) => args.first().map(|arg| arg.ty.clone()),
+            ExprKind::Tuple(inner) => {
+                Some(Type::Tuple(inner.iter().map(|e| e.ty.clone()).collect()))
+            }
+            ExprKind::TupleAccess(inner, idx) => match &inner.ty {
+                Type::Tuple(inner) => inner.get(*idx).cloned(),
+                _ => None,
+            },
+            ExprKind::LambdaParameterAccess(_, _) => None,
+            ExprKind::Variable(var) => self.variables.get(var).cloned(),
+        }
+    }
+
+    fn constant(c: &Constant) -> Type {
+        match c {
+            Constant::Str(_) => Type::String,
+            Constant::Bool(_) => Type::Bool,
+            Constant::UInt(_, bits) => Type::UInt(*bits),
+            Constant::Int(_, bits) => Type::Int(*bits),
+            Constant::Float32(_) => Type::Float32,
+            Constant::Float64(_) => Type::Float64,
+            Constant::Tuple(inner) => Type::Tuple(inner.iter().map(Self::constant).collect()),
+        }
+    }
+
+    /// Returns a copy of the context that does not know about any windows.
+    ///
+    /// Used for the expressions inside window declarations, as windows can be declared in any order.
+    fn without_windows(&self) -> Self {
+        Self {
+            streams: self.streams.clone(),
+            parameters: self.parameters.clone(),
+            windows: HashMap::new(),
+            variables: self.variables.clone(),
+        }
+    }
+
+    /// Returns a copy of the context in which `var` is bound to a value of type `ty`.
+    fn with_variable(&self, var: VariableReference, ty: Type) -> Self {
+        let mut ctx = self.clone();
+        ctx.variables.insert(var, ty);
+        ctx
+    }
+}
+
+/// The names of the functions in the textual representation
+const FUNCTIONS: [(&str, Function); 20] = [
+    ("sqrt", Function::Sqrt),
+    ("abs", Function::Abs),
+    ("sin", Function::Sin),
+    ("arcsin", Function::Arcsin),
+    ("cos", Function::Cos),
+    ("arccos", Function::Arccos),
+    ("tan", Function::Tan),
+    ("arctan", Function::Arctan),
+    ("min", Function::Min),
+    ("max", Function::Max),
+    ("floor", Function::Floor),
+    ("ceil", Function::Ceil),
+    ("round", Function::Round),
+    ("exp", Function::Exp),
+    ("ln", Function::Ln),
+    ("log", Function::Log),
+    ("pow", Function::Pow),
+    ("signum", Function::Signum),
+    ("clamp", Function::Clamp),
+    ("atan2", Function::Atan2),
+];
+
+/// The binary operators in the textual representation together with their binding power
+const BINARY_OPERATORS: [(&str, Operator, u8); 19] = [
+    ("||", Operator::Or, 1),
+    ("&&", Operator::And, 2),
+    ("==", Operator::Eq, 3),
+    ("!=", Operator::Ne, 3),
+    ("<=", Operator::Le, 3),
+    (">=", Operator::Ge, 3),
+    ("<<", Operator::Shl, 7),
+    (">>", Operator::Shr, 7),
+    ("<", Operator::Lt, 3),
+    (">", Operator::Gt, 3),
+    ("|", Operator::BitOr, 4),
+    ("^", Operator::BitXor, 5),
+    ("&", Operator::BitAnd, 6),
+    ("+", Operator::Add, 8),
+    ("-", Operator::Sub, 8),
+    ("**", Operator::Pow, 10),
+    ("*", Operator::Mul, 9),
+    ("/", Operator::Div, 9),
+    ("%", Operator::Rem, 9),
+];
+
+/// The unary operators in the textual representation
+const UNARY_OPERATORS: [(&str, Operator); 3] = [
+    ("!", Operator::Not),
+    ("-", Operator::Neg),
+    ("~", Operator::BitNot),
+];
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use crate::ir::{parse::parse_ir, StreamIr};
+
+    fn roundtrip(text: &str) -> StreamIr {
+        let ir = StreamIr::from_text(text).unwrap_or_else(|e| panic!("{e}"));
+        let printed = ir.to_text();
+        let reparsed = StreamIr::from_text(&printed).unwrap_or_else(|e| panic!("{e}\n{printed}"));
+        assert_eq!(printed, reparsed.to_text());
+        assert_eq!(ir.stmt, reparsed.stmt);
+        assert_eq!(ir.sr2memory, reparsed.sr2memory);
+        assert_eq!(ir.wref2window, reparsed.wref2window);
+        assert_eq!(ir.lref2lfreq, reparsed.lref2lfreq);
+        assert_eq!(ir.static_schedule, reparsed.static_schedule);
+        let outputs = ir.outputs().collect::<Vec<_>>();
+        for (a, b) in outputs
+            .iter()
+            .copied()
+            .cartesian_product(outputs.iter().copied())
+        {
+            assert_eq!(
+                ir.livetime_equivalences.is_equivalent_outputs(a, b),
+                reparsed.livetime_equivalences.is_equivalent_outputs(a, b),
+                "livetime of {a:?} and {b:?}"
+            );
+        }
+        for sr in ir.streams() {
+            assert_eq!(
+                ir.livetime_equivalences.is_static(sr),
+                reparsed.livetime_equivalences.is_static(sr)
+            );
+        }
+        assert_eq!(ir.triggers, reparsed.triggers);
+        assert_eq!(ir.accesses, reparsed.accesses);
+        assert_eq!(ir.accessed_by, reparsed.accessed_by);
+        assert_eq!(ir.sources, reparsed.sources);
+        assert_eq!(ir.overflow, reparsed.overflow);
+        ir
+    }
+
+    const CANONICAL: &str = "input in0 a: UInt(64) = static(single);
+input in1 b: Float(64) = static(bounded(3));
+output out0 c: UInt(64) = dynamic(single, spawn, close);
+output out1 \"trigger 0\": String = none delayed(1);
+output pout0 d(p: UInt(64), q: Bool): Float(64) = instances(unbounded, max 4);
+trigger out1 = 0;
+local 0 = 1500ms on out0;
+window sw0: Float(64) = sum of in1 over sliding(1s, 10 x 100ms, nowait) in out0.eval_with(0) when @in1 at 5:8;
+window dw0: UInt(64) = count of in0 over discrete(5, wait) in out0.eval_with(0) when Local(0);
+window iw0: Float(64) = nth_percentile(95) of pout0 over instances(fresh, |p: UInt(64), q: Bool| (lambda(iw0, 1) : Bool)) in out0.spawn when Global(2s);
+livetime [inputs] [out0, out1] [pout0];
+schedule 2s { 1500ms [spawn out0, eval out0]; 500ms [close out0] };
+accesses out0 { in0 [eval_with(0) sync, eval_when(0) offset(past 2)]; in1 [eval_with(0) sliding_window(sw0)] };
+accessed_by in0 { out0 [eval_with(0) sync, eval_when(0) offset(past 2)] };
+accessed_by in1 { out0 [eval_with(0) sliding_window(sw0)] };
+overflow saturate;
+source \"spec.lola\";
+location in0 = 1:7;
+location out0 = 3:8;
+stmt seq {
+    par {
+        input in0;
+        input in1
+    };
+    shift in1;
+    if @in0 && (?out0 || Expr(in0 > 3u64)) then
+        spawn out0 clocks [0] windows [sw0, dw0] at 4:3
+    else
+        shift in0
+    fi;
+    if @in0 && FastAnd(in0, in1) then
+        spawn pout0 with (in0, true)
+    fi;
+    if Local(0) then
+        eval_0 out0 with (cast<UInt(64)>(in1.aggregate(sw0) * 2.5f64) + in0.offset(2, or: 0u64)) - (3u64 * min(in0, 4u64)) at 5:8
+    fi;
+    iterate [pout0]
+        if Expr(param(pout0, 1) && !in0(param(pout0, 0)).is_fresh()) then
+            eval_1 pout0 with ite(param(pout0, 1), -1.5e-7f64, sqrt(in1.hold(or: inff64)))
+        fi;
+    assign [pout0] (in0, false)
+        close pout0;
+    eval_0 out1 with \"a \\\"quoted\\\" string\\n\";
+    let var0 = in0 * 2u64
+        eval_0 out0 with tuple(var0, var0).1;
+    eval_0 out0 with const(1u64, (true, -2i8)).0;
+    eval_0 out0 with in0.offset(1).defaults(to: in0.get().defaults(to: 1u64));
+    eval_0 out0 with clamp(pow(in0, 2u64), 1u64, cast<UInt(64)>(atan2(log(in1.hold(or: 2.5f64), 10.5f64), floor(extern<Float(64)> scale(in1.hold(or: 1.5f64), in0)))));
+    close out0 clocks [0] windows [sw0, dw0]
+}";
+
+    #[test]
+    fn canonical() {
+        let ir = roundtrip(CANONICAL);
+        assert_eq!(ir.to_text(), CANONICAL);
+        assert!(ir.livetime_equivalences.is_equivalent_outputs(
+            crate::ir::OutputReference::Unparameterized(0),
+            crate::ir::OutputReference::Unparameterized(1)
+        ));
+        assert_eq!(ir.static_schedule.unwrap().deadlines.len(), 2);
+        assert_eq!(ir.wref2window.len(), 3);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        // JSON cannot represent non-finite floats
+        let text = CANONICAL.replace("inff64", "0.5f64");
+        let ir = StreamIr::from_text(&text).unwrap();
+        let json = serde_json::to_string(&ir).unwrap();
+        let deserialized: StreamIr = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized.to_text(), text);
+        assert_eq!(serde_json::to_string(&deserialized).unwrap(), json);
+    }
+
+    #[test]
+    fn test_fixture() {
+        let ir = parse_ir(
+            "seq {
+                input 0;
+                if @0 && Expr(s0 == p0) then
+                    iterate 0
+                        eval 0 s1 || (true && p2)
+                fi;
+                assign 1 (true, s0)
+                    eval 1 false
+            }",
+        );
+        let reparsed = roundtrip(&ir.to_text());
+        assert_eq!(ir.stmt, reparsed.stmt);
+        assert_eq!(ir.sr2memory, reparsed.sr2memory);
+    }
+
+    #[test]
+    fn inferred_types() {
+        let text = "input in0 a: Int(8) = static(single);
+output out0 b: Int(8) = static(single);
+stmt eval_0 out0 with (foo(in0) : Int(8))";
+        assert!(StreamIr::from_text(text).is_err());
+        let text = "input in0 a: Int(8) = static(single);
+output out0 b: Int(8) = static(single);
+stmt eval_0 out0 with -(in0 + -1i8) * 2i8 ** 3i8 ** 1i8";
+        let ir = StreamIr::from_text(text).unwrap();
+        assert_eq!(
+            ir.to_text().lines().last().unwrap(),
+            "stmt eval_0 out0 with -(in0 + -1i8) * (2i8 ** (3i8 ** 1i8))"
+        );
+        let text = "input in0 a: Int(8) = static(single);
+output out0 b: Option<Int(8)> = static(single);
+stmt eval_0 out0 with in0.hold()";
+        let ir = StreamIr::from_text(text).unwrap();
+        assert_eq!(
+            ir.to_text().lines().last().unwrap(),
+            "stmt eval_0 out0 with in0.hold()"
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let undeclared = "input in0 a: Int(8) = static(single);
+stmt eval_0 out0 with in0";
+        let err = StreamIr::from_text(undeclared).unwrap_err();
+        assert!(err.to_string().contains("out0"), "{err}");
+        let missing_type = "input in0 a: Int(8) = static(single);
+stmt eval_0 out0 with lambda(iw0, 0)";
+        assert!(StreamIr::from_text(missing_type).is_err());
+        let no_stmt = "input in0 a: Int(8) = static(single);";
+        assert!(StreamIr::from_text(no_stmt).is_err());
+    }
+}
@@ -0,0 +1,1460 @@
+use std::{collections::HashMap, time::Duration};
+
+use disjoint::DisjointSet;
+use itertools::Itertools;
+use winnow::{
+    ascii::multispace0,
+    error::{ContextError, ErrMode, FromExternalError},
+    token::take_till,
+    ModalResult, Parser,
+};
+
+use crate::ir::{
+    expressions::{Constant, Expr, ExprKind, Function, FunctionSignature},
+    memory::{Memory, Parameter, StreamBuffer, StreamMemory},
+    windows::{InstanceSelection, Window, WindowKind, WindowOperation},
+    Accesses, Deadline, Guard, IfStmt, LivetimeEquivalences, LocalFreq, LocalFreqRef, Offset,
+    Origin, OutputReference, OverflowPolicy, SourceMap, SourceSpan, Span, StaticSchedule, Stmt,
+    StreamAccessKind, StreamIr, StreamReference, Task, Type, VariableReference, WindowReference,
+};
+
+use super::{TextParseError, TypeContext, BINARY_OPERATORS, FUNCTIONS, UNARY_OPERATORS};
+
+pub(super) fn parse(s: &str) -> Result<StreamIr, TextParseError> {
+    let mut parser = TextParser::default();
+    (|input: &mut &str| parser.program(input))
+        .parse(s)
+        .map_err(|e| TextParseError(e.to_string()))
+}
+
+fn fail<T>(msg: impl Into<String>) -> ModalResult<T> {
+    Err(ErrMode::Cut(ContextError::from_external_error(
+        &"",
+        TextParseError(msg.into()),
+    )))
+}
+
+/// Skips whitespace and comments
+fn ws(input: &mut &str) -> ModalResult<()> {
+    loop {
+        multispace0.parse_next(input)?;
+        if input.starts_with("//") {
+            take_till(0.., '\n').void().parse_next(input)?;
+        } else {
+            return Ok(());
+        }
+    }
+}
+
+/// Consumes the given token if it is the next one in the input
+fn try_punct(input: &mut &str, p: &str) -> ModalResult<bool> {
+    let checkpoint = *input;
+    ws(input)?;
+    if let Some(rest) = input.strip_prefix(p) {
+        *input = rest;
+        Ok(true)
+    } else {
+        *input = checkpoint;
+        Ok(false)
+    }
+}
+
+fn punct(input: &mut &str, p: &str) -> ModalResult<()> {
+    if try_punct(input, p)? {
+        Ok(())
+    } else {
+        fail(format!("expected `{p}`"))
+    }
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Returns whether the input starts with the `defaults` method, which is not a stream access
+fn is_defaults(mut input: &str) -> bool {
+    try_keyword(&mut input, "defaults").unwrap_or(false)
+}
+
+fn try_ident<'a>(input: &mut &'a str) -> ModalResult<Option<&'a str>> {
+    ws(input)?;
+    if !input.starts_with(is_ident_start) {
+        return Ok(None);
+    }
+    let len = input.find(|c| !is_ident_char(c)).unwrap_or(input.len());
+    let (ident, rest) = input.split_at(len);
+    *input = rest;
+    Ok(Some(ident))
+}
+
+fn ident<'a>(input: &mut &'a str, expected: &str) -> ModalResult<&'a str> {
+    match try_ident(input)? {
+        Some(ident) => Ok(ident),
+        None => fail(format!("expected {expected}")),
+    }
+}
+
+/// Consumes the given keyword if it is the next identifier in the input
+fn try_keyword(input: &mut &str, keyword: &str) -> ModalResult<bool> {
+    let checkpoint = *input;
+    if try_ident(input)? == Some(keyword) {
+        Ok(true)
+    } else {
+        *input = checkpoint;
+        Ok(false)
+    }
+}
+
+fn keyword(input: &mut &str, kw: &str) -> ModalResult<()> {
+    if try_keyword(input, kw)? {
+        Ok(())
+    } else {
+        fail(format!("expected `{kw}`"))
+    }
+}
+
+fn span(input: &mut &str) -> ModalResult<Span> {
+    let line = uint(input)?;
+    punct(input, ":")?;
+    let col = uint(input)?;
+    Ok(Span { line, col })
+}
+
+/// Parses the optional location `at line:col` of a statement or window
+fn at(input: &mut &str) -> ModalResult<SourceSpan> {
+    if try_keyword(input, "at")? {
+        span(input).map(SourceSpan::from)
+    } else {
+        Ok(SourceSpan::UNKNOWN)
+    }
+}
+
+fn uint(input: &mut &str) -> ModalResult<usize> {
+    ws(input)?;
+    let len = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    match input[..len].parse() {
+        Ok(n) => {
+            *input = &input[len..];
+            Ok(n)
+        }
+        Err(_) => fail("expected a number"),
+    }
+}
+
+/// Parses a list of elements separated by `,` and enclosed by the given delimiters
+fn list<T>(
+    input: &mut &str,
+    open: &str,
+    close: &str,
+    mut element: impl FnMut(&mut &str) -> ModalResult<T>,
+) -> ModalResult<Vec<T>> {
+    punct(input, open)?;
+    let mut res = Vec::new();
+    if try_punct(input, close)? {
+        return Ok(res);
+    }
+    loop {
+        res.push(element(input)?);
+        if !try_punct(input, ",")? {
+            punct(input, close)?;
+            return Ok(res);
+        }
+    }
+}
+
+fn name(input: &mut &str) -> ModalResult<String> {
+    ws(input)?;
+    if input.starts_with('"') {
+        string(input)
+    } else {
+        ident(input, "a name").map(String::from)
+    }
+}
+
+fn parameter(input: &mut &str) -> ModalResult<Parameter> {
+    let name = name(input)?;
+    punct(input, ":")?;
+    let ty = ty(input)?;
+    Ok(Parameter { name, ty })
+}
+
+fn string(input: &mut &str) -> ModalResult<String> {
+    punct(input, "\"")?;
+    let s = *input;
+    let mut res = String::new();
+    let mut chars = s.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => {
+                *input = &s[i + 1..];
+                return Ok(res);
+            }
+            '\\' => {
+                let escaped = match chars.next().map(|(_, c)| c) {
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some('r') => '\r',
+                    Some('0') => '\0',
+                    Some(c @ ('\\' | '"' | '\'')) => c,
+                    Some('u') => {
+                        let code = chars
+                            .by_ref()
+                            .map(|(_, c)| c)
+                            .skip_while(|c| *c == '{')
+                            .take_while(|c| *c != '}')
+                            .collect::<String>();
+                        match u32::from_str_radix(&code, 16).ok().and_then(char::from_u32) {
+                            Some(c) => c,
+                            None => return fail(format!("invalid unicode escape `{code}`")),
+                        }
+                    }
+                    _ => return fail("invalid escape sequence"),
+                };
+                res.push(escaped);
+            }
+            c => res.push(c),
+        }
+    }
+    fail("unterminated string")
+}
+
+fn duration(input: &mut &str) -> ModalResult<Duration> {
+    let value = uint(input)? as u128;
+    let factor = if try_keyword(input, "s")? {
+        1_000_000_000
+    } else if try_keyword(input, "ms")? {
+        1_000_000
+    } else if try_keyword(input, "us")? {
+        1_000
+    } else if try_keyword(input, "ns")? {
+        1
+    } else {
+        return fail("expected a duration unit (s, ms, us or ns)");
+    };
+    let nanos = value * factor;
+    Ok(Duration::new(
+        (nanos / 1_000_000_000) as u64,
+        (nanos % 1_000_000_000) as u32,
+    ))
+}
+
+/// Parses a variable reference of the form `var<i>`
+fn variable(s: &str) -> ModalResult<VariableReference> {
+    match s
+        .strip_prefix("var")
+        .filter(|i| !i.is_empty() && i.chars().all(|c| c.is_ascii_digit()))
+        .and_then(|i| i.parse().ok())
+    {
+        Some(var) => Ok(var),
+        None => fail(format!("expected a variable, found `{s}`")),
+    }
+}
+
+fn parse_sr(s: &str) -> Option<StreamReference> {
+    let idx = |s: &str| {
+        (!s.is_empty() && s.chars().all(|c| c.is_ascii_digit()))
+            .then(|| s.parse().ok())
+            .flatten()
+    };
+    if let Some(i) = s.strip_prefix("pout") {
+        idx(i).map(|i| StreamReference::Out(OutputReference::Parameterized(i)))
+    } else if let Some(i) = s.strip_prefix("out") {
+        idx(i).map(|i| StreamReference::Out(OutputReference::Unparameterized(i)))
+    } else if let Some(i) = s.strip_prefix("in") {
+        idx(i).map(StreamReference::In)
+    } else {
+        None
+    }
+}
+
+fn wref(input: &mut &str) -> ModalResult<WindowReference> {
+    let word = ident(input, "a window reference")?;
+    let idx = |s: &str| s.parse().ok();
+    let wref = if let Some(i) = word.strip_prefix("sw") {
+        idx(i).map(WindowReference::Sliding)
+    } else if let Some(i) = word.strip_prefix("dw") {
+        idx(i).map(WindowReference::Discrete)
+    } else if let Some(i) = word.strip_prefix("iw") {
+        idx(i).map(WindowReference::Instance)
+    } else {
+        None
+    };
+    match wref {
+        Some(wref) => Ok(wref),
+        None => fail(format!("`{word}` is not a window reference")),
+    }
+}
+
+fn ty(input: &mut &str) -> ModalResult<Type> {
+    ws(input)?;
+    if input.starts_with('(') {
+        return list(input, "(", ")", ty).map(Type::Tuple);
+    }
+    let word = ident(input, "a type")?;
+    let bits = |input: &mut &str| -> ModalResult<u16> {
+        punct(input, "(")?;
+        let bits = uint(input)?;
+        punct(input, ")")?;
+        Ok(bits as u16)
+    };
+    match word {
+        "Int" => bits(input).map(Type::Int),
+        "UInt" => bits(input).map(Type::UInt),
+        "Bool" => Ok(Type::Bool),
+        "String" => Ok(Type::String),
+        "Bytes" => Ok(Type::Bytes),
+        "Float" => match bits(input)? {
+            32 => Ok(Type::Float32),
+            64 => Ok(Type::Float64),
+            other => fail(format!("unsupported float width {other}")),
+        },
+        "Option" => {
+            punct(input, "<")?;
+            let inner = ty(input)?;
+            punct(input, ">")?;
+            Ok(Type::Option(Box::new(inner)))
+        }
+        other => {
+            if let Some(Ok(bits)) = other.strip_prefix("UFixed").map(str::parse) {
+                Ok(Type::UFixed(bits))
+            } else if let Some(Ok(bits)) = other.strip_prefix("Fixed").map(str::parse) {
+                Ok(Type::Fixed(bits))
+            } else {
+                fail(format!("unknown type `{other}`"))
+            }
+        }
+    }
+}
+
+fn buffer(input: &mut &str) -> ModalResult<StreamBuffer> {
+    match ident(input, "a buffer")? {
+        "single" => Ok(StreamBuffer::SingleValue),
+        "unbounded" => Ok(StreamBuffer::UnBounded),
+        "bounded" => {
+            punct(input, "(")?;
+            let bound = uint(input)?;
+            punct(input, ")")?;
+            Ok(StreamBuffer::Bounded(bound))
+        }
+        other => fail(format!("unknown buffer `{other}`")),
+    }
+}
+
+fn origin(input: &mut &str) -> ModalResult<Origin> {
+    let clause = |input: &mut &str| -> ModalResult<usize> {
+        punct(input, "(")?;
+        let clause = uint(input)?;
+        punct(input, ")")?;
+        Ok(clause)
+    };
+    match ident(input, "an origin")? {
+        "spawn" => Ok(Origin::Spawn),
+        "close" => Ok(Origin::Close),
+        "eval_when" => clause(input).map(Origin::EvalWhen),
+        "eval_with" => clause(input).map(Origin::EvalWith),
+        other => fail(format!("unknown origin `{other}`")),
+    }
+}
+
+fn access_kind(input: &mut &str) -> ModalResult<StreamAccessKind> {
+    let window = |input: &mut &str| -> ModalResult<WindowReference> {
+        punct(input, "(")?;
+        let wref = wref(input)?;
+        punct(input, ")")?;
+        Ok(wref)
+    };
+    match ident(input, "an access kind")? {
+        "sync" => Ok(StreamAccessKind::Sync),
+        "hold" => Ok(StreamAccessKind::Hold),
+        "get" => Ok(StreamAccessKind::Get),
+        "fresh" => Ok(StreamAccessKind::Fresh),
+        "discrete_window" => window(input).map(StreamAccessKind::DiscreteWindow),
+        "sliding_window" => window(input).map(StreamAccessKind::SlidingWindow),
+        "instance_aggregation" => window(input).map(StreamAccessKind::InstanceAggregation),
+        "offset" => {
+            punct(input, "(")?;
+            let offset = if try_keyword(input, "past")? {
+                Offset::Past(uint(input)? as u32)
+            } else {
+                keyword(input, "future")?;
+                Offset::Future(uint(input)? as u32)
+            };
+            punct(input, ")")?;
+            Ok(StreamAccessKind::Offset(offset))
+        }
+        other => fail(format!("unknown access kind `{other}`")),
+    }
+}
+
+/// Parses a numeric constant with its type suffix, e.g., `-12i32` or `1.5e-3f64`
+fn number(input: &mut &str) -> ModalResult<Constant> {
+    ws(input)?;
+    let s = *input;
+    let digits = |from: usize| {
+        s[from..]
+            .find(|c: char| !c.is_ascii_digit())
+            .map(|l| from + l)
+            .unwrap_or(s.len())
+    };
+    let mut end = usize::from(s.starts_with('-'));
+    if s[end..].starts_with("inf") || s[end..].starts_with("NaN") {
+        end += 3;
+    } else {
+        let start = end;
+        end = digits(end);
+        if end == start {
+            return fail("expected a number");
+        }
+        if s[end..].starts_with('.') {
+            end = digits(end + 1);
+        }
+        if s[end..].starts_with(['e', 'E']) {
+            let exp = end + 1 + usize::from(s[end + 1..].starts_with(['+', '-']));
+            end = digits(exp);
+        }
+    }
+    let value = &s[..end];
+    let suffix_end = s[end..]
+        .find(|c| !is_ident_char(c))
+        .map(|l| end + l)
+        .unwrap_or(s.len());
+    let suffix = &s[end..suffix_end];
+    *input = &s[suffix_end..];
+    let constant = match suffix.split_at_checked(1) {
+        Some(("u", bits)) => bits
+            .parse()
+            .ok()
+            .and_then(|bits| Some(Constant::UInt(value.parse().ok()?, bits))),
+        Some(("i", bits)) => bits
+            .parse()
+            .ok()
+            .and_then(|bits| Some(Constant::Int(value.parse().ok()?, bits))),
+        Some(("f", "32")) => value.parse().ok().map(Constant::Float32),
+        Some(("f", "64")) => value.parse().ok().map(Constant::Float64),
+        _ => None,
+    };
+    match constant {
+        Some(c) => Ok(c),
+        None => fail(format!("invalid constant `{value}{suffix}`")),
+    }
+}
+
+/// Parses a constant that is not a tuple
+fn constant_value(input: &mut &str) -> ModalResult<Constant> {
+    ws(input)?;
+    if input.starts_with('"') {
+        string(input).map(Constant::Str)
+    } else if try_keyword(input, "true")? {
+        Ok(Constant::Bool(true))
+    } else if try_keyword(input, "false")? {
+        Ok(Constant::Bool(false))
+    } else {
+        number(input)
+    }
+}
+
+/// Parses an element of a constant tuple
+fn constant_element(input: &mut &str) -> ModalResult<Constant> {
+    ws(input)?;
+    if input.starts_with('(') {
+        list(input, "(", ")", constant_element).map(Constant::Tuple)
+    } else {
+        constant_value(input)
+    }
+}
+
+/// An expression whose type is not known yet
+struct Typed {
+    kind: ExprKind,
+    ty: Option<Type>,
+}
+
+#[derive(Default)]
+struct TextParser {
+    ctx: TypeContext,
+    sr2memory: HashMap<StreamReference, Memory>,
+    wref2window: HashMap<WindowReference, Window>,
+    lref2lfreq: HashMap<LocalFreqRef, LocalFreq>,
+    livetime_equivalences: Option<LivetimeEquivalences>,
+    static_schedule: Option<StaticSchedule>,
+    triggers: HashMap<OutputReference, usize>,
+    accesses: HashMap<StreamReference, Accesses>,
+    accessed_by: HashMap<StreamReference, Accesses>,
+    sources: SourceMap,
+    overflow: OverflowPolicy,
+    /// Whether a window declaration is parsed
+    in_window: bool,
+}
+
+impl TextParser {
+    fn program(&mut self, input: &mut &str) -> ModalResult<StreamIr> {
+        loop {
+            match ident(input, "a declaration or `stmt`")? {
+                kw @ ("input" | "output") => self.stream(input, kw)?,
+                "trigger" => self.trigger(input)?,
+                "local" => self.local_freq(input)?,
+                "window" => self.window(input)?,
+                "livetime" => self.livetime(input)?,
+                "schedule" => self.schedule(input)?,
+                "accesses" => {
+                    let (sr, accesses) = self.accesses(input)?;
+                    self.accesses.insert(sr, accesses);
+                }
+                "accessed_by" => {
+                    let (sr, accessed_by) = self.accesses(input)?;
+                    self.accessed_by.insert(sr, accessed_by);
+                }
+                "overflow" => {
+                    let policy = ident(input, "an overflow policy")?;
+                    self.overflow = match policy.parse() {
+                        Ok(policy) => policy,
+                        Err(e) => return fail(e.to_string()),
+                    };
+                }
+                "source" => self.sources.file = Some(string(input)?),
+                "location" => self.location(input)?,
+                "stmt" => {
+                    let stmt = self.stmt(input)?;
+                    try_punct(input, ";")?;
+                    ws(input)?;
+                    return self.finish(stmt);
+                }
+                other => return fail(format!("unknown declaration `{other}`")),
+            }
+            punct(input, ";")?;
+        }
+    }
+
+    fn finish(&mut self, stmt: Stmt) -> ModalResult<StreamIr> {
+        let outputs = self
+            .sr2memory
+            .keys()
+            .filter_map(|sr| match sr {
+                StreamReference::In(_) => None,
+                StreamReference::Out(o) => Some(*o),
+            })
+            .sorted()
+            .collect::<Vec<_>>();
+        let livetime_equivalences = match self.livetime_equivalences.take() {
+            Some(eq) => {
+                if let Some(o) = outputs.iter().find(|o| !eq.idx.contains_key(o)) {
+                    return fail(format!(
+                        "livetime declaration is missing {}",
+                        super::printer::TextPrinter::sr(o.sr())
+                    ));
+                }
+                eq
+            }
+            None => {
+                // Without a declaration, only the outputs that are never spawned or closed live as long as the inputs.
+                let input_idx = outputs.len();
+                let mut sets = DisjointSet::with_len(input_idx + 1);
+                for (i, o) in outputs.iter().enumerate() {
+                    if matches!(
+                        self.sr2memory[&o.sr()].buffer,
+                        StreamMemory::NoMemory | StreamMemory::Static(_)
+                    ) {
+                        sets.join(i, input_idx);
+                    }
+                }
+                LivetimeEquivalences {
+                    idx: outputs.iter().enumerate().map(|(i, o)| (*o, i)).collect(),
+                    sets,
+                    input_idx,
+                }
+            }
+        };
+        Ok(StreamIr {
+            stmt,
+            sr2memory: std::mem::take(&mut self.sr2memory),
+            wref2window: std::mem::take(&mut self.wref2window),
+            lref2lfreq: std::mem::take(&mut self.lref2lfreq),
+            livetime_equivalences,
+            static_schedule: self.static_schedule.take(),
+            triggers: std::mem::take(&mut self.triggers),
+            accesses: std::mem::take(&mut self.accesses),
+            accessed_by: std::mem::take(&mut self.accessed_by),
+            sources: std::mem::take(&mut self.sources),
+            overflow: self.overflow,
+        })
+    }
+
+    fn stream(&mut self, input: &mut &str, kw: &str) -> ModalResult<()> {
+        let word = ident(input, "a stream reference")?;
+        let sr = match (parse_sr(word), kw) {
+            (Some(sr @ StreamReference::In(_)), "input")
+            | (Some(sr @ StreamReference::Out(_)), "output") => sr,
+            _ => return fail(format!("`{word}` is not an {kw} stream reference")),
+        };
+        if self.sr2memory.contains_key(&sr) {
+            return fail(format!("stream {word} is declared twice"));
+        }
+        let name = name(input)?;
+        ws(input)?;
+        let parameters = if input.starts_with('(') {
+            Some(list(input, "(", ")", parameter)?)
+        } else {
+            None
+        };
+        punct(input, ":")?;
+        let ty = ty(input)?;
+        punct(input, "=")?;
+        let buffer = match ident(input, "a memory kind")? {
+            "none" => StreamMemory::NoMemory,
+            "static" => {
+                punct(input, "(")?;
+                let buffer = buffer(input)?;
+                punct(input, ")")?;
+                StreamMemory::Static(buffer)
+            }
+            "dynamic" => {
+                punct(input, "(")?;
+                let buffer = buffer(input)?;
+                let (mut has_spawn, mut has_close) = (false, false);
+                while try_punct(input, ",")? {
+                    match ident(input, "`spawn` or `close`")? {
+                        "spawn" => has_spawn = true,
+                        "close" => has_close = true,
+                        other => {
+                            return fail(format!("expected `spawn` or `close`, found `{other}`"))
+                        }
+                    }
+                }
+                punct(input, ")")?;
+                StreamMemory::Dynamic {
+                    buffer,
+                    has_spawn,
+                    has_close,
+                }
+            }
+            "instances" => {
+                punct(input, "(")?;
+                let buffer = buffer(input)?;
+                let bound = if try_punct(input, ",")? {
+                    keyword(input, "max")?;
+                    Some(uint(input)?)
+                } else {
+                    None
+                };
+                punct(input, ")")?;
+                StreamMemory::Instances {
+                    buffer,
+                    parameter: parameters.clone().unwrap_or_default(),
+                    bound,
+                }
+            }
+            other => return fail(format!("unknown memory kind `{other}`")),
+        };
+        let delay = if try_keyword(input, "delayed")? {
+            punct(input, "(")?;
+            let delay = uint(input)? as u32;
+            punct(input, ")")?;
+            delay
+        } else {
+            0
+        };
+        match (&buffer, parameters) {
+            (StreamMemory::Instances { parameter, .. }, _) => {
+                self.ctx
+                    .parameters
+                    .insert(sr, parameter.iter().map(|p| p.ty.clone()).collect());
+            }
+            (_, Some(_)) => return fail(format!("stream {word} has parameters but no instances")),
+            (_, None) => {}
+        }
+        self.ctx.streams.insert(sr, ty.clone());
+        self.sr2memory.insert(
+            sr,
+            Memory {
+                buffer,
+                ty,
+                name,
+                delay,
+            },
+        );
+        Ok(())
+    }
+
+    fn declared_sr(&self, input: &mut &str) -> ModalResult<StreamReference> {
+        let word = ident(input, "a stream reference")?;
+        match parse_sr(word) {
+            Some(sr) if self.sr2memory.contains_key(&sr) => Ok(sr),
+            Some(_) => fail(format!("stream {word} is not declared")),
+            None => fail(format!("`{word}` is not a stream reference")),
+        }
+    }
+
+    fn declared_output(&self, input: &mut &str) -> ModalResult<OutputReference> {
+        match self.declared_sr(input)? {
+            StreamReference::Out(o) => Ok(o),
+            StreamReference::In(_) => fail("expected an output stream"),
+        }
+    }
+
+    fn trigger(&mut self, input: &mut &str) -> ModalResult<()> {
+        let sr = self.declared_output(input)?;
+        punct(input, "=")?;
+        let trigger = uint(input)?;
+        self.triggers.insert(sr, trigger);
+        Ok(())
+    }
+
+    fn location(&mut self, input: &mut &str) -> ModalResult<()> {
+        let sr = self.declared_sr(input)?;
+        punct(input, "=")?;
+        let span = span(input)?;
+        self.sources.streams.insert(sr, span);
+        Ok(())
+    }
+
+    fn local_freq(&mut self, input: &mut &str) -> ModalResult<()> {
+        let reference = uint(input)?;
+        punct(input, "=")?;
+        let dur = duration(input)?;
+        keyword(input, "on")?;
+        let sr = self.declared_output(input)?;
+        self.lref2lfreq
+            .insert(reference, LocalFreq { dur, sr, reference });
+        Ok(())
+    }
+
+    fn window(&mut self, input: &mut &str) -> ModalResult<()> {
+        let wref = wref(input)?;
+        if self.wref2window.contains_key(&wref) {
+            return fail("window is declared twice");
+        }
+        punct(input, ":")?;
+        let ty = ty(input)?;
+        punct(input, "=")?;
+        let op = match ident(input, "a window operation")? {
+            "sum" => WindowOperation::Sum,
+            "average" => WindowOperation::Average,
+            "conjunction" => WindowOperation::Conjunction,
+            "disjunction" => WindowOperation::Disjunction,
+            "min" => WindowOperation::Min,
+            "max" => WindowOperation::Max,
+            "integral" => WindowOperation::Integral,
+            "count" => WindowOperation::Count,
+            "product" => WindowOperation::Product,
+            "last" => WindowOperation::Last,
+            "variance" => WindowOperation::Variance,
+            "covariance" => WindowOperation::Covariance,
+            "standard_deviation" => WindowOperation::StandardDeviation,
+            "nth_percentile" => {
+                punct(input, "(")?;
+                let p = uint(input)?;
+                punct(input, ")")?;
+                WindowOperation::NthPercentile(p as u8)
+            }
+            other => return fail(format!("unknown window operation `{other}`")),
+        };
+        keyword(input, "of")?;
+        let target = self.declared_sr(input)?;
+        keyword(input, "over")?;
+        let windows = std::mem::take(&mut self.ctx.windows);
+        self.in_window = true;
+        let res = self.window_rest(input, wref, op, target, ty);
+        self.in_window = false;
+        self.ctx.windows = windows;
+        let window = res?;
+        self.ctx.windows.insert(wref, window.ty.clone());
+        self.wref2window.insert(wref, window);
+        Ok(())
+    }
+
+    fn window_rest(
+        &self,
+        input: &mut &str,
+        wref: WindowReference,
+        op: WindowOperation,
+        target: StreamReference,
+        ty: Type,
+    ) -> ModalResult<Window> {
+        let wait = |input: &mut &str| -> ModalResult<bool> {
+            match ident(input, "`wait` or `nowait`")? {
+                "wait" => Ok(true),
+                "nowait" => Ok(false),
+                other => fail(format!("expected `wait` or `nowait`, found `{other}`")),
+            }
+        };
+        let kind = match ident(input, "a window kind")? {
+            "sliding" => {
+                punct(input, "(")?;
+                let duration = duration(input)?;
+                punct(input, ",")?;
+                let bucket_count = uint(input)?;
+                keyword(input, "x")?;
+                let bucket_duration = self::duration(input)?;
+                punct(input, ",")?;
+                let wait = wait(input)?;
+                punct(input, ")")?;
+                WindowKind::Sliding {
+                    duration,
+                    bucket_count,
+                    bucket_duration,
+                    wait,
+                }
+            }
+            "discrete" => {
+                punct(input, "(")?;
+                let num_values = uint(input)?;
+                punct(input, ",")?;
+                let wait = wait(input)?;
+                punct(input, ")")?;
+                WindowKind::Discrete { num_values, wait }
+            }
+            "instances" => {
+                punct(input, "(")?;
+                let fresh = match ident(input, "`all` or `fresh`")? {
+                    "all" => false,
+                    "fresh" => true,
+                    other => return fail(format!("expected `all` or `fresh`, found `{other}`")),
+                };
+                let selection = if try_punct(input, ",")? {
+                    let parameters = list(input, "|", "|", parameter)?;
+                    let cond = self.expr(input)?;
+                    if fresh {
+                        InstanceSelection::FilteredFresh { parameters, cond }
+                    } else {
+                        InstanceSelection::FilteredAll { parameters, cond }
+                    }
+                } else if fresh {
+                    InstanceSelection::Fresh
+                } else {
+                    InstanceSelection::All
+                };
+                punct(input, ")")?;
+                WindowKind::Instances { selection }
+            }
+            other => return fail(format!("unknown window kind `{other}`")),
+        };
+        keyword(input, "in")?;
+        let caller = self.declared_sr(input)?;
+        punct(input, ".")?;
+        let origin = origin(input)?;
+        keyword(input, "when")?;
+        let origin_pacing = self.guard(input)?;
+        let span = at(input)?;
+        Ok(Window {
+            wref,
+            op,
+            target,
+            caller,
+            origin,
+            origin_pacing,
+            kind,
+            ty,
+            span,
+        })
+    }
+
+    fn livetime(&mut self, input: &mut &str) -> ModalResult<()> {
+        let mut classes = Vec::new();
+        ws(input)?;
+        while input.starts_with('[') {
+            classes.push(list(input, "[", "]", |input| {
+                if try_keyword(input, "inputs")? {
+                    Ok(None)
+                } else {
+                    self.declared_output(input).map(Some)
+                }
+            })?);
+            ws(input)?;
+        }
+        let outputs = classes
+            .iter()
+            .flatten()
+            .flatten()
+            .copied()
+            .collect::<Vec<_>>();
+        if !outputs.iter().all_unique() {
+            return fail("an output is part of multiple livetime classes");
+        }
+        let idx: HashMap<_, _> = outputs.iter().enumerate().map(|(i, o)| (*o, i)).collect();
+        let input_idx = outputs.len();
+        let mut sets = DisjointSet::with_len(input_idx + 1);
+        for class in classes {
+            let class = class
+                .into_iter()
+                .map(|o| o.map(|o| idx[&o]).unwrap_or(input_idx))
+                .collect::<Vec<_>>();
+            for other in class.iter().skip(1) {
+                sets.join(class[0], *other);
+            }
+        }
+        self.livetime_equivalences = Some(LivetimeEquivalences {
+            idx,
+            sets,
+            input_idx,
+        });
+        Ok(())
+    }
+
+    fn schedule(&mut self, input: &mut &str) -> ModalResult<()> {
+        let hyper_period = duration(input)?;
+        punct(input, "{")?;
+        let mut deadlines = Vec::new();
+        while !try_punct(input, "}")? {
+            let pause = duration(input)?;
+            let due = list(input, "[", "]", |input| {
+                let task = ident(input, "a task")?;
+                let sr = self.declared_output(input)?;
+                match task {
+                    "spawn" => Ok(Task::Spawn(sr)),
+                    "eval" => Ok(Task::Eval(sr)),
+                    "close" => Ok(Task::Close(sr)),
+                    other => fail(format!("unknown task `{other}`")),
+                }
+            })?;
+            deadlines.push(Deadline { pause, due });
+            if !try_punct(input, ";")? {
+                punct(input, "}")?;
+                break;
+            }
+        }
+        self.static_schedule = Some(StaticSchedule {
+            hyper_period,
+            deadlines,
+        });
+        Ok(())
+    }
+
+    fn accesses(&self, input: &mut &str) -> ModalResult<(StreamReference, Accesses)> {
+        let sr = self.declared_sr(input)?;
+        punct(input, "{")?;
+        let mut accesses = Vec::new();
+        while !try_punct(input, "}")? {
+            let target = self.declared_sr(input)?;
+            let kinds = list(input, "[", "]", |input| {
+                let origin = origin(input)?;
+                let kind = access_kind(input)?;
+                Ok((origin, kind))
+            })?;
+            accesses.push((target, kinds));
+            if !try_punct(input, ";")? {
+                punct(input, "}")?;
+                break;
+            }
+        }
+        Ok((sr, accesses))
+    }
+
+    fn stmt(&mut self, input: &mut &str) -> ModalResult<Stmt> {
+        let stmt = match ident(input, "a statement")? {
+            "skip" => Stmt::Skip,
+            "seq" => Stmt::Seq(self.block(input)?),
+            "par" => Stmt::Parallel(self.block(input)?),
+            "shift" => Stmt::Shift(self.declared_sr(input)?),
+            "input" => match self.declared_sr(input)? {
+                StreamReference::In(i) => Stmt::Input(i),
+                StreamReference::Out(_) => return fail("expected an input stream"),
+            },
+            "spawn" => {
+                let sr = self.declared_output(input)?;
+                let with = if try_keyword(input, "with")? {
+                    Some(list(input, "(", ")", |input| self.expr(input))?)
+                } else {
+                    None
+                };
+                let (local_frequencies, windows) = self.clocks_and_windows(input)?;
+                Stmt::Spawn {
+                    sr,
+                    with,
+                    local_frequencies,
+                    windows,
+                    span: at(input)?,
+                }
+            }
+            "close" => {
+                let sr = self.declared_output(input)?;
+                let (local_frequencies, windows) = self.clocks_and_windows(input)?;
+                Stmt::Close {
+                    sr,
+                    local_frequencies,
+                    windows,
+                    span: at(input)?,
+                }
+            }
+            "if" => {
+                let guard = self.guard(input)?;
+                keyword(input, "then")?;
+                let cons = self.stmt(input)?;
+                let alt = if try_keyword(input, "else")? {
+                    self.stmt(input)?
+                } else {
+                    Stmt::Skip
+                };
+                keyword(input, "fi")?;
+                Stmt::If(IfStmt {
+                    guard,
+                    cons: Box::new(cons),
+                    alt: Box::new(alt),
+                })
+            }
+            "iterate" => {
+                let sr = list(input, "[", "]", |input| self.declared_output(input))?;
+                let stmt = self.stmt(input)?;
+                Stmt::Iterate {
+                    sr,
+                    stmt: Box::new(stmt),
+                }
+            }
+            "assign" => {
+                let sr = list(input, "[", "]", |input| self.declared_output(input))?;
+                let parameter_expr = list(input, "(", ")", |input| self.expr(input))?;
+                let stmt = self.stmt(input)?;
+                Stmt::Assign {
+                    parameter_expr,
+                    sr,
+                    stmt: Box::new(stmt),
+                }
+            }
+            "let" => {
+                let var = variable(ident(input, "a variable")?)?;
+                punct(input, "=")?;
+                let expr = self.expr(input)?;
+                let shadowed = self.ctx.variables.insert(var, expr.ty.clone());
+                let stmt = self.stmt(input);
+                match shadowed {
+                    Some(ty) => self.ctx.variables.insert(var, ty),
+                    None => self.ctx.variables.remove(&var),
+                };
+                Stmt::Let {
+                    var,
+                    expr,
+                    stmt: Box::new(stmt?),
+                }
+            }
+            other => match other.strip_prefix("eval_").map(str::parse) {
+                Some(Ok(idx)) => {
+                    let sr = self.declared_output(input)?;
+                    keyword(input, "with")?;
+                    let with = self.expr(input)?;
+                    // the expression of an eval clause is located at its clause
+                    let span = at(input)?;
+                    Stmt::Eval {
+                        sr,
+                        with: Expr { span, ..with },
+                        idx,
+                        span,
+                    }
+                }
+                _ => return fail(format!("unknown statement `{other}`")),
+            },
+        };
+        Ok(stmt)
+    }
+
+    fn block(&mut self, input: &mut &str) -> ModalResult<Vec<Stmt>> {
+        punct(input, "{")?;
+        let mut stmts = Vec::new();
+        while !try_punct(input, "}")? {
+            stmts.push(self.stmt(input)?);
+            if !try_punct(input, ";")? {
+                punct(input, "}")?;
+                break;
+            }
+        }
+        Ok(stmts)
+    }
+
+    fn clocks_and_windows(
+        &self,
+        input: &mut &str,
+    ) -> ModalResult<(Vec<LocalFreqRef>, Vec<WindowReference>)> {
+        let clocks = if try_keyword(input, "clocks")? {
+            list(input, "[", "]", |input| self.declared_local_freq(input))?
+        } else {
+            Vec::new()
+        };
+        let windows = if try_keyword(input, "windows")? {
+            list(input, "[", "]", |input| self.declared_wref(input))?
+        } else {
+            Vec::new()
+        };
+        Ok((clocks, windows))
+    }
+
+    fn declared_local_freq(&self, input: &mut &str) -> ModalResult<LocalFreqRef> {
+        let lref = uint(input)?;
+        if self.lref2lfreq.contains_key(&lref) {
+            Ok(lref)
+        } else {
+            fail(format!("local frequency {lref} is not declared"))
+        }
+    }
+
+    fn declared_wref(&self, input: &mut &str) -> ModalResult<WindowReference> {
+        let wref = wref(input)?;
+        if self.in_window || self.wref2window.contains_key(&wref) {
+            Ok(wref)
+        } else {
+            fail("window is not declared")
+        }
+    }
+
+    fn guard(&self, input: &mut &str) -> ModalResult<Guard> {
+        let mut lhs = self.guard_conjunction(input)?;
+        while try_punct(input, "||")? {
+            let rhs = self.guard_conjunction(input)?;
+            lhs = Guard::Or {
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            };
+        }
+        Ok(lhs)
+    }
+
+    fn guard_conjunction(&self, input: &mut &str) -> ModalResult<Guard> {
+        let mut lhs = self.guard_atom(input)?;
+        while try_punct(input, "&&")? {
+            let rhs = self.guard_atom(input)?;
+            lhs = Guard::And {
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            };
+        }
+        Ok(lhs)
+    }
+
+    fn guard_atom(&self, input: &mut &str) -> ModalResult<Guard> {
+        if try_punct(input, "(")? {
+            let guard = self.guard(input)?;
+            punct(input, ")")?;
+            return Ok(guard);
+        }
+        if try_punct(input, "@")? {
+            return self.declared_sr(input).map(Guard::Stream);
+        }
+        if try_punct(input, "?")? {
+            return self.declared_sr(input).map(Guard::Alive);
+        }
+        let guard = match ident(input, "a guard")? {
+            "true" => Guard::Constant(true),
+            "false" => Guard::Constant(false),
+            "Global" => {
+                punct(input, "(")?;
+                let dur = duration(input)?;
+                punct(input, ")")?;
+                Guard::GlobalFreq(dur)
+            }
+            "Local" => {
+                punct(input, "(")?;
+                let lref = self.declared_local_freq(input)?;
+                punct(input, ")")?;
+                Guard::LocalFreq(lref)
+            }
+            "Expr" => {
+                punct(input, "(")?;
+                let expr = self.expr(input)?;
+                punct(input, ")")?;
+                Guard::Dynamic(expr)
+            }
+            "FastAnd" => Guard::FastAnd(list(input, "(", ")", |input| self.declared_sr(input))?),
+            "FastOr" => Guard::FastOr(list(input, "(", ")", |input| self.declared_sr(input))?),
+            other => return fail(format!("unknown guard `{other}`")),
+        };
+        Ok(guard)
+    }
+
+    fn typed(&self, kind: ExprKind) -> Typed {
+        let ty = self.ctx.infer(&kind);
+        Typed { kind, ty }
+    }
+
+    fn resolve(&self, typed: Typed) -> ModalResult<Expr> {
+        match typed.ty {
+            Some(ty) => Ok(Expr {
+                ty,
+                kind: typed.kind,
+                span: SourceSpan::UNKNOWN,
+            }),
+            None => {
+                fail("can not infer the type of the expression, annotate it with `(expr : type)`")
+            }
+        }
+    }
+
+    fn expr(&self, input: &mut &str) -> ModalResult<Expr> {
+        let typed = self.binary(input, 0)?;
+        self.resolve(typed)
+    }
+
+    fn binary(&self, input: &mut &str, min_binding_power: u8) -> ModalResult<Typed> {
+        let mut lhs = self.unary(input)?;
+        loop {
+            let checkpoint = *input;
+            ws(input)?;
+            let Some((symbol, op, binding_power)) = BINARY_OPERATORS
+                .iter()
+                .find(|(symbol, _, _)| input.starts_with(symbol))
+            else {
+                *input = checkpoint;
+                break;
+            };
+            if *binding_power < min_binding_power {
+                *input = checkpoint;
+                break;
+            }
+            *input = &input[symbol.len()..];
+            // the power operator is right associative
+            let rhs_binding_power = if *symbol == "**" {
+                *binding_power
+            } else {
+                binding_power + 1
+            };
+            let rhs = self.binary(input, rhs_binding_power)?;
+            let kind = ExprKind::BinaryOperation(
+                *op,
+                Box::new(self.resolve(lhs)?),
+                Box::new(self.resolve(rhs)?),
+            );
+            lhs = self.typed(kind);
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&self, input: &mut &str) -> ModalResult<Typed> {
+        ws(input)?;
+        let negative_number = input.strip_prefix('-').is_some_and(|rest| {
+            rest.starts_with(|c: char| c.is_ascii_digit())
+                || rest.starts_with("inf")
+                || rest.starts_with("NaN")
+        });
+        if !negative_number {
+            if let Some((symbol, op)) = UNARY_OPERATORS
+                .iter()
+                .find(|(symbol, _)| input.starts_with(symbol))
+            {
+                *input = &input[symbol.len()..];
+                let operand = self.unary(input)?;
+                let kind = ExprKind::UnaryOperation(*op, Box::new(self.resolve(operand)?));
+                return Ok(self.typed(kind));
+            }
+        }
+        self.postfix(input)
+    }
+
+    fn postfix(&self, input: &mut &str) -> ModalResult<Typed> {
+        let mut expr = self.atom(input)?;
+        loop {
+            let checkpoint = *input;
+            ws(input)?;
+            let Some(mut rest) = input.strip_prefix('.') else {
+                *input = checkpoint;
+                return Ok(expr);
+            };
+            if rest.starts_with(|c: char| c.is_ascii_digit()) {
+                *input = rest;
+                let idx = uint(input)?;
+                expr = self.typed(ExprKind::TupleAccess(Box::new(self.resolve(expr)?), idx));
+            } else if try_keyword(&mut rest, "defaults")? {
+                *input = rest;
+                punct(input, "(")?;
+                keyword(input, "to")?;
+                punct(input, ":")?;
+                let default = self.expr(input)?;
+                punct(input, ")")?;
+                expr = self.typed(ExprKind::Default {
+                    expr: Box::new(self.resolve(expr)?),
+                    default: Box::new(default),
+                });
+            } else {
+                *input = checkpoint;
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn atom(&self, input: &mut &str) -> ModalResult<Typed> {
+        ws(input)?;
+        if try_punct(input, "(")? {
+            let inner = self.binary(input, 0)?;
+            let res = if try_punct(input, ":")? {
+                Typed {
+                    kind: inner.kind,
+                    ty: Some(ty(input)?),
+                }
+            } else {
+                inner
+            };
+            punct(input, ")")?;
+            return Ok(res);
+        }
+        if input.starts_with(|c: char| c == '"' || c == '-' || c.is_ascii_digit())
+            || input.starts_with("inf")
+            || input.starts_with("NaN")
+        {
+            return constant_value(input).map(|c| self.typed(ExprKind::Constant(c)));
+        }
+        let checkpoint = *input;
+        let kind = match ident(input, "an expression")? {
+            "true" => ExprKind::Constant(Constant::Bool(true)),
+            "false" => ExprKind::Constant(Constant::Bool(false)),
+            "ite" => {
+                let args = list(input, "(", ")", |input| self.expr(input))?;
+                let Ok([c, cons, alt]) = <[Expr; 3]>::try_from(args) else {
+                    return fail("`ite` expects three arguments");
+                };
+                ExprKind::Ite(Box::new(c), Box::new(cons), Box::new(alt))
+            }
+            "cast" => {
+                punct(input, "<")?;
+                let ty = ty(input)?;
+                punct(input, ">")?;
+                punct(input, "(")?;
+                let expr = self.expr(input)?;
+                punct(input, ")")?;
+                ExprKind::Cast(ty, Box::new(expr))
+            }
+            "extern" => {
+                punct(input, "<")?;
+                let ret = ty(input)?;
+                punct(input, ">")?;
+                let name = ident(input, "a function name")?.to_string();
+                let args = list(input, "(", ")", |input| self.expr(input))?;
+                let signature = FunctionSignature {
+                    args: args.iter().map(|arg| arg.ty.clone()).collect(),
+                    ret,
+                };
+                ExprKind::FunctionCall(Function::External(name, signature), args)
+            }
+            "tuple" => ExprKind::Tuple(list(input, "(", ")", |input| self.expr(input))?),
+            "const" => {
+                ExprKind::Constant(Constant::Tuple(list(input, "(", ")", constant_element)?))
+            }
+            "param" => {
+                punct(input, "(")?;
+                let sr = self.declared_sr(input)?;
+                punct(input, ",")?;
+                let idx = uint(input)?;
+                punct(input, ")")?;
+                ExprKind::ParameterAccess(sr, idx)
+            }
+            "lambda" => {
+                punct(input, "(")?;
+                let wref = wref(input)?;
+                punct(input, ",")?;
+                let idx = uint(input)?;
+                punct(input, ")")?;
+                ExprKind::LambdaParameterAccess(wref, idx)
+            }
+            word => {
+                if let Some((_, f)) = FUNCTIONS.iter().find(|(name, _)| *name == word) {
+                    let args = list(input, "(", ")", |input| self.expr(input))?;
+                    ExprKind::FunctionCall(f.clone(), args)
+                } else if word.starts_with("var") {
+                    ExprKind::Variable(variable(word)?)
+                } else if parse_sr(word).is_some() {
+                    *input = checkpoint;
+                    self.stream_access(input)?
+                } else {
+                    return fail(format!("unknown expression `{word}`"));
+                }
+            }
+        };
+        Ok(self.typed(kind))
+    }
+
+    fn stream_access(&self, input: &mut &str) -> ModalResult<ExprKind> {
+        let target = self.declared_sr(input)?;
+        ws(input)?;
+        let parameters = if input.starts_with('(') {
+            list(input, "(", ")", |input| self.expr(input))?
+        } else {
+            Vec::new()
+        };
+        let checkpoint = *input;
+        ws(input)?;
+        let is_method = input
+            .strip_prefix('.')
+            .is_some_and(|rest| rest.starts_with(is_ident_start) && !is_defaults(rest));
+        if !is_method {
+            *input = checkpoint;
+            return Ok(ExprKind::SyncStreamAccess { target, parameters });
+        }
+        *input = &input[1..];
+        let default = |input: &mut &str| -> ModalResult<Box<Expr>> {
+            keyword(input, "or")?;
+            punct(input, ":")?;
+            self.expr(input).map(Box::new)
+        };
+        let kind = match ident(input, "a stream access")? {
+            "offset" => {
+                punct(input, "(")?;
+                let offset = uint(input)? as u32;
+                let default = if try_punct(input, ",")? {
+                    Some(default(input)?)
+                } else {
+                    None
+                };
+                punct(input, ")")?;
+                ExprKind::OffsetStreamAccess {
+                    target,
+                    offset,
+                    default,
+                    parameters,
+                }
+            }
+            "hold" => {
+                punct(input, "(")?;
+                let default = if try_punct(input, ")")? {
+                    None
+                } else {
+                    let default = default(input)?;
+                    punct(input, ")")?;
+                    Some(default)
+                };
+                ExprKind::HoldStreamAccess {
+                    target,
+                    default,
+                    parameters,
+                }
+            }
+            "get" => {
+                punct(input, "(")?;
+                let default = if try_punct(input, ")")? {
+                    None
+                } else {
+                    let default = default(input)?;
+                    punct(input, ")")?;
+                    Some(default)
+                };
+                ExprKind::GetAccess {
+                    target,
+                    default,
+                    parameters,
+                }
+            }
+            "is_fresh" => {
+                punct(input, "(")?;
+                punct(input, ")")?;
+                ExprKind::IsFresh { target, parameters }
+            }
+            "aggregate" => {
+                punct(input, "(")?;
+                let window = self.declared_wref(input)?;
+                let default = if try_punct(input, ",")? {
+                    Some(default(input)?)
+                } else {
+                    None
+                };
+                punct(input, ")")?;
+                ExprKind::WindowAccess {
+                    target,
+                    window,
+                    parameters,
+                    default,
+                }
+            }
+            other => return fail(format!("unknown stream access `{other}`")),
+        };
+        Ok(kind)
+    }
+}
@@ -0,0 +1,703 @@
+use std::time::Duration;
+
+use itertools::Itertools;
+
+use crate::ir::{
+    expressions::{Constant, Expr, ExprKind, Function},
+    memory::{Memory, Parameter, StreamBuffer, StreamMemory},
+    windows::{InstanceSelection, Window, WindowKind, WindowOperation},
+    Guard, IfStmt, LivetimeEquivalences, OutputReference, OverflowPolicy, SourceSpan, Stmt,
+    StreamIr, StreamReference, Task, WindowReference,
+};
+
+use super::{TypeContext, BINARY_OPERATORS, FUNCTIONS, UNARY_OPERATORS};
+
+pub(super) struct TextPrinter {
+    ctx: TypeContext,
+}
+
+impl TextPrinter {
+    pub(super) fn new(ir: &StreamIr) -> Self {
+        let streams = ir
+            .sr2memory
+            .iter()
+            .map(|(sr, m)| (*sr, m.ty.clone()))
+            .collect();
+        let parameters = ir
+            .sr2memory
+            .iter()
+            .filter_map(|(sr, m)| {
+                m.parameters()
+                    .map(|p| (*sr, p.iter().map(|p| p.ty.clone()).collect()))
+            })
+            .collect();
+        let windows = ir
+            .wref2window
+            .iter()
+            .map(|(wref, w)| (*wref, w.ty.clone()))
+            .collect();
+        Self {
+            ctx: TypeContext {
+                streams,
+                parameters,
+                windows,
+                variables: Default::default(),
+            },
+        }
+    }
+
+    pub(super) fn print(&self, ir: &StreamIr) -> String {
+        let StreamIr {
+            stmt,
+            sr2memory,
+            wref2window,
+            lref2lfreq,
+            livetime_equivalences,
+            static_schedule,
+            triggers,
+            accesses,
+            accessed_by,
+            sources,
+            overflow,
+        } = ir;
+        let mut lines = Vec::new();
+        lines.extend(
+            sr2memory
+                .iter()
+                .sorted_by_key(|(sr, _)| **sr)
+                .map(|(sr, memory)| Self::stream(*sr, memory)),
+        );
+        lines.extend(
+            triggers
+                .iter()
+                .sorted()
+                .map(|(sr, t)| format!("trigger {} = {t};", Self::sr(sr.sr()))),
+        );
+        lines.extend(lref2lfreq.iter().sorted_by_key(|(r, _)| **r).map(|(r, f)| {
+            format!(
+                "local {r} = {} on {};",
+                Self::duration(f.dur),
+                Self::sr(f.sr.sr())
+            )
+        }));
+        let window_printer = Self {
+            ctx: self.ctx.without_windows(),
+        };
+        lines.extend(
+            wref2window
+                .iter()
+                .sorted_by_key(|(wref, _)| **wref)
+                .map(|(wref, window)| window_printer.window(*wref, window)),
+        );
+        lines.push(Self::livetime(livetime_equivalences));
+        if let Some(schedule) = static_schedule {
+            let deadlines = schedule
+                .deadlines
+                .iter()
+                .map(|dl| {
+                    let due = dl
+                        .due
+                        .iter()
+                        .map(|task| match task {
+                            Task::Spawn(o) => format!("spawn {}", Self::sr(o.sr())),
+                            Task::Eval(o) => format!("eval {}", Self::sr(o.sr())),
+                            Task::Close(o) => format!("close {}", Self::sr(o.sr())),
+                        })
+                        .join(", ");
+                    format!("{} [{due}]", Self::duration(dl.pause))
+                })
+                .join("; ");
+            lines.push(format!(
+                "schedule {} {{ {deadlines} }};",
+                Self::duration(schedule.hyper_period)
+            ));
+        }
+        for (keyword, accesses) in [("accesses", accesses), ("accessed_by", accessed_by)] {
+            lines.extend(
+                accesses
+                    .iter()
+                    .sorted_by_key(|(sr, _)| **sr)
+                    .map(|(sr, accesses)| {
+                        let accesses = accesses
+                            .iter()
+                            .map(|(target, kinds)| {
+                                let kinds = kinds
+                                    .iter()
+                                    .map(|(origin, kind)| format!("{origin} {kind}"))
+                                    .join(", ");
+                                format!("{} [{kinds}]", Self::sr(*target))
+                            })
+                            .join("; ");
+                        if accesses.is_empty() {
+                            format!("{keyword} {} {{}};", Self::sr(*sr))
+                        } else {
+                            format!("{keyword} {} {{ {accesses} }};", Self::sr(*sr))
+                        }
+                    }),
+            );
+        }
+        if *overflow != OverflowPolicy::default() {
+            lines.push(format!("overflow {overflow};"));
+        }
+        if let Some(file) = &sources.file {
+            lines.push(format!("source {file:?};"));
+        }
+        lines.extend(
+            sources
+                .streams
+                .iter()
+                .sorted()
+                .map(|(sr, span)| format!("location {} = {span};", Self::sr(*sr))),
+        );
+        lines.push(format!("stmt {}", self.stmt(stmt, 0)));
+        lines.join("\n")
+    }
+
+    fn stream(sr: StreamReference, memory: &Memory) -> String {
+        let Memory {
+            buffer,
+            ty,
+            name,
+            delay,
+        } = memory;
+        let keyword = match sr {
+            StreamReference::In(_) => "input",
+            StreamReference::Out(_) => "output",
+        };
+        let parameters = memory
+            .parameters()
+            .map(|p| {
+                let p = p
+                    .iter()
+                    .map(|Parameter { name, ty }| format!("{}: {ty}", Self::name(name)))
+                    .join(", ");
+                format!("({p})")
+            })
+            .unwrap_or_default();
+        let memory = match buffer {
+            StreamMemory::NoMemory => "none".into(),
+            StreamMemory::Static(b) => format!("static({})", Self::buffer(*b)),
+            StreamMemory::Dynamic {
+                buffer,
+                has_spawn,
+                has_close,
+            } => format!(
+                "dynamic({}{}{})",
+                Self::buffer(*buffer),
+                if *has_spawn { ", spawn" } else { "" },
+                if *has_close { ", close" } else { "" }
+            ),
+            StreamMemory::Instances { buffer, bound, .. } => format!(
+                "instances({}{})",
+                Self::buffer(*buffer),
+                bound.map(|b| format!(", max {b}")).unwrap_or_default()
+            ),
+        };
+        let delay = if *delay > 0 {
+            format!(" delayed({delay})")
+        } else {
+            String::new()
+        };
+        format!(
+            "{keyword} {} {}{parameters}: {ty} = {memory}{delay};",
+            Self::sr(sr),
+            Self::name(name)
+        )
+    }
+
+    fn buffer(buffer: StreamBuffer) -> String {
+        match buffer {
+            StreamBuffer::SingleValue => "single".into(),
+            StreamBuffer::Bounded(b) => format!("bounded({b})"),
+            StreamBuffer::UnBounded => "unbounded".into(),
+        }
+    }
+
+    fn window(&self, wref: WindowReference, window: &Window) -> String {
+        let Window {
+            wref: _,
+            op,
+            target,
+            caller,
+            origin,
+            origin_pacing,
+            kind,
+            ty,
+            span,
+        } = window;
+        let op = match op {
+            WindowOperation::Sum => "sum".into(),
+            WindowOperation::Average => "average".into(),
+            WindowOperation::Conjunction => "conjunction".into(),
+            WindowOperation::Disjunction => "disjunction".into(),
+            WindowOperation::Min => "min".into(),
+            WindowOperation::Max => "max".into(),
+            WindowOperation::Integral => "integral".into(),
+            WindowOperation::Count => "count".into(),
+            WindowOperation::Product => "product".into(),
+            WindowOperation::Last => "last".into(),
+            WindowOperation::Variance => "variance".into(),
+            WindowOperation::Covariance => "covariance".into(),
+            WindowOperation::StandardDeviation => "standard_deviation".into(),
+            WindowOperation::NthPercentile(p) => format!("nth_percentile({p})"),
+        };
+        let wait = |wait: bool| if wait { "wait" } else { "nowait" };
+        let kind = match kind {
+            WindowKind::Sliding {
+                duration,
+                bucket_count,
+                bucket_duration,
+                wait: w,
+            } => format!(
+                "sliding({}, {bucket_count} x {}, {})",
+                Self::duration(*duration),
+                Self::duration(*bucket_duration),
+                wait(*w)
+            ),
+            WindowKind::Discrete {
+                num_values,
+                wait: w,
+            } => {
+                format!("discrete({num_values}, {})", wait(*w))
+            }
+            WindowKind::Instances { selection } => {
+                let selection = match selection {
+                    InstanceSelection::All => "all".into(),
+                    InstanceSelection::Fresh => "fresh".into(),
+                    InstanceSelection::FilteredAll { parameters, cond } => {
+                        format!("all, {}", self.lambda(parameters, cond))
+                    }
+                    InstanceSelection::FilteredFresh { parameters, cond } => {
+                        format!("fresh, {}", self.lambda(parameters, cond))
+                    }
+                };
+                format!("instances({selection})")
+            }
+        };
+        format!(
+            "window {wref}: {ty} = {op} of {} over {kind} in {}.{origin} when {}{};",
+            Self::sr(*target),
+            Self::sr(*caller),
+            self.guard(origin_pacing),
+            Self::at(*span)
+        )
+    }
+
+    fn lambda(&self, parameters: &[Parameter], cond: &Expr) -> String {
+        let parameters = parameters
+            .iter()
+            .map(|Parameter { name, ty }| format!("{}: {ty}", Self::name(name)))
+            .join(", ");
+        format!("|{parameters}| {}", self.expr(cond))
+    }
+
+    fn livetime(eq: &LivetimeEquivalences) -> String {
+        let idx2sr = eq
+            .idx
+            .iter()
+            .map(|(sr, idx)| (*idx, *sr))
+            .collect::<std::collections::HashMap<_, _>>();
+        let mut classes = eq
+            .sets
+            .sets()
+            .into_iter()
+            .map(|set| {
+                let inputs = set.contains(&eq.input_idx);
+                let outputs = set
+                    .into_iter()
+                    .filter_map(|i| idx2sr.get(&i).copied())
+                    .sorted()
+                    .collect::<Vec<_>>();
+                (inputs, outputs)
+            })
+            .filter(|(inputs, outputs)| *inputs || !outputs.is_empty())
+            .collect::<Vec<_>>();
+        if !classes.iter().any(|(inputs, _)| *inputs) {
+            classes.push((true, Vec::new()));
+        }
+        let classes = classes
+            .into_iter()
+            .sorted_by_key(|(inputs, outputs)| (!*inputs, outputs.first().copied()))
+            .map(|(inputs, outputs)| {
+                let elements = inputs
+                    .then(|| "inputs".to_string())
+                    .into_iter()
+                    .chain(outputs.into_iter().map(|o| Self::sr(o.sr())))
+                    .join(", ");
+                format!("[{elements}]")
+            })
+            .join(" ");
+        format!("livetime {classes};")
+    }
+
+    pub(super) fn sr(sr: StreamReference) -> String {
+        match sr {
+            StreamReference::In(i) => format!("in{i}"),
+            StreamReference::Out(OutputReference::Unparameterized(i)) => format!("out{i}"),
+            StreamReference::Out(OutputReference::Parameterized(i)) => format!("pout{i}"),
+        }
+    }
+
+    fn srs(srs: &[OutputReference]) -> String {
+        srs.iter().map(|sr| Self::sr(sr.sr())).join(", ")
+    }
+
+    fn name(name: &str) -> String {
+        let mut chars = name.chars();
+        let is_ident = chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if is_ident {
+            name.into()
+        } else {
+            format!("{name:?}")
+        }
+    }
+
+    fn duration(dur: Duration) -> String {
+        let mut value = dur.as_nanos();
+        let mut unit = "ns";
+        for next in ["us", "ms", "s"] {
+            if value / 1000 * 1000 != value {
+                break;
+            }
+            value /= 1000;
+            unit = next;
+        }
+        format!("{value}{unit}")
+    }
+
+    fn indent(level: usize) -> String {
+        "    ".repeat(level)
+    }
+
+    fn stmt(&self, stmt: &Stmt, level: usize) -> String {
+        match stmt {
+            Stmt::Skip => "skip".into(),
+            Stmt::Seq(inner) => self.block("seq", inner, level),
+            Stmt::Parallel(inner) => self.block("par", inner, level),
+            Stmt::Shift(sr) => format!("shift {}", Self::sr(*sr)),
+            Stmt::Input(i) => format!("input {}", Self::sr(StreamReference::In(*i))),
+            Stmt::Spawn {
+                sr,
+                with,
+                local_frequencies,
+                windows,
+                span,
+            } => {
+                let with = with
+                    .as_ref()
+                    .map(|with| format!(" with ({})", self.exprs(with)))
+                    .unwrap_or_default();
+                format!(
+                    "spawn {}{with}{}{}",
+                    Self::sr(sr.sr()),
+                    Self::clocks_and_windows(local_frequencies, windows),
+                    Self::at(*span)
+                )
+            }
+            Stmt::Eval {
+                sr,
+                with,
+                idx,
+                span,
+            } => format!(
+                "eval_{idx} {} with {}{}",
+                Self::sr(sr.sr()),
+                self.expr(with),
+                Self::at(*span)
+            ),
+            Stmt::Close {
+                sr,
+                local_frequencies,
+                windows,
+                span,
+            } => format!(
+                "close {}{}{}",
+                Self::sr(sr.sr()),
+                Self::clocks_and_windows(local_frequencies, windows),
+                Self::at(*span)
+            ),
+            Stmt::If(IfStmt { guard, cons, alt }) => {
+                let inner = Self::indent(level + 1);
+                let outer = Self::indent(level);
+                let cons = self.stmt(cons, level + 1);
+                let alt = if matches!(**alt, Stmt::Skip) {
+                    String::new()
+                } else {
+                    format!("\n{outer}else\n{inner}{}", self.stmt(alt, level + 1))
+                };
+                format!(
+                    "if {} then\n{inner}{cons}{alt}\n{outer}fi",
+                    self.guard(guard)
+                )
+            }
+            Stmt::Iterate { sr, stmt } => format!(
+                "iterate [{}]\n{}{}",
+                Self::srs(sr),
+                Self::indent(level + 1),
+                self.stmt(stmt, level + 1)
+            ),
+            Stmt::Assign {
+                parameter_expr,
+                sr,
+                stmt,
+            } => format!(
+                "assign [{}] ({})\n{}{}",
+                Self::srs(sr),
+                self.exprs(parameter_expr),
+                Self::indent(level + 1),
+                self.stmt(stmt, level + 1)
+            ),
+            Stmt::Let { var, expr, stmt } => {
+                let inner = Self {
+                    ctx: self.ctx.with_variable(*var, expr.ty.clone()),
+                };
+                format!(
+                    "let var{var} = {}\n{}{}",
+                    self.expr(expr),
+                    Self::indent(level + 1),
+                    inner.stmt(stmt, level + 1)
+                )
+            }
+        }
+    }
+
+    fn block(&self, keyword: &str, inner: &[Stmt], level: usize) -> String {
+        if inner.is_empty() {
+            return format!("{keyword} {{}}");
+        }
+        let indent = Self::indent(level + 1);
+        let inner = inner
+            .iter()
+            .map(|stmt| format!("{indent}{}", self.stmt(stmt, level + 1)))
+            .join(";\n");
+        format!("{keyword} {{\n{inner}\n{}}}", Self::indent(level))
+    }
+
+    fn at(span: SourceSpan) -> String {
+        span.0.map(|span| format!(" at {span}")).unwrap_or_default()
+    }
+
+    fn clocks_and_windows(local_frequencies: &[usize], windows: &[WindowReference]) -> String {
+        let mut res = String::new();
+        if !local_frequencies.is_empty() {
+            res += &format!(" clocks [{}]", local_frequencies.iter().join(", "));
+        }
+        if !windows.is_empty() {
+            res += &format!(
+                " windows [{}]",
+                windows.iter().map(|w| w.to_string()).join(", ")
+            );
+        }
+        res
+    }
+
+    fn guard(&self, guard: &Guard) -> String {
+        match guard {
+            Guard::Stream(sr) => format!("@{}", Self::sr(*sr)),
+            Guard::Alive(sr) => format!("?{}", Self::sr(*sr)),
+            Guard::Dynamic(expr) => format!("Expr({})", self.expr(expr)),
+            Guard::GlobalFreq(dur) => format!("Global({})", Self::duration(*dur)),
+            Guard::LocalFreq(lref) => format!("Local({lref})"),
+            Guard::And { lhs, rhs } => {
+                format!("{} && {}", self.guard_operand(lhs), self.guard_operand(rhs))
+            }
+            Guard::Or { lhs, rhs } => {
+                format!("{} || {}", self.guard_operand(lhs), self.guard_operand(rhs))
+            }
+            Guard::Constant(b) => b.to_string(),
+            Guard::FastAnd(srs) => {
+                format!("FastAnd({})", srs.iter().map(|sr| Self::sr(*sr)).join(", "))
+            }
+            Guard::FastOr(srs) => {
+                format!("FastOr({})", srs.iter().map(|sr| Self::sr(*sr)).join(", "))
+            }
+        }
+    }
+
+    fn guard_operand(&self, guard: &Guard) -> String {
+        match guard {
+            Guard::And { .. } | Guard::Or { .. } => format!("({})", self.guard(guard)),
+            other => self.guard(other),
+        }
+    }
+
+    fn exprs(&self, exprs: &[Expr]) -> String {
+        exprs.iter().map(|e| self.expr(e)).join(", ")
+    }
+
+    fn expr(&self, expr: &Expr) -> String {
+        let kind = self.expr_kind(&expr.kind);
+        if self.is_annotated(expr) {
+            format!("({kind} : {})", expr.ty)
+        } else {
+            kind
+        }
+    }
+
+    fn is_annotated(&self, expr: &Expr) -> bool {
+        self.ctx.infer(&expr.kind).as_ref() != Some(&expr.ty)
+    }
+
+    /// Returns the expression in a form that can be used as the operand of an unary or binary operator
+    fn operand(&self, expr: &Expr) -> String {
+        match &expr.kind {
+            ExprKind::BinaryOperation(..) if !self.is_annotated(expr) => {
+                format!("({})", self.expr(expr))
+            }
+            _ => self.expr(expr),
+        }
+    }
+
+    /// Returns the expression in a form that can be used in front of a tuple access or a default
+    fn postfix_operand(&self, expr: &Expr) -> String {
+        match &expr.kind {
+            ExprKind::BinaryOperation(..) | ExprKind::UnaryOperation(..)
+                if !self.is_annotated(expr) =>
+            {
+                format!("({})", self.expr(expr))
+            }
+            _ => self.expr(expr),
+        }
+    }
+
+    fn expr_kind(&self, kind: &ExprKind) -> String {
+        match kind {
+            ExprKind::Constant(c) => Self::constant(c),
+            ExprKind::BinaryOperation(op, lhs, rhs) => {
+                let (op, _, _) = BINARY_OPERATORS.iter().find(|(_, o, _)| o == op).unwrap();
+                format!("{} {op} {}", self.operand(lhs), self.operand(rhs))
+            }
+            ExprKind::UnaryOperation(op, operand) => {
+                let (op, _) = UNARY_OPERATORS.iter().find(|(_, o)| o == op).unwrap();
+                format!("{op}{}", self.operand(operand))
+            }
+            ExprKind::Ite(c, cons, alt) => format!(
+                "ite({}, {}, {})",
+                self.expr(c),
+                self.expr(cons),
+                self.expr(alt)
+            ),
+            ExprKind::SyncStreamAccess { target, parameters } => {
+                self.stream_access(*target, parameters)
+            }
+            ExprKind::OffsetStreamAccess {
+                target,
+                offset,
+                default,
+                parameters,
+            } => {
+                let default = default
+                    .as_ref()
+                    .map(|d| format!(", or: {}", self.expr(d)))
+                    .unwrap_or_default();
+                format!(
+                    "{}.offset({offset}{default})",
+                    self.stream_access(*target, parameters)
+                )
+            }
+            ExprKind::HoldStreamAccess {
+                target,
+                default,
+                parameters,
+            } => format!(
+                "{}.hold({})",
+                self.stream_access(*target, parameters),
+                self.default(default)
+            ),
+            ExprKind::IsFresh { target, parameters } => {
+                format!("{}.is_fresh()", self.stream_access(*target, parameters))
+            }
+            ExprKind::GetAccess {
+                target,
+                default,
+                parameters,
+            } => format!(
+                "{}.get({})",
+                self.stream_access(*target, parameters),
+                self.default(default)
+            ),
+            ExprKind::WindowAccess {
+                target,
+                window,
+                parameters,
+                default,
+            } => {
+                let default = default
+                    .as_ref()
+                    .map(|d| format!(", or: {}", self.expr(d)))
+                    .unwrap_or_default();
+                format!(
+                    "{}.aggregate({window}{default})",
+                    self.stream_access(*target, parameters)
+                )
+            }
+            ExprKind::Cast(ty, expr) => format!("cast<{ty}>({})", self.expr(expr)),
+            ExprKind::ParameterAccess(sr, idx) => format!("param({}, {idx})", Self::sr(*sr)),
+            ExprKind::FunctionCall(Function::External(name, signature), args) => {
+                format!("extern<{}> {name}({})", signature.ret, self.exprs(args))
+            }
+            ExprKind::FunctionCall(f, args) => {
+                let (name, _) = FUNCTIONS.iter().find(|(_, func)| func == f).unwrap();
+                format!("{name}({})", self.exprs(args))
+            }
+            ExprKind::Tuple(inner) => format!("tuple({})", self.exprs(inner)),
+            ExprKind::TupleAccess(inner, idx) => format!("{}.{idx}", self.postfix_operand(inner)),
+            ExprKind::LambdaParameterAccess(wref, idx) => {
+                format!("lambda({wref}, {idx})")
+            }
+            ExprKind::Variable(var) => format!("var{var}"),
+            ExprKind::Default { expr, default } => format!(
+                "{}.defaults(to: {})",
+                self.postfix_operand(expr),
+                self.expr(default)
+            ),
+        }
+    }
+
+    fn default(&self, default: &Option<Box<Expr>>) -> String {
+        default
+            .as_ref()
+            .map(|d| format!("or: {}", self.expr(d)))
+            .unwrap_or_default()
+    }
+
+    fn stream_access(&self, target: StreamReference, parameters: &[Expr]) -> String {
+        if parameters.is_empty() {
+            Self::sr(target)
+        } else {
+            format!("{}({})", Self::sr(target), self.exprs(parameters))
+        }
+    }
+
+    fn constant(c: &Constant) -> String {
+        match c {
+            Constant::Tuple(inner) => format!("const({})", Self::constant_tuple_elements(inner)),
+            other => Self::constant_value(other),
+        }
+    }
+
+    fn constant_tuple_elements(inner: &[Constant]) -> String {
+        inner
+            .iter()
+            .map(|c| match c {
+                Constant::Tuple(inner) => format!("({})", Self::constant_tuple_elements(inner)),
+                other => Self::constant_value(other),
+            })
+            .join(", ")
+    }
+
+    fn constant_value(c: &Constant) -> String {
+        match c {
+            Constant::Str(s) => format!("{s:?}"),
+            Constant::Bool(b) => b.to_string(),
+            Constant::UInt(v, bits) => format!("{v}u{bits}"),
+            Constant::Int(v, bits) => format!("{v}i{bits}"),
+            Constant::Float32(v) => format!("{v:?}f32"),
+            Constant::Float64(v) => format!("{v:?}f64"),
+            Constant::Tuple(inner) => format!("({})", Self::constant_tuple_elements(inner)),
+        }
+    }
+}
@@ -0,0 +1,467 @@
+//! Checks the structural invariants of the StreamIR.
+//!
+//! The backends rely on these invariants without checking them, so a rewriting rule that breaks one of them
+//! usually only shows up as a panic deep inside a backend.
+//! [StreamIr::verify] reports the broken invariant directly.
+
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use super::{
+    bdd::{Bdd, GuardBdd},
+    expressions::{Expr, ExprKind, Function, Operator},
+    memory::{StreamBuffer, StreamMemory},
+    Guard, IfStmt, LocalFreqRef, OutputReference, SourceSpan, Stmt, StreamIr, StreamReference,
+    Type, WindowReference,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+/// An invariant of the StreamIR that does not hold
+pub enum VerifyError {
+    #[error("the stream {0:?} is referenced but has no memory")]
+    /// A stream is referenced that does not exist
+    UnknownStream(StreamReference),
+    #[error("the window {0:?} is referenced but does not exist")]
+    /// A window is referenced that does not exist
+    UnknownWindow(WindowReference),
+    #[error("the local frequency {0} is referenced but does not exist")]
+    /// A local frequency is referenced that does not exist
+    UnknownLocalFreq(LocalFreqRef),
+    #[error("the stream {0} is evaluated without being shifted before in the same cycle")]
+    /// An output stream with a buffer of more than one value is evaluated without a preceding shift
+    EvalWithoutShift(String),
+    #[error("the parameterized stream {0} is evaluated outside of an iterate or assign statement")]
+    /// A parameterized stream is evaluated without a selected instance
+    EvalOutsideInstance(String),
+    #[error("the parameterized stream {0} is closed outside of an iterate or assign statement")]
+    /// A parameterized stream is closed without a selected instance
+    CloseOutsideInstance(String),
+    #[error(
+        "an expression in {location} has type {found}, but its operands result in type {expected}"
+    )]
+    /// The type of an expression does not match the type resulting from its operands
+    TypeMismatch {
+        /// The statement containing the expression
+        location: String,
+        /// The type annotated at the expression
+        found: Type,
+        /// The type resulting from the operands
+        expected: Type,
+    },
+}
+
+impl StreamIr {
+    /// Checks the structural invariants of the StreamIR that the backends rely on.
+    ///
+    /// The following invariants are checked:
+    /// - every referenced stream, window and local frequency exists
+    /// - every evaluation of a stream that stores more than a single value is preceded by a shift of the stream in the same cycle,
+    ///   whenever the evaluation is executed
+    /// - evaluations and closes of parameterized streams are inside an iterate or assign statement of the stream
+    /// - the type of each operation matches the types of its operands
+    pub fn verify(&self) -> Result<(), VerifyError> {
+        Verifier {
+            ir: self,
+            bdd: GuardBdd::new(&self.livetime_equivalences),
+        }
+        .stmt(&self.stmt, Bdd::TRUE, &mut HashMap::new(), &[])
+    }
+}
+
+struct Verifier<'a> {
+    ir: &'a StreamIr,
+    bdd: GuardBdd<'a>,
+}
+
+impl Verifier<'_> {
+    /// Checks the statement, which is executed whenever `path` holds.
+    ///
+    /// `shifted` holds for each stream a guard under which the stream has certainly been shifted before in the current cycle,
+    /// and `instances` holds the streams for which an instance is selected.
+    /// The statements of parallel blocks are assumed to be executed in the given order.
+    fn stmt(
+        &mut self,
+        stmt: &Stmt,
+        path: Bdd,
+        shifted: &mut HashMap<StreamReference, Bdd>,
+        instances: &[OutputReference],
+    ) -> Result<(), VerifyError> {
+        match stmt {
+            Stmt::Skip => Ok(()),
+            Stmt::Seq(stmts) | Stmt::Parallel(stmts) => stmts
+                .iter()
+                .try_for_each(|stmt| self.stmt(stmt, path, shifted, instances)),
+            Stmt::Shift(sr) => {
+                self.stream(*sr)?;
+                let before = shifted.get(sr).copied().unwrap_or(Bdd::FALSE);
+                let after = self.bdd.or(before, path);
+                shifted.insert(*sr, after);
+                Ok(())
+            }
+            Stmt::Input(i) => self.stream(StreamReference::In(*i)),
+            Stmt::Spawn {
+                sr,
+                with,
+                local_frequencies,
+                windows,
+                span,
+            } => {
+                self.stream(sr.sr())?;
+                let location = self.clause("the spawn", *sr, *span);
+                with.iter()
+                    .flatten()
+                    .try_for_each(|expr| self.expr(expr, &location))?;
+                self.local_frequencies(local_frequencies)?;
+                self.windows(windows)
+            }
+            Stmt::Eval { sr, with, span, .. } => {
+                self.stream(sr.sr())?;
+                let name = self.ir.name(sr.sr());
+                self.expr(with, &self.clause("the evaluation", *sr, *span))?;
+                if self.is_parameterized(*sr) && !instances.contains(sr) {
+                    return Err(VerifyError::EvalOutsideInstance(name.into()));
+                }
+                let needs_shift = matches!(
+                    self.ir.stream_memory(sr.sr()).buffer.buffer(),
+                    Some(StreamBuffer::Bounded(_) | StreamBuffer::UnBounded)
+                );
+                let shifted_under = shifted.get(&sr.sr()).copied().unwrap_or(Bdd::FALSE);
+                if needs_shift && !self.bdd.implies(path, shifted_under) {
+                    return Err(VerifyError::EvalWithoutShift(name.into()));
+                }
+                Ok(())
+            }
+            Stmt::Close {
+                sr,
+                local_frequencies,
+                windows,
+                ..
+            } => {
+                self.stream(sr.sr())?;
+                if self.is_parameterized(*sr) && !instances.contains(sr) {
+                    return Err(VerifyError::CloseOutsideInstance(
+                        self.ir.name(sr.sr()).into(),
+                    ));
+                }
+                self.local_frequencies(local_frequencies)?;
+                self.windows(windows)
+            }
+            Stmt::If(IfStmt { guard, cons, alt }) => {
+                self.guard(guard)?;
+                let guard = self.bdd.from_guard(guard);
+                let cons_path = self.bdd.and(path, guard);
+                // guards can not be negated, so the shifts in the alternative are only known to happen under `path`
+                // inside the alternative, and afterwards only if the consequence shifts the stream under `path` as well
+                let mut alt_shifted = shifted.clone();
+                self.stmt(cons, cons_path, shifted, instances)?;
+                self.stmt(alt, path, &mut alt_shifted, instances)?;
+                for (sr, alt_shifted) in alt_shifted {
+                    let cons_shifted = shifted.get(&sr).copied().unwrap_or(Bdd::FALSE);
+                    if self.bdd.implies(cons_path, cons_shifted) {
+                        let both = self.bdd.and(alt_shifted, path);
+                        let after = self.bdd.or(cons_shifted, both);
+                        shifted.insert(sr, after);
+                    }
+                }
+                Ok(())
+            }
+            Stmt::Iterate { sr, stmt } => {
+                sr.iter().try_for_each(|sr| self.stream(sr.sr()))?;
+                let instances = [instances, sr].concat();
+                self.stmt(stmt, path, shifted, &instances)
+            }
+            Stmt::Assign {
+                parameter_expr,
+                sr,
+                stmt,
+            } => {
+                sr.iter().try_for_each(|sr| self.stream(sr.sr()))?;
+                parameter_expr
+                    .iter()
+                    .try_for_each(|expr| self.expr(expr, "an assign statement"))?;
+                let instances = [instances, sr].concat();
+                self.stmt(stmt, path, shifted, &instances)
+            }
+            Stmt::Let { var, expr, stmt } => {
+                self.expr(expr, &format!("the binding of var{var}"))?;
+                self.stmt(stmt, path, shifted, instances)
+            }
+        }
+    }
+
+    /// Describes the clause of the stream, including its location in the specification if it is known
+    fn clause(&self, clause: &str, sr: OutputReference, span: SourceSpan) -> String {
+        let name = self.ir.name(sr.sr());
+        match self.ir.sources.locate(span, sr.sr()) {
+            Some(location) => format!("{clause} of {name} at {location}"),
+            None => format!("{clause} of {name}"),
+        }
+    }
+
+    fn guard(&self, guard: &Guard) -> Result<(), VerifyError> {
+        match guard {
+            Guard::Stream(sr) | Guard::Alive(sr) => self.stream(*sr),
+            Guard::Dynamic(expr) => self.expr(expr, "a guard"),
+            Guard::LocalFreq(lref) => self.local_frequencies(&[*lref]),
+            Guard::And { lhs, rhs } | Guard::Or { lhs, rhs } => {
+                self.guard(lhs)?;
+                self.guard(rhs)
+            }
+            Guard::FastAnd(srs) | Guard::FastOr(srs) => {
+                srs.iter().try_for_each(|sr| self.stream(*sr))
+            }
+            Guard::GlobalFreq(_) | Guard::Constant(_) => Ok(()),
+        }
+    }
+
+    fn expr(&self, expr: &Expr, location: &str) -> Result<(), VerifyError> {
+        match &expr.kind {
+            ExprKind::SyncStreamAccess { target, .. }
+            | ExprKind::OffsetStreamAccess { target, .. }
+            | ExprKind::HoldStreamAccess { target, .. }
+            | ExprKind::IsFresh { target, .. }
+            | ExprKind::GetAccess { target, .. }
+            | ExprKind::ParameterAccess(target, _) => self.stream(*target)?,
+            ExprKind::WindowAccess { target, window, .. } => {
+                self.stream(*target)?;
+                self.windows(&[*window])?;
+            }
+            _ => {}
+        }
+        let mismatch = |found: &Type, expected: Type| {
+            (*found != expected).then(|| VerifyError::TypeMismatch {
+                location: location.into(),
+                found: found.clone(),
+                expected,
+            })
+        };
+        if let Some(error) = expected_type(expr).and_then(|expected| mismatch(&expr.ty, expected)) {
+            return Err(error);
+        }
+        if let ExprKind::Ite(_, cons, alt) = &expr.kind {
+            if let Some(error) = mismatch(&alt.ty, cons.ty.clone()) {
+                return Err(error);
+            }
+        }
+        expr.children()
+            .into_iter()
+            .try_for_each(|child| self.expr(child, location))
+    }
+
+    fn stream(&self, sr: StreamReference) -> Result<(), VerifyError> {
+        if self.ir.sr2memory.contains_key(&sr) {
+            Ok(())
+        } else {
+            Err(VerifyError::UnknownStream(sr))
+        }
+    }
+
+    fn windows(&self, windows: &[WindowReference]) -> Result<(), VerifyError> {
+        match windows
+            .iter()
+            .find(|wref| !self.ir.wref2window.contains_key(wref))
+        {
+            Some(wref) => Err(VerifyError::UnknownWindow(*wref)),
+            None => Ok(()),
+        }
+    }
+
+    fn local_frequencies(&self, local_frequencies: &[LocalFreqRef]) -> Result<(), VerifyError> {
+        match local_frequencies
+            .iter()
+            .find(|lref| !self.ir.lref2lfreq.contains_key(lref))
+        {
+            Some(lref) => Err(VerifyError::UnknownLocalFreq(*lref)),
+            None => Ok(()),
+        }
+    }
+
+    fn is_parameterized(&self, sr: OutputReference) -> bool {
+        matches!(
+            self.ir.stream_memory(sr.sr()).buffer,
+            StreamMemory::Instances { .. }
+        )
+    }
+}
+
+/// Returns the type that results from the operands of the expression, if it is determined by them
+fn expected_type(expr: &Expr) -> Option<Type> {
+    match &expr.kind {
+        ExprKind::BinaryOperation(op, lhs, _) => match op {
+            Operator::And
+            | Operator::Or
+            | Operator::Eq
+            | Operator::Lt
+            | Operator::Le
+            | Operator::Ne
+            | Operator::Ge
+            | Operator::Gt => Some(Type::Bool),
+            // the exponent and the shift amount can have another type than the base, which determines the result
+            Operator::Pow | Operator::Shl | Operator::Shr => Some(lhs.ty.clone()),
+            Operator::Add
+            | Operator::Sub
+            | Operator::Mul
+            | Operator::Div
+            | Operator::Rem
+            | Operator::BitXor
+            | Operator::BitAnd
+            | Operator::BitOr => Some(lhs.ty.clone()),
+            Operator::Not | Operator::Neg | Operator::BitNot => None,
+        },
+        ExprKind::UnaryOperation(_, operand) => Some(operand.ty.clone()),
+        ExprKind::Ite(_, cons, _) => Some(cons.ty.clone()),
+        ExprKind::Cast(ty, _) => Some(ty.clone()),
+        ExprKind::Tuple(elements) => {
+            Some(Type::Tuple(elements.iter().map(|e| e.ty.clone()).collect()))
+        }
+        ExprKind::TupleAccess(inner, idx) => match &inner.ty {
+            Type::Tuple(elements) => elements.get(*idx).cloned(),
+            _ => None,
+        },
+        ExprKind::Default { default, .. } => Some(default.ty.clone()),
+        ExprKind::FunctionCall(Function::External(_, signature), _) => Some(signature.ret.clone()),
+        ExprKind::Constant(_)
+        | ExprKind::SyncStreamAccess { .. }
+        | ExprKind::OffsetStreamAccess { .. }
+        | ExprKind::HoldStreamAccess { .. }
+        | ExprKind::IsFresh { .. }
+        | ExprKind::GetAccess { .. }
+        | ExprKind::WindowAccess { .. }
+        | ExprKind::ParameterAccess(_, _)
+        | ExprKind::FunctionCall(_, _)
+        | ExprKind::LambdaParameterAccess(_, _)
+        | ExprKind::Variable(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ir::{parse::parse_text, WindowReference};
+
+    use super::VerifyError;
+
+    const STREAMS: &str = "input in0 a: UInt(64) = static(single);
+output out0 b: UInt(64) = static(bounded(2));
+output pout0 c(p: UInt(64)): UInt(64) = instances(single);
+window dw0: UInt(64) = sum of in0 over discrete(5, wait) in out0.eval_with(0) when @in0;
+";
+
+    fn verify(stmt: &str) -> Result<(), VerifyError> {
+        parse_text(STREAMS, stmt).verify()
+    }
+
+    #[test]
+    fn valid() {
+        assert_eq!(
+            verify(
+                "seq {
+    input in0;
+    if @in0 then
+        shift out0
+    fi;
+    if @in0 then
+        eval_0 out0 with in0 + in0.aggregate(dw0)
+    fi;
+    iterate [pout0]
+        eval_0 pout0 with param(pout0, 0)
+}"
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn eval_without_shift() {
+        assert_eq!(
+            verify("seq { eval_0 out0 with in0; shift out0 }"),
+            Err(VerifyError::EvalWithoutShift("b".into()))
+        );
+        assert_eq!(verify("par { shift out0; eval_0 out0 with in0 }"), Ok(()));
+        // the statements of a parallel block are executed in order
+        assert_eq!(
+            verify("par { eval_0 out0 with in0; shift out0 }"),
+            Err(VerifyError::EvalWithoutShift("b".into()))
+        );
+    }
+
+    #[test]
+    fn shift_on_every_path() {
+        // the shift only happens if the guard holds
+        assert_eq!(
+            verify("seq { if @in0 then shift out0 fi; eval_0 out0 with in0 }"),
+            Err(VerifyError::EvalWithoutShift("b".into()))
+        );
+        assert_eq!(
+            verify("seq { if @in0 then shift out0 else skip fi; eval_0 out0 with in0 }"),
+            Err(VerifyError::EvalWithoutShift("b".into()))
+        );
+        assert_eq!(
+            verify("seq { if @in0 then skip else shift out0 fi; eval_0 out0 with in0 }"),
+            Err(VerifyError::EvalWithoutShift("b".into()))
+        );
+        assert_eq!(
+            verify("seq { if @in0 then shift out0 else shift out0 fi; eval_0 out0 with in0 }"),
+            Ok(())
+        );
+        assert_eq!(
+            verify("if @in0 then skip else seq { shift out0; eval_0 out0 with in0 } fi"),
+            Ok(())
+        );
+        assert_eq!(
+            verify(
+                "seq { if @in0 && Expr(in0 > 1u64) then shift out0 fi; if @in0 then eval_0 out0 with in0 fi }"
+            ),
+            Err(VerifyError::EvalWithoutShift("b".into()))
+        );
+    }
+
+    #[test]
+    fn outside_instance() {
+        assert_eq!(
+            verify("eval_0 pout0 with 1u64"),
+            Err(VerifyError::EvalOutsideInstance("c".into()))
+        );
+        assert_eq!(
+            verify("close pout0"),
+            Err(VerifyError::CloseOutsideInstance("c".into()))
+        );
+    }
+
+    #[test]
+    fn unknown_references() {
+        let mut ir = parse_text(
+            STREAMS,
+            "if @in0 then seq { shift out0; eval_0 out0 with in0.aggregate(dw0) } fi",
+        );
+        ir.wref2window.clear();
+        assert_eq!(
+            ir.verify(),
+            Err(VerifyError::UnknownWindow(WindowReference::Discrete(0)))
+        );
+    }
+
+    #[test]
+    fn type_mismatch() {
+        assert!(matches!(
+            verify("seq { shift out0; eval_0 out0 with (in0 + 1u64 : Bool) }"),
+            Err(VerifyError::TypeMismatch { .. })
+        ));
+        assert!(matches!(
+            verify("seq { shift out0; eval_0 out0 with ite(true, in0, true) }"),
+            Err(VerifyError::TypeMismatch { .. })
+        ));
+        assert!(matches!(
+            verify("seq { shift out0; eval_0 out0 with (in0 << 1u8 : UInt(8)) }"),
+            Err(VerifyError::TypeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn pow_and_shifts() {
+        assert_eq!(
+            verify("seq { shift out0; eval_0 out0 with in0 ** 2u8 + (in0 << 1u8 >> 2u32) }"),
+            Ok(())
+        );
+    }
+}
@@ -3,10 +3,12 @@
 use std::time::Duration;
 
 use super::{
-    expressions::Expr, memory::Parameter, Guard, Origin, StreamReference, Type, WindowReference,
+    expressions::Expr, memory::Parameter, Guard, Origin, SourceSpan, StreamReference, Type,
+    WindowReference,
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// The representation of any window
 pub struct Window {
     /// The reference of the window
@@ -25,9 +27,12 @@ pub struct Window {
     pub kind: WindowKind,
     /// The return type of the window aggregation
     pub ty: Type,
+    /// The location of the clause the window is accessed in
+    pub span: SourceSpan,
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// The window operation
 pub enum WindowOperation {
     /// A sum aggregation
@@ -82,7 +87,8 @@ impl WindowOperation {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// The kind of a window
 pub enum WindowKind {
     /// The window is a sliding window
@@ -110,7 +116,8 @@ pub enum WindowKind {
     },
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// The instance selection of a instance aggregation
 pub enum InstanceSelection {
     /// All values are aggregated
@@ -15,13 +15,9 @@
 )]
 
 use formatter::StreamIrFormatter;
-use ir::{LoweringError, StreamIr};
-use rewrite_rules::{
-    CombineIf, CombineIterate, CombineNestedIf, CombineSeq, FastGuards, ImpliedGuards,
-    IterateAssign, MemoryOptimizations, MoveIfOutside, RemoveClose, RemoveIfs, RemoveShift,
-    RemoveSkip, RewriteError, RewriteRule, Rewriter, SimplifyGuard,
-};
-use rewrite_rules::{MoveCommonGuardsOutside, RemoveSpawn};
+use ir::{LoweringError, Spans, StreamIr};
+use pipeline::Pipeline;
+use rewrite_rules::{RemoveSkip, RewriteError, RewriteRule, Rewriter};
 pub use rtlola_frontend::ParserConfig;
 use rtlola_frontend::RtLolaError;
 pub use rtlola_frontend::{FrontendConfig, MemoryBoundMode, ParserConfigExt};
@@ -29,7 +25,9 @@ use thiserror::Error;
 
 pub mod formatter;
 pub mod ir;
+pub mod pipeline;
 pub mod rewrite_rules;
+pub mod work;
 
 #[derive(Clone, Debug, Error)]
 /// An error that can happen when parsing a specification into the StreamIR
@@ -52,35 +50,43 @@ pub fn parse<'a>(config: impl Into<FrontendConfig<'a>>) -> Result<StreamIr, Pars
     Ok(optimize(streamir, vec![Box::new(RemoveSkip)]).unwrap())
 }
 
-/// Applies all general optimizations to the given StreamIR.
+/// Parse a specification into the StreamIR representation and record the locations of its streams.
+///
+/// In contrast to [parse], the statements, windows and clause expressions of the resulting StreamIR carry the spans
+/// of the frontend and the StreamIR carries the [SourceMap](ir::SourceMap) of the specification,
+/// such that the backends can refer to the origin of a statement in the specification.
+pub fn parse_with_sources(config: &ParserConfig) -> Result<StreamIr, ParseError> {
+    parse_with_external_functions(config, &[])
+}
+
+/// Parse a specification into the StreamIR representation like [parse_with_sources], declaring the given external functions.
+///
+/// Calls of the functions named in `external_functions` are lowered to [Function::External](ir::expressions::Function::External),
+/// which the backends map to a native implementation.
+/// Calls of any other function that is not built into the StreamIR are rejected with [LoweringError::UnsupportedFunction].
+pub fn parse_with_external_functions(
+    config: &ParserConfig,
+    external_functions: &[String],
+) -> Result<StreamIr, ParseError> {
+    let ast = rtlola_frontend::parse_to_ast(config)?;
+    let spans = Spans::new(&ast, config.spec());
+    let mir = rtlola_frontend::parse(config)?;
+    let mut streamir = StreamIr::lower(mir, &spans, external_functions)?;
+    streamir.sources.file = config
+        .path()
+        .as_deref()
+        .map(|file| file.display().to_string());
+    Ok(optimize(streamir, vec![Box::new(RemoveSkip)]).unwrap())
+}
+
+/// Applies all general optimizations to the given StreamIR (the `all` preset of [Pipeline]).
 pub fn optimize_all(ir: StreamIr) -> Result<StreamIr, RewriteError> {
-    let ir = optimize(
-        ir,
-        vec![
-            Box::new(CombineIf),
-            Box::new(SimplifyGuard),
-            Box::new(MoveCommonGuardsOutside),
-            Box::new(ImpliedGuards),
-            Box::new(SimplifyGuard),
-            Box::new(RemoveIfs),
-            Box::new(CombineSeq),
-            Box::new(MoveIfOutside),
-            Box::new(IterateAssign),
-            Box::new(CombineNestedIf),
-            Box::new(CombineIterate),
-            Box::new(RemoveIfs),
-            Box::new(RemoveShift),
-            Box::new(MemoryOptimizations),
-            Box::new(RemoveSpawn),
-            Box::new(RemoveClose),
-        ],
-    )?;
-    optimize(ir, vec![Box::new(FastGuards)])
+    Pipeline::preset("all").unwrap().run(ir)
 }
 
 /// Applies a given list of rewriting rules to the StreamIR.
 pub fn optimize(ir: StreamIr, rules: Vec<Box<dyn RewriteRule>>) -> Result<StreamIr, RewriteError> {
-    let rewriter = Rewriter::new(rules);
+    let rewriter = Rewriter::try_new(rules)?;
     rewriter.run(ir)
 }
 
@@ -0,0 +1,301 @@
+//! Named sequences of rewriting rules.
+//!
+//! A [Pipeline] consists of stages, each a list of rewriting rules that are applied until a fixedpoint is reached.
+//! The stages are applied one after another.
+//! Pipelines can be given textually, where the rules are referred to by their name in a [RuleRegistry]:
+//! Rules of a stage are separated by `,`, stages by `;` or a newline, and `#` starts a comment.
+//! ```text
+//! # the rewriting rules
+//! CombineIf, SimplifyGuard, RemoveIfs
+//! # applied afterwards
+//! FastGuards
+//! ```
+//! Instead of a list of rules, the text can also be the name of a preset (see [Pipeline::PRESETS]).
+
+use std::{collections::HashMap, fmt::Display, path::Path, str::FromStr};
+
+use itertools::Itertools;
+use thiserror::Error;
+
+use crate::{
+    ir::StreamIr,
+    rewrite_rules::{
+        CombineIf, CombineIterate, CombineNestedIf, CombineSeq, CommonSubexpressionElimination,
+        ConstantFolding, EvaluateGuards, FastGuards, ImpliedGuards, IterateAssign,
+        LoopInvariantCodeMotion, MemoryOptimizations, MoveCommonGuardsOutside, MoveIfOutside,
+        RemoveClose, RemoveIfs, RemoveShift, RemoveSkip, RemoveSpawn, RewriteError, RewriteRule,
+        Rewriter, SimplifyGuard,
+    },
+};
+
+/// Constructs a rewriting rule registered in a [RuleRegistry]
+pub type RuleConstructor = fn() -> Box<dyn RewriteRule>;
+
+#[derive(Debug, Error)]
+/// An error when reading a [Pipeline]
+pub enum PipelineError {
+    #[error("unknown rewrite rule `{0}`")]
+    /// The pipeline refers to a rule that is not registered
+    UnknownRule(String),
+    #[error("reading the pipeline file failed: {0}")]
+    /// The pipeline file could not be read
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Debug, Clone)]
+/// Maps names to rewriting rules, such that they can be referred to in textual pipelines
+pub struct RuleRegistry(HashMap<String, RuleConstructor>);
+
+impl Default for RuleRegistry {
+    fn default() -> Self {
+        Self(HashMap::new())
+            .register("CombineIf", || Box::new(CombineIf))
+            .register("CombineIterate", || Box::new(CombineIterate))
+            .register("CombineNestedIf", || Box::new(CombineNestedIf))
+            .register("CombineSeq", || Box::new(CombineSeq))
+            .register("CommonSubexpressionElimination", || {
+                Box::new(CommonSubexpressionElimination)
+            })
+            .register("ConstantFolding", || Box::new(ConstantFolding))
+            .register("EvaluateEventGuards", || {
+                Box::new(EvaluateGuards::only_event_based())
+            })
+            .register("EvaluateTimeGuards", || {
+                Box::new(EvaluateGuards::only_time_based())
+            })
+            .register("FastGuards", || Box::new(FastGuards))
+            .register("ImpliedGuards", || Box::new(ImpliedGuards))
+            .register("IterateAssign", || Box::new(IterateAssign))
+            .register("LoopInvariantCodeMotion", || {
+                Box::new(LoopInvariantCodeMotion)
+            })
+            .register("MemoryOptimizations", || Box::new(MemoryOptimizations))
+            .register("MoveCommonGuardsOutside", || {
+                Box::new(MoveCommonGuardsOutside)
+            })
+            .register("MoveIfOutside", || Box::new(MoveIfOutside))
+            .register("RemoveClose", || Box::new(RemoveClose))
+            .register("RemoveIfs", || Box::new(RemoveIfs))
+            .register("RemoveShift", || Box::new(RemoveShift))
+            .register("RemoveSkip", || Box::new(RemoveSkip))
+            .register("RemoveSpawn", || Box::new(RemoveSpawn))
+            .register("SimplifyGuard", || Box::new(SimplifyGuard))
+    }
+}
+
+impl RuleRegistry {
+    /// Constructs a registry containing all rules of this crate that do not need arguments
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a rule under the given name, replacing a rule of the same name
+    pub fn register(mut self, name: impl Into<String>, constructor: RuleConstructor) -> Self {
+        self.0.insert(name.into(), constructor);
+        self
+    }
+
+    /// Returns the constructor of the rule with the given name
+    pub fn get(&self, name: &str) -> Option<RuleConstructor> {
+        self.0.get(name).copied()
+    }
+
+    /// Returns the names of all registered rules in alphabetical order
+    pub fn names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.0.keys().map(String::as_str).sorted()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+/// A sequence of stages of rewriting rules
+pub struct Pipeline {
+    stages: Vec<Vec<(String, RuleConstructor)>>,
+}
+
+impl Pipeline {
+    /// The names of the predefined pipelines together with their definition
+    pub const PRESETS: &'static [(&'static str, &'static str)] = &[
+        // no rewriting beyond the partial evaluation performed by the backends
+        ("partial-eval", ""),
+        (
+            "rewriting",
+            "CombineIf, SimplifyGuard, MoveCommonGuardsOutside, ImpliedGuards, SimplifyGuard, RemoveIfs, \
+             CombineSeq, MoveIfOutside, IterateAssign, CombineNestedIf, CombineIterate, RemoveIfs",
+        ),
+        (
+            "memory",
+            "RemoveShift, MemoryOptimizations, RemoveSpawn, RemoveClose",
+        ),
+        (
+            "all",
+            "ConstantFolding, CombineIf, SimplifyGuard, MoveCommonGuardsOutside, ImpliedGuards, SimplifyGuard, \
+             RemoveIfs, CombineSeq, MoveIfOutside, IterateAssign, LoopInvariantCodeMotion, CombineNestedIf, \
+             CombineIterate, RemoveIfs, RemoveShift, MemoryOptimizations, RemoveSpawn, RemoveClose; FastGuards",
+        ),
+        // the rules applied by the JIT interpreter without further optimizations
+        ("jit", "FastGuards"),
+        // the optimizations supported by the Solidity backend
+        (
+            "solidity",
+            "CombineIf, SimplifyGuard, MoveCommonGuardsOutside, ImpliedGuards, SimplifyGuard, RemoveIfs, \
+             CombineSeq, MoveIfOutside, IterateAssign, CombineNestedIf, CombineIterate, RemoveIfs, \
+             RemoveShift, MemoryOptimizations, RemoveSpawn, RemoveClose",
+        ),
+    ];
+
+    /// Returns the predefined pipeline with the given name
+    pub fn preset(name: &str) -> Option<Self> {
+        Self::PRESETS
+            .iter()
+            .find(|(preset, _)| *preset == name)
+            .map(|(_, definition)| {
+                Self::parse(definition, &RuleRegistry::default()).expect("invalid preset")
+            })
+    }
+
+    /// Parses a textual pipeline (or the name of a preset), looking up the rules in the given registry
+    pub fn parse(s: &str, registry: &RuleRegistry) -> Result<Self, PipelineError> {
+        if let Some(preset) = Self::preset(s.trim()) {
+            return Ok(preset);
+        }
+        let stages = s
+            .lines()
+            .map(|line| line.split_once('#').map_or(line, |(line, _comment)| line))
+            .flat_map(|line| line.split(';'))
+            .map(|stage| {
+                stage
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map(|name| {
+                        registry
+                            .get(name)
+                            .map(|constructor| (name.to_owned(), constructor))
+                            .ok_or_else(|| PipelineError::UnknownRule(name.to_owned()))
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .filter_ok(|stage| !stage.is_empty())
+            .collect::<Result<_, _>>()?;
+        Ok(Self { stages })
+    }
+
+    /// Reads a textual pipeline from the given file, looking up the rules in the given registry
+    pub fn from_path(
+        path: impl AsRef<Path>,
+        registry: &RuleRegistry,
+    ) -> Result<Self, PipelineError> {
+        let s = std::fs::read_to_string(path)?;
+        Self::parse(&s, registry)
+    }
+
+    /// Appends a stage consisting of the given rule
+    pub fn with_rule(mut self, name: impl Into<String>, constructor: RuleConstructor) -> Self {
+        self.stages.push(vec![(name.into(), constructor)]);
+        self
+    }
+
+    /// Returns the names of the rules of each stage
+    pub fn stages(&self) -> impl Iterator<Item = Vec<&str>> + '_ {
+        self.stages
+            .iter()
+            .map(|stage| stage.iter().map(|(name, _)| name.as_str()).collect())
+    }
+
+    /// Returns a [Rewriter] for each stage of the pipeline, or the error of constructing it (see [Rewriter::try_new])
+    pub fn rewriters(&self) -> impl Iterator<Item = Result<Rewriter, RewriteError>> + '_ {
+        self.stages.iter().map(|stage| {
+            Rewriter::try_new(stage.iter().map(|(_, constructor)| constructor()).collect())
+        })
+    }
+
+    /// Applies the stages of the pipeline one after another to the StreamIR
+    pub fn run(&self, ir: StreamIr) -> Result<StreamIr, RewriteError> {
+        self.rewriters()
+            .try_fold(ir, |ir, rewriter| rewriter?.run(ir))
+    }
+}
+
+impl FromStr for Pipeline {
+    type Err = PipelineError;
+
+    /// Parses a textual pipeline with the rules of the default registry.
+    ///
+    /// A string starting with `@` refers to a file containing the pipeline.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix('@') {
+            Some(path) => Self::from_path(path, &RuleRegistry::default()),
+            None => Self::parse(s, &RuleRegistry::default()),
+        }
+    }
+}
+
+impl Display for Pipeline {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            self.stages().map(|stage| stage.join(", ")).join("; ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ir::StreamIr, rewrite_rules::RemoveShift};
+
+    use super::{Pipeline, PipelineError, RuleRegistry};
+
+    #[test]
+    fn presets() {
+        for (name, definition) in Pipeline::PRESETS {
+            let preset = Pipeline::preset(name).unwrap();
+            let parsed: Pipeline = definition.parse().unwrap();
+            assert_eq!(preset.to_string(), parsed.to_string());
+            assert_eq!(
+                name.parse::<Pipeline>().unwrap().to_string(),
+                preset.to_string()
+            );
+        }
+        let all = Pipeline::preset("all").unwrap();
+        assert_eq!(all.stages().count(), 2);
+        assert_eq!(all.stages().nth(1).unwrap(), vec!["FastGuards"]);
+        assert_eq!(
+            Pipeline::preset("partial-eval").unwrap().stages().count(),
+            0
+        );
+    }
+
+    #[test]
+    fn parse() {
+        let text = "# first stage\n\
+        CombineIf, SimplifyGuard,\n\
+        RemoveIfs # second stage\n\
+        \n\
+        FastGuards; RemoveSkip";
+        let pipeline: Pipeline = text.parse().unwrap();
+        assert_eq!(
+            pipeline.to_string(),
+            "CombineIf, SimplifyGuard; RemoveIfs; FastGuards; RemoveSkip"
+        );
+        assert!(matches!(
+            "CombineIf, Unknown".parse::<Pipeline>(),
+            Err(PipelineError::UnknownRule(name)) if name == "Unknown"
+        ));
+    }
+
+    #[test]
+    fn registry() {
+        let registry = RuleRegistry::default().register("DropShifts", || Box::new(RemoveShift));
+        assert!(registry.names().any(|name| name == "DropShifts"));
+        let pipeline = Pipeline::parse("DropShifts", &registry).unwrap();
+        let ir = StreamIr::from_text(
+            "input in0 a: UInt(64) = static(single);
+output out0 b: UInt(64) = static(single);
+stmt seq { input in0; shift out0; eval_0 out0 with in0 }",
+        )
+        .unwrap();
+        let ir = pipeline.run(ir).unwrap();
+        assert!(!ir.to_text().contains("shift"));
+    }
+}
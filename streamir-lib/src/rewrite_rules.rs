@@ -4,13 +4,15 @@
 use std::{
     collections::{HashMap, HashSet, VecDeque},
     ops::{Add, AddAssign},
+    time::{Duration, Instant},
 };
 
 use thiserror::Error;
 
 use crate::ir::{
+    expressions::{Expr, ExprKind},
     memory::{Memory, StreamBuffer, StreamMemory},
-    Guard, IfStmt, LivetimeEquivalences, Stmt, StreamIr, StreamReference,
+    Guard, IfStmt, LivetimeEquivalences, Stmt, StreamIr, StreamReference, VerifyError,
 };
 mod common_guards_outside;
 pub use common_guards_outside::MoveCommonGuardsOutside;
@@ -26,14 +28,24 @@ mod combine_iterate;
 pub use combine_iterate::CombineIterate;
 mod combine_seq;
 pub use combine_seq::CombineSeq;
+mod constant_folding;
+pub use constant_folding::ConstantFolding;
+mod cse;
+pub use cse::CommonSubexpressionElimination;
+mod dead_streams;
+pub use dead_streams::DeadStreamElimination;
 mod fast_guards;
 pub use fast_guards::FastGuards;
 mod if_outside;
 pub use if_outside::MoveIfOutside;
 mod implied_guards;
 pub use implied_guards::ImpliedGuards;
+mod licm;
+pub use licm::LoopInvariantCodeMotion;
 mod memory_optimizations;
 pub use memory_optimizations::MemoryOptimizations;
+mod narrow_integers;
+pub use narrow_integers::NarrowIntegers;
 mod nested_ifs;
 pub use nested_ifs::CombineNestedIf;
 mod partial_evaluation;
@@ -46,6 +58,8 @@ mod simplify_guard;
 pub use simplify_guard::SimplifyGuard;
 mod skip;
 pub use skip::RemoveSkip;
+mod specialize_inputs;
+pub use specialize_inputs::{SpecializeError, SpecializeInputs};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 /// A global change that can be a side effect from a rewriting rule
@@ -53,6 +67,8 @@ enum GlobalChangeInstruction {
     /// A change to memory
     #[allow(dead_code)]
     ReplaceMemory(StreamReference, Memory),
+    /// The removal of output streams, renumbering the remaining streams
+    RemoveStreams(Vec<StreamReference>),
 }
 
 impl GlobalChangeInstruction {
@@ -61,6 +77,9 @@ impl GlobalChangeInstruction {
             GlobalChangeInstruction::ReplaceMemory(sr, memory) => {
                 *ir.sr2memory.get_mut(&sr).unwrap() = memory
             }
+            GlobalChangeInstruction::RemoveStreams(streams) => {
+                dead_streams::remove_streams(ir, &streams)
+            }
         }
     }
 }
@@ -77,7 +96,8 @@ pub struct ChangeSet {
 }
 
 impl ChangeSet {
-    fn local_change() -> ChangeSet {
+    /// A change set recording a local change of the rewritten construct without global changes
+    pub fn local_change() -> ChangeSet {
         ChangeSet {
             local_change: true,
             global_instructions: HashSet::new(),
@@ -119,11 +139,37 @@ pub enum RewriteError {
     #[error("other error: {0}")]
     /// An error that does not fit any of the other categories
     Other(String),
+    #[error("no fixedpoint was reached after {0} rounds")]
+    /// The rewriting did not reach a fixedpoint within the maximal number of rounds
+    MaxRoundsExceeded(usize),
+    #[error("the cleanup rules of the rewrite rules expand into more than {0} rules")]
+    /// The cleanup rules of the rules keep requiring further cleanup rules, possibly infinitely
+    CleanupExpansion(usize),
+    #[error("the StreamIR violates an invariant before rewriting: {0}")]
+    /// The StreamIR passed to the rewriter violates one of its invariants
+    InvalidInput(VerifyError),
+    #[error("the rewrite rule {rule} broke an invariant of the StreamIR: {error}")]
+    /// A rewriting rule produced a StreamIR that violates one of its invariants
+    Verification {
+        /// The rewriting rule that was applied last
+        rule: String,
+        /// The violated invariant
+        error: VerifyError,
+    },
 }
 
 /// A trait representing a rewriting rule
 /// Desribes rewriting to different parts of the StreamIR.
 pub trait RewriteRule: std::fmt::Debug {
+    /// The name of the rule, as shown in reports and errors.
+    ///
+    /// Defaults to the name of the type implementing the rule.
+    fn name(&self) -> String {
+        let path = std::any::type_name::<Self>();
+        let path = path.split('<').next().unwrap_or(path);
+        path.rsplit("::").next().unwrap_or(path).to_owned()
+    }
+
     /// Rewrite a statement.
     /// Is called recursively for all children automatically.
     fn rewrite_stmt(
@@ -146,6 +192,17 @@ pub trait RewriteRule: std::fmt::Debug {
         Ok((guard, ChangeSet::default()))
     }
 
+    /// Rewrite a stream expression.
+    /// Is called recursively for all children automatically.
+    fn rewrite_expr(
+        &self,
+        expr: Expr,
+        _memory: &HashMap<StreamReference, Memory>,
+        _liveness_equivalences: &LivetimeEquivalences,
+    ) -> Result<(Expr, ChangeSet), RewriteError> {
+        Ok((expr, ChangeSet::default()))
+    }
+
     /// Rewrites the memory of a stream.
     fn rewrite_memory(
         &self,
@@ -174,12 +231,49 @@ pub trait RewriteRule: std::fmt::Debug {
     ) -> Result<(Stmt, ChangeSet), RewriteError> {
         let mut cs = ChangeSet::default();
         stmt = match stmt {
-            old @ (Stmt::Skip
-            | Stmt::Input(_)
-            | Stmt::Shift(_)
-            | Stmt::Spawn { .. }
-            | Stmt::Eval { .. }
-            | Stmt::Close { .. }) => old,
+            old @ (Stmt::Skip | Stmt::Input(_) | Stmt::Shift(_) | Stmt::Close { .. }) => old,
+            Stmt::Spawn {
+                sr,
+                with,
+                local_frequencies,
+                windows,
+                span,
+            } => {
+                let with = with
+                    .map(|with| {
+                        with.into_iter()
+                            .map(|expr| {
+                                let (expr, c) =
+                                    self.apply_expr(expr, memory, livetime_equivalences)?;
+                                cs += c;
+                                Ok(expr)
+                            })
+                            .collect::<Result<_, _>>()
+                    })
+                    .transpose()?;
+                Stmt::Spawn {
+                    sr,
+                    with,
+                    local_frequencies,
+                    windows,
+                    span,
+                }
+            }
+            Stmt::Eval {
+                sr,
+                with,
+                idx,
+                span,
+            } => {
+                let (with, c) = self.apply_expr(with, memory, livetime_equivalences)?;
+                cs += c;
+                Stmt::Eval {
+                    sr,
+                    with,
+                    idx,
+                    span,
+                }
+            }
             Stmt::Seq(stmts) => {
                 let inner = stmts
                     .into_iter()
@@ -226,6 +320,14 @@ pub trait RewriteRule: std::fmt::Debug {
                 sr,
                 stmt,
             } => {
+                let parameter_expr = parameter_expr
+                    .into_iter()
+                    .map(|expr| {
+                        let (expr, c) = self.apply_expr(expr, memory, livetime_equivalences)?;
+                        cs += c;
+                        Ok(expr)
+                    })
+                    .collect::<Result<_, _>>()?;
                 let (stmt, c) = self.apply_stmt(*stmt, memory, livetime_equivalences)?;
                 cs += c;
                 Stmt::Assign {
@@ -234,6 +336,17 @@ pub trait RewriteRule: std::fmt::Debug {
                     stmt: Box::new(stmt),
                 }
             }
+            Stmt::Let { var, expr, stmt } => {
+                let (expr, c) = self.apply_expr(expr, memory, livetime_equivalences)?;
+                cs += c;
+                let (stmt, c) = self.apply_stmt(*stmt, memory, livetime_equivalences)?;
+                cs += c;
+                Stmt::Let {
+                    var,
+                    expr,
+                    stmt: Box::new(stmt),
+                }
+            }
         };
         let (new_stmt, cur_cs) = self.rewrite_stmt(stmt, memory, livetime_equivalences)?;
         stmt = new_stmt;
@@ -269,6 +382,11 @@ pub trait RewriteRule: std::fmt::Debug {
                     rhs: Box::new(rhs),
                 }
             }
+            Guard::Dynamic(expr) => {
+                let (expr, c) = self.apply_expr(expr, memory, livetime_equivalences)?;
+                cs += c;
+                Guard::Dynamic(expr)
+            }
             other => other,
         };
 
@@ -279,6 +397,141 @@ pub trait RewriteRule: std::fmt::Debug {
         Ok((guard, cs))
     }
 
+    /// Rewrites the top level expression.
+    /// Is NOT called automatically for all children.
+    fn apply_expr(
+        &self,
+        expr: Expr,
+        memory: &HashMap<StreamReference, Memory>,
+        livetime_equivalences: &LivetimeEquivalences,
+    ) -> Result<(Expr, ChangeSet), RewriteError> {
+        let mut cs = ChangeSet::default();
+        let Expr { ty, kind, span } = expr;
+        let mut apply = |expr: Expr| -> Result<Expr, RewriteError> {
+            let (expr, c) = self.apply_expr(expr, memory, livetime_equivalences)?;
+            cs += c;
+            Ok(expr)
+        };
+        let kind = match kind {
+            old @ (ExprKind::Constant(_)
+            | ExprKind::ParameterAccess(_, _)
+            | ExprKind::LambdaParameterAccess(_, _)
+            | ExprKind::Variable(_)) => old,
+            ExprKind::BinaryOperation(op, lhs, rhs) => {
+                ExprKind::BinaryOperation(op, Box::new(apply(*lhs)?), Box::new(apply(*rhs)?))
+            }
+            ExprKind::UnaryOperation(op, inner) => {
+                ExprKind::UnaryOperation(op, Box::new(apply(*inner)?))
+            }
+            ExprKind::Ite(cond, cons, alt) => ExprKind::Ite(
+                Box::new(apply(*cond)?),
+                Box::new(apply(*cons)?),
+                Box::new(apply(*alt)?),
+            ),
+            ExprKind::SyncStreamAccess { target, parameters } => ExprKind::SyncStreamAccess {
+                target,
+                parameters: parameters
+                    .into_iter()
+                    .map(&mut apply)
+                    .collect::<Result<_, _>>()?,
+            },
+            ExprKind::OffsetStreamAccess {
+                target,
+                offset,
+                default,
+                parameters,
+            } => ExprKind::OffsetStreamAccess {
+                target,
+                offset,
+                default: default
+                    .map(|default| apply(*default).map(Box::new))
+                    .transpose()?,
+                parameters: parameters
+                    .into_iter()
+                    .map(&mut apply)
+                    .collect::<Result<_, _>>()?,
+            },
+            ExprKind::HoldStreamAccess {
+                target,
+                default,
+                parameters,
+            } => ExprKind::HoldStreamAccess {
+                target,
+                default: default
+                    .map(|default| apply(*default).map(Box::new))
+                    .transpose()?,
+                parameters: parameters
+                    .into_iter()
+                    .map(&mut apply)
+                    .collect::<Result<_, _>>()?,
+            },
+            ExprKind::IsFresh { target, parameters } => ExprKind::IsFresh {
+                target,
+                parameters: parameters
+                    .into_iter()
+                    .map(&mut apply)
+                    .collect::<Result<_, _>>()?,
+            },
+            ExprKind::GetAccess {
+                target,
+                default,
+                parameters,
+            } => ExprKind::GetAccess {
+                target,
+                default: default
+                    .map(|default| apply(*default).map(Box::new))
+                    .transpose()?,
+                parameters: parameters
+                    .into_iter()
+                    .map(&mut apply)
+                    .collect::<Result<_, _>>()?,
+            },
+            ExprKind::WindowAccess {
+                target,
+                window,
+                parameters,
+                default,
+            } => ExprKind::WindowAccess {
+                target,
+                window,
+                parameters: parameters
+                    .into_iter()
+                    .map(&mut apply)
+                    .collect::<Result<_, _>>()?,
+                default: default
+                    .map(|default| apply(*default).map(Box::new))
+                    .transpose()?,
+            },
+            ExprKind::Cast(ty, inner) => ExprKind::Cast(ty, Box::new(apply(*inner)?)),
+            ExprKind::FunctionCall(function, args) => ExprKind::FunctionCall(
+                function,
+                args.into_iter().map(&mut apply).collect::<Result<_, _>>()?,
+            ),
+            ExprKind::Tuple(elements) => ExprKind::Tuple(
+                elements
+                    .into_iter()
+                    .map(&mut apply)
+                    .collect::<Result<_, _>>()?,
+            ),
+            ExprKind::TupleAccess(inner, idx) => {
+                ExprKind::TupleAccess(Box::new(apply(*inner)?), idx)
+            }
+            ExprKind::Default { expr, default } => ExprKind::Default {
+                expr: Box::new(apply(*expr)?),
+                default: Box::new(apply(*default)?),
+            },
+        };
+
+        let (mut expr, cur_cs) =
+            self.rewrite_expr(Expr { ty, kind, span }, memory, livetime_equivalences)?;
+        if expr.span.0.is_none() {
+            // a rewritten clause expression remains located at its clause
+            expr.span = span;
+        }
+        cs += cur_cs;
+        Ok((expr, cs))
+    }
+
     /// Rewrites the whole memory.
     fn apply_memory(
         &self,
@@ -288,8 +541,8 @@ pub trait RewriteRule: std::fmt::Debug {
         let mut cs = ChangeSet::default();
         let new_memory = memory
             .into_iter()
-            .map(|(sr, Memory { buffer, ty, name })| {
-                let (new_buffer, cur_cs) = self.rewrite_memory(sr, buffer)?;
+            .map(|(sr, memory)| {
+                let (new_buffer, cur_cs) = self.rewrite_memory(sr, memory.buffer)?;
                 let new_buffer = match new_buffer {
                     StreamMemory::NoMemory => StreamMemory::NoMemory,
                     StreamMemory::Static(buffer) => {
@@ -310,12 +563,17 @@ pub trait RewriteRule: std::fmt::Debug {
                             has_close,
                         }
                     }
-                    StreamMemory::Instances { buffer, parameter } => {
+                    StreamMemory::Instances {
+                        buffer,
+                        parameter,
+                        bound,
+                    } => {
                         let (new_buffer, cur_cs) = self.rewrite_buffer(sr, buffer)?;
                         cs += cur_cs;
                         StreamMemory::Instances {
                             buffer: new_buffer,
                             parameter,
+                            bound,
                         }
                     }
                 };
@@ -324,8 +582,7 @@ pub trait RewriteRule: std::fmt::Debug {
                     sr,
                     Memory {
                         buffer: new_buffer,
-                        ty,
-                        name,
+                        ..memory
                     },
                 ))
             })
@@ -339,15 +596,44 @@ pub trait RewriteRule: std::fmt::Debug {
     }
 }
 
-#[derive(Debug)]
+/// A callback that receives the name of a rewriting rule together with the StreamIR before and after its application
+pub type SnapshotCallback = Box<dyn Fn(&str, &StreamIr, &StreamIr)>;
+
 /// A rewriter that holds a set of rewriting rules and can apply them to StreamIR's.
 pub struct Rewriter {
     rules: Vec<Box<dyn RewriteRule>>,
+    verify: bool,
+    max_rounds: usize,
+    snapshot: Option<SnapshotCallback>,
+}
+
+impl std::fmt::Debug for Rewriter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Rewriter")
+            .field("rules", &self.rules)
+            .field("verify", &self.verify)
+            .field("max_rounds", &self.max_rounds)
+            .field("snapshot", &self.snapshot.is_some())
+            .finish()
+    }
 }
 
+/// The number of rounds after which [Rewriter::run] gives up reaching a fixedpoint, unless set by [Rewriter::with_max_rounds]
+pub const DEFAULT_MAX_ROUNDS: usize = 1000;
+
 impl Rewriter {
     /// Construct a new Rewriter with the given rules.
+    ///
+    /// # Panics
+    /// Panics if the cleanup rules of the rules expand into more than ten times the number of given rules (see [Rewriter::try_new]).
     pub fn new(rules: Vec<Box<dyn RewriteRule>>) -> Self {
+        Self::try_new(rules).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Construct a new Rewriter with the given rules and their cleanup rules.
+    ///
+    /// Fails with [RewriteError::CleanupExpansion] if the cleanup rules of the rules expand into more than ten times the number of given rules.
+    pub fn try_new(rules: Vec<Box<dyn RewriteRule>>) -> Result<Self, RewriteError> {
         let original_length = rules.len();
         let mut stack = rules
             .into_iter()
@@ -359,26 +645,102 @@ impl Rewriter {
             rules.push(rule);
             stack.extend(cleanup);
             if rules.len() > original_length * 10 {
-                panic!("possible infinite loop in rewrite rule expansion")
+                return Err(RewriteError::CleanupExpansion(original_length * 10));
             }
         }
-        Self { rules }
+        Ok(Self {
+            rules,
+            verify: false,
+            max_rounds: DEFAULT_MAX_ROUNDS,
+            snapshot: None,
+        })
+    }
+
+    /// Sets whether the StreamIR is verified after each application of a rule (see [StreamIr::verify]).
+    ///
+    /// Verification is disabled by default.
+    /// The StreamIR passed to [Rewriter::run] is verified as well, such that a broken invariant is blamed on the rule that broke it.
+    pub fn with_verification(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    /// Limits the number of rounds in which all rules are applied, which is [DEFAULT_MAX_ROUNDS] by default.
+    ///
+    /// If no fixedpoint is reached after the given number of rounds, [Rewriter::run] returns [RewriteError::MaxRoundsExceeded].
+    pub fn with_max_rounds(mut self, max_rounds: usize) -> Self {
+        self.max_rounds = max_rounds;
+        self
+    }
+
+    /// Calls the given callback after each application of a rule that changed the StreamIR.
+    ///
+    /// The callback receives the name of the rule and the StreamIR before and after the application.
+    pub fn with_snapshots(
+        mut self,
+        callback: impl Fn(&str, &StreamIr, &StreamIr) + 'static,
+    ) -> Self {
+        self.snapshot = Some(Box::new(callback));
+        self
+    }
+
+    /// Run the rewriting on the given StreamIR until a fixedpoint is reached.
+    pub fn run(&self, ir: StreamIr) -> Result<StreamIr, RewriteError> {
+        self.run_with_report(ir).map(|(ir, _report)| ir)
     }
 
     /// Run the rewriting on the given StreamIR until a fixedpoint is reached.
-    pub fn run(&self, mut ir: StreamIr) -> Result<StreamIr, RewriteError> {
+    /// Additionally returns statistics on the application of each rule.
+    pub fn run_with_report(
+        &self,
+        mut ir: StreamIr,
+    ) -> Result<(StreamIr, RewriteReport), RewriteError> {
+        let mut report = RewriteReport {
+            rounds: 0,
+            rules: self
+                .rules
+                .iter()
+                .map(|rule| RuleStatistics {
+                    name: rule.name(),
+                    applications: 0,
+                    changes: 0,
+                    time: Duration::ZERO,
+                })
+                .collect(),
+        };
+        if self.verify {
+            ir.verify().map_err(RewriteError::InvalidInput)?;
+        }
         let mut changed = true;
         while changed {
-            (ir, changed) = self.apply(ir)?;
+            if report.rounds >= self.max_rounds {
+                return Err(RewriteError::MaxRoundsExceeded(report.rounds));
+            }
+            (ir, changed) = self.apply_round(ir, &mut report, self.verify)?;
         }
-        Ok(ir)
+        Ok((ir, report))
     }
 
     /// Apply all rewriting rules once. Returns the resulting StreamIR and a boolean indicating
     /// whether something changed for any of the rules.
-    fn apply(&self, mut ir: StreamIr) -> Result<(StreamIr, bool), RewriteError> {
+    ///
+    /// The StreamIR is not verified, regardless of [Rewriter::with_verification].
+    fn apply(&self, ir: StreamIr) -> Result<(StreamIr, bool), RewriteError> {
+        let mut report = RewriteReport::default();
+        self.apply_round(ir, &mut report, false)
+    }
+
+    fn apply_round(
+        &self,
+        mut ir: StreamIr,
+        report: &mut RewriteReport,
+        verify: bool,
+    ) -> Result<(StreamIr, bool), RewriteError> {
+        report.rounds += 1;
         let mut changed = false;
-        for rule in &self.rules {
+        for (i, rule) in self.rules.iter().enumerate() {
+            let start = Instant::now();
+            let before = self.snapshot.as_ref().map(|_| ir.clone());
             let StreamIr {
                 stmt,
                 sr2memory,
@@ -389,6 +751,8 @@ impl Rewriter {
                 triggers,
                 accesses,
                 accessed_by,
+                sources,
+                overflow,
             } = ir;
 
             let (sr2memory, cs_memory) = rule.apply_memory(sr2memory, &livetime_equivalences)?;
@@ -404,16 +768,225 @@ impl Rewriter {
                 triggers,
                 accesses,
                 accessed_by,
+                sources,
+                overflow,
             };
             let ChangeSet {
                 local_change,
                 global_instructions,
             } = cs_memory + cs_stmt;
-            changed |= local_change || !global_instructions.is_empty();
+            let rule_changed = local_change || !global_instructions.is_empty();
+            changed |= rule_changed;
             for i in global_instructions {
                 i.apply(&mut ir);
             }
+            if let Some(statistics) = report.rules.get_mut(i) {
+                statistics.applications += 1;
+                statistics.changes += usize::from(rule_changed);
+                statistics.time += start.elapsed();
+            }
+            if let (Some(callback), Some(before)) = (&self.snapshot, before) {
+                if rule_changed {
+                    callback(&rule.name(), &before, &ir);
+                }
+            }
+            if verify {
+                ir.verify().map_err(|error| RewriteError::Verification {
+                    rule: rule.name(),
+                    error,
+                })?;
+            }
         }
         Ok((ir, changed))
     }
 }
+
+#[derive(Debug, Clone, Default)]
+/// Statistics on a run of the [Rewriter]
+pub struct RewriteReport {
+    /// The number of rounds in which all rules were applied
+    pub rounds: usize,
+    /// The statistics of each rule, in the order of application
+    pub rules: Vec<RuleStatistics>,
+}
+
+#[derive(Debug, Clone)]
+/// Statistics on the application of a single rewriting rule
+pub struct RuleStatistics {
+    /// The name of the rule
+    pub name: String,
+    /// The number of times the rule was applied
+    pub applications: usize,
+    /// The number of applications that changed the StreamIR
+    pub changes: usize,
+    /// The total time spent applying the rule
+    pub time: Duration,
+}
+
+impl std::fmt::Display for RewriteReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} rounds", self.rounds)?;
+        for RuleStatistics {
+            name,
+            applications,
+            changes,
+            time,
+        } in &self.rules
+        {
+            writeln!(
+                f,
+                "{name}: {applications} applications, {changes} changes, {time:?}"
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+    use crate::ir::{
+        memory::Memory, LivetimeEquivalences, Stmt, StreamIr, StreamReference, VerifyError,
+    };
+
+    use super::{ChangeSet, RemoveSkip, RewriteError, RewriteRule, Rewriter};
+
+    const TEXT: &str = "input in0 a: UInt(64) = static(single);
+output out0 b: UInt(64) = static(bounded(2));
+stmt seq { shift out0; eval_0 out0 with in0 }";
+
+    #[derive(Debug)]
+    struct DropShifts;
+
+    impl RewriteRule for DropShifts {
+        fn rewrite_stmt(
+            &self,
+            stmt: Stmt,
+            _memory: &HashMap<StreamReference, Memory>,
+            _liveness_equivalences: &LivetimeEquivalences,
+        ) -> Result<(Stmt, ChangeSet), RewriteError> {
+            match stmt {
+                Stmt::Shift(_) => Ok((Stmt::Skip, ChangeSet::local_change())),
+                other => Ok((other, ChangeSet::default())),
+            }
+        }
+    }
+
+    #[derive(Debug)]
+    struct NeverDone;
+
+    impl RewriteRule for NeverDone {
+        fn rewrite_stmt(
+            &self,
+            stmt: Stmt,
+            _memory: &HashMap<StreamReference, Memory>,
+            _liveness_equivalences: &LivetimeEquivalences,
+        ) -> Result<(Stmt, ChangeSet), RewriteError> {
+            Ok((stmt, ChangeSet::local_change()))
+        }
+    }
+
+    #[test]
+    fn rewriter_reports_rule() {
+        let ir = StreamIr::from_text(TEXT).unwrap();
+        let rewriter = Rewriter::new(vec![Box::new(DropShifts)]).with_verification(true);
+        match rewriter.run(ir) {
+            Err(RewriteError::Verification { rule, error }) => {
+                assert_eq!(rule, "DropShifts");
+                assert_eq!(error, VerifyError::EvalWithoutShift("b".into()));
+            }
+            other => panic!("expected a verification error, found {other:?}"),
+        }
+    }
+
+    #[test]
+    fn rewriter_reports_invalid_input() {
+        let ir = StreamIr::from_text(TEXT).unwrap();
+        let ir = Rewriter::new(vec![Box::new(DropShifts)])
+            .with_verification(false)
+            .run(ir)
+            .unwrap();
+        let rewriter = Rewriter::new(vec![Box::new(RemoveSkip)]).with_verification(true);
+        assert!(matches!(
+            rewriter.run(ir),
+            Err(RewriteError::InvalidInput(VerifyError::EvalWithoutShift(name))) if name == "b"
+        ));
+    }
+
+    #[test]
+    fn report() {
+        let ir = StreamIr::from_text(TEXT).unwrap();
+        let snapshots = Rc::new(RefCell::new(Vec::new()));
+        let rewriter = Rewriter::new(vec![Box::new(DropShifts), Box::new(RemoveSkip)])
+            .with_verification(false)
+            .with_snapshots({
+                let snapshots = snapshots.clone();
+                move |rule, before, after| {
+                    snapshots.borrow_mut().push((
+                        rule.to_owned(),
+                        before.stmt.clone(),
+                        after.stmt.clone(),
+                    ))
+                }
+            });
+        let (ir, report) = rewriter.run_with_report(ir).unwrap();
+        assert!(!ir.to_text().contains("shift"));
+        assert_eq!(report.rounds, 2);
+        let counts = report
+            .rules
+            .iter()
+            .map(|r| (r.name.as_str(), r.applications, r.changes))
+            .collect::<Vec<_>>();
+        assert_eq!(counts, [("DropShifts", 2, 1), ("RemoveSkip", 2, 1)]);
+        let snapshots = snapshots.borrow();
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[0].0, "DropShifts");
+        assert_eq!(snapshots[0].2, snapshots[1].1);
+    }
+
+    #[derive(Debug)]
+    struct EndlessCleanup;
+
+    impl RewriteRule for EndlessCleanup {
+        fn rewrite_stmt(
+            &self,
+            stmt: Stmt,
+            _memory: &HashMap<StreamReference, Memory>,
+            _liveness_equivalences: &LivetimeEquivalences,
+        ) -> Result<(Stmt, ChangeSet), RewriteError> {
+            Ok((stmt, ChangeSet::default()))
+        }
+
+        fn cleanup_rules(&self) -> Vec<Box<dyn RewriteRule>> {
+            vec![Box::new(EndlessCleanup)]
+        }
+    }
+
+    #[test]
+    fn cleanup_expansion() {
+        assert!(matches!(
+            Rewriter::try_new(vec![Box::new(EndlessCleanup)]),
+            Err(RewriteError::CleanupExpansion(10))
+        ));
+    }
+
+    #[test]
+    fn default_max_rounds() {
+        let ir = StreamIr::from_text(TEXT).unwrap();
+        assert!(matches!(
+            Rewriter::new(vec![Box::new(NeverDone)]).run(ir),
+            Err(RewriteError::MaxRoundsExceeded(super::DEFAULT_MAX_ROUNDS))
+        ));
+    }
+
+    #[test]
+    fn max_rounds() {
+        let ir = StreamIr::from_text(TEXT).unwrap();
+        let rewriter = Rewriter::new(vec![Box::new(NeverDone)]).with_max_rounds(5);
+        assert!(matches!(
+            rewriter.run(ir),
+            Err(RewriteError::MaxRoundsExceeded(5))
+        ));
+    }
+}
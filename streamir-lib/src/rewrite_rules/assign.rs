@@ -107,6 +107,7 @@ impl Guard {
             Guard::Dynamic(Expr {
                 kind: ExprKind::BinaryOperation(Operator::Eq, lhs, rhs),
                 ty,
+                span,
             }) => match (*lhs, *rhs) {
                 (
                     Expr {
@@ -125,6 +126,7 @@ impl Guard {
                 (lhs, rhs) => Err(Guard::Dynamic(Expr {
                     kind: ExprKind::BinaryOperation(Operator::Eq, Box::new(lhs), Box::new(rhs)),
                     ty,
+                    span,
                 })),
             },
             Guard::Dynamic(_) => Err(self),
@@ -181,7 +183,7 @@ mod tests {
                 fi
         ",
         );
-        let rewriter = Rewriter::new(vec![Box::new(IterateAssign {})]);
+        let rewriter = Rewriter::new(vec![Box::new(IterateAssign {})]).with_verification(true);
         let (res, changed) = rewriter.apply(ir.clone()).unwrap();
         assert!(!changed);
         assert!(res.stmt.eq(&ir.stmt));
@@ -209,7 +211,8 @@ mod tests {
             Box::new(SimplifyGuard {}),
             Box::new(RemoveIfs {}),
             Box::new(RemoveSkip {}),
-        ]);
+        ])
+        .with_verification(true);
         let (res, changed) = rewriter.apply(ir).unwrap();
         assert!(changed);
         assert!(res.stmt.eq(&reference.stmt));
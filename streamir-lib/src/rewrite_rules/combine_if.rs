@@ -3,18 +3,20 @@ use std::collections::HashMap;
 use itertools::Itertools;
 
 use crate::{
-    ir::{memory::Memory, Guard, IfStmt, LivetimeEquivalences, Stmt, StreamReference},
+    ir::{
+        memory::Memory, Bdd, Guard, GuardBdd, IfStmt, LivetimeEquivalences, Stmt, StreamReference,
+    },
     rewrite_rules::{CombineSeq, RemoveSkip},
 };
 
 use super::{ChangeSet, RewriteError, RewriteRule};
 
 #[derive(Debug, Clone, Copy)]
-/// A rewriting rule combining if conditions in sequences/parallel that have the same guard condition
+/// A rewriting rule combining if conditions in sequences/parallel that have equivalent guard conditions
 pub struct CombineIf;
 
 impl CombineIf {
-    fn apply(lhs: Stmt, rhs: Stmt) -> Result<Stmt, (Stmt, Stmt)> {
+    fn apply(lhs: Stmt, rhs: Stmt, bdd: &mut GuardBdd) -> Result<Stmt, (Stmt, Stmt)> {
         match (lhs, rhs) {
             (
                 Stmt::If(IfStmt {
@@ -27,7 +29,7 @@ impl CombineIf {
                     cons: rhs_cons,
                     alt: rhs_alt,
                 }),
-            ) if lhs_g.eq(&rhs_g) => Ok(Stmt::If(IfStmt {
+            ) if bdd.from_guard(&lhs_g) == bdd.from_guard(&rhs_g) => Ok(Stmt::If(IfStmt {
                 guard: lhs_g,
                 cons: Box::new(Stmt::seq([*lhs_cons, *rhs_cons])),
                 alt: Box::new(Stmt::seq([*lhs_alt, *rhs_alt])),
@@ -44,10 +46,21 @@ impl RewriteRule for CombineIf {
         _memory: &HashMap<StreamReference, Memory>,
         liveness_equivalences: &LivetimeEquivalences,
     ) -> Result<(Stmt, ChangeSet), RewriteError> {
+        let mut bdd = GuardBdd::new(liveness_equivalences);
         match stmt {
             Stmt::Seq(inner) => {
                 let old_length = inner.len();
-                let inner: Vec<Stmt> = inner.into_iter().coalesce(Self::apply).collect();
+                let mut combined: Vec<Stmt> = Vec::with_capacity(old_length);
+                for stmt in inner {
+                    match combined.pop() {
+                        Some(last) => match Self::apply(last, stmt, &mut bdd) {
+                            Ok(stmt) => combined.push(stmt),
+                            Err((last, stmt)) => combined.extend([last, stmt]),
+                        },
+                        None => combined.push(stmt),
+                    }
+                }
+                let inner = combined;
                 let cs = if old_length == inner.len() {
                     ChangeSet::default()
                 } else {
@@ -66,22 +79,22 @@ impl RewriteRule for CombineIf {
                 let num_guarded = guarded.len();
                 let guarded = guarded.into_iter().fold(
                     Vec::new(),
-                    |mut guarded: Vec<(Guard, Vec<Stmt>, Vec<Stmt>)>, (guard, cons, alt)| {
-                        if let Some((_, conss, alts)) = guarded
-                            .iter_mut()
-                            .find(|(g, _, _)| g.eq_liveness(&guard, liveness_equivalences))
+                    |mut guarded: Vec<(Bdd, Guard, Vec<Stmt>, Vec<Stmt>)>, (guard, cons, alt)| {
+                        let guard_bdd = bdd.from_guard(&guard);
+                        if let Some((_, _, conss, alts)) =
+                            guarded.iter_mut().find(|(g, _, _, _)| *g == guard_bdd)
                         {
                             conss.push(*cons);
                             alts.push(*alt);
                         } else {
-                            guarded.push((guard, vec![*cons], vec![*alt]));
+                            guarded.push((guard_bdd, guard, vec![*cons], vec![*alt]));
                         }
                         guarded
                     },
                 );
                 let guarded: Vec<Stmt> = guarded
                     .into_iter()
-                    .map(|(g, cons, alt)| {
+                    .map(|(_, g, cons, alt)| {
                         Stmt::If(IfStmt {
                             guard: g,
                             cons: Box::new(Stmt::parallel(cons)),
@@ -134,7 +147,7 @@ mod tests {
             fi
         ",
         );
-        let rewriter = Rewriter::new(vec![Box::new(CombineIf {})]);
+        let rewriter = Rewriter::new(vec![Box::new(CombineIf {})]).with_verification(true);
         let (res, changed) = rewriter.apply(ir).unwrap();
         assert!(changed);
         assert!(res.stmt.eq(&reference.stmt));
@@ -161,7 +174,34 @@ mod tests {
             fi
         ",
         );
-        let rewriter = Rewriter::new(vec![Box::new(CombineIf {})]);
+        let rewriter = Rewriter::new(vec![Box::new(CombineIf {})]).with_verification(true);
+        let (res, changed) = rewriter.apply(ir).unwrap();
+        assert!(changed);
+        assert!(res.stmt.eq(&reference.stmt));
+    }
+
+    #[test]
+    fn equivalent_guards() {
+        let ir = parse_ir(
+            "par {
+                if @0 && (@1 || @2) then
+                    input 0
+                fi;
+                if (@2 || @1) && FastAnd(0) then
+                    input 1
+                fi
+        }",
+        );
+        let reference = parse_ir(
+            "if @0 && (@1 || @2) then
+                par {
+                    input 0;
+                    input 1
+                }
+            fi
+        ",
+        );
+        let rewriter = Rewriter::new(vec![Box::new(CombineIf {})]).with_verification(true);
         let (res, changed) = rewriter.apply(ir).unwrap();
         assert!(changed);
         assert!(res.stmt.eq(&reference.stmt));
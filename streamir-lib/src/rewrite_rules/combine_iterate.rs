@@ -185,7 +185,7 @@ mod tests {
             fi
         ",
         );
-        let rewriter = Rewriter::new(vec![Box::new(CombineIterate {})]);
+        let rewriter = Rewriter::new(vec![Box::new(CombineIterate {})]).with_verification(true);
         let (res, changed) = rewriter.apply(ir).unwrap();
         assert!(changed);
         assert!(res.stmt.eq(&reference.stmt));
@@ -209,7 +209,7 @@ mod tests {
                 }
         ",
         );
-        let rewriter = Rewriter::new(vec![Box::new(CombineIterate {})]);
+        let rewriter = Rewriter::new(vec![Box::new(CombineIterate {})]).with_verification(true);
         let (res, changed) = rewriter.apply(ir).unwrap();
         assert!(changed);
         assert!(res.stmt.eq(&reference.stmt));
@@ -96,7 +96,7 @@ mod tests {
             input 3
         }",
         );
-        let rewriter = Rewriter::new(vec![Box::new(CombineSeq {})]);
+        let rewriter = Rewriter::new(vec![Box::new(CombineSeq {})]).with_verification(true);
         let (res, changed) = rewriter.apply(ir).unwrap();
         assert!(changed);
         assert!(res.stmt.eq(&reference.stmt));
@@ -5,12 +5,16 @@ use std::{
 
 use itertools::Itertools;
 
-use crate::ir::{memory::Memory, Guard, IfStmt, LivetimeEquivalences, Stmt, StreamReference};
+use crate::ir::{
+    memory::Memory, Guard, GuardBdd, IfStmt, LivetimeEquivalences, Stmt, StreamReference,
+};
 
 use super::{ChangeSet, RewriteError, RewriteRule};
 
 #[derive(Debug, Clone, Copy)]
 /// Tries to find guard conditions that are used in multiple conditionals in sequences/parallel and moves them outside
+///
+/// The conjuncts of the guards are compared by their BDDs, such that equivalent conditions are found as well.
 pub struct MoveCommonGuardsOutside;
 
 impl RewriteRule for MoveCommonGuardsOutside {
@@ -18,8 +22,9 @@ impl RewriteRule for MoveCommonGuardsOutside {
         &self,
         stmt: Stmt,
         _memory: &HashMap<StreamReference, Memory>,
-        _liveness_equivalences: &LivetimeEquivalences,
+        liveness_equivalences: &LivetimeEquivalences,
     ) -> Result<(Stmt, ChangeSet), RewriteError> {
+        let mut bdd = GuardBdd::new(liveness_equivalences);
         match stmt {
             Stmt::Seq(inner) => {
                 let inner_len = inner.len();
@@ -38,10 +43,16 @@ impl RewriteRule for MoveCommonGuardsOutside {
                                 alt: a2,
                             }),
                         ) if matches!(*a1, Stmt::Skip) && matches!(*a2, Stmt::Skip) => {
-                            let lhs_conjuncts = g1.implied_subguards().collect::<HashSet<_>>();
-                            let rhs_conjuncts = g2.implied_subguards().collect::<HashSet<_>>();
-                            let common = lhs_conjuncts
-                                .intersection(&rhs_conjuncts)
+                            let rhs_conjuncts = g2
+                                .implied_subguards()
+                                .map(|g| bdd.from_guard(&g))
+                                .collect::<HashSet<_>>();
+                            let common = g1
+                                .implied_subguards()
+                                .map(|g| (bdd.from_guard(&g), g))
+                                .filter(|(g, _)| rhs_conjuncts.contains(g))
+                                .unique_by(|(g, _)| *g)
+                                .map(|(_, g)| g)
                                 .collect::<Vec<_>>();
                             if common.is_empty() {
                                 Err((
@@ -60,7 +71,6 @@ impl RewriteRule for MoveCommonGuardsOutside {
                                 // we keep the inner guards and rely on the `ImpliedGuards` rewriting rule to remove those
                                 let common_guard = common
                                     .into_iter()
-                                    .cloned()
                                     .reduce(|a, b| Guard::And {
                                         lhs: Box::new(a),
                                         rhs: Box::new(b),
@@ -107,21 +117,27 @@ impl RewriteRule for MoveCommonGuardsOutside {
                         }
                         _ => vec![],
                     })
-                    .fold(HashMap::<_, HashSet<_>>::new(), |mut hm, (i, g)| {
-                        hm.entry(g).or_default().insert(i);
-                        hm
-                    });
-                let Some((guard, stmts)) = subguard_map
+                    .fold(
+                        HashMap::<_, (Guard, HashSet<_>)>::new(),
+                        |mut hm, (i, g)| {
+                            // equivalent conjuncts are represented by the first occurrence
+                            hm.entry(bdd.from_guard(&g))
+                                .or_insert_with(|| (g, HashSet::new()))
+                                .1
+                                .insert(i);
+                            hm
+                        },
+                    );
+                let Some((_, (guard, stmts))) = subguard_map
                     .iter()
-                    .sorted_by_key(|(g, _)| {
+                    .sorted_by_key(|(g_bdd, (g, _))| {
                         // for the tests we want some deterministic order
-                        // (disregard hash collisions for now)
                         let mut hasher = DefaultHasher::new();
                         g.hash(&mut hasher);
-                        hasher.finish()
+                        (hasher.finish(), **g_bdd)
                     })
-                    .max_by_key(|(_, stmts)| stmts.len())
-                    .filter(|(_, stmts)| stmts.len() > 1)
+                    .max_by_key(|(_, (_, stmts))| stmts.len())
+                    .filter(|(_, (_, stmts))| stmts.len() > 1)
                 else {
                     // there are no common if condition in the parallel statement
                     return Ok((Stmt::Parallel(inner), ChangeSet::default()));
@@ -199,7 +215,8 @@ mod tests {
             Box::new(SimplifyGuard),
             Box::new(RemoveIfs),
             Box::new(CombineSeq),
-        ]);
+        ])
+        .with_verification(true);
         let (res, changed) = rewriter.apply(ir).unwrap();
         assert!(changed);
         assert!(res.stmt.eq(&reference.stmt));
@@ -230,6 +247,50 @@ mod tests {
         assert!(res2.stmt.eq(&reference2.stmt));
     }
 
+    #[test]
+    fn equivalent_conjuncts() {
+        let ir = parse_ir(
+            "
+		seq {
+			input 0 ;
+			if @0 && (@3 || @4) then
+				input 1
+			fi ;
+			if (@4 || @3) && @1 then
+				input 2
+			fi ;
+			input 4
+		}",
+        );
+        let reference = parse_ir(
+            "
+		seq {
+			input 0;
+			if @3 || @4 then
+				seq {
+					if @0 then
+						input 1
+					fi ;
+					if @1 then
+						input 2
+					fi
+				}
+			fi ;
+			input 4
+		}",
+        );
+        let rewriter = Rewriter::new(vec![
+            Box::new(MoveCommonGuardsOutside),
+            Box::new(ImpliedGuards),
+            Box::new(SimplifyGuard),
+            Box::new(RemoveIfs),
+            Box::new(CombineSeq),
+        ])
+        .with_verification(true);
+        let res = rewriter.run(ir).unwrap();
+        assert!(res.stmt.eq(&reference.stmt));
+    }
+
     #[test]
     fn test2() {
         let ir = parse_ir(
@@ -254,7 +315,8 @@ mod tests {
             Box::new(SimplifyGuard),
             Box::new(RemoveIfs),
             Box::new(CombineSeq),
-        ]);
+        ])
+        .with_verification(true);
         let res = rewriter.run(ir).unwrap();
         let reference = parse_ir(
             "par {
@@ -299,7 +361,8 @@ mod tests {
             Box::new(SimplifyGuard),
             Box::new(RemoveIfs),
             Box::new(CombineSeq),
-        ]);
+        ])
+        .with_verification(true);
         let res = rewriter.run(ir).unwrap();
         println!("{}", res.display());
     }
@@ -0,0 +1,377 @@
+use std::collections::HashMap;
+
+use crate::ir::{
+    expressions::{Constant, Expr, ExprKind, Operator},
+    memory::Memory,
+    LivetimeEquivalences, StreamReference, Type,
+};
+
+use super::{ChangeSet, RemoveIfs, RewriteError, RewriteRule, SimplifyGuard};
+
+#[derive(Debug, Clone, Copy)]
+/// A rewriting rule that evaluates all parts of stream expressions that only depend on constants.
+///
+/// Additionally removes operations with a neutral element, e.g., `x * 1` or `x && true`.
+/// Integer operations are only folded if the result fits into the type of the expression,
+/// as the behavior on overflows is up to the target language.
+pub struct ConstantFolding;
+
+impl RewriteRule for ConstantFolding {
+    fn rewrite_expr(
+        &self,
+        expr: Expr,
+        _memory: &HashMap<StreamReference, Memory>,
+        _liveness_equivalences: &LivetimeEquivalences,
+    ) -> Result<(Expr, ChangeSet), RewriteError> {
+        match expr.fold() {
+            Some(folded) => Ok((folded, ChangeSet::local_change())),
+            None => Ok((expr, ChangeSet::default())),
+        }
+    }
+
+    fn cleanup_rules(&self) -> Vec<Box<dyn RewriteRule>> {
+        vec![Box::new(SimplifyGuard), Box::new(RemoveIfs)]
+    }
+}
+
+impl Expr {
+    /// Returns the simplified expression, or None if the expression can not be simplified.
+    /// Assumes that all subexpressions are already folded.
+    pub(super) fn fold(&self) -> Option<Expr> {
+        let Expr { ty, kind, span } = self;
+        let constant = |c: Constant| {
+            Some(Expr {
+                ty: ty.clone(),
+                kind: ExprKind::Constant(c),
+                span: *span,
+            })
+        };
+        match kind {
+            ExprKind::BinaryOperation(op, lhs, rhs) => match (&lhs.kind, &rhs.kind) {
+                (ExprKind::Constant(l), ExprKind::Constant(r)) => {
+                    binary_operation(*op, l, r).and_then(constant)
+                }
+                (_, ExprKind::Constant(c)) if is_neutral(*op, c, true) && lhs.ty == *ty => {
+                    Some(lhs.as_ref().clone())
+                }
+                (ExprKind::Constant(c), _) if is_neutral(*op, c, false) && rhs.ty == *ty => {
+                    Some(rhs.as_ref().clone())
+                }
+                (_, ExprKind::Constant(Constant::Bool(b)))
+                | (ExprKind::Constant(Constant::Bool(b)), _)
+                    if (*op == Operator::And && !b) || (*op == Operator::Or && *b) =>
+                {
+                    constant(Constant::Bool(*b))
+                }
+                _ => None,
+            },
+            ExprKind::UnaryOperation(op, inner) => match &inner.kind {
+                ExprKind::Constant(c) => unary_operation(*op, c).and_then(constant),
+                _ => None,
+            },
+            ExprKind::Cast(target, inner) => match &inner.kind {
+                ExprKind::Constant(c) => cast(target, c).and_then(constant),
+                _ => None,
+            },
+            ExprKind::Ite(cond, cons, alt) => match &cond.kind {
+                ExprKind::Constant(Constant::Bool(true)) => Some(cons.as_ref().clone()),
+                ExprKind::Constant(Constant::Bool(false)) => Some(alt.as_ref().clone()),
+                _ => None,
+            },
+            ExprKind::Tuple(elements) => elements
+                .iter()
+                .map(|e| match &e.kind {
+                    ExprKind::Constant(c) => Some(c.clone()),
+                    _ => None,
+                })
+                .collect::<Option<_>>()
+                .map(Constant::Tuple)
+                .and_then(constant),
+            ExprKind::TupleAccess(inner, idx) => match &inner.kind {
+                ExprKind::Constant(Constant::Tuple(elements)) => {
+                    elements.get(*idx).cloned().and_then(constant)
+                }
+                ExprKind::Tuple(elements) => elements.get(*idx).cloned(),
+                _ => None,
+            },
+            ExprKind::Constant(_)
+            | ExprKind::SyncStreamAccess { .. }
+            | ExprKind::OffsetStreamAccess { .. }
+            | ExprKind::HoldStreamAccess { .. }
+            | ExprKind::IsFresh { .. }
+            | ExprKind::GetAccess { .. }
+            | ExprKind::WindowAccess { .. }
+            | ExprKind::ParameterAccess(_, _)
+            | ExprKind::FunctionCall(_, _)
+            | ExprKind::LambdaParameterAccess(_, _)
+            | ExprKind::Variable(_)
+            | ExprKind::Default { .. } => None,
+        }
+    }
+}
+
+/// Returns whether `c` is the neutral element of the operation, on the right hand side (`right`) or on the left hand side.
+fn is_neutral(op: Operator, c: &Constant, right: bool) -> bool {
+    match (op, c) {
+        (
+            Operator::Add | Operator::BitOr | Operator::BitXor,
+            Constant::UInt(0, _) | Constant::Int(0, _),
+        ) => true,
+        (
+            Operator::Sub | Operator::Shl | Operator::Shr,
+            Constant::UInt(0, _) | Constant::Int(0, _),
+        ) => right,
+        (Operator::Mul, Constant::UInt(1, _) | Constant::Int(1, _)) => true,
+        (Operator::Mul, Constant::Float32(f) | Constant::Float64(f)) => *f == 1.0,
+        (Operator::Div | Operator::Pow, Constant::UInt(1, _) | Constant::Int(1, _)) => right,
+        (Operator::Div | Operator::Pow, Constant::Float32(f) | Constant::Float64(f)) => {
+            right && *f == 1.0
+        }
+        (Operator::And, Constant::Bool(true)) | (Operator::Or, Constant::Bool(false)) => true,
+        _ => false,
+    }
+}
+
+fn binary_operation(op: Operator, lhs: &Constant, rhs: &Constant) -> Option<Constant> {
+    match (lhs, rhs) {
+        (Constant::Bool(l), Constant::Bool(r)) => match op {
+            Operator::And => Some(Constant::Bool(*l && *r)),
+            Operator::Or => Some(Constant::Bool(*l || *r)),
+            _ => compare(op, l, r),
+        },
+        (Constant::Str(l), Constant::Str(r)) => match op {
+            Operator::Eq | Operator::Ne => compare(op, l, r),
+            _ => None,
+        },
+        (Constant::UInt(l, bits), Constant::UInt(r, _)) => compare(op, l, r).or_else(|| {
+            let res = match op {
+                Operator::Add => l.checked_add(*r),
+                Operator::Sub => l.checked_sub(*r),
+                Operator::Mul => l.checked_mul(*r),
+                Operator::Div => l.checked_div(*r),
+                Operator::Rem => l.checked_rem(*r),
+                Operator::Pow => l.checked_pow(u32::try_from(*r).ok()?),
+                Operator::BitAnd => Some(l & r),
+                Operator::BitOr => Some(l | r),
+                Operator::BitXor => Some(l ^ r),
+                Operator::Shl if *r < u64::from(*bits) => l.checked_shl(*r as u32),
+                Operator::Shr if *r < u64::from(*bits) => l.checked_shr(*r as u32),
+                _ => None,
+            }?;
+            fits_uint(res, *bits).then_some(Constant::UInt(res, *bits))
+        }),
+        (Constant::Int(l, bits), Constant::Int(r, _)) => compare(op, l, r).or_else(|| {
+            let res = match op {
+                Operator::Add => l.checked_add(*r),
+                Operator::Sub => l.checked_sub(*r),
+                Operator::Mul => l.checked_mul(*r),
+                Operator::Div => l.checked_div(*r),
+                Operator::Rem => l.checked_rem(*r),
+                Operator::Pow => l.checked_pow(u32::try_from(*r).ok()?),
+                Operator::BitAnd => Some(l & r),
+                Operator::BitOr => Some(l | r),
+                Operator::BitXor => Some(l ^ r),
+                _ => None,
+            }?;
+            fits_int(res, *bits).then_some(Constant::Int(res, *bits))
+        }),
+        (Constant::Float32(l), Constant::Float32(r)) => compare(op, l, r).or_else(|| {
+            float_operation(op, *l, *r).map(|res| Constant::Float32(res as f32 as f64))
+        }),
+        (Constant::Float64(l), Constant::Float64(r)) => {
+            compare(op, l, r).or_else(|| float_operation(op, *l, *r).map(Constant::Float64))
+        }
+        _ => None,
+    }
+}
+
+fn compare<T: PartialOrd>(op: Operator, lhs: &T, rhs: &T) -> Option<Constant> {
+    let res = match op {
+        Operator::Eq => lhs == rhs,
+        Operator::Ne => lhs != rhs,
+        Operator::Lt => lhs < rhs,
+        Operator::Le => lhs <= rhs,
+        Operator::Gt => lhs > rhs,
+        Operator::Ge => lhs >= rhs,
+        _ => return None,
+    };
+    Some(Constant::Bool(res))
+}
+
+fn float_operation(op: Operator, lhs: f64, rhs: f64) -> Option<f64> {
+    match op {
+        Operator::Add => Some(lhs + rhs),
+        Operator::Sub => Some(lhs - rhs),
+        Operator::Mul => Some(lhs * rhs),
+        Operator::Div => Some(lhs / rhs),
+        Operator::Rem => Some(lhs % rhs),
+        Operator::Pow => Some(lhs.powf(rhs)),
+        _ => None,
+    }
+}
+
+fn unary_operation(op: Operator, c: &Constant) -> Option<Constant> {
+    match (op, c) {
+        (Operator::Not, Constant::Bool(b)) => Some(Constant::Bool(!b)),
+        (Operator::Neg, Constant::Int(i, bits)) => i
+            .checked_neg()
+            .filter(|res| fits_int(*res, *bits))
+            .map(|res| Constant::Int(res, *bits)),
+        (Operator::Neg, Constant::Float32(f)) => Some(Constant::Float32(-f)),
+        (Operator::Neg, Constant::Float64(f)) => Some(Constant::Float64(-f)),
+        (Operator::BitNot, Constant::UInt(u, bits)) => {
+            let mask = if *bits >= 64 {
+                u64::MAX
+            } else {
+                (1 << bits) - 1
+            };
+            Some(Constant::UInt(!u & mask, *bits))
+        }
+        (Operator::BitNot, Constant::Int(i, bits)) => Some(Constant::Int(!i, *bits)),
+        _ => None,
+    }
+}
+
+/// Casts the constant to the given type.
+/// Casts from floating point numbers to integers are not folded, as their semantics depend on the target language.
+fn cast(ty: &Type, c: &Constant) -> Option<Constant> {
+    match (c, ty) {
+        (Constant::UInt(u, _), Type::UInt(bits)) => {
+            fits_uint(*u, *bits).then_some(Constant::UInt(*u, *bits))
+        }
+        (Constant::UInt(u, _), Type::Int(bits)) => i64::try_from(*u)
+            .ok()
+            .filter(|i| fits_int(*i, *bits))
+            .map(|i| Constant::Int(i, *bits)),
+        (Constant::Int(i, _), Type::UInt(bits)) => u64::try_from(*i)
+            .ok()
+            .filter(|u| fits_uint(*u, *bits))
+            .map(|u| Constant::UInt(u, *bits)),
+        (Constant::Int(i, _), Type::Int(bits)) => {
+            fits_int(*i, *bits).then_some(Constant::Int(*i, *bits))
+        }
+        (Constant::UInt(u, _), Type::Float32) => Some(Constant::Float32(*u as f32 as f64)),
+        (Constant::UInt(u, _), Type::Float64) => Some(Constant::Float64(*u as f64)),
+        (Constant::Int(i, _), Type::Float32) => Some(Constant::Float32(*i as f32 as f64)),
+        (Constant::Int(i, _), Type::Float64) => Some(Constant::Float64(*i as f64)),
+        (Constant::Float32(f) | Constant::Float64(f), Type::Float32) => {
+            Some(Constant::Float32(*f as f32 as f64))
+        }
+        (Constant::Float32(f) | Constant::Float64(f), Type::Float64) => Some(Constant::Float64(*f)),
+        (Constant::Bool(_), Type::Bool) | (Constant::Str(_), Type::String) => Some(c.clone()),
+        _ => None,
+    }
+}
+
+fn fits_uint(u: u64, bits: u16) -> bool {
+    bits >= 64 || u >> bits == 0
+}
+
+fn fits_int(i: i64, bits: u16) -> bool {
+    bits >= 64 || (i >> (bits - 1) == 0 || i >> (bits - 1) == -1)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ir::{parse::parse_text, StreamIr},
+        rewrite_rules::{constant_folding::ConstantFolding, Rewriter},
+    };
+
+    const STREAMS: &str = "input in0 a: UInt(64) = static(single);
+input in1 b: Float(64) = static(single);
+output out0 c: UInt(64) = static(single);
+output out1 d: Float(64) = static(single);
+output out2 e: UInt(8) = static(single);
+output out3 f: Int(8) = static(single);
+";
+
+    fn fold(stmt: &str) -> StreamIr {
+        let ir = parse_text(STREAMS, stmt);
+        let rewriter = Rewriter::new(vec![Box::new(ConstantFolding)]).with_verification(true);
+        rewriter.run(ir).unwrap()
+    }
+
+    #[test]
+    fn arithmetic() {
+        let res = fold(
+            "seq {
+                eval_0 out0 with (2u64 + 3u64) * in0 * 1u64;
+                eval_0 out1 with in1 * 1.0f64 + 2.0f64 ** 3.0f64;
+                eval_0 out2 with 200u8 + 50u8;
+                eval_0 out3 with -(3i8 - 5i8) * 4i8
+            }",
+        );
+        let reference = parse_text(
+            STREAMS,
+            "seq {
+                eval_0 out0 with 5u64 * in0;
+                eval_0 out1 with in1 + 8.0f64;
+                eval_0 out2 with 250u8;
+                eval_0 out3 with 8i8
+            }",
+        );
+        assert_eq!(res.stmt, reference.stmt);
+    }
+
+    #[test]
+    fn overflow() {
+        let stmt = "seq {
+            eval_0 out0 with 1u64 / 0u64;
+            eval_0 out2 with 200u8 + 100u8;
+            eval_0 out3 with cast<Int(8)>(300u64);
+            eval_0 out3 with -(-128i8)
+        }";
+        assert_eq!(fold(stmt).stmt, parse_text(STREAMS, stmt).stmt);
+    }
+
+    #[test]
+    fn guards() {
+        let res = fold(
+            "seq {
+                if Expr(1u64 < 2u64) && @in0 then
+                    eval_0 out0 with in0
+                else
+                    eval_0 out0 with 0u64
+                fi;
+                if @in1 && Expr(in1 > 2.0f64 && 1.5f64 > 2.0f64) then
+                    eval_0 out1 with in1
+                fi
+            }",
+        );
+        let reference = parse_text(
+            STREAMS,
+            "seq {
+                if @in0 then
+                    eval_0 out0 with in0
+                else
+                    eval_0 out0 with 0u64
+                fi;
+                skip
+            }",
+        );
+        assert_eq!(res.stmt, reference.stmt);
+    }
+
+    #[test]
+    fn ite_cast_tuple() {
+        let res = fold(
+            "seq {
+                eval_0 out0 with ite(false, in0, 3u64);
+                eval_0 out1 with cast<Float(64)>(3u64) + cast<Float(64)>(in0);
+                eval_0 out3 with const(1u64, (true, -2i8)).1.1;
+                eval_0 out0 with tuple(in0, 1u64).0 + tuple(in0, 1u64).1
+            }",
+        );
+        let reference = parse_text(
+            STREAMS,
+            "seq {
+                eval_0 out0 with 3u64;
+                eval_0 out1 with 3.0f64 + cast<Float(64)>(in0);
+                eval_0 out3 with -2i8;
+                eval_0 out0 with in0 + 1u64
+            }",
+        );
+        assert_eq!(res.stmt, reference.stmt);
+    }
+}
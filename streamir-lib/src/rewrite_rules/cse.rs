@@ -0,0 +1,411 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::ir::{
+    expressions::{Expr, ExprKind, Operator},
+    memory::Memory,
+    Guard, IfStmt, LivetimeEquivalences, Stmt, StreamReference, VariableReference, WindowReference,
+};
+
+use super::{ChangeSet, RewriteError, RewriteRule};
+
+#[derive(Debug, Clone, Copy)]
+/// A rewriting rule that binds subexpressions shared by the statements of a sequence or parallel block to a variable.
+///
+/// Only subexpressions that are evaluated unconditionally when executing the statements of the block are considered,
+/// i.e. the expressions of evaluations, spawns, assignments and bindings and the dynamic guard of conditionals.
+/// An occurrence is only replaced by the variable if no statement between the binding and the occurrence
+/// changes a stream or window the subexpression reads.
+pub struct CommonSubexpressionElimination;
+
+impl RewriteRule for CommonSubexpressionElimination {
+    fn rewrite_stmt(
+        &self,
+        stmt: Stmt,
+        _memory: &HashMap<StreamReference, Memory>,
+        _liveness_equivalences: &LivetimeEquivalences,
+    ) -> Result<(Stmt, ChangeSet), RewriteError> {
+        match stmt {
+            Stmt::Seq(mut stmts) => {
+                let Some((expr, first, last)) = Self::shared_subexpression(&stmts, false) else {
+                    return Ok((Stmt::Seq(stmts), ChangeSet::default()));
+                };
+                let var = Self::fresh_variable(&stmts);
+                stmts[first..=last]
+                    .iter_mut()
+                    .for_each(|stmt| Self::replace_in_stmt(stmt, &expr, var));
+                let mut inner = stmts.split_off(first);
+                let inner = if inner.len() == 1 {
+                    inner.remove(0)
+                } else {
+                    Stmt::Seq(inner)
+                };
+                let binding = Stmt::Let {
+                    var,
+                    expr,
+                    stmt: Box::new(inner),
+                };
+                let stmt = if stmts.is_empty() {
+                    binding
+                } else {
+                    stmts.push(binding);
+                    Stmt::Seq(stmts)
+                };
+                Ok((stmt, ChangeSet::local_change()))
+            }
+            Stmt::Parallel(mut stmts) => {
+                let Some((expr, _, _)) = Self::shared_subexpression(&stmts, true) else {
+                    return Ok((Stmt::Parallel(stmts), ChangeSet::default()));
+                };
+                let var = Self::fresh_variable(&stmts);
+                stmts
+                    .iter_mut()
+                    .for_each(|stmt| Self::replace_in_stmt(stmt, &expr, var));
+                let stmt = Stmt::Let {
+                    var,
+                    expr,
+                    stmt: Box::new(Stmt::Parallel(stmts)),
+                };
+                Ok((stmt, ChangeSet::local_change()))
+            }
+            stmt => Ok((stmt, ChangeSet::default())),
+        }
+    }
+}
+
+impl CommonSubexpressionElimination {
+    /// Returns the subexpression with the most shareable occurrences in the block, preferring larger ones,
+    /// together with the index of the first and the last statement containing a shareable occurrence.
+    ///
+    /// Sharing the most frequent subexpression first allows sharing the expressions containing it in the inner block afterwards.
+    fn shared_subexpression(stmts: &[Stmt], parallel: bool) -> Option<(Expr, usize, usize)> {
+        let occurrences: Vec<Vec<&Expr>> = stmts
+            .iter()
+            .map(|stmt| {
+                let mut occurrences = Vec::new();
+                Self::strict_roots(stmt)
+                    .into_iter()
+                    .for_each(|root| Self::strict_subexpressions(root, &mut occurrences));
+                occurrences
+            })
+            .collect();
+        let counts: Vec<HashMap<&Expr, usize>> = occurrences
+            .iter()
+            .map(|occurrences| {
+                let mut counts = HashMap::new();
+                occurrences
+                    .iter()
+                    .for_each(|occurrence| *counts.entry(*occurrence).or_default() += 1);
+                counts
+            })
+            .collect();
+        let mut best: Option<(&Expr, usize, usize, (usize, usize))> = None;
+        let mut seen: HashSet<&Expr> = HashSet::new();
+        for (first, candidates) in occurrences.iter().enumerate() {
+            for &candidate in candidates {
+                if !Self::is_candidate(candidate) || !seen.insert(candidate) {
+                    continue;
+                }
+                let mut reads = (Vec::new(), Vec::new());
+                Self::reads(candidate, &mut reads);
+                if parallel && stmts.iter().any(|stmt| Self::writes(stmt, &reads)) {
+                    continue;
+                }
+                let mut count = 0;
+                let mut last = first;
+                for (idx, stmt) in stmts.iter().enumerate().skip(first) {
+                    let found = counts[idx].get(candidate).copied().unwrap_or_default();
+                    if found > 0 {
+                        count += found;
+                        last = idx;
+                    }
+                    if !parallel && Self::writes(stmt, &reads) {
+                        break;
+                    }
+                }
+                let rank = (count, Self::size(candidate));
+                if count >= 2 && best.is_none_or(|(_, _, _, best_rank)| best_rank < rank) {
+                    best = Some((candidate, first, last, rank));
+                }
+            }
+        }
+        best.map(|(expr, first, last, _)| (expr.clone(), first, last))
+    }
+
+    /// Returns whether binding the expression to a variable can save any work
+    pub(super) fn is_candidate(expr: &Expr) -> bool {
+        fn lambda_free(expr: &Expr) -> bool {
+            !matches!(expr.kind, ExprKind::LambdaParameterAccess(_, _))
+                && expr.children().into_iter().all(lambda_free)
+        }
+        !matches!(
+            expr.kind,
+            ExprKind::Constant(_)
+                | ExprKind::ParameterAccess(_, _)
+                | ExprKind::LambdaParameterAccess(_, _)
+                | ExprKind::Variable(_)
+        ) && lambda_free(expr)
+    }
+
+    pub(super) fn size(expr: &Expr) -> usize {
+        1 + expr.children().into_iter().map(Self::size).sum::<usize>()
+    }
+
+    /// Returns the expressions that are always evaluated when the statement is executed
+    pub(super) fn strict_roots(stmt: &Stmt) -> Vec<&Expr> {
+        fn guard_roots(guard: &Guard) -> Vec<&Expr> {
+            match guard {
+                Guard::Dynamic(expr) => vec![expr],
+                Guard::And { lhs, .. } | Guard::Or { lhs, .. } => guard_roots(lhs),
+                _ => Vec::new(),
+            }
+        }
+        match stmt {
+            Stmt::Spawn {
+                with: Some(with), ..
+            } => with.iter().collect(),
+            Stmt::Eval { with, .. } => vec![with],
+            Stmt::Assign { parameter_expr, .. } => parameter_expr.iter().collect(),
+            Stmt::Let { expr, .. } => vec![expr],
+            Stmt::If(IfStmt { guard, .. }) => guard_roots(guard),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Returns the number of direct subexpressions that are always evaluated when the expression is evaluated
+    ///
+    /// The strict subexpressions are always the first ones returned by [Expr::children].
+    fn strict_children(expr: &Expr) -> usize {
+        match &expr.kind {
+            ExprKind::Ite(..)
+            | ExprKind::Default { .. }
+            | ExprKind::BinaryOperation(Operator::And, _, _)
+            | ExprKind::BinaryOperation(Operator::Or, _, _) => 1,
+            ExprKind::OffsetStreamAccess { parameters, .. }
+            | ExprKind::HoldStreamAccess { parameters, .. }
+            | ExprKind::GetAccess { parameters, .. }
+            | ExprKind::WindowAccess { parameters, .. } => parameters.len(),
+            _ => expr.children().len(),
+        }
+    }
+
+    pub(super) fn strict_subexpressions<'a>(expr: &'a Expr, res: &mut Vec<&'a Expr>) {
+        res.push(expr);
+        expr.children()
+            .into_iter()
+            .take(Self::strict_children(expr))
+            .for_each(|child| Self::strict_subexpressions(child, res));
+    }
+
+    /// Collects the streams and windows read by the expression
+    pub(super) fn reads(expr: &Expr, reads: &mut (Vec<StreamReference>, Vec<WindowReference>)) {
+        match &expr.kind {
+            ExprKind::SyncStreamAccess { target, .. }
+            | ExprKind::OffsetStreamAccess { target, .. }
+            | ExprKind::HoldStreamAccess { target, .. }
+            | ExprKind::GetAccess { target, .. }
+            | ExprKind::IsFresh { target, .. }
+            | ExprKind::ParameterAccess(target, _) => reads.0.push(*target),
+            ExprKind::WindowAccess { target, window, .. } => {
+                reads.0.push(*target);
+                reads.1.push(*window);
+            }
+            _ => {}
+        }
+        expr.children()
+            .into_iter()
+            .for_each(|child| Self::reads(child, reads));
+    }
+
+    /// Returns whether executing the statement might change one of the given streams or windows
+    pub(super) fn writes(
+        stmt: &Stmt,
+        reads: &(Vec<StreamReference>, Vec<WindowReference>),
+    ) -> bool {
+        match stmt {
+            Stmt::Skip => false,
+            Stmt::Seq(stmts) | Stmt::Parallel(stmts) => {
+                stmts.iter().any(|stmt| Self::writes(stmt, reads))
+            }
+            Stmt::Shift(sr) => reads.0.contains(sr),
+            Stmt::Input(i) => reads.0.contains(&StreamReference::In(*i)),
+            Stmt::Eval { sr, .. } => reads.0.contains(&sr.sr()),
+            Stmt::Spawn { sr, windows, .. } | Stmt::Close { sr, windows, .. } => {
+                reads.0.contains(&sr.sr()) || windows.iter().any(|w| reads.1.contains(w))
+            }
+            Stmt::If(IfStmt { cons, alt, .. }) => {
+                Self::writes(cons, reads) || Self::writes(alt, reads)
+            }
+            Stmt::Iterate { stmt, .. } | Stmt::Assign { stmt, .. } | Stmt::Let { stmt, .. } => {
+                Self::writes(stmt, reads)
+            }
+        }
+    }
+
+    /// Returns a variable that is neither bound nor accessed in the given statements
+    pub(super) fn fresh_variable(stmts: &[Stmt]) -> VariableReference {
+        fn guard_max(guard: &Guard) -> Option<VariableReference> {
+            match guard {
+                Guard::Dynamic(expr) => expr_max(expr),
+                Guard::And { lhs, rhs } | Guard::Or { lhs, rhs } => {
+                    guard_max(lhs).max(guard_max(rhs))
+                }
+                _ => None,
+            }
+        }
+        fn expr_max(expr: &Expr) -> Option<VariableReference> {
+            expr.variables().last().map(|(var, _)| *var)
+        }
+        fn stmt_max(stmt: &Stmt) -> Option<VariableReference> {
+            match stmt {
+                Stmt::Skip
+                | Stmt::Shift(_)
+                | Stmt::Input(_)
+                | Stmt::Spawn { with: None, .. }
+                | Stmt::Close { .. } => None,
+                Stmt::Seq(stmts) | Stmt::Parallel(stmts) => stmts.iter().filter_map(stmt_max).max(),
+                Stmt::Spawn {
+                    with: Some(with), ..
+                } => with.iter().filter_map(expr_max).max(),
+                Stmt::Eval { with, .. } => expr_max(with),
+                Stmt::If(IfStmt { guard, cons, alt }) => {
+                    guard_max(guard).max(stmt_max(cons)).max(stmt_max(alt))
+                }
+                Stmt::Iterate { stmt, .. } => stmt_max(stmt),
+                Stmt::Assign {
+                    parameter_expr,
+                    stmt,
+                    ..
+                } => parameter_expr
+                    .iter()
+                    .filter_map(expr_max)
+                    .max()
+                    .max(stmt_max(stmt)),
+                Stmt::Let { var, expr, stmt } => Some(*var).max(expr_max(expr)).max(stmt_max(stmt)),
+            }
+        }
+        stmts
+            .iter()
+            .filter_map(stmt_max)
+            .max()
+            .map_or(0, |var| var + 1)
+    }
+
+    /// Replaces the occurrences of `expr` that are always evaluated when executing the statement with the variable
+    fn replace_in_stmt(stmt: &mut Stmt, expr: &Expr, var: VariableReference) {
+        fn replace_in_guard(guard: &mut Guard, expr: &Expr, var: VariableReference) {
+            match guard {
+                Guard::Dynamic(e) => CommonSubexpressionElimination::replace(e, expr, var),
+                Guard::And { lhs, .. } | Guard::Or { lhs, .. } => replace_in_guard(lhs, expr, var),
+                _ => {}
+            }
+        }
+        match stmt {
+            Stmt::Spawn {
+                with: Some(with), ..
+            } => with.iter_mut().for_each(|e| Self::replace(e, expr, var)),
+            Stmt::Eval { with, .. } => Self::replace(with, expr, var),
+            Stmt::Assign { parameter_expr, .. } => parameter_expr
+                .iter_mut()
+                .for_each(|e| Self::replace(e, expr, var)),
+            Stmt::Let { expr: e, .. } => Self::replace(e, expr, var),
+            Stmt::If(IfStmt { guard, .. }) => replace_in_guard(guard, expr, var),
+            _ => {}
+        }
+    }
+
+    fn replace(e: &mut Expr, expr: &Expr, var: VariableReference) {
+        if e == expr {
+            *e = Expr {
+                ty: expr.ty.clone(),
+                kind: ExprKind::Variable(var),
+                span: e.span,
+            };
+        } else {
+            let strict = Self::strict_children(e);
+            e.children_mut()
+                .into_iter()
+                .take(strict)
+                .for_each(|child| Self::replace(child, expr, var));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ir::{parse::parse_text, Stmt},
+        rewrite_rules::{CommonSubexpressionElimination, Rewriter},
+    };
+
+    const STREAMS: &str = "input in0 a: UInt(64) = static(single);
+input in1 b: UInt(64) = static(single);
+output out0 c: UInt(64) = static(single);
+output out1 d: UInt(64) = static(single);
+output out2 e: Bool = static(single);
+";
+
+    fn rewrite(stmt: &str) -> Stmt {
+        let rewriter =
+            Rewriter::new(vec![Box::new(CommonSubexpressionElimination)]).with_verification(true);
+        rewriter.run(parse_text(STREAMS, stmt)).unwrap().stmt
+    }
+
+    #[test]
+    fn shared_between_evals() {
+        let res = rewrite(
+            "seq {
+                input in0;
+                eval_0 out0 with in0.hold(or: 0u64) * 2u64;
+                eval_0 out1 with (in0.hold(or: 0u64) * 2u64) + in1.hold(or: 1u64);
+                eval_0 out2 with in0.hold(or: 0u64) > 3u64
+            }",
+        );
+        let reference = parse_text(
+            STREAMS,
+            "seq {
+                input in0;
+                let var0 = in0.hold(or: 0u64)
+                    let var1 = var0 * 2u64
+                        seq {
+                            eval_0 out0 with var1;
+                            eval_0 out1 with var1 + in1.hold(or: 1u64);
+                            eval_0 out2 with var0 > 3u64
+                        }
+            }",
+        );
+        assert_eq!(res, reference.stmt);
+    }
+
+    #[test]
+    fn writes_between_occurrences() {
+        let stmt = "seq {
+                eval_0 out0 with in0.hold(or: 0u64) + 1u64;
+                input in0;
+                eval_0 out1 with in0.hold(or: 0u64) + 1u64
+            }";
+        assert_eq!(rewrite(stmt), parse_text(STREAMS, stmt).stmt);
+    }
+
+    #[test]
+    fn conditional_occurrences() {
+        let res = rewrite(
+            "par {
+                if Expr(in0 > 1u64) then
+                    eval_0 out0 with in1 + in1
+                fi;
+                eval_0 out1 with ite(in0 > 1u64, 1u64, in1 + in1)
+            }",
+        );
+        let reference = parse_text(
+            STREAMS,
+            "let var0 = in0 > 1u64
+                par {
+                    if Expr(var0) then
+                        eval_0 out0 with in1 + in1
+                    fi;
+                    eval_0 out1 with ite(var0, 1u64, in1 + in1)
+                }",
+        );
+        assert_eq!(res, reference.stmt);
+    }
+}
@@ -0,0 +1,640 @@
+use std::collections::{HashMap, HashSet};
+
+use disjoint::DisjointSet;
+use itertools::Itertools;
+
+use crate::ir::{
+    expressions::{Expr, ExprKind},
+    memory::Memory,
+    windows::{InstanceSelection, Window, WindowKind},
+    Guard, IfStmt, LivetimeEquivalences, OutputReference, StaticSchedule, Stmt, StreamAccessKind,
+    StreamIr, StreamReference, Task, WindowReference,
+};
+
+use super::{ChangeSet, GlobalChangeInstruction, RemoveSkip, RewriteError, RewriteRule};
+
+#[derive(Debug, Clone)]
+/// A rewriting rule that removes all output streams that can not influence a set of observed streams.
+///
+/// A stream influences an observed stream if the observed stream depends on it directly or transitively,
+/// either through a stream access (as recorded in [StreamIr::accesses]) or through a guard, a binding or an
+/// assignment enclosing one of its statements.
+/// The statements, memory, windows and local frequencies of all other output streams are removed
+/// and the remaining output streams and windows are renumbered. Input streams are never removed.
+///
+/// Because of the renumbering, the rule identifies the streams to keep by their name.
+pub struct DeadStreamElimination {
+    live: HashSet<String>,
+}
+
+impl DeadStreamElimination {
+    /// Creates the rule keeping all streams of the StreamIR that can influence one of the `observed` streams.
+    pub fn new(ir: &StreamIr, observed: impl IntoIterator<Item = StreamReference>) -> Self {
+        let mut dependencies: HashMap<StreamReference, HashSet<StreamReference>> = HashMap::new();
+        for (sr, accesses) in &ir.accesses {
+            dependencies
+                .entry(*sr)
+                .or_default()
+                .extend(accesses.iter().map(|(target, _)| *target));
+        }
+        Self::statement_dependencies(&ir.stmt, &[], ir, &mut dependencies);
+
+        let mut live: HashSet<StreamReference> = ir.inputs().map(StreamReference::In).collect();
+        let mut stack: Vec<StreamReference> = observed.into_iter().collect();
+        while let Some(sr) = stack.pop() {
+            if live.insert(sr) {
+                stack.extend(dependencies.get(&sr).into_iter().flatten().copied());
+            }
+        }
+        let live = live
+            .into_iter()
+            .filter_map(|sr| ir.sr2memory.get(&sr))
+            .map(|memory| memory.name.clone())
+            .collect();
+        Self { live }
+    }
+
+    /// Creates the rule removing exactly the given output streams of the StreamIR.
+    ///
+    /// Unlike [DeadStreamElimination::new], the accesses to these streams are not considered,
+    /// so they must be removed by another rule in the same round.
+    pub(super) fn removing(ir: &StreamIr, dead: &[StreamReference]) -> Self {
+        let live = ir
+            .streams()
+            .filter(|sr| !dead.contains(sr))
+            .map(|sr| ir.name(sr).to_owned())
+            .collect();
+        Self { live }
+    }
+
+    /// Adds the streams the statements of an output depend on because of the enclosing statements
+    fn statement_dependencies(
+        stmt: &Stmt,
+        context: &[StreamReference],
+        ir: &StreamIr,
+        dependencies: &mut HashMap<StreamReference, HashSet<StreamReference>>,
+    ) {
+        let inner_context = |streams: Vec<StreamReference>| {
+            context.iter().copied().chain(streams).collect::<Vec<_>>()
+        };
+        match stmt {
+            Stmt::Skip | Stmt::Input(_) => {}
+            Stmt::Seq(stmts) | Stmt::Parallel(stmts) => stmts
+                .iter()
+                .for_each(|stmt| Self::statement_dependencies(stmt, context, ir, dependencies)),
+            Stmt::Shift(sr) => dependencies
+                .entry(*sr)
+                .or_default()
+                .extend(context.iter().copied()),
+            Stmt::Spawn { sr, .. } | Stmt::Eval { sr, .. } | Stmt::Close { sr, .. } => dependencies
+                .entry(sr.sr())
+                .or_default()
+                .extend(context.iter().copied()),
+            Stmt::If(IfStmt { guard, cons, alt }) => {
+                let context = inner_context(Self::guard_reads(guard, ir));
+                Self::statement_dependencies(cons, &context, ir, dependencies);
+                Self::statement_dependencies(alt, &context, ir, dependencies);
+            }
+            Stmt::Iterate { stmt, .. } => {
+                Self::statement_dependencies(stmt, context, ir, dependencies)
+            }
+            Stmt::Assign {
+                parameter_expr,
+                stmt,
+                ..
+            } => {
+                let context = inner_context(parameter_expr.iter().flat_map(Self::reads).collect());
+                Self::statement_dependencies(stmt, &context, ir, dependencies)
+            }
+            Stmt::Let { expr, stmt, .. } => {
+                let context = inner_context(Self::reads(expr));
+                Self::statement_dependencies(stmt, &context, ir, dependencies)
+            }
+        }
+    }
+
+    fn guard_reads(guard: &Guard, ir: &StreamIr) -> Vec<StreamReference> {
+        match guard {
+            Guard::Stream(sr) | Guard::Alive(sr) => vec![*sr],
+            Guard::Dynamic(expr) => Self::reads(expr),
+            Guard::LocalFreq(lref) => vec![ir.lref2lfreq[lref].sr.sr()],
+            Guard::And { lhs, rhs } | Guard::Or { lhs, rhs } => {
+                let mut reads = Self::guard_reads(lhs, ir);
+                reads.extend(Self::guard_reads(rhs, ir));
+                reads
+            }
+            Guard::FastAnd(srs) | Guard::FastOr(srs) => srs.clone(),
+            Guard::GlobalFreq(_) | Guard::Constant(_) => Vec::new(),
+        }
+    }
+
+    fn reads(expr: &Expr) -> Vec<StreamReference> {
+        let mut reads = match &expr.kind {
+            ExprKind::SyncStreamAccess { target, .. }
+            | ExprKind::OffsetStreamAccess { target, .. }
+            | ExprKind::HoldStreamAccess { target, .. }
+            | ExprKind::GetAccess { target, .. }
+            | ExprKind::IsFresh { target, .. }
+            | ExprKind::WindowAccess { target, .. }
+            | ExprKind::ParameterAccess(target, _) => vec![*target],
+            _ => Vec::new(),
+        };
+        reads.extend(expr.children().into_iter().flat_map(Self::reads));
+        reads
+    }
+
+    fn is_dead(&self, sr: StreamReference, memory: &HashMap<StreamReference, Memory>) -> bool {
+        matches!(sr, StreamReference::Out(_))
+            && memory
+                .get(&sr)
+                .is_some_and(|memory| !self.live.contains(&memory.name))
+    }
+}
+
+impl RewriteRule for DeadStreamElimination {
+    fn rewrite_stmt(
+        &self,
+        stmt: Stmt,
+        memory: &HashMap<StreamReference, Memory>,
+        _liveness_equivalences: &LivetimeEquivalences,
+    ) -> Result<(Stmt, ChangeSet), RewriteError> {
+        match stmt {
+            Stmt::Shift(sr) if self.is_dead(sr, memory) => {
+                Ok((Stmt::Skip, ChangeSet::local_change()))
+            }
+            Stmt::Spawn { sr, .. } | Stmt::Eval { sr, .. } | Stmt::Close { sr, .. }
+                if self.is_dead(sr.sr(), memory) =>
+            {
+                Ok((Stmt::Skip, ChangeSet::local_change()))
+            }
+            Stmt::Iterate { sr, stmt } if sr.iter().any(|o| self.is_dead(o.sr(), memory)) => {
+                let sr: Vec<_> = sr
+                    .into_iter()
+                    .filter(|o| !self.is_dead(o.sr(), memory))
+                    .collect();
+                let stmt = if sr.is_empty() {
+                    Stmt::Skip
+                } else {
+                    Stmt::Iterate { sr, stmt }
+                };
+                Ok((stmt, ChangeSet::local_change()))
+            }
+            Stmt::Assign {
+                sr,
+                parameter_expr,
+                stmt,
+            } if sr.iter().any(|o| self.is_dead(o.sr(), memory)) => {
+                let sr: Vec<_> = sr
+                    .into_iter()
+                    .filter(|o| !self.is_dead(o.sr(), memory))
+                    .collect();
+                let stmt = if sr.is_empty() {
+                    Stmt::Skip
+                } else {
+                    Stmt::Assign {
+                        sr,
+                        parameter_expr,
+                        stmt,
+                    }
+                };
+                Ok((stmt, ChangeSet::local_change()))
+            }
+            // The guard might refer to a removed stream
+            Stmt::If(IfStmt { cons, alt, .. })
+                if matches!(*cons, Stmt::Skip) && matches!(*alt, Stmt::Skip) =>
+            {
+                Ok((Stmt::Skip, ChangeSet::local_change()))
+            }
+            // The expression might refer to a removed stream
+            Stmt::Let { stmt, .. } if matches!(*stmt, Stmt::Skip) => {
+                Ok((Stmt::Skip, ChangeSet::local_change()))
+            }
+            stmt => Ok((stmt, ChangeSet::default())),
+        }
+    }
+
+    fn apply_memory(
+        &self,
+        memory: HashMap<StreamReference, Memory>,
+        _livetime_equivalences: &LivetimeEquivalences,
+    ) -> Result<(HashMap<StreamReference, Memory>, ChangeSet), RewriteError> {
+        let dead: Vec<_> = memory
+            .keys()
+            .copied()
+            .filter(|sr| self.is_dead(*sr, &memory))
+            .sorted()
+            .collect();
+        let cs = if dead.is_empty() {
+            ChangeSet::default()
+        } else {
+            ChangeSet {
+                local_change: false,
+                global_instructions: [GlobalChangeInstruction::RemoveStreams(dead)]
+                    .into_iter()
+                    .collect(),
+            }
+        };
+        Ok((memory, cs))
+    }
+
+    fn cleanup_rules(&self) -> Vec<Box<dyn RewriteRule>> {
+        vec![Box::new(RemoveSkip)]
+    }
+}
+
+/// Removes the given output streams together with their windows and local frequencies from the StreamIR
+/// and renumbers the remaining output streams and windows.
+///
+/// Local frequencies keep their references, as they are only referenced through [StreamIr::lref2lfreq].
+///
+/// The statement must not refer to any of the removed streams anymore.
+pub(super) fn remove_streams(ir: &mut StreamIr, dead: &[StreamReference]) {
+    let renaming = Renaming::new(ir, dead);
+    let StreamIr {
+        stmt,
+        sr2memory,
+        wref2window,
+        lref2lfreq,
+        livetime_equivalences,
+        static_schedule,
+        triggers,
+        accesses,
+        accessed_by,
+        sources,
+        overflow: _,
+    } = ir;
+
+    renaming.stmt(stmt);
+    *sr2memory = std::mem::take(sr2memory)
+        .into_iter()
+        .filter_map(|(sr, memory)| Some((renaming.sr(sr)?, memory)))
+        .collect();
+    *wref2window = std::mem::take(wref2window)
+        .into_iter()
+        .filter_map(|(wref, mut window)| {
+            let wref = renaming.wref(wref)?;
+            renaming.window(&mut window);
+            Some((wref, window))
+        })
+        .collect();
+    *lref2lfreq = std::mem::take(lref2lfreq)
+        .into_iter()
+        .filter_map(|(lref, mut lfreq)| {
+            lfreq.sr = renaming.output(lfreq.sr)?;
+            Some((lref, lfreq))
+        })
+        .collect();
+    *triggers = std::mem::take(triggers)
+        .into_iter()
+        .filter_map(|(o, idx)| Some((renaming.output(o)?, idx)))
+        .sorted_by_key(|(_, idx)| *idx)
+        .enumerate()
+        .map(|(idx, (o, _))| (o, idx))
+        .collect();
+    for accesses in [accesses, accessed_by] {
+        *accesses = std::mem::take(accesses)
+            .into_iter()
+            .filter_map(|(sr, accesses)| {
+                let accesses = accesses
+                    .into_iter()
+                    .filter_map(|(target, kinds)| {
+                        let kinds = kinds
+                            .into_iter()
+                            .map(|(origin, kind)| (origin, renaming.access_kind(kind)))
+                            .collect();
+                        Some((renaming.sr(target)?, kinds))
+                    })
+                    .collect();
+                Some((renaming.sr(sr)?, accesses))
+            })
+            .collect();
+    }
+    if let Some(StaticSchedule { deadlines, .. }) = static_schedule {
+        deadlines.iter_mut().for_each(|deadline| {
+            deadline.due = std::mem::take(&mut deadline.due)
+                .into_iter()
+                .filter_map(|task| renaming.task(task))
+                .collect();
+        });
+    }
+    *livetime_equivalences = renaming.livetime_equivalences(livetime_equivalences);
+    sources.streams = std::mem::take(&mut sources.streams)
+        .into_iter()
+        .filter_map(|(sr, span)| Some((renaming.sr(sr)?, span)))
+        .collect();
+}
+
+/// Maps the streams and windows remaining after removing streams to their new references
+struct Renaming {
+    streams: HashMap<StreamReference, StreamReference>,
+    windows: HashMap<WindowReference, WindowReference>,
+}
+
+impl Renaming {
+    fn new(ir: &StreamIr, dead: &[StreamReference]) -> Self {
+        let streams = ir
+            .streams()
+            .filter(|sr| !dead.contains(sr))
+            .sorted()
+            .chunk_by(|sr| match sr {
+                StreamReference::In(_) => 0,
+                StreamReference::Out(OutputReference::Unparameterized(_)) => 1,
+                StreamReference::Out(OutputReference::Parameterized(_)) => 2,
+            })
+            .into_iter()
+            .flat_map(|(_, streams)| {
+                streams.enumerate().map(|(idx, sr)| {
+                    let new = match sr {
+                        StreamReference::In(_) => StreamReference::In(idx),
+                        StreamReference::Out(OutputReference::Unparameterized(_)) => {
+                            StreamReference::Out(OutputReference::Unparameterized(idx))
+                        }
+                        StreamReference::Out(OutputReference::Parameterized(_)) => {
+                            StreamReference::Out(OutputReference::Parameterized(idx))
+                        }
+                    };
+                    (sr, new)
+                })
+            })
+            .collect();
+        let windows = ir
+            .wref2window
+            .iter()
+            .filter(|(_, window)| !dead.contains(&window.caller))
+            .map(|(wref, _)| *wref)
+            .sorted()
+            .chunk_by(std::mem::discriminant)
+            .into_iter()
+            .flat_map(|(_, windows)| {
+                windows.enumerate().map(|(idx, wref)| {
+                    let new = match wref {
+                        WindowReference::Sliding(_) => WindowReference::Sliding(idx),
+                        WindowReference::Discrete(_) => WindowReference::Discrete(idx),
+                        WindowReference::Instance(_) => WindowReference::Instance(idx),
+                    };
+                    (wref, new)
+                })
+            })
+            .collect();
+        Self { streams, windows }
+    }
+
+    fn sr(&self, sr: StreamReference) -> Option<StreamReference> {
+        self.streams.get(&sr).copied()
+    }
+
+    fn output(&self, sr: OutputReference) -> Option<OutputReference> {
+        self.sr(sr.sr()).map(StreamReference::out_idx)
+    }
+
+    fn wref(&self, wref: WindowReference) -> Option<WindowReference> {
+        self.windows.get(&wref).copied()
+    }
+
+    fn stmt(&self, stmt: &mut Stmt) {
+        let renamed_sr = |sr: &mut StreamReference| *sr = self.streams[sr];
+        let renamed_output = |sr: &mut OutputReference| *sr = self.streams[&sr.sr()].out_idx();
+        let renamed_wref = |wref: &mut WindowReference| *wref = self.windows[wref];
+        match stmt {
+            Stmt::Skip | Stmt::Input(_) => {}
+            Stmt::Seq(stmts) | Stmt::Parallel(stmts) => {
+                stmts.iter_mut().for_each(|stmt| self.stmt(stmt))
+            }
+            Stmt::Shift(sr) => renamed_sr(sr),
+            Stmt::Spawn {
+                sr, with, windows, ..
+            } => {
+                renamed_output(sr);
+                with.iter_mut().flatten().for_each(|expr| self.expr(expr));
+                windows.iter_mut().for_each(renamed_wref);
+            }
+            Stmt::Eval { sr, with, .. } => {
+                renamed_output(sr);
+                self.expr(with);
+            }
+            Stmt::Close { sr, windows, .. } => {
+                renamed_output(sr);
+                windows.iter_mut().for_each(renamed_wref);
+            }
+            Stmt::If(IfStmt { guard, cons, alt }) => {
+                self.guard(guard);
+                self.stmt(cons);
+                self.stmt(alt);
+            }
+            Stmt::Iterate { sr, stmt } => {
+                sr.iter_mut().for_each(renamed_output);
+                self.stmt(stmt);
+            }
+            Stmt::Assign {
+                parameter_expr,
+                sr,
+                stmt,
+            } => {
+                sr.iter_mut().for_each(renamed_output);
+                parameter_expr.iter_mut().for_each(|expr| self.expr(expr));
+                self.stmt(stmt);
+            }
+            Stmt::Let { expr, stmt, .. } => {
+                self.expr(expr);
+                self.stmt(stmt);
+            }
+        }
+    }
+
+    fn guard(&self, guard: &mut Guard) {
+        match guard {
+            Guard::Stream(sr) | Guard::Alive(sr) => *sr = self.streams[sr],
+            Guard::Dynamic(expr) => self.expr(expr),
+            Guard::And { lhs, rhs } | Guard::Or { lhs, rhs } => {
+                self.guard(lhs);
+                self.guard(rhs);
+            }
+            Guard::FastAnd(srs) | Guard::FastOr(srs) => {
+                srs.iter_mut().for_each(|sr| *sr = self.streams[sr])
+            }
+            Guard::GlobalFreq(_) | Guard::LocalFreq(_) | Guard::Constant(_) => {}
+        }
+    }
+
+    fn expr(&self, expr: &mut Expr) {
+        match &mut expr.kind {
+            ExprKind::SyncStreamAccess { target, .. }
+            | ExprKind::OffsetStreamAccess { target, .. }
+            | ExprKind::HoldStreamAccess { target, .. }
+            | ExprKind::GetAccess { target, .. }
+            | ExprKind::IsFresh { target, .. }
+            | ExprKind::ParameterAccess(target, _) => *target = self.streams[target],
+            ExprKind::WindowAccess { target, window, .. } => {
+                *target = self.streams[target];
+                *window = self.windows[window];
+            }
+            ExprKind::LambdaParameterAccess(window, _) => *window = self.windows[window],
+            _ => {}
+        }
+        expr.children_mut()
+            .into_iter()
+            .for_each(|child| self.expr(child));
+    }
+
+    fn window(&self, window: &mut Window) {
+        window.wref = self.windows[&window.wref];
+        window.target = self.streams[&window.target];
+        window.caller = self.streams[&window.caller];
+        self.guard(&mut window.origin_pacing);
+        if let WindowKind::Instances {
+            selection:
+                InstanceSelection::FilteredAll { cond, .. }
+                | InstanceSelection::FilteredFresh { cond, .. },
+        } = &mut window.kind
+        {
+            self.expr(cond);
+        }
+    }
+
+    fn access_kind(&self, kind: StreamAccessKind) -> StreamAccessKind {
+        match kind {
+            StreamAccessKind::DiscreteWindow(wref) => {
+                StreamAccessKind::DiscreteWindow(self.windows[&wref])
+            }
+            StreamAccessKind::SlidingWindow(wref) => {
+                StreamAccessKind::SlidingWindow(self.windows[&wref])
+            }
+            StreamAccessKind::InstanceAggregation(wref) => {
+                StreamAccessKind::InstanceAggregation(self.windows[&wref])
+            }
+            kind => kind,
+        }
+    }
+
+    fn task(&self, task: Task) -> Option<Task> {
+        match task {
+            Task::Spawn(sr) => self.output(sr).map(Task::Spawn),
+            Task::Eval(sr) => self.output(sr).map(Task::Eval),
+            Task::Close(sr) => self.output(sr).map(Task::Close),
+        }
+    }
+
+    fn livetime_equivalences(&self, old: &LivetimeEquivalences) -> LivetimeEquivalences {
+        let outputs: Vec<_> = old
+            .idx
+            .iter()
+            .filter_map(|(sr, idx)| Some((self.output(*sr)?, *idx)))
+            .sorted()
+            .collect();
+        let input_idx = outputs.len();
+        let mut sets = DisjointSet::with_len(outputs.len() + 1);
+        for (i, (_, old_i)) in outputs.iter().enumerate() {
+            if old.sets.is_joined(*old_i, old.input_idx) {
+                sets.join(i, input_idx);
+            }
+            for (j, (_, old_j)) in outputs.iter().enumerate().take(i) {
+                if old.sets.is_joined(*old_i, *old_j) {
+                    sets.join(i, j);
+                }
+            }
+        }
+        LivetimeEquivalences {
+            idx: outputs
+                .into_iter()
+                .enumerate()
+                .map(|(i, (sr, _))| (sr, i))
+                .collect(),
+            sets,
+            input_idx,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ir::{OutputReference, StreamIr, StreamReference},
+        rewrite_rules::{DeadStreamElimination, Rewriter},
+    };
+
+    const SPEC: &str = "input in0 a: UInt(64) = static(single);
+output out0 debug: UInt(64) = static(single);
+output out1 b: UInt(64) = static(single);
+output out2 c: UInt(64) = static(single);
+output out3 \"trigger 0\": String = none;
+trigger out3 = 0;
+livetime [inputs, out0, out1, out2, out3];
+accesses out0 { in0 [eval_with(0) sync] };
+accesses out1 { in0 [eval_with(0) sync] };
+accesses out2 { out1 [eval_with(0) sync] };
+accessed_by in0 { out0 [eval_with(0) sync]; out1 [eval_with(0) sync] };
+accessed_by out1 { out2 [eval_with(0) sync] };
+stmt seq {
+    input in0;
+    if @in0 then
+        seq {
+            eval_0 out0 with in0 * 2u64;
+            eval_0 out1 with in0 + 1u64;
+            eval_0 out2 with out1 + out1;
+            if Expr(out1 > 3u64) then
+                eval_0 out3 with \"alarm\"
+            fi
+        }
+    fi;
+    shift out0;
+    shift out2
+}";
+
+    fn eliminate(observed: &[StreamReference]) -> StreamIr {
+        let ir = StreamIr::from_text(SPEC).unwrap();
+        let rule = DeadStreamElimination::new(&ir, observed.iter().copied());
+        Rewriter::new(vec![Box::new(rule)])
+            .with_verification(true)
+            .run(ir)
+            .unwrap()
+    }
+
+    #[test]
+    fn renumbering() {
+        let ir = eliminate(&[StreamReference::Out(OutputReference::Unparameterized(2))]);
+        let reference = "input in0 a: UInt(64) = static(single);
+output out0 b: UInt(64) = static(single);
+output out1 c: UInt(64) = static(single);
+livetime [inputs, out0, out1];
+accesses out0 { in0 [eval_with(0) sync] };
+accesses out1 { out0 [eval_with(0) sync] };
+accessed_by in0 { out0 [eval_with(0) sync] };
+accessed_by out0 { out1 [eval_with(0) sync] };
+stmt seq {
+    input in0;
+    if @in0 then
+        seq {
+            eval_0 out0 with in0 + 1u64;
+            eval_0 out1 with out0 + out0
+        }
+    fi;
+    shift out1
+}";
+        assert_eq!(ir.to_text(), reference);
+    }
+
+    #[test]
+    fn guard_dependencies() {
+        let ir = eliminate(&[StreamReference::Out(OutputReference::Unparameterized(3))]);
+        let reference = "input in0 a: UInt(64) = static(single);
+output out0 b: UInt(64) = static(single);
+output out1 \"trigger 0\": String = none;
+trigger out1 = 0;
+livetime [inputs, out0, out1];
+accesses out0 { in0 [eval_with(0) sync] };
+accessed_by in0 { out0 [eval_with(0) sync] };
+accessed_by out0 {};
+stmt seq {
+    input in0;
+    if @in0 then
+        seq {
+            eval_0 out0 with in0 + 1u64;
+            if Expr(out0 > 3u64) then
+                eval_0 out1 with \"alarm\"
+            fi
+        }
+    fi
+}";
+        assert_eq!(ir.to_text(), reference);
+    }
+}
@@ -78,8 +78,7 @@ mod tests {
 
     use crate::{
         ir::{parse::parse_guard, LivetimeEquivalences},
-        rewrite_rules::{fast_guards::FastGuards, RewriteRule},
-        SimplifyGuard,
+        rewrite_rules::{fast_guards::FastGuards, RewriteRule, SimplifyGuard},
     };
 
     #[test]
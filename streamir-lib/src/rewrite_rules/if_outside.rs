@@ -144,7 +144,8 @@ mod tests {
                 fi
         ",
         );
-        let rewriter = Rewriter::new(vec![Box::new(MoveIfOutside {}), Box::new(RemoveSkip {})]);
+        let rewriter = Rewriter::new(vec![Box::new(MoveIfOutside {}), Box::new(RemoveSkip {})])
+            .with_verification(true);
         let (res, changed) = rewriter.apply(ir).unwrap();
         assert!(changed);
         assert!(res.stmt.eq(&reference.stmt));
@@ -159,7 +160,8 @@ mod tests {
                 fi
         ",
         );
-        let rewriter = Rewriter::new(vec![Box::new(MoveIfOutside {}), Box::new(RemoveSkip {})]);
+        let rewriter = Rewriter::new(vec![Box::new(MoveIfOutside {}), Box::new(RemoveSkip {})])
+            .with_verification(true);
         let (res, changed) = rewriter.apply(ir.clone()).unwrap();
         assert!(!changed);
         assert!(res.stmt.eq(&ir.stmt));
@@ -183,7 +185,8 @@ mod tests {
             fi
         ",
         );
-        let rewriter = Rewriter::new(vec![Box::new(MoveIfOutside {}), Box::new(RemoveSkip {})]);
+        let rewriter = Rewriter::new(vec![Box::new(MoveIfOutside {}), Box::new(RemoveSkip {})])
+            .with_verification(true);
         let (res, changed) = rewriter.apply(ir).unwrap();
         assert!(changed);
         assert!(res.stmt.eq(&reference.stmt));
@@ -199,6 +202,7 @@ mod tests {
                 parameters: vec![],
                 default: None,
             },
+            span: crate::ir::SourceSpan::UNKNOWN,
         };
         let g1 = Guard::LocalFreq(0);
         let g2 = Guard::Dynamic(expr);
@@ -1,11 +1,14 @@
 use std::iter;
 
-use crate::ir::{Guard, IfStmt, LivetimeEquivalences, Stmt};
+use crate::ir::{Bdd, Guard, GuardBdd, IfStmt, LivetimeEquivalences, Stmt};
 
 use super::{ChangeSet, RewriteError, RewriteRule};
 
 #[derive(Debug, Clone, Copy)]
 /// Remove guard conditions that are already implied because the guard is nested inside an outer guard block.
+///
+/// The guards are compared by their BDDs, such that a guard is simplified whenever it is equivalent to a smaller guard
+/// under the conjunction of the outer guards.
 pub struct ImpliedGuards;
 
 impl RewriteRule for ImpliedGuards {
@@ -13,16 +16,17 @@ impl RewriteRule for ImpliedGuards {
         &self,
         stmt: Stmt,
         _memory: &std::collections::HashMap<crate::ir::StreamReference, crate::ir::memory::Memory>,
-        _le: &LivetimeEquivalences,
+        le: &LivetimeEquivalences,
     ) -> Result<(Stmt, ChangeSet), RewriteError> {
-        Self::remove_implied_guards(stmt, Vec::new())
+        Self::remove_implied_guards(stmt, &mut GuardBdd::new(le), Bdd::TRUE)
     }
 }
 
 impl ImpliedGuards {
     fn remove_implied_guards(
         stmt: Stmt,
-        implied_guards: Vec<Guard>,
+        bdd: &mut GuardBdd,
+        implied: Bdd,
     ) -> Result<(Stmt, ChangeSet), RewriteError> {
         match stmt {
             Stmt::Shift(_)
@@ -37,8 +41,7 @@ impl ImpliedGuards {
                     stmts
                         .into_iter()
                         .map(|stmt| {
-                            let (stmt, cur_cs) =
-                                Self::remove_implied_guards(stmt, implied_guards.clone())?;
+                            let (stmt, cur_cs) = Self::remove_implied_guards(stmt, bdd, implied)?;
                             cs += cur_cs;
                             Ok(stmt)
                         })
@@ -52,8 +55,7 @@ impl ImpliedGuards {
                     stmts
                         .into_iter()
                         .map(|stmt| {
-                            let (stmt, cur_cs) =
-                                Self::remove_implied_guards(stmt, implied_guards.clone())?;
+                            let (stmt, cur_cs) = Self::remove_implied_guards(stmt, bdd, implied)?;
                             cs += cur_cs;
                             Ok(stmt)
                         })
@@ -62,7 +64,7 @@ impl ImpliedGuards {
                 Ok((stmt, cs))
             }
             Stmt::Iterate { sr, stmt } => {
-                let (stmt, cs) = Self::remove_implied_guards(*stmt, implied_guards)?;
+                let (stmt, cs) = Self::remove_implied_guards(*stmt, bdd, implied)?;
                 Ok((
                     Stmt::Iterate {
                         sr,
@@ -76,7 +78,7 @@ impl ImpliedGuards {
                 sr,
                 stmt,
             } => {
-                let (stmt, cs) = Self::remove_implied_guards(*stmt, implied_guards)?;
+                let (stmt, cs) = Self::remove_implied_guards(*stmt, bdd, implied)?;
                 Ok((
                     Stmt::Assign {
                         parameter_expr,
@@ -86,12 +88,32 @@ impl ImpliedGuards {
                     cs,
                 ))
             }
+            Stmt::Let { var, expr, stmt } => {
+                // the binding shadows the variable in guards of outer statements
+                let implied = bdd.forget(implied, |g| {
+                    matches!(g, Guard::Dynamic(e) if e.variables().iter().any(|(v, _)| *v == var))
+                });
+                let (stmt, cs) = Self::remove_implied_guards(*stmt, bdd, implied)?;
+                Ok((
+                    Stmt::Let {
+                        var,
+                        expr,
+                        stmt: Box::new(stmt),
+                    },
+                    cs,
+                ))
+            }
             Stmt::If(IfStmt { guard, cons, alt }) => {
-                let (new_guard, changed) = guard.remove_implied(&implied_guards);
-                let mut cons_implied_guards = implied_guards.clone();
-                cons_implied_guards.extend(new_guard.implied_subguards());
-                let (cons, cs1) = Self::remove_implied_guards(*cons, cons_implied_guards)?;
-                let (alt, cs2) = Self::remove_implied_guards(*alt, implied_guards)?;
+                let guard_bdd = bdd.from_guard(&guard);
+                let simplified = bdd.simplify(guard_bdd, implied);
+                let (new_guard, changed) = if simplified.num_atoms() < guard.num_atoms() {
+                    (simplified, true)
+                } else {
+                    (guard, false)
+                };
+                let cons_implied = bdd.and(implied, guard_bdd);
+                let (cons, cs1) = Self::remove_implied_guards(*cons, bdd, cons_implied)?;
+                let (alt, cs2) = Self::remove_implied_guards(*alt, bdd, implied)?;
                 let stmt = Stmt::If(IfStmt {
                     guard: new_guard,
                     cons: Box::new(cons),
@@ -122,41 +144,7 @@ impl Guard {
             Guard::And { lhs, rhs } => {
                 Box::new(lhs.implied_subguards().chain(rhs.implied_subguards()))
             }
-            Guard::FastAnd(_) => panic!("FastAnd not supported for this rewrite rule"),
-        }
-    }
-
-    fn remove_implied(self, guards: &Vec<Guard>) -> (Guard, bool) {
-        if let Guard::Constant(true) = self {
-            return (self, false);
-        }
-        if guards.contains(&self) {
-            return (Guard::Constant(true), true);
-        }
-        match self {
-            Guard::And { lhs, rhs } => {
-                let (new_lhs, changed1) = lhs.remove_implied(guards);
-                let (new_rhs, changed2) = rhs.remove_implied(guards);
-                (
-                    Guard::And {
-                        lhs: Box::new(new_lhs),
-                        rhs: Box::new(new_rhs),
-                    },
-                    changed1 || changed2,
-                )
-            }
-            Guard::Or { lhs, rhs } => {
-                let (new_lhs, changed1) = lhs.remove_implied(guards);
-                let (new_rhs, changed2) = rhs.remove_implied(guards);
-                (
-                    Guard::Or {
-                        lhs: Box::new(new_lhs),
-                        rhs: Box::new(new_rhs),
-                    },
-                    changed1 || changed2,
-                )
-            }
-            _ => (self, false),
+            Guard::FastAnd(srs) => Box::new(srs.iter().map(|sr| Guard::Stream(*sr))),
         }
     }
 }
@@ -192,7 +180,8 @@ mod tests {
             Box::new(ImpliedGuards {}),
             Box::new(SimplifyGuard {}),
             Box::new(RemoveIfs {}),
-        ]);
+        ])
+        .with_verification(true);
         let (res, changed) = rewriter.apply(ir).unwrap();
         assert!(changed);
         assert!(res.stmt.eq(&reference.stmt));
@@ -219,7 +208,8 @@ mod tests {
             Box::new(ImpliedGuards {}),
             Box::new(SimplifyGuard {}),
             Box::new(RemoveIfs {}),
-        ]);
+        ])
+        .with_verification(true);
         let (res, changed) = rewriter.apply(ir).unwrap();
         assert!(changed);
         assert!(res.stmt.eq(&reference.stmt));
@@ -248,7 +238,8 @@ mod tests {
             Box::new(ImpliedGuards {}),
             Box::new(SimplifyGuard {}),
             Box::new(RemoveIfs {}),
-        ]);
+        ])
+        .with_verification(true);
         let (res, changed) = rewriter.apply(ir).unwrap();
         assert!(changed);
         assert!(res.stmt.eq(&reference.stmt));
@@ -275,7 +266,8 @@ mod tests {
             Box::new(ImpliedGuards {}),
             Box::new(SimplifyGuard {}),
             Box::new(RemoveIfs {}),
-        ]);
+        ])
+        .with_verification(true);
         let (res, changed) = rewriter.apply(ir).unwrap();
         assert!(changed);
         assert!(res.stmt.eq(&reference.stmt));
@@ -296,7 +288,8 @@ mod tests {
             Box::new(ImpliedGuards {}),
             Box::new(SimplifyGuard {}),
             Box::new(RemoveIfs {}),
-        ]);
+        ])
+        .with_verification(true);
         let (res, changed) = rewriter.apply(ir.clone()).unwrap();
         assert!(!changed);
         assert!(res.stmt.eq(&res.stmt));
@@ -324,7 +317,8 @@ mod tests {
             Box::new(ImpliedGuards {}),
             Box::new(SimplifyGuard {}),
             Box::new(RemoveIfs {}),
-        ]);
+        ])
+        .with_verification(true);
         let (res, changed) = rewriter.apply(ir).unwrap();
         assert!(changed);
         assert!(res.stmt.eq(&reference.stmt));
@@ -370,7 +364,36 @@ mod tests {
             Box::new(ImpliedGuards {}),
             Box::new(SimplifyGuard {}),
             Box::new(RemoveIfs {}),
-        ]);
+        ])
+        .with_verification(true);
+        let (res, changed) = rewriter.apply(ir).unwrap();
+        assert!(changed);
+        assert!(res.stmt.eq(&reference.stmt));
+    }
+
+    #[test]
+    fn fast_and() {
+        let ir = parse_ir(
+            "
+			if FastAnd(0, 1) then
+				if @1 && (@2 || @0) then
+					input 0
+				fi
+			fi
+		",
+        );
+        let reference = parse_ir(
+            "
+		if FastAnd(0, 1) then
+			input 0
+		fi",
+        );
+        let rewriter = Rewriter::new(vec![
+            Box::new(ImpliedGuards {}),
+            Box::new(SimplifyGuard {}),
+            Box::new(RemoveIfs {}),
+        ])
+        .with_verification(true);
         let (res, changed) = rewriter.apply(ir).unwrap();
         assert!(changed);
         assert!(res.stmt.eq(&reference.stmt));
@@ -0,0 +1,453 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::ir::{
+    expressions::{Expr, ExprKind, Function},
+    memory::Memory,
+    Guard, IfStmt, LivetimeEquivalences, OutputReference, OverflowPolicy, Stmt, StreamReference,
+    Type, VariableReference,
+};
+
+use super::{ChangeSet, CommonSubexpressionElimination as Cse, RewriteError, RewriteRule};
+
+#[derive(Debug, Clone, Copy)]
+/// A rewriting rule that moves work not depending on the current instance outside of iterate and assign statements.
+///
+/// The invariant conjuncts of the guard of a conditional directly inside the statement are checked before the statement,
+/// and the largest invariant subexpression that is always evaluated for an instance is bound to a variable before the statement,
+/// unless it is a leaf such as a bare stream access.
+/// Invariant bindings directly inside the statement are moved outside as a whole.
+/// A guard or expression is invariant if it neither accesses the parameters, the instances of the iterated streams,
+/// windows, local frequencies nor variables bound inside the statement, and if the statement does not change the streams it reads.
+/// The binding is evaluated even if no instance is alive, so expressions are assumed to be free of side effects,
+/// and integer arithmetic, which can fail on a division by zero or an overflow under [OverflowPolicy::Trap], is not moved.
+pub struct LoopInvariantCodeMotion;
+
+impl RewriteRule for LoopInvariantCodeMotion {
+    fn rewrite_stmt(
+        &self,
+        stmt: Stmt,
+        _memory: &HashMap<StreamReference, Memory>,
+        _liveness_equivalences: &LivetimeEquivalences,
+    ) -> Result<(Stmt, ChangeSet), RewriteError> {
+        let (streams, body) = match &stmt {
+            Stmt::Iterate { sr, stmt } | Stmt::Assign { sr, stmt, .. } => (sr, stmt.as_ref()),
+            _ => return Ok((stmt, ChangeSet::default())),
+        };
+        let invariant = Invariant::new(streams, body);
+
+        if let Stmt::If(IfStmt { guard, cons, alt }) = body {
+            let (hoisted, remaining): (Vec<&Guard>, Vec<&Guard>) = conjuncts(guard)
+                .into_iter()
+                .partition(|conjunct| invariant.guard(conjunct));
+            let alt_skip = matches!(**alt, Stmt::Skip);
+            if !hoisted.is_empty() && (remaining.is_empty() || alt_skip) {
+                let inner = match conjunction(remaining) {
+                    Some(guard) => Stmt::If(IfStmt {
+                        guard,
+                        cons: cons.clone(),
+                        alt: Box::new(Stmt::Skip),
+                    }),
+                    None => *cons.clone(),
+                };
+                let alt = if alt_skip {
+                    Stmt::Skip
+                } else {
+                    Self::with_body(&stmt, *alt.clone())
+                };
+                let stmt = Stmt::If(IfStmt {
+                    guard: conjunction(hoisted).unwrap(),
+                    cons: Box::new(Self::with_body(&stmt, inner)),
+                    alt: Box::new(alt),
+                });
+                return Ok((stmt, ChangeSet::local_change()));
+            }
+        }
+
+        if let Stmt::Let {
+            var,
+            expr,
+            stmt: inner,
+        } = body
+        {
+            if invariant.expr(expr) {
+                let stmt = Stmt::Let {
+                    var: *var,
+                    expr: expr.clone(),
+                    stmt: Box::new(Self::with_body(&stmt, *inner.clone())),
+                };
+                return Ok((stmt, ChangeSet::local_change()));
+            }
+        }
+
+        let mut candidates = Vec::new();
+        Self::always_evaluated(body)
+            .into_iter()
+            .for_each(|root| Cse::strict_subexpressions(root, &mut candidates));
+        let Some(expr) = candidates
+            .into_iter()
+            .filter(|expr| {
+                Cse::size(expr) >= Self::MIN_SIZE && Cse::is_candidate(expr) && invariant.expr(expr)
+            })
+            .min_by_key(|expr| std::cmp::Reverse(Cse::size(expr)))
+            .cloned()
+        else {
+            return Ok((stmt, ChangeSet::default()));
+        };
+        let var = Cse::fresh_variable(std::slice::from_ref(&stmt));
+        let mut body = body.clone();
+        Self::replace_in_stmt(&mut body, &expr, var);
+        let stmt = Stmt::Let {
+            var,
+            expr,
+            stmt: Box::new(Self::with_body(&stmt, body)),
+        };
+        Ok((stmt, ChangeSet::local_change()))
+    }
+}
+
+impl LoopInvariantCodeMotion {
+    /// The smallest size of a hoisted subexpression, binding a leaf such as a bare stream access to a variable saves no work
+    const MIN_SIZE: usize = 2;
+
+    /// Returns the iterate or assign statement with the given statement executed for the instance
+    fn with_body(stmt: &Stmt, body: Stmt) -> Stmt {
+        match stmt {
+            Stmt::Iterate { sr, .. } => Stmt::Iterate {
+                sr: sr.clone(),
+                stmt: Box::new(body),
+            },
+            Stmt::Assign {
+                parameter_expr, sr, ..
+            } => Stmt::Assign {
+                parameter_expr: parameter_expr.clone(),
+                sr: sr.clone(),
+                stmt: Box::new(body),
+            },
+            _ => unreachable!("only called for iterate and assign statements"),
+        }
+    }
+
+    /// Returns the expressions that are always evaluated when the statement is executed
+    fn always_evaluated(stmt: &Stmt) -> Vec<&Expr> {
+        match stmt {
+            Stmt::Seq(stmts) | Stmt::Parallel(stmts) => {
+                stmts.iter().flat_map(Self::always_evaluated).collect()
+            }
+            Stmt::Let { stmt: inner, .. } => {
+                let mut roots = Cse::strict_roots(stmt);
+                roots.extend(Self::always_evaluated(inner));
+                roots
+            }
+            stmt => Cse::strict_roots(stmt),
+        }
+    }
+
+    /// Replaces all occurrences of `expr` in the statement with the variable
+    fn replace_in_stmt(stmt: &mut Stmt, expr: &Expr, var: VariableReference) {
+        fn replace_in_guard(guard: &mut Guard, expr: &Expr, var: VariableReference) {
+            match guard {
+                Guard::Dynamic(e) => LoopInvariantCodeMotion::replace(e, expr, var),
+                Guard::And { lhs, rhs } | Guard::Or { lhs, rhs } => {
+                    replace_in_guard(lhs, expr, var);
+                    replace_in_guard(rhs, expr, var);
+                }
+                _ => {}
+            }
+        }
+        match stmt {
+            Stmt::Skip
+            | Stmt::Shift(_)
+            | Stmt::Input(_)
+            | Stmt::Spawn { with: None, .. }
+            | Stmt::Close { .. } => {}
+            Stmt::Seq(stmts) | Stmt::Parallel(stmts) => stmts
+                .iter_mut()
+                .for_each(|stmt| Self::replace_in_stmt(stmt, expr, var)),
+            Stmt::Spawn {
+                with: Some(with), ..
+            } => with.iter_mut().for_each(|e| Self::replace(e, expr, var)),
+            Stmt::Eval { with, .. } => Self::replace(with, expr, var),
+            Stmt::If(IfStmt { guard, cons, alt }) => {
+                replace_in_guard(guard, expr, var);
+                Self::replace_in_stmt(cons, expr, var);
+                Self::replace_in_stmt(alt, expr, var);
+            }
+            Stmt::Iterate { stmt, .. } => Self::replace_in_stmt(stmt, expr, var),
+            Stmt::Assign {
+                parameter_expr,
+                stmt,
+                ..
+            } => {
+                parameter_expr
+                    .iter_mut()
+                    .for_each(|e| Self::replace(e, expr, var));
+                Self::replace_in_stmt(stmt, expr, var);
+            }
+            Stmt::Let { expr: e, stmt, .. } => {
+                Self::replace(e, expr, var);
+                Self::replace_in_stmt(stmt, expr, var);
+            }
+        }
+    }
+
+    fn replace(e: &mut Expr, expr: &Expr, var: VariableReference) {
+        if e == expr {
+            *e = Expr {
+                ty: expr.ty.clone(),
+                kind: ExprKind::Variable(var),
+                span: e.span,
+            };
+        } else {
+            e.children_mut()
+                .into_iter()
+                .for_each(|child| Self::replace(child, expr, var));
+        }
+    }
+}
+
+/// Decides which guards and expressions inside an iterate or assign statement do not depend on the instance
+struct Invariant<'a> {
+    /// The iterated or assigned streams
+    streams: &'a [OutputReference],
+    /// The statement executed for each instance
+    body: &'a Stmt,
+    /// The variables bound inside the body
+    bound: HashSet<VariableReference>,
+}
+
+impl<'a> Invariant<'a> {
+    fn new(streams: &'a [OutputReference], body: &'a Stmt) -> Self {
+        fn bound(stmt: &Stmt, vars: &mut HashSet<VariableReference>) {
+            match stmt {
+                Stmt::Skip
+                | Stmt::Shift(_)
+                | Stmt::Input(_)
+                | Stmt::Spawn { .. }
+                | Stmt::Eval { .. }
+                | Stmt::Close { .. } => {}
+                Stmt::Seq(stmts) | Stmt::Parallel(stmts) => {
+                    stmts.iter().for_each(|stmt| bound(stmt, vars))
+                }
+                Stmt::If(IfStmt { cons, alt, .. }) => {
+                    bound(cons, vars);
+                    bound(alt, vars);
+                }
+                Stmt::Iterate { stmt, .. } | Stmt::Assign { stmt, .. } => bound(stmt, vars),
+                Stmt::Let { var, stmt, .. } => {
+                    vars.insert(*var);
+                    bound(stmt, vars);
+                }
+            }
+        }
+        let mut vars = HashSet::new();
+        bound(body, &mut vars);
+        Self {
+            streams,
+            body,
+            bound: vars,
+        }
+    }
+
+    fn guard(&self, guard: &Guard) -> bool {
+        match guard {
+            Guard::Stream(sr) | Guard::Alive(sr) => {
+                !matches!(sr, StreamReference::Out(OutputReference::Parameterized(_)))
+                    && self.unchanged(*sr)
+            }
+            Guard::FastAnd(srs) | Guard::FastOr(srs) => srs.iter().all(|sr| {
+                !matches!(sr, StreamReference::Out(OutputReference::Parameterized(_)))
+                    && self.unchanged(*sr)
+            }),
+            Guard::GlobalFreq(_) | Guard::Constant(_) => true,
+            Guard::LocalFreq(_) => false,
+            Guard::Dynamic(expr) => self.expr(expr),
+            Guard::And { lhs, rhs } | Guard::Or { lhs, rhs } => self.guard(lhs) && self.guard(rhs),
+        }
+    }
+
+    /// Returns whether the expression does not depend on the instance and can be evaluated even if no instance is alive
+    fn expr(&self, expr: &Expr) -> bool {
+        let mut reads = (Vec::new(), Vec::new());
+        Cse::reads(expr, &mut reads);
+        self.independent(expr) && !Cse::writes(self.body, &reads) && !can_fail(expr)
+    }
+
+    /// Returns whether the expression does not refer to the current instance
+    fn independent(&self, expr: &Expr) -> bool {
+        let own = match &expr.kind {
+            ExprKind::ParameterAccess(..)
+            | ExprKind::LambdaParameterAccess(..)
+            | ExprKind::WindowAccess { .. } => false,
+            ExprKind::Variable(var) => !self.bound.contains(var),
+            ExprKind::SyncStreamAccess { target, parameters }
+            | ExprKind::IsFresh { target, parameters }
+            | ExprKind::OffsetStreamAccess {
+                target, parameters, ..
+            }
+            | ExprKind::HoldStreamAccess {
+                target, parameters, ..
+            }
+            | ExprKind::GetAccess {
+                target, parameters, ..
+            } => match target {
+                StreamReference::Out(sr) if self.streams.contains(sr) => false,
+                // an access to a parameterized stream without parameters refers to the current instance
+                StreamReference::Out(OutputReference::Parameterized(_)) => !parameters.is_empty(),
+                _ => true,
+            },
+            _ => true,
+        };
+        own && expr
+            .children()
+            .into_iter()
+            .all(|child| self.independent(child))
+    }
+
+    fn unchanged(&self, sr: StreamReference) -> bool {
+        !Cse::writes(self.body, &(vec![sr], Vec::new()))
+    }
+}
+
+/// Returns whether evaluating the expression might fail, which is the case for integer arithmetic
+fn can_fail(expr: &Expr) -> bool {
+    let integer = |e: &Expr| matches!(e.ty, Type::Int(_) | Type::UInt(_));
+    let own = match &expr.kind {
+        ExprKind::BinaryOperation(op, lhs, _) => OverflowPolicy::governs(*op) && integer(lhs),
+        ExprKind::UnaryOperation(op, operand) => OverflowPolicy::governs(*op) && integer(operand),
+        ExprKind::FunctionCall(Function::Pow, _) => integer(expr),
+        _ => false,
+    };
+    own || expr.children().into_iter().any(can_fail)
+}
+
+/// Returns the conjuncts of the guard
+fn conjuncts(guard: &Guard) -> Vec<&Guard> {
+    match guard {
+        Guard::And { lhs, rhs } => {
+            let mut res = conjuncts(lhs);
+            res.extend(conjuncts(rhs));
+            res
+        }
+        guard => vec![guard],
+    }
+}
+
+/// Returns the right-nested conjunction of the guards, or None if there are no guards
+fn conjunction(guards: Vec<&Guard>) -> Option<Guard> {
+    guards
+        .into_iter()
+        .cloned()
+        .rev()
+        .reduce(|rhs, lhs| Guard::And {
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ir::{parse::parse_text, Stmt},
+        rewrite_rules::{LoopInvariantCodeMotion, Rewriter},
+    };
+
+    const STREAMS: &str = "input in0 a: UInt(64) = static(single);
+input in1 b: UInt(64) = static(single);
+output out0 c: UInt(64) = static(single);
+output pout0 d(p: UInt(64)): UInt(64) = instances(unbounded);
+local 0 = 1s on pout0;
+";
+
+    fn rewrite(stmt: &str) -> Stmt {
+        let rewriter =
+            Rewriter::new(vec![Box::new(LoopInvariantCodeMotion)]).with_verification(true);
+        rewriter.run(parse_text(STREAMS, stmt)).unwrap().stmt
+    }
+
+    #[test]
+    fn invariant_conjuncts() {
+        let res = rewrite(
+            "iterate [pout0]
+                if @in0 && Expr(param(pout0, 0) > 1u64) && Expr(in1 > 2u64) then
+                    eval_0 pout0 with param(pout0, 0)
+                fi",
+        );
+        let reference = parse_text(
+            STREAMS,
+            "if @in0 && Expr(in1 > 2u64) then
+                iterate [pout0]
+                    if Expr(param(pout0, 0) > 1u64) then
+                        eval_0 pout0 with param(pout0, 0)
+                    fi
+            fi",
+        );
+        assert_eq!(res, reference.stmt);
+    }
+
+    #[test]
+    fn invariant_subexpression() {
+        let res = rewrite(
+            "assign [pout0] (in0)
+                iterate [pout0]
+                    eval_0 pout0 with param(pout0, 0) + (in1.hold(or: 0u64) * 2u64)",
+        );
+        // the multiplication might overflow, so it is only evaluated for alive instances
+        let reference = parse_text(
+            STREAMS,
+            "let var0 = in1.hold(or: 0u64)
+                assign [pout0] (in0)
+                    iterate [pout0]
+                        eval_0 pout0 with param(pout0, 0) + (var0 * 2u64)",
+        );
+        assert_eq!(res, reference.stmt);
+    }
+
+    #[test]
+    fn failing_arithmetic() {
+        let res = rewrite(
+            "iterate [pout0]
+                if Expr(in0 / in1 > 2u64) then
+                    let var0 = in0 % in1
+                        eval_0 pout0 with param(pout0, 0) + var0
+                fi",
+        );
+        // the division and the remainder fail without an alive instance if the divisor is zero,
+        // and binding their bare operands to variables saves no work
+        let reference = parse_text(
+            STREAMS,
+            "iterate [pout0]
+                if Expr((in0 / in1) > 2u64) then
+                    let var0 = in0 % in1
+                        eval_0 pout0 with param(pout0, 0) + var0
+                fi",
+        );
+        assert_eq!(res, reference.stmt);
+    }
+
+    #[test]
+    fn instance_dependent() {
+        let stmts = [
+            // the alive check and the local frequency of the instance
+            "iterate [pout0]
+                if ?pout0 && Local(0) then
+                    eval_0 pout0 with 1u64
+                fi",
+            // the stream written for each instance
+            "iterate [pout0]
+                seq {
+                    eval_0 out0 with param(pout0, 0);
+                    eval_0 pout0 with out0.hold(or: 0u64) * 2u64
+                }",
+            // the guard of an alternative without all conjuncts being invariant
+            "iterate [pout0]
+                if @in0 && Expr(param(pout0, 0) > 1u64) then
+                    eval_0 pout0 with 1u64
+                else
+                    eval_0 pout0 with 2u64
+                fi",
+        ];
+        for stmt in stmts {
+            assert_eq!(rewrite(stmt), parse_text(STREAMS, stmt).stmt);
+        }
+    }
+}
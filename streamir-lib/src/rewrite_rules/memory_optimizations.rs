@@ -40,10 +40,12 @@ impl RewriteRule for MemoryOptimizations {
             StreamMemory::Instances {
                 buffer: StreamBuffer::Bounded(0),
                 parameter,
+                bound,
             } => Ok((
                 StreamMemory::Instances {
                     buffer: StreamBuffer::SingleValue,
                     parameter,
+                    bound,
                 },
                 ChangeSet::local_change(),
             )),
@@ -0,0 +1,439 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::ir::{
+    expressions::{Expr, ExprKind},
+    memory::Memory,
+    Guard, IfStmt, LivetimeEquivalences, SourceSpan, Stmt, StreamIr, StreamReference, Type,
+    ValueRanges,
+};
+
+use super::{ChangeSet, ConstantFolding, RewriteError, RewriteRule};
+
+/// The widths that integer streams are narrowed to
+const WIDTHS: [u16; 3] = [8, 16, 32];
+
+#[derive(Debug, Clone)]
+/// A rewriting rule that stores the values of integer output streams with fewer bits if their range allows it.
+///
+/// The ranges are computed by [StreamIr::value_ranges].
+/// The type of a narrowed stream is replaced in its memory, and its accesses are cast back to the original type,
+/// while the expressions of its evaluations are cast to the narrower type.
+/// The casts are pushed into conditionals and cancel out with the casts of other narrowed streams,
+/// such that, e.g., a stream holding the value of another narrowed stream needs no conversion at all.
+///
+/// Input streams keep their type, as it is given by the interface of the monitor.
+/// Streams that are aggregated by a window or accessed without a default value are not narrowed either,
+/// nor are streams with a default value that does not fit the narrower type.
+/// Like [SpecializeInputs](super::SpecializeInputs), the rule identifies the streams by their name.
+pub struct NarrowIntegers {
+    types: HashMap<String, Type>,
+}
+
+impl NarrowIntegers {
+    /// Creates the rule narrowing the streams according to the ranges of [StreamIr::value_ranges].
+    pub fn new(ir: &StreamIr) -> Self {
+        Self::with_ranges(ir, &ir.value_ranges())
+    }
+
+    /// Creates the rule narrowing the streams according to the given ranges,
+    /// e.g., computed by [StreamIr::value_ranges_with] under bounds on the input streams.
+    pub fn with_ranges(ir: &StreamIr, ranges: &ValueRanges) -> Self {
+        let mut types: HashMap<StreamReference, Type> = ir
+            .outputs()
+            .map(|o| o.sr())
+            .filter_map(|sr| {
+                let range = ranges.streams.get(&sr)?;
+                let narrow = match &ir.stream_memory(sr).ty {
+                    Type::UInt(bits) => WIDTHS
+                        .iter()
+                        .filter(|width| *width < bits)
+                        .map(|width| Type::UInt(*width))
+                        .find(|ty| range.fits(ty))?,
+                    Type::Int(bits) => WIDTHS
+                        .iter()
+                        .filter(|width| *width < bits)
+                        .map(|width| Type::Int(*width))
+                        .find(|ty| range.fits(ty))?,
+                    _ => return None,
+                };
+                Some((sr, narrow))
+            })
+            .collect();
+
+        let mut excluded: HashSet<StreamReference> = ir
+            .wref2window
+            .values()
+            .map(|window| window.target)
+            .collect();
+        Self::excluded_in_stmt(&ir.stmt, &types, &mut excluded);
+        types.retain(|sr, _| !excluded.contains(sr));
+        let types = types
+            .into_iter()
+            .map(|(sr, ty)| (ir.name(sr).to_owned(), ty))
+            .collect();
+        Self { types }
+    }
+
+    /// Collects the streams that are accessed in a way that prevents their narrowing
+    fn excluded_in_stmt(
+        stmt: &Stmt,
+        types: &HashMap<StreamReference, Type>,
+        excluded: &mut HashSet<StreamReference>,
+    ) {
+        let mut exprs = |exprs: &mut dyn Iterator<Item = &Expr>| {
+            exprs.for_each(|expr| Self::excluded_in_expr(expr, types, excluded))
+        };
+        match stmt {
+            Stmt::Skip | Stmt::Shift(_) | Stmt::Input(_) | Stmt::Close { .. } => {}
+            Stmt::Spawn { with, .. } => exprs(&mut with.iter().flatten()),
+            Stmt::Eval { with, .. } => exprs(&mut std::iter::once(with)),
+            Stmt::Seq(stmts) | Stmt::Parallel(stmts) => stmts
+                .iter()
+                .for_each(|stmt| Self::excluded_in_stmt(stmt, types, excluded)),
+            Stmt::If(IfStmt { guard, cons, alt }) => {
+                Self::excluded_in_guard(guard, types, excluded);
+                Self::excluded_in_stmt(cons, types, excluded);
+                Self::excluded_in_stmt(alt, types, excluded);
+            }
+            Stmt::Iterate { stmt, .. } => Self::excluded_in_stmt(stmt, types, excluded),
+            Stmt::Assign {
+                parameter_expr,
+                stmt,
+                ..
+            } => {
+                exprs(&mut parameter_expr.iter());
+                Self::excluded_in_stmt(stmt, types, excluded)
+            }
+            Stmt::Let { expr, stmt, .. } => {
+                exprs(&mut std::iter::once(expr));
+                Self::excluded_in_stmt(stmt, types, excluded)
+            }
+        }
+    }
+
+    fn excluded_in_guard(
+        guard: &Guard,
+        types: &HashMap<StreamReference, Type>,
+        excluded: &mut HashSet<StreamReference>,
+    ) {
+        match guard {
+            Guard::Dynamic(expr) => Self::excluded_in_expr(expr, types, excluded),
+            Guard::And { lhs, rhs } | Guard::Or { lhs, rhs } => {
+                Self::excluded_in_guard(lhs, types, excluded);
+                Self::excluded_in_guard(rhs, types, excluded);
+            }
+            _ => {}
+        }
+    }
+
+    fn excluded_in_expr(
+        expr: &Expr,
+        types: &HashMap<StreamReference, Type>,
+        excluded: &mut HashSet<StreamReference>,
+    ) {
+        match &expr.kind {
+            ExprKind::OffsetStreamAccess {
+                target, default, ..
+            }
+            | ExprKind::HoldStreamAccess {
+                target, default, ..
+            }
+            | ExprKind::GetAccess {
+                target, default, ..
+            } => {
+                let fits = match (default, types.get(target)) {
+                    (_, None) => true,
+                    // the default has to be a constant that fits the narrower type
+                    (Some(default), Some(ty)) => {
+                        matches!(default.kind, ExprKind::Constant(_))
+                            && matches!(
+                                Self::narrow(default.as_ref().clone(), ty).kind,
+                                ExprKind::Constant(_)
+                            )
+                    }
+                    (None, Some(_)) => false,
+                };
+                if !fits {
+                    excluded.insert(*target);
+                }
+            }
+            _ => {}
+        }
+        expr.children()
+            .into_iter()
+            .for_each(|child| Self::excluded_in_expr(child, types, excluded));
+    }
+
+    /// Converts the expression to the narrower type, pushing the cast into conditionals and removing widening casts
+    fn narrow(expr: Expr, ty: &Type) -> Expr {
+        let Expr { kind, span, .. } = expr;
+        let kind = match kind {
+            ExprKind::Cast(_, inner) if inner.ty == *ty => return Expr { span, ..*inner },
+            ExprKind::Ite(cond, cons, alt) => ExprKind::Ite(
+                cond,
+                Box::new(Self::narrow(*cons, ty)),
+                Box::new(Self::narrow(*alt, ty)),
+            ),
+            kind => {
+                let wide = Expr {
+                    ty: expr.ty,
+                    kind,
+                    span,
+                };
+                let cast = Expr {
+                    ty: ty.clone(),
+                    kind: ExprKind::Cast(ty.clone(), Box::new(wide)),
+                    span,
+                };
+                return cast.fold().unwrap_or(cast);
+            }
+        };
+        Expr {
+            ty: ty.clone(),
+            kind,
+            span,
+        }
+    }
+}
+
+impl RewriteRule for NarrowIntegers {
+    fn rewrite_stmt(
+        &self,
+        stmt: Stmt,
+        memory: &HashMap<StreamReference, Memory>,
+        _liveness_equivalences: &LivetimeEquivalences,
+    ) -> Result<(Stmt, ChangeSet), RewriteError> {
+        match stmt {
+            Stmt::Eval {
+                sr,
+                with,
+                idx,
+                span,
+            } if with.ty != memory[&sr.sr()].ty
+                && matches!(memory[&sr.sr()].ty, Type::Int(_) | Type::UInt(_)) =>
+            {
+                let with = Self::narrow(with, &memory[&sr.sr()].ty);
+                Ok((
+                    Stmt::Eval {
+                        sr,
+                        with,
+                        idx,
+                        span,
+                    },
+                    ChangeSet::local_change(),
+                ))
+            }
+            stmt => Ok((stmt, ChangeSet::default())),
+        }
+    }
+
+    fn rewrite_expr(
+        &self,
+        expr: Expr,
+        memory: &HashMap<StreamReference, Memory>,
+        _liveness_equivalences: &LivetimeEquivalences,
+    ) -> Result<(Expr, ChangeSet), RewriteError> {
+        let (ExprKind::SyncStreamAccess { target, .. }
+        | ExprKind::OffsetStreamAccess { target, .. }
+        | ExprKind::HoldStreamAccess { target, .. }
+        | ExprKind::GetAccess { target, .. }) = &expr.kind
+        else {
+            return Ok((expr, ChangeSet::default()));
+        };
+        let ty = memory[target].ty.clone();
+        if expr.ty == ty || !matches!(ty, Type::Int(_) | Type::UInt(_)) {
+            return Ok((expr, ChangeSet::default()));
+        }
+        let Expr {
+            ty: wide,
+            kind,
+            span,
+        } = expr;
+        let narrow_default = |default: Option<Box<Expr>>| {
+            default.map(|default| Box::new(Self::narrow(*default, &ty)))
+        };
+        let kind = match kind {
+            ExprKind::OffsetStreamAccess {
+                target,
+                offset,
+                default,
+                parameters,
+            } => ExprKind::OffsetStreamAccess {
+                target,
+                offset,
+                default: narrow_default(default),
+                parameters,
+            },
+            ExprKind::HoldStreamAccess {
+                target,
+                default,
+                parameters,
+            } => ExprKind::HoldStreamAccess {
+                target,
+                default: narrow_default(default),
+                parameters,
+            },
+            ExprKind::GetAccess {
+                target,
+                default,
+                parameters,
+            } => ExprKind::GetAccess {
+                target,
+                default: narrow_default(default),
+                parameters,
+            },
+            kind => kind,
+        };
+        let access = Expr {
+            ty: ty.clone(),
+            kind,
+            span,
+        };
+        Ok((
+            Expr {
+                ty: wide.clone(),
+                kind: ExprKind::Cast(wide, Box::new(access)),
+                span,
+            },
+            ChangeSet::local_change(),
+        ))
+    }
+
+    fn apply_memory(
+        &self,
+        memory: HashMap<StreamReference, Memory>,
+        _livetime_equivalences: &LivetimeEquivalences,
+    ) -> Result<(HashMap<StreamReference, Memory>, ChangeSet), RewriteError> {
+        let mut cs = ChangeSet::default();
+        let memory = memory
+            .into_iter()
+            .map(|(sr, mut memory)| {
+                if let Some(ty) = self.types.get(&memory.name) {
+                    if memory.ty != *ty {
+                        memory.ty = ty.clone();
+                        cs += ChangeSet::local_change();
+                    }
+                }
+                (sr, memory)
+            })
+            .collect();
+        Ok((memory, cs))
+    }
+
+    fn cleanup_rules(&self) -> Vec<Box<dyn RewriteRule>> {
+        vec![Box::new(ConstantFolding)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{
+        ir::{
+            expressions::ExprKind, parse::parse_text, OutputReference, Span, Stmt, StreamIr,
+            StreamReference, Type, ValueRange,
+        },
+        rewrite_rules::{NarrowIntegers, Rewriter},
+    };
+
+    const STREAMS: &str = "input in0 a: UInt(64) = static(single);
+output out0 b: UInt(64) = static(single);
+output out1 c: UInt(64) = static(single);
+output out2 d: Int(64) = static(single);
+output out3 e: UInt(64) = static(single);
+";
+
+    #[test]
+    fn narrow() {
+        let ir = parse_text(
+            STREAMS,
+            "seq {
+                input in0;
+                eval_0 out0 with ite(in0 > 10u64, 200u64, 3u64);
+                eval_0 out1 with out0.offset(1, or: 0u64) + in0;
+                eval_0 out2 with ite(in0 > 10u64, -1000i64, 1000i64);
+                eval_0 out3 with ite(in0 > 5u64, out0.hold(or: 1u64), 7u64)
+            }",
+        );
+        let rule = NarrowIntegers::new(&ir);
+        let res = Rewriter::new(vec![Box::new(rule)])
+            .with_verification(true)
+            .run(ir)
+            .unwrap();
+        let types = (0..4)
+            .map(|i| {
+                res.stream_memory(OutputReference::Unparameterized(i).sr())
+                    .ty
+                    .clone()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            types,
+            vec![Type::UInt(8), Type::UInt(64), Type::Int(16), Type::UInt(8)]
+        );
+        let reference = StreamIr::from_text(
+            "input in0 a: UInt(64) = static(single);
+output out0 b: UInt(8) = static(single);
+output out1 c: UInt(64) = static(single);
+output out2 d: Int(16) = static(single);
+output out3 e: UInt(8) = static(single);
+stmt seq {
+                input in0;
+                eval_0 out0 with ite(in0 > 10u64, 200u8, 3u8);
+                eval_0 out1 with cast<UInt(64)>(out0.offset(1, or: 0u8)) + in0;
+                eval_0 out2 with ite(in0 > 10u64, -1000i16, 1000i16);
+                eval_0 out3 with ite(in0 > 5u64, out0.hold(or: 1u8), 7u8)
+            }",
+        )
+        .unwrap();
+        assert_eq!(res.stmt, reference.stmt);
+    }
+
+    #[test]
+    fn input_bounds() {
+        let ir = parse_text(STREAMS, "seq { input in0; eval_0 out1 with in0 * 2u64 }");
+        let unbounded = Rewriter::new(vec![Box::new(NarrowIntegers::new(&ir))])
+            .with_verification(true)
+            .run(ir.clone())
+            .unwrap();
+        assert_eq!(
+            unbounded
+                .stream_memory(OutputReference::Unparameterized(1).sr())
+                .ty,
+            Type::UInt(64)
+        );
+        let bounds = HashMap::from([(
+            StreamReference::In(0),
+            ValueRange::Int { min: 0, max: 1000 },
+        )]);
+        let rule = NarrowIntegers::with_ranges(&ir, &ir.value_ranges_with(&bounds));
+        let bounded = Rewriter::new(vec![Box::new(rule)])
+            .with_verification(true)
+            .run(ir)
+            .unwrap();
+        assert_eq!(
+            bounded
+                .stream_memory(OutputReference::Unparameterized(1).sr())
+                .ty,
+            Type::UInt(16)
+        );
+    }
+
+    #[test]
+    fn narrow_keeps_spans() {
+        let ir = parse_text(STREAMS, "eval_0 out0 with in0 + 1u64");
+        let Stmt::Eval { mut with, .. } = ir.stmt else {
+            unreachable!()
+        };
+        let span = Span { line: 2, col: 13 };
+        with.span = span.into();
+        let narrowed = NarrowIntegers::narrow(with, &Type::UInt(8));
+        assert_eq!(narrowed.span.0, Some(span));
+        let ExprKind::Cast(Type::UInt(8), inner) = narrowed.kind else {
+            panic!("expected a cast, got {:?}", narrowed.kind)
+        };
+        assert_eq!(inner.ty, Type::UInt(64));
+        assert_eq!(inner.span.0, Some(span));
+    }
+}
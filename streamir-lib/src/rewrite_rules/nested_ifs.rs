@@ -83,7 +83,7 @@ mod tests {
 			input 1
 		fi",
         );
-        let rewriter = Rewriter::new(vec![Box::new(CombineNestedIf {})]);
+        let rewriter = Rewriter::new(vec![Box::new(CombineNestedIf {})]).with_verification(true);
         let (res, changed) = rewriter.apply(ir).unwrap();
         assert!(changed);
         assert!(res.stmt.eq(&reference.stmt));
@@ -102,7 +102,7 @@ mod tests {
 		fi	
 		",
         );
-        let rewriter = Rewriter::new(vec![Box::new(CombineNestedIf {})]);
+        let rewriter = Rewriter::new(vec![Box::new(CombineNestedIf {})]).with_verification(true);
         let (res, changed) = rewriter.apply(ir.clone()).unwrap();
         assert!(!changed);
         assert!(res.stmt.eq(&ir.stmt));
@@ -1,7 +1,7 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
 
 use crate::{
-    ir::{memory::Memory, Guard, LivetimeEquivalences, StreamReference},
+    ir::{memory::Memory, Guard, LivetimeEquivalences, LocalFreqRef, StreamReference},
     rewrite_rules::{RemoveIfs, RemoveSkip, SimplifyGuard},
 };
 
@@ -73,6 +73,24 @@ impl EvaluateGuards {
             _ => None,
         }))
     }
+
+    /// Partially evaluate the periodic activation conditions for a deadline of the static schedule at which exactly the global frequencies `due` are due.
+    pub fn only_global_frequencies(due: Vec<Duration>) -> Self {
+        Self(Box::new(move |g| match g {
+            Guard::GlobalFreq(dur) => Some(due.contains(dur)),
+            Guard::LocalFreq(_) => Some(false),
+            _ => None,
+        }))
+    }
+
+    /// Partially evaluate the periodic activation conditions for a deadline at which only the local frequency `lref` is due.
+    pub fn only_local_frequency(lref: LocalFreqRef) -> Self {
+        Self(Box::new(move |g| match g {
+            Guard::GlobalFreq(_) => Some(false),
+            Guard::LocalFreq(l) => Some(*l == lref),
+            _ => None,
+        }))
+    }
 }
 
 #[cfg(test)]
@@ -93,7 +111,8 @@ mod tests {
 				input 0	
 			fi",
         );
-        let rewriter = Rewriter::new(vec![Box::new(EvaluateGuards::only_event_based())]);
+        let rewriter = Rewriter::new(vec![Box::new(EvaluateGuards::only_event_based())])
+            .with_verification(true);
         let (res, changed) = rewriter.apply(ir).unwrap();
         assert!(changed);
         assert!(res.stmt.eq(&reference.stmt));
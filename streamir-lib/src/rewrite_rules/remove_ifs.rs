@@ -50,7 +50,7 @@ mod tests {
 		",
         );
         let reference = parse_ir("input 0");
-        let rewriter = Rewriter::new(vec![Box::new(RemoveIfs {})]);
+        let rewriter = Rewriter::new(vec![Box::new(RemoveIfs {})]).with_verification(true);
         let (res, changed) = rewriter.apply(ir).unwrap();
         assert!(changed);
         assert!(res.stmt.eq(&reference.stmt));
@@ -68,7 +68,7 @@ mod tests {
 		",
         );
         let reference = parse_ir("input 1");
-        let rewriter = Rewriter::new(vec![Box::new(RemoveIfs {})]);
+        let rewriter = Rewriter::new(vec![Box::new(RemoveIfs {})]).with_verification(true);
         let (res, changed) = rewriter.apply(ir).unwrap();
         assert!(changed);
         assert!(res.stmt.eq(&reference.stmt));
@@ -85,7 +85,7 @@ mod tests {
 		fi	
 		",
         );
-        let rewriter = Rewriter::new(vec![Box::new(RemoveIfs {})]);
+        let rewriter = Rewriter::new(vec![Box::new(RemoveIfs {})]).with_verification(true);
         let (res, changed) = rewriter.apply(ir.clone()).unwrap();
         assert!(!changed);
         assert!(res.stmt.eq(&ir.stmt));
@@ -5,15 +5,30 @@ use itertools::Itertools;
 use crate::ir::{
     expressions::{Constant, Expr, ExprKind, Operator},
     memory::Memory,
-    Guard, LivetimeEquivalences, StreamReference,
+    Guard, GuardBdd, LivetimeEquivalences, StreamReference,
 };
 
 use super::{ChangeSet, RewriteError, RewriteRule};
 
 #[derive(Debug, Clone, Copy)]
 /// A rewriting rule simplifying guard conditions by moving conjunctions/disjunctions and constants from expression level to guard level.
+///
+/// Conjunctions and disjunctions are replaced by their smallest equivalent guard, removing duplicated and absorbed conditions.
 pub struct SimplifyGuard;
 
+impl SimplifyGuard {
+    fn minimize(guard: Guard, livetime_equivalences: &LivetimeEquivalences) -> (Guard, ChangeSet) {
+        let mut bdd = GuardBdd::new(livetime_equivalences);
+        let guard_bdd = bdd.from_guard(&guard);
+        let minimal = bdd.to_guard(guard_bdd);
+        if minimal.num_atoms() < guard.num_atoms() {
+            (minimal, ChangeSet::local_change())
+        } else {
+            (guard, ChangeSet::default())
+        }
+    }
+}
+
 impl RewriteRule for SimplifyGuard {
     fn rewrite_guard(
         &self,
@@ -58,12 +73,12 @@ impl RewriteRule for SimplifyGuard {
                 (Guard::Constant(false), _) | (_, Guard::Constant(false)) => {
                     Ok((Guard::Constant(false), ChangeSet::local_change()))
                 }
-                (lhs, rhs) => Ok((
+                (lhs, rhs) => Ok(Self::minimize(
                     Guard::And {
                         lhs: Box::new(lhs),
                         rhs: Box::new(rhs),
                     },
-                    ChangeSet::default(),
+                    livetime_equivalences,
                 )),
             },
             Guard::Or { lhs, rhs } => match (*lhs, *rhs) {
@@ -73,12 +88,12 @@ impl RewriteRule for SimplifyGuard {
                 (Guard::Constant(false), other) | (other, Guard::Constant(false)) => {
                     Ok((other, ChangeSet::local_change()))
                 }
-                (lhs, rhs) => Ok((
+                (lhs, rhs) => Ok(Self::minimize(
                     Guard::Or {
                         lhs: Box::new(lhs),
                         rhs: Box::new(rhs),
                     },
-                    ChangeSet::default(),
+                    livetime_equivalences,
                 )),
             },
             Guard::Alive(sr) => {
@@ -94,7 +109,7 @@ impl RewriteRule for SimplifyGuard {
 
 impl Expr {
     fn split_guards(self) -> (Guard, bool) {
-        let Self { ty, kind } = self;
+        let Self { ty, kind, span } = self;
         match kind {
             ExprKind::BinaryOperation(Operator::And, lhs, rhs) => {
                 let (lhs, _) = lhs.split_guards();
@@ -119,7 +134,7 @@ impl Expr {
                 )
             }
             ExprKind::Constant(Constant::Bool(c)) => (Guard::Constant(c), true),
-            kind => (Guard::Dynamic(Expr { kind, ty }), false),
+            kind => (Guard::Dynamic(Expr { kind, ty, span }), false),
         }
     }
 }
@@ -127,7 +142,7 @@ impl Expr {
 #[cfg(test)]
 mod tests {
     use crate::{
-        ir::parse::parse_ir,
+        ir::{parse::parse_ir, Guard, IfStmt, Stmt, StreamIr},
         rewrite_rules::{simplify_guard::SimplifyGuard, Rewriter},
     };
 
@@ -146,7 +161,7 @@ mod tests {
             fi
         ",
         );
-        let rewriter = Rewriter::new(vec![Box::new(SimplifyGuard {})]);
+        let rewriter = Rewriter::new(vec![Box::new(SimplifyGuard {})]).with_verification(true);
         let (res, changed) = rewriter.apply(ir).unwrap();
         assert!(changed);
         assert!(res.stmt.eq(&reference.stmt));
@@ -167,9 +182,54 @@ mod tests {
             fi
         ",
         );
-        let rewriter = Rewriter::new(vec![Box::new(SimplifyGuard {})]);
+        let rewriter = Rewriter::new(vec![Box::new(SimplifyGuard {})]).with_verification(true);
+        let (res, changed) = rewriter.apply(ir).unwrap();
+        assert!(changed);
+        assert!(res.stmt.eq(&reference.stmt));
+    }
+
+    #[test]
+    fn absorption() {
+        let ir = parse_ir(
+            "
+			if (@0 || (@1 && @0)) && (@2 || @2) then
+				input 0
+			fi
+        ",
+        );
+        let reference = parse_ir(
+            "if @0 && @2 then
+				input 0
+            fi
+        ",
+        );
+        let rewriter = Rewriter::new(vec![Box::new(SimplifyGuard {})]).with_verification(true);
         let (res, changed) = rewriter.apply(ir).unwrap();
         assert!(changed);
         assert!(res.stmt.eq(&reference.stmt));
     }
+
+    #[test]
+    fn dynamic_after_stream_guards() {
+        let ir = StreamIr::from_text(
+            "input in0 a: UInt(64) = static(single);
+input in1 b: UInt(64) = static(single);
+stmt if (@in0 && Expr(in0 > 1u64)) || (@in1 && Expr(in0 > 1u64)) then
+    input in0
+fi",
+        )
+        .unwrap();
+        let rewriter = Rewriter::new(vec![Box::new(SimplifyGuard {})]).with_verification(true);
+        let (res, changed) = rewriter.apply(ir).unwrap();
+        assert!(changed);
+        let Stmt::If(IfStmt { guard, .. }) = &res.stmt else {
+            panic!("expected a conditional, found {:?}", res.stmt)
+        };
+        // the expression is only evaluated if one of the inputs has a new value
+        let Guard::And { lhs, rhs } = guard else {
+            panic!("expected a conjunction, found {guard:?}")
+        };
+        assert!(matches!(**lhs, Guard::Or { .. }));
+        assert!(matches!(**rhs, Guard::Dynamic(_)));
+    }
 }
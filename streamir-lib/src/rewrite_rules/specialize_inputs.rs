@@ -0,0 +1,364 @@
+use std::collections::{HashMap, HashSet};
+
+use itertools::Itertools;
+use thiserror::Error;
+
+use crate::ir::{
+    expressions::{Constant, Expr, ExprKind},
+    memory::Memory,
+    Guard, IfStmt, LivetimeEquivalences, OutputReference, Stmt, StreamIr, StreamReference, Type,
+};
+
+use super::{
+    ChangeSet, ConstantFolding, DeadStreamElimination, RemoveSkip, RewriteError, RewriteRule,
+};
+
+#[derive(Debug, Error)]
+/// An error when specializing the StreamIR to constant input values
+pub enum SpecializeError {
+    #[error("input stream {0} does not exist in the specification")]
+    /// No input stream has the given name
+    UnknownInput(String),
+    #[error("the value {value:?} does not have the type {ty} of input stream {name}")]
+    /// The value does not match the type of the input stream
+    TypeMismatch {
+        /// The name of the input stream
+        name: String,
+        /// The given value
+        value: Constant,
+        /// The type of the input stream
+        ty: Type,
+    },
+}
+
+#[derive(Debug, Clone)]
+/// A rewriting rule that specializes the StreamIR to input streams carrying a fixed value for the whole run.
+///
+/// Synchronous accesses and hold accesses with a default to these inputs are replaced by their value,
+/// i.e., the inputs are assumed to carry the value from the start of the run.
+/// Offset and get accesses depend on when the inputs received values and are kept.
+/// Unparameterized output streams that evaluate to the same constant in all their evaluations are treated alike,
+/// except that only their synchronous accesses are replaced.
+/// The resulting expressions are folded and conditionals with constant guards are removed by the cleanup rules.
+///
+/// Output streams that become constant are removed if all accesses to them are replaced, i.e., if they are
+/// no triggers and are neither accessed by offset, get, hold or window accesses nor referenced by a guard.
+/// The input streams are kept, as they remain part of the events.
+/// Like [DeadStreamElimination], the rule identifies the streams by their name.
+pub struct SpecializeInputs {
+    values: HashMap<String, Constant>,
+    dead: DeadStreamElimination,
+}
+
+impl SpecializeInputs {
+    /// Creates the rule specializing the StreamIR to the input streams with the given names carrying the given values.
+    pub fn new(
+        ir: &StreamIr,
+        inputs: impl IntoIterator<Item = (String, Constant)>,
+    ) -> Result<Self, SpecializeError> {
+        let mut values = HashMap::new();
+        for (name, value) in inputs {
+            let Some(sr) = ir
+                .stream_by_name(&name)
+                .filter(|sr| matches!(sr, StreamReference::In(_)))
+            else {
+                return Err(SpecializeError::UnknownInput(name));
+            };
+            let ty = &ir.stream_memory(sr).ty;
+            if !value.has_type(ty) {
+                return Err(SpecializeError::TypeMismatch {
+                    name,
+                    value,
+                    ty: ty.clone(),
+                });
+            }
+            values.insert(sr, value);
+        }
+
+        let mut evals: HashMap<OutputReference, Vec<&Expr>> = HashMap::new();
+        Self::evals(&ir.stmt, &mut evals);
+        loop {
+            let constant = evals
+                .iter()
+                .filter(|(sr, _)| !values.contains_key(&sr.sr()))
+                .filter_map(|(sr, withs)| {
+                    withs
+                        .iter()
+                        .map(|with| {
+                            let mut with = (*with).clone();
+                            Self::specialize(&mut with, &|sr| values.get(&sr));
+                            match with.kind {
+                                ExprKind::Constant(c) => Some(c),
+                                _ => None,
+                            }
+                        })
+                        .collect::<Option<Vec<_>>>()?
+                        .into_iter()
+                        .all_equal_value()
+                        .ok()
+                        .map(|c| (sr.sr(), c))
+                })
+                .collect::<Vec<_>>();
+            if constant.is_empty() {
+                break;
+            }
+            values.extend(constant);
+        }
+        let mut referenced = HashSet::new();
+        Self::referenced(&ir.stmt, ir, &mut referenced);
+        let dead = values
+            .keys()
+            .copied()
+            .filter(|sr| matches!(sr, StreamReference::Out(o) if !ir.triggers.contains_key(o)))
+            .filter(|sr| !referenced.contains(sr))
+            .collect::<Vec<_>>();
+        let dead = DeadStreamElimination::removing(ir, &dead);
+        let values = values
+            .into_iter()
+            .map(|(sr, value)| (ir.name(sr).to_owned(), value))
+            .collect();
+        Ok(Self { values, dead })
+    }
+
+    /// Collects the expressions of the evaluations of unparameterized output streams
+    fn evals<'a>(stmt: &'a Stmt, evals: &mut HashMap<OutputReference, Vec<&'a Expr>>) {
+        match stmt {
+            Stmt::Skip
+            | Stmt::Shift(_)
+            | Stmt::Input(_)
+            | Stmt::Spawn { .. }
+            | Stmt::Close { .. } => {}
+            Stmt::Eval {
+                sr: sr @ OutputReference::Unparameterized(_),
+                with,
+                ..
+            } => evals.entry(*sr).or_default().push(with),
+            Stmt::Eval { .. } => {}
+            Stmt::Seq(stmts) | Stmt::Parallel(stmts) => {
+                stmts.iter().for_each(|stmt| Self::evals(stmt, evals))
+            }
+            Stmt::If(IfStmt { cons, alt, .. }) => {
+                Self::evals(cons, evals);
+                Self::evals(alt, evals);
+            }
+            Stmt::Iterate { stmt, .. } | Stmt::Assign { stmt, .. } | Stmt::Let { stmt, .. } => {
+                Self::evals(stmt, evals)
+            }
+        }
+    }
+
+    /// Collects the streams the statement refers to other than through synchronous accesses without parameters
+    fn referenced(stmt: &Stmt, ir: &StreamIr, referenced: &mut HashSet<StreamReference>) {
+        match stmt {
+            Stmt::Skip | Stmt::Shift(_) | Stmt::Input(_) | Stmt::Close { .. } => {}
+            Stmt::Spawn { with, .. } => with
+                .iter()
+                .flatten()
+                .for_each(|expr| Self::accessed(expr, referenced)),
+            Stmt::Eval { with, .. } => Self::accessed(with, referenced),
+            Stmt::Seq(stmts) | Stmt::Parallel(stmts) => stmts
+                .iter()
+                .for_each(|stmt| Self::referenced(stmt, ir, referenced)),
+            Stmt::If(IfStmt { guard, cons, alt }) => {
+                Self::guarded(guard, ir, referenced);
+                Self::referenced(cons, ir, referenced);
+                Self::referenced(alt, ir, referenced);
+            }
+            Stmt::Iterate { stmt, .. } => Self::referenced(stmt, ir, referenced),
+            Stmt::Assign {
+                parameter_expr,
+                stmt,
+                ..
+            } => {
+                parameter_expr
+                    .iter()
+                    .for_each(|expr| Self::accessed(expr, referenced));
+                Self::referenced(stmt, ir, referenced)
+            }
+            Stmt::Let { expr, stmt, .. } => {
+                Self::accessed(expr, referenced);
+                Self::referenced(stmt, ir, referenced)
+            }
+        }
+    }
+
+    /// Collects the streams the guard refers to other than through synchronous accesses without parameters
+    fn guarded(guard: &Guard, ir: &StreamIr, referenced: &mut HashSet<StreamReference>) {
+        match guard {
+            Guard::Stream(sr) | Guard::Alive(sr) => {
+                referenced.insert(*sr);
+            }
+            Guard::Dynamic(expr) => Self::accessed(expr, referenced),
+            Guard::LocalFreq(lref) => {
+                referenced.insert(ir.lref2lfreq[lref].sr.sr());
+            }
+            Guard::And { lhs, rhs } | Guard::Or { lhs, rhs } => {
+                Self::guarded(lhs, ir, referenced);
+                Self::guarded(rhs, ir, referenced);
+            }
+            Guard::FastAnd(srs) | Guard::FastOr(srs) => referenced.extend(srs.iter().copied()),
+            Guard::GlobalFreq(_) | Guard::Constant(_) => {}
+        }
+    }
+
+    /// Collects the streams the expression accesses other than through synchronous accesses without parameters
+    fn accessed(expr: &Expr, referenced: &mut HashSet<StreamReference>) {
+        match &expr.kind {
+            ExprKind::SyncStreamAccess { parameters, .. } if parameters.is_empty() => {}
+            ExprKind::SyncStreamAccess { target, .. }
+            | ExprKind::OffsetStreamAccess { target, .. }
+            | ExprKind::HoldStreamAccess { target, .. }
+            | ExprKind::GetAccess { target, .. }
+            | ExprKind::IsFresh { target, .. }
+            | ExprKind::WindowAccess { target, .. }
+            | ExprKind::ParameterAccess(target, _) => {
+                referenced.insert(*target);
+            }
+            _ => {}
+        }
+        expr.children()
+            .into_iter()
+            .for_each(|child| Self::accessed(child, referenced));
+    }
+
+    /// Returns the value of the expression if it accesses a stream with a known value
+    fn value<'a>(
+        expr: &Expr,
+        value: &impl Fn(StreamReference) -> Option<&'a Constant>,
+    ) -> Option<&'a Constant> {
+        match &expr.kind {
+            ExprKind::SyncStreamAccess { target, parameters } if parameters.is_empty() => {
+                value(*target)
+            }
+            ExprKind::HoldStreamAccess {
+                target: target @ StreamReference::In(_),
+                default: Some(_),
+                ..
+            } => value(*target),
+            _ => None,
+        }
+    }
+
+    /// Replaces the accesses to streams with a known value and folds the expression
+    fn specialize<'a>(expr: &mut Expr, value: &impl Fn(StreamReference) -> Option<&'a Constant>) {
+        expr.children_mut()
+            .into_iter()
+            .for_each(|child| Self::specialize(child, value));
+        if let Some(c) = Self::value(expr, value) {
+            expr.kind = ExprKind::Constant(c.clone());
+        } else if let Some(folded) = expr.fold() {
+            *expr = folded;
+        }
+    }
+}
+
+impl RewriteRule for SpecializeInputs {
+    fn rewrite_stmt(
+        &self,
+        stmt: Stmt,
+        memory: &HashMap<StreamReference, Memory>,
+        liveness_equivalences: &LivetimeEquivalences,
+    ) -> Result<(Stmt, ChangeSet), RewriteError> {
+        self.dead.rewrite_stmt(stmt, memory, liveness_equivalences)
+    }
+
+    fn apply_memory(
+        &self,
+        memory: HashMap<StreamReference, Memory>,
+        livetime_equivalences: &LivetimeEquivalences,
+    ) -> Result<(HashMap<StreamReference, Memory>, ChangeSet), RewriteError> {
+        self.dead.apply_memory(memory, livetime_equivalences)
+    }
+
+    fn rewrite_expr(
+        &self,
+        expr: Expr,
+        memory: &HashMap<StreamReference, Memory>,
+        _liveness_equivalences: &LivetimeEquivalences,
+    ) -> Result<(Expr, ChangeSet), RewriteError> {
+        let value = |sr: StreamReference| {
+            memory
+                .get(&sr)
+                .and_then(|memory| self.values.get(&memory.name))
+        };
+        match Self::value(&expr, &value) {
+            Some(c) => Ok((
+                Expr {
+                    ty: expr.ty,
+                    kind: ExprKind::Constant(c.clone()),
+                    span: expr.span,
+                },
+                ChangeSet::local_change(),
+            )),
+            None => Ok((expr, ChangeSet::default())),
+        }
+    }
+
+    fn cleanup_rules(&self) -> Vec<Box<dyn RewriteRule>> {
+        vec![Box::new(ConstantFolding), Box::new(RemoveSkip)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ir::{expressions::Constant, parse::parse_text},
+        rewrite_rules::{Rewriter, SpecializeError, SpecializeInputs},
+    };
+
+    const STREAMS: &str = "input in0 a: UInt(64) = static(single);
+input in1 b: UInt(64) = static(single);
+output out0 c: UInt(64) = static(single);
+output out1 d: Bool = static(single);
+output out2 e: UInt(64) = static(single);
+";
+
+    #[test]
+    fn specialize() {
+        let ir = parse_text(
+            STREAMS,
+            "seq {
+                input in1;
+                eval_0 out0 with in0.hold(or: 0u64) * 2u64;
+                if Expr(out0 > 5u64) then
+                    eval_0 out1 with in0 < out0
+                fi;
+                eval_0 out2 with out0.hold(or: 1u64) + in1
+            }",
+        );
+        let rule = SpecializeInputs::new(&ir, [("a".to_string(), Constant::UInt(3, 64))]).unwrap();
+        let res = Rewriter::new(vec![Box::new(rule)])
+            .with_verification(true)
+            .run(ir)
+            .unwrap();
+        // d is removed and e takes its place, while c is still accessed by its hold access
+        let reference = parse_text(
+            "input in0 a: UInt(64) = static(single);
+input in1 b: UInt(64) = static(single);
+output out0 c: UInt(64) = static(single);
+output out1 e: UInt(64) = static(single);
+",
+            "seq {
+                input in1;
+                eval_0 out0 with 6u64;
+                eval_0 out1 with out0.hold(or: 1u64) + in1
+            }",
+        );
+        assert_eq!(res.stmt, reference.stmt);
+        assert_eq!(res.stream_by_name("d"), None);
+        assert_eq!(res.stream_by_name("e"), reference.stream_by_name("e"));
+    }
+
+    #[test]
+    fn errors() {
+        let ir = parse_text(STREAMS, "input in0");
+        assert!(matches!(
+            SpecializeInputs::new(&ir, [("c".to_string(), Constant::UInt(3, 64))]),
+            Err(SpecializeError::UnknownInput(name)) if name == "c"
+        ));
+        assert!(matches!(
+            SpecializeInputs::new(&ir, [("a".to_string(), Constant::Int(3, 64))]),
+            Err(SpecializeError::TypeMismatch { name, .. }) if name == "a"
+        ));
+    }
+}
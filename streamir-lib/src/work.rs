@@ -0,0 +1,539 @@
+//! A static upper bound on the work a monitor performs for an event or at a deadline.
+//!
+//! The work is counted on the statement that remains after partially evaluating the guards for the event or deadline
+//! and applying a [Pipeline] of rewriting rules, such as the preset `all`.
+//! Conditionals whose guard is not known statically count the work of their more expensive branch,
+//! and statements iterating over the instances of a stream count the work of their body once per instance.
+//! The work is thus a polynomial in the numbers of instances, written `n(p)` for the stream `p`,
+//! which bounds the work of every run of the monitor without executing it.
+
+use std::{collections::BTreeMap, fmt::Display, ops::Add, time::Duration};
+
+use itertools::Itertools;
+
+use crate::{
+    ir::{
+        memory::StreamMemory, windows::WindowKind, InputReference, Stmt, StreamIr, StreamReference,
+    },
+    optimize,
+    pipeline::Pipeline,
+    rewrite_rules::{EvaluateGuards, RewriteError, RewriteRule},
+};
+
+/// The number of input streams up to which [WorkReport::new] considers all combinations of inputs
+pub const MAX_COMBINED_INPUTS: usize = 8;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// The number of statements of each kind that are executed
+pub struct Counts {
+    /// The evaluations of output streams
+    pub evals: usize,
+    /// The guards of conditionals that are checked
+    pub guards: usize,
+    /// The values added to sliding and discrete windows
+    pub window_updates: usize,
+    /// The iterations over the instances of parameterized streams
+    pub iterations: usize,
+    /// The spawns of output streams
+    pub spawns: usize,
+    /// The closes of output streams
+    pub closes: usize,
+}
+
+impl Counts {
+    fn max(self, other: Self) -> Self {
+        Self {
+            evals: self.evals.max(other.evals),
+            guards: self.guards.max(other.guards),
+            window_updates: self.window_updates.max(other.window_updates),
+            iterations: self.iterations.max(other.iterations),
+            spawns: self.spawns.max(other.spawns),
+            closes: self.closes.max(other.closes),
+        }
+    }
+
+    fn scale(self, factor: usize) -> Self {
+        Self {
+            evals: self.evals * factor,
+            guards: self.guards * factor,
+            window_updates: self.window_updates * factor,
+            iterations: self.iterations * factor,
+            spawns: self.spawns * factor,
+            closes: self.closes * factor,
+        }
+    }
+
+    /// Returns the number of each kind of statement, in the order of [Counts::KINDS]
+    fn numbers(self) -> [usize; 6] {
+        [
+            self.evals,
+            self.guards,
+            self.window_updates,
+            self.iterations,
+            self.spawns,
+            self.closes,
+        ]
+    }
+
+    /// The names of the kinds of statements
+    const KINDS: [&'static str; 6] = [
+        "evals",
+        "guards",
+        "window updates",
+        "iterations",
+        "spawns",
+        "closes",
+    ];
+}
+
+impl Add for Counts {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self {
+            evals: self.evals + rhs.evals,
+            guards: self.guards + rhs.guards,
+            window_updates: self.window_updates + rhs.window_updates,
+            iterations: self.iterations + rhs.iterations,
+            spawns: self.spawns + rhs.spawns,
+            closes: self.closes + rhs.closes,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// The work as a polynomial in the numbers of instances of the parameterized streams
+pub struct Work {
+    /// Maps the names of streams to the counts that are multiplied with the product of their numbers of instances
+    ///
+    /// The counts of the empty list of names are independent of the instances.
+    pub terms: BTreeMap<Vec<String>, Counts>,
+}
+
+impl Work {
+    /// Computes an upper bound on the work of executing the statement of the StreamIR once
+    pub fn of(ir: &StreamIr) -> Self {
+        let mut work = Work::default();
+        work.stmt(ir, &ir.stmt, &[]);
+        work
+    }
+
+    /// Returns the work that is independent of the instances
+    pub fn constant(&self) -> Counts {
+        self.terms.get(&Vec::new()).copied().unwrap_or_default()
+    }
+
+    /// Returns the work if each stream has the given number of instances
+    pub fn evaluate(&self, instances: impl Fn(&str) -> usize) -> Counts {
+        self.terms
+            .iter()
+            .map(|(streams, counts)| counts.scale(streams.iter().map(|s| instances(s)).product()))
+            .fold(Counts::default(), Add::add)
+    }
+
+    /// Returns whether no statement is executed
+    pub fn is_empty(&self) -> bool {
+        self.terms
+            .values()
+            .all(|counts| *counts == Counts::default())
+    }
+
+    /// Returns an upper bound on the work of both
+    pub fn max(mut self, other: Self) -> Self {
+        for (streams, counts) in other.terms {
+            let entry = self.terms.entry(streams).or_default();
+            *entry = entry.max(counts);
+        }
+        self
+    }
+
+    fn count(&mut self, streams: &[String], counts: Counts) {
+        let mut streams = streams.to_vec();
+        streams.sort();
+        let entry = self.terms.entry(streams).or_default();
+        *entry = *entry + counts;
+    }
+
+    fn stmt(&mut self, ir: &StreamIr, stmt: &Stmt, instances: &[String]) {
+        match stmt {
+            Stmt::Skip | Stmt::Shift(_) => {}
+            Stmt::Seq(stmts) | Stmt::Parallel(stmts) => {
+                stmts.iter().for_each(|stmt| self.stmt(ir, stmt, instances))
+            }
+            Stmt::Input(i) => self.window_updates(ir, StreamReference::In(*i), instances),
+            Stmt::Spawn { .. } => self.count(
+                instances,
+                Counts {
+                    spawns: 1,
+                    ..Default::default()
+                },
+            ),
+            Stmt::Eval { sr, .. } => {
+                self.count(
+                    instances,
+                    Counts {
+                        evals: 1,
+                        ..Default::default()
+                    },
+                );
+                self.window_updates(ir, StreamReference::Out(*sr), instances);
+            }
+            Stmt::Close { .. } => self.count(
+                instances,
+                Counts {
+                    closes: 1,
+                    ..Default::default()
+                },
+            ),
+            Stmt::If(stmt) => {
+                self.count(
+                    instances,
+                    Counts {
+                        guards: 1,
+                        ..Default::default()
+                    },
+                );
+                let mut cons = Work::default();
+                cons.stmt(ir, stmt.cons(), instances);
+                let mut alt = Work::default();
+                if let Some(stmt) = stmt.alt() {
+                    alt.stmt(ir, stmt, instances);
+                }
+                for (streams, counts) in cons.max(alt).terms {
+                    self.count(&streams, counts);
+                }
+            }
+            Stmt::Iterate { sr, stmt } => {
+                // the streams iterated together have the same instances
+                let instances = instances
+                    .iter()
+                    .cloned()
+                    .chain([ir.name(StreamReference::Out(sr[0])).to_owned()])
+                    .collect::<Vec<_>>();
+                self.count(
+                    &instances,
+                    Counts {
+                        iterations: 1,
+                        ..Default::default()
+                    },
+                );
+                self.stmt(ir, stmt, &instances);
+            }
+            Stmt::Assign { stmt, .. } | Stmt::Let { stmt, .. } => self.stmt(ir, stmt, instances),
+        }
+    }
+
+    /// Counts the updates of the windows aggregating a new value of the stream
+    ///
+    /// If the stream calling the window is parameterized, each of its instances has its own window.
+    fn window_updates(&mut self, ir: &StreamIr, target: StreamReference, instances: &[String]) {
+        for window in ir.wref2window.values().filter(|w| w.target == target) {
+            if matches!(window.kind, WindowKind::Instances { .. }) {
+                continue;
+            }
+            let mut instances = instances.to_vec();
+            if matches!(
+                ir.stream_memory(window.caller).buffer,
+                StreamMemory::Instances { .. }
+            ) {
+                instances.push(ir.name(window.caller).to_owned());
+            }
+            self.count(
+                &instances,
+                Counts {
+                    window_updates: 1,
+                    ..Default::default()
+                },
+            );
+        }
+    }
+}
+
+impl Display for Work {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return write!(f, "nothing");
+        }
+        let kinds = Counts::KINDS
+            .iter()
+            .enumerate()
+            .filter_map(|(kind, name)| {
+                let terms = self
+                    .terms
+                    .iter()
+                    .sorted_by_key(|(streams, _)| streams.len())
+                    .filter_map(|(streams, counts)| {
+                        let factor = streams.iter().map(|s| format!("n({s})")).join("*");
+                        match (counts.numbers()[kind], factor.as_str()) {
+                            (0, _) => None,
+                            (count, "") => Some(count.to_string()),
+                            (1, factor) => Some(factor.to_owned()),
+                            (count, factor) => Some(format!("{count}*{factor}")),
+                        }
+                    })
+                    .join(" + ");
+                (!terms.is_empty()).then(|| format!("{name} {terms}"))
+            })
+            .join(", ");
+        write!(f, "{kinds}")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The work for an event with new values for a set of input streams
+pub struct EventWork {
+    /// The names of the input streams receiving a new value
+    pub inputs: Vec<String>,
+    /// The work of the event
+    pub work: Work,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The work at a deadline of the static schedule
+pub struct DeadlineWork {
+    /// The time of the deadline since the start of the hyper period
+    pub time: Duration,
+    /// The work at the deadline
+    pub work: Work,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The work when a local frequency of the instances of a stream is due
+pub struct LocalFrequencyWork {
+    /// The name of the stream the frequency belongs to
+    pub stream: String,
+    /// The period of the frequency
+    pub period: Duration,
+    /// The work when the frequency is due for all instances
+    pub work: Work,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// An upper bound on the work of the monitor for each kind of event and deadline, as computed by [WorkReport::new]
+pub struct WorkReport {
+    /// The work for each considered set of inputs arriving together
+    pub events: Vec<EventWork>,
+    /// The work at each deadline of the static schedule
+    pub deadlines: Vec<DeadlineWork>,
+    /// The work for each local frequency
+    pub local_frequencies: Vec<LocalFrequencyWork>,
+}
+
+impl WorkReport {
+    /// Computes the work for each deadline and each event, after applying the pipeline to the specialized StreamIR
+    ///
+    /// For up to [MAX_COMBINED_INPUTS] input streams, all combinations of inputs are considered as events,
+    /// otherwise only the events with a single input stream and with all input streams.
+    pub fn new(ir: &StreamIr, pipeline: &Pipeline) -> Result<Self, RewriteError> {
+        let inputs = ir.inputs().sorted().collect::<Vec<_>>();
+        let events = if inputs.len() <= MAX_COMBINED_INPUTS {
+            inputs.iter().copied().powerset().skip(1).collect()
+        } else {
+            inputs
+                .iter()
+                .map(|i| vec![*i])
+                .chain([inputs.clone()])
+                .collect()
+        };
+        Self::with_events(ir, pipeline, events)
+    }
+
+    /// Computes the work for each deadline and for events with new values for exactly the given sets of input streams
+    pub fn with_events(
+        ir: &StreamIr,
+        pipeline: &Pipeline,
+        events: Vec<Vec<InputReference>>,
+    ) -> Result<Self, RewriteError> {
+        let specialized = |rules: Vec<Box<dyn RewriteRule>>| -> Result<Work, RewriteError> {
+            let ir = pipeline.run(optimize(ir.clone(), rules)?)?;
+            Ok(Work::of(&ir))
+        };
+        let events = events
+            .into_iter()
+            .map(|inputs| {
+                let streams = inputs.iter().map(|i| StreamReference::In(*i)).collect_vec();
+                let work = specialized(vec![
+                    Box::new(EvaluateGuards::only_event_based()),
+                    Box::new(EvaluateGuards::only_streams(streams.clone())),
+                ])?;
+                let inputs = streams.iter().map(|sr| ir.name(*sr).to_owned()).collect();
+                Ok(EventWork { inputs, work })
+            })
+            .collect::<Result<Vec<_>, RewriteError>>()?;
+
+        let (frequencies, _) = ir.all_periodic_pacings();
+        let mut time = Duration::ZERO;
+        let deadlines = ir
+            .static_schedule
+            .iter()
+            .flat_map(|schedule| &schedule.deadlines)
+            .map(|deadline| {
+                time += deadline.pause;
+                let due = frequencies
+                    .iter()
+                    .filter(|freq| time.as_nanos().is_multiple_of(freq.as_nanos()))
+                    .copied()
+                    .collect();
+                let work = specialized(vec![
+                    Box::new(EvaluateGuards::only_time_based()),
+                    Box::new(EvaluateGuards::only_global_frequencies(due)),
+                ])?;
+                Ok(DeadlineWork { time, work })
+            })
+            .collect::<Result<Vec<_>, RewriteError>>()?;
+
+        let local_frequencies = ir
+            .lref2lfreq
+            .iter()
+            .sorted_by_key(|(lref, _)| **lref)
+            .map(|(lref, freq)| {
+                let work = specialized(vec![
+                    Box::new(EvaluateGuards::only_time_based()),
+                    Box::new(EvaluateGuards::only_local_frequency(*lref)),
+                ])?;
+                Ok(LocalFrequencyWork {
+                    stream: ir.name(StreamReference::Out(freq.sr)).to_owned(),
+                    period: freq.dur,
+                    work,
+                })
+            })
+            .collect::<Result<Vec<_>, RewriteError>>()?;
+
+        Ok(Self {
+            events,
+            deadlines,
+            local_frequencies,
+        })
+    }
+
+    /// Returns an upper bound on the work of every event
+    pub fn worst_event(&self) -> Work {
+        self.events.iter().fold(Work::default(), |worst, event| {
+            worst.max(event.work.clone())
+        })
+    }
+}
+
+impl Display for WorkReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "events:")?;
+        for event in &self.events {
+            writeln!(f, "    {}: {}", event.inputs.join(", "), event.work)?;
+        }
+        if !self.deadlines.is_empty() {
+            writeln!(f, "deadlines:")?;
+            for deadline in &self.deadlines {
+                writeln!(f, "    {:?}: {}", deadline.time, deadline.work)?;
+            }
+        }
+        if !self.local_frequencies.is_empty() {
+            writeln!(f, "local frequencies:")?;
+            for freq in &self.local_frequencies {
+                writeln!(f, "    {:?} of {}: {}", freq.period, freq.stream, freq.work)?;
+            }
+        }
+        write!(f, "worst event: {}", self.worst_event())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{ir::StreamIr, pipeline::Pipeline};
+
+    use super::{Counts, WorkReport};
+
+    const TEXT: &str = "input in0 a: UInt(64) = static(single);
+input in1 b: UInt(64) = static(single);
+output out0 x: UInt(64) = static(single);
+output pout0 p(p: UInt(64)): UInt(64) = instances(single);
+output out1 y: UInt(64) = static(single);
+output out2 z: UInt(64) = static(single);
+local 0 = 1500ms on pout0;
+window sw0: UInt(64) = sum of in0 over sliding(1s, 10 x 100ms, nowait) in out1.eval_with(0) when Global(1s);
+schedule 1s { 500ms [eval out2]; 500ms [eval out1, eval out2] };
+stmt seq {
+    par {
+        if @in0 then
+            input in0
+        fi;
+        if @in1 then
+            input in1
+        fi
+    };
+    if @in0 && @in1 then
+        if Expr(in0 > 5u64) then
+            seq {
+                eval_0 out0 with in0;
+                spawn pout0 with (in1)
+            }
+        else
+            eval_0 out0 with in1
+        fi
+    fi;
+    iterate [pout0]
+        if @in1 then
+            eval_0 pout0 with param(pout0, 0) + in1
+        fi;
+    iterate [pout0]
+        if Local(0) then
+            close pout0
+        fi;
+    if Global(1s) then
+        eval_0 out1 with 1u64
+    fi;
+    if Global(500ms) then
+        eval_0 out2 with 2u64
+    fi
+}";
+
+    #[test]
+    fn report() {
+        let ir = StreamIr::from_text(TEXT).unwrap_or_else(|e| panic!("{e}"));
+        let pipeline = Pipeline::preset("partial-eval").unwrap();
+        let report = WorkReport::new(&ir, &pipeline).unwrap();
+        let events = report
+            .events
+            .iter()
+            .map(|event| format!("{}: {}", event.inputs.join(", "), event.work))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            vec![
+                "a: window updates 1",
+                "b: evals n(p), iterations n(p)",
+                "a, b: evals 1 + n(p), guards 1, window updates 1, iterations n(p), spawns 1",
+            ]
+        );
+        let worst = report.worst_event();
+        assert_eq!(
+            worst.evaluate(|stream| if stream == "p" { 10 } else { 0 }),
+            Counts {
+                evals: 11,
+                guards: 1,
+                window_updates: 1,
+                iterations: 10,
+                spawns: 1,
+                closes: 0,
+            }
+        );
+        let deadlines = report
+            .deadlines
+            .iter()
+            .map(|deadline| (deadline.time, deadline.work.constant().evals))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            deadlines,
+            vec![(Duration::from_millis(500), 1), (Duration::from_secs(1), 2)]
+        );
+        assert_eq!(
+            report.local_frequencies[0].work.to_string(),
+            "iterations n(p), closes n(p)"
+        );
+        assert!(report.to_string().ends_with(
+            "worst event: evals 1 + n(p), guards 1, window updates 1, iterations n(p), spawns 1"
+        ));
+        assert!(WorkReport::new(&ir, &Pipeline::preset("all").unwrap()).is_ok());
+    }
+}